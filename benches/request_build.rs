@@ -0,0 +1,89 @@
+//! Request building benchmarks for TWCai
+//!
+//! Requests go through a transport that answers at once with a canned
+//! body, so the numbers cover building, sending and parsing without the
+//! network. Run with `cargo bench --features tower`.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use twcai::api::{AgentClientExt, ConversationsExt};
+use twcai::types::{ChatCompletionRequest, ChatMessage};
+use twcai::{CloudAIClient, TwcError};
+
+const MODELS_RESPONSE: &str = r#"{"object":"list","data":[]}"#;
+
+const CONVERSATION_RESPONSE: &str =
+    r#"{"id":"conv_1","object":"conversation","created_at":1741900000}"#;
+
+const CHAT_RESPONSE: &str = r#"{
+    "id": "chatcmpl-1",
+    "object": "chat.completion",
+    "created": 1700000000,
+    "model": "deepseek-reason",
+    "choices": [{
+        "index": 0,
+        "message": {"role": "assistant", "content": "Done."},
+        "finish_reason": "stop"
+    }],
+    "usage": {"prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7}
+}"#;
+
+/// Client whose transport answers every request with `body`
+fn client(body: &'static str) -> CloudAIClient {
+    let transport = tower::service_fn(move |_request: reqwest::Request| async move {
+        let response = http::Response::builder()
+            .status(200)
+            .header("content-type", "application/json")
+            .body(body)
+            .unwrap();
+        Ok::<_, TwcError>(reqwest::Response::from(response))
+    });
+    CloudAIClient::builder()
+        .token("bench-token")
+        .max_retries(0)
+        .transport(transport)
+        .build()
+        .unwrap()
+}
+
+fn bench_requests(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    let models = client(MODELS_RESPONSE);
+    c.bench_function("list_models", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { models.list_models("agent-1").await.unwrap() })
+    });
+
+    let conversations = client(CONVERSATION_RESPONSE);
+    c.bench_function("get_conversation", |b| {
+        b.to_async(&runtime).iter(|| async {
+            conversations
+                .get_conversation("agent-1", "conv 1/2")
+                .await
+                .unwrap()
+        })
+    });
+
+    let chat = client(CHAT_RESPONSE);
+    let request = ChatCompletionRequest {
+        messages: vec![ChatMessage::user("Summarize this")],
+        ..Default::default()
+    };
+    c.bench_function("chat_completions", |b| {
+        b.to_async(&runtime).iter(|| async {
+            chat.chat_completions("agent-1", request.clone())
+                .await
+                .unwrap()
+        })
+    });
+
+    c.bench_function("with_token", |b| {
+        b.iter(|| models.with_token("rotated-token"))
+    });
+}
+
+criterion_group!(benches, bench_requests);
+criterion_main!(benches);
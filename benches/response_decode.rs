@@ -0,0 +1,82 @@
+//! Response decoding benchmarks for TWCai
+//!
+//! Compares reading a ~5 MB JSON body as text and parsing the string with
+//! reading it once as bytes and parsing the slice, as the client does. Run
+//! with `cargo bench --features tower`.
+
+use bytes::Bytes;
+use criterion::{Criterion, criterion_group, criterion_main};
+use twcai::api::AgentClientExt;
+use twcai::types::ModelsResponse;
+use twcai::{CloudAIClient, TwcError};
+
+/// Models list of about 5 MB
+fn large_body() -> Bytes {
+    let models: Vec<_> = (0..50_000)
+        .map(|i| {
+            serde_json::json!({
+                "id": format!("model-{:06}-with-a-reasonably-long-name", i),
+                "object": "model",
+                "created": 1700000000,
+                "owned_by": "timeweb"
+            })
+        })
+        .collect();
+    let body = serde_json::json!({"object": "list", "data": models}).to_string();
+    Bytes::from(body)
+}
+
+fn response(body: &Bytes) -> reqwest::Response {
+    let response = http::Response::builder()
+        .status(200)
+        .header("content-type", "application/json")
+        .body(body.clone())
+        .unwrap();
+    reqwest::Response::from(response)
+}
+
+fn bench_decode(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let body = large_body();
+
+    let mut group = c.benchmark_group("decode_5mb");
+    group.sample_size(20);
+
+    group.bench_function("text_then_from_str", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let text = response(&body).text().await.unwrap();
+            serde_json::from_str::<ModelsResponse>(&text).unwrap()
+        })
+    });
+
+    group.bench_function("bytes_then_from_slice", |b| {
+        b.to_async(&runtime).iter(|| async {
+            let bytes = response(&body).bytes().await.unwrap();
+            serde_json::from_slice::<ModelsResponse>(&bytes).unwrap()
+        })
+    });
+
+    let transport_body = body.clone();
+    let transport = tower::service_fn(move |_request: reqwest::Request| {
+        let response = response(&transport_body);
+        async move { Ok::<_, TwcError>(response) }
+    });
+    let client = CloudAIClient::builder()
+        .token("bench-token")
+        .max_retries(0)
+        .transport(transport)
+        .build()
+        .unwrap();
+    group.bench_function("list_models", |b| {
+        b.to_async(&runtime)
+            .iter(|| async { client.list_models("agent-1").await.unwrap() })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_decode);
+criterion_main!(benches);
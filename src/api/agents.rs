@@ -0,0 +1,103 @@
+//! Agents management API endpoints (Timeweb Cloud control plane)
+//!
+//! Provides methods for:
+//! - Listing the account's agents
+//! - Getting a single agent
+//! - Verifying the token
+//!
+//! These endpoints are served from the control-plane URL and prefix
+//! (see [`ClientBuilder::control_plane_url`](crate::ClientBuilder::control_plane_url)).
+
+use std::time::Duration;
+
+use reqwest::Method;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use super::endpoint::Endpoint;
+use super::http::{RequestSpec, send_json, send_json_with_meta};
+use crate::{CloudAIClient, Result, types::*};
+
+/// Page size used when walking every agent of the account
+const AGENTS_PAGE_SIZE: u64 = 100;
+
+/// Extension trait for agents management operations
+pub trait AgentsAdminExt {
+    /// List all agents on the account
+    ///
+    /// GET /api/v1/cloud-ai/agents
+    ///
+    /// Follows `limit`/`offset` pagination until every agent is fetched.
+    fn list_agents(&self) -> impl std::future::Future<Output = Result<Vec<AgentInfo>>> + Send;
+
+    /// Get an agent by its control-plane ID
+    ///
+    /// GET /api/v1/cloud-ai/agents/{agent_id}
+    fn get_agent(
+        &self,
+        agent_id: u64,
+    ) -> impl std::future::Future<Output = Result<AgentInfo>> + Send;
+
+    /// Check that the token is valid for the account, without naming an
+    /// agent; returns the round-trip time
+    ///
+    /// GET /api/v1/cloud-ai/agents?limit=1
+    ///
+    /// A bad token fails with [`TwcError::Unauthorized`](crate::TwcError::Unauthorized).
+    fn verify_token(&self) -> impl std::future::Future<Output = Result<Duration>> + Send;
+}
+
+#[derive(Deserialize)]
+struct AgentsPage {
+    agents: Vec<AgentInfo>,
+    meta: Option<PageMeta>,
+}
+
+#[derive(Deserialize)]
+struct PageMeta {
+    total: u64,
+}
+
+#[derive(Deserialize)]
+struct AgentEnvelope {
+    agent: AgentInfo,
+}
+
+impl AgentsAdminExt for CloudAIClient {
+    async fn list_agents(&self) -> Result<Vec<AgentInfo>> {
+        let mut agents = Vec::new();
+
+        loop {
+            let spec = RequestSpec::control_plane(Method::GET, Endpoint::Agents).query(&json!({
+                "limit": AGENTS_PAGE_SIZE,
+                "offset": agents.len(),
+            }))?;
+            let page: AgentsPage = send_json(&self.config, spec).await?;
+
+            let received = page.agents.len();
+            agents.extend(page.agents);
+
+            let total = page.meta.map(|m| m.total).unwrap_or(0);
+            if received == 0 || agents.len() as u64 >= total {
+                break;
+            }
+        }
+
+        Ok(agents)
+    }
+
+    async fn get_agent(&self, agent_id: u64) -> Result<AgentInfo> {
+        let spec = RequestSpec::control_plane(Method::GET, Endpoint::Agent(agent_id));
+        let envelope: AgentEnvelope = send_json(&self.config, spec).await?;
+
+        Ok(envelope.agent)
+    }
+
+    async fn verify_token(&self) -> Result<Duration> {
+        let spec = RequestSpec::control_plane(Method::GET, Endpoint::Agents)
+            .query(&json!({ "limit": 1 }))?;
+        let page: WithMeta<Value> = send_json_with_meta(&self.config, spec).await?;
+
+        Ok(page.meta.latency)
+    }
+}
@@ -0,0 +1,117 @@
+//! Reports of chat completion batches
+//!
+//! Provides [`BatchReport`], which partitions the items of a batch into
+//! successes and failures and can re-run the failed ones.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+
+use super::client::AgentClientExt;
+use crate::{
+    CloudAIClient, TwcError,
+    types::{ChatCompletionRequest, ChatCompletionResponse, Usage},
+};
+
+/// Outcome of a chat completion batch, split into successes and failures
+///
+/// Returned by
+/// [`chat_completions_batch_report`](AgentClientExt::chat_completions_batch_report).
+/// Items are identified by their index in the submitted requests; both
+/// lists are sorted by index.
+#[derive(Debug)]
+pub struct BatchReport {
+    /// Completed items with their index
+    pub succeeded: Vec<(usize, ChatCompletionResponse)>,
+    /// Failed items with their index
+    pub failed: Vec<(usize, TwcError)>,
+    /// Token usage summed over the successes
+    pub usage_total: Usage,
+    /// Wall-clock time spent, including retries
+    pub elapsed: Duration,
+    /// Agent the batch was sent to
+    agent_access_id: String,
+    /// Requests of the failed items, for retrying them
+    failed_requests: HashMap<usize, ChatCompletionRequest>,
+}
+
+impl BatchReport {
+    /// Run `items` against the agent, at most `concurrency` at once
+    pub(crate) async fn run(
+        client: &CloudAIClient,
+        agent_access_id: &str,
+        items: Vec<(usize, ChatCompletionRequest)>,
+        concurrency: usize,
+    ) -> Self {
+        let started = Instant::now();
+        let mut report = BatchReport {
+            succeeded: Vec::new(),
+            failed: Vec::new(),
+            usage_total: Usage::default(),
+            elapsed: Duration::ZERO,
+            agent_access_id: agent_access_id.to_string(),
+            failed_requests: HashMap::new(),
+        };
+
+        let mut completions = futures_util::stream::iter(items)
+            .map(|(index, request)| async move {
+                let result = client
+                    .chat_completions(agent_access_id, request.clone())
+                    .await;
+                (index, request, result)
+            })
+            .buffer_unordered(concurrency.max(1));
+
+        while let Some((index, request, result)) = completions.next().await {
+            match result {
+                Ok(response) => {
+                    report.usage_total += &response.usage;
+                    report.succeeded.push((index, response));
+                }
+                Err(error) => {
+                    report.failed.push((index, error));
+                    report.failed_requests.insert(index, request);
+                }
+            }
+        }
+
+        report.succeeded.sort_by_key(|(index, _)| *index);
+        report.failed.sort_by_key(|(index, _)| *index);
+        report.elapsed = started.elapsed();
+        report
+    }
+
+    /// Share of items that succeeded, from 0.0 to 1.0 (1.0 for an empty batch)
+    pub fn success_rate(&self) -> f64 {
+        let total = self.succeeded.len() + self.failed.len();
+        if total == 0 {
+            return 1.0;
+        }
+        self.succeeded.len() as f64 / total as f64
+    }
+
+    /// Send the failed items again, at most `concurrency` at once
+    ///
+    /// Items that now succeed move to `succeeded` and add to `usage_total`;
+    /// items that fail again stay in `failed` with their new error. The
+    /// retry time is added to `elapsed`.
+    pub async fn retry_failed(&mut self, client: &CloudAIClient, concurrency: usize) {
+        let mut items = Vec::new();
+        for (index, error) in std::mem::take(&mut self.failed) {
+            match self.failed_requests.remove(&index) {
+                Some(request) => items.push((index, request)),
+                None => self.failed.push((index, error)),
+            }
+        }
+
+        let retry = Self::run(client, &self.agent_access_id, items, concurrency).await;
+        self.succeeded.extend(retry.succeeded);
+        self.succeeded.sort_by_key(|(index, _)| *index);
+        self.failed.extend(retry.failed);
+        self.failed.sort_by_key(|(index, _)| *index);
+        self.failed_requests.extend(retry.failed_requests);
+        self.usage_total += &retry.usage_total;
+        self.elapsed += retry.elapsed;
+    }
+}
@@ -2,14 +2,32 @@
 //!
 //! Provides methods for:
 //! - Simple agent calls
-//! - Chat completions (OpenAI-compatible)
-//! - Text completions (legacy)
-//! - Model listing
+//! - Chat completions (OpenAI-compatible), buffered and streaming, or as text deltas only
+//! - Text completions (legacy), buffered and streaming
+//! - Concurrent chat completion batches
+//! - Model listing and health checks
 //! - Widget embed code
 
-use reqwest::header::AUTHORIZATION;
+use std::time::Duration;
 
-use crate::{CloudAIClient, Result, TwcError, types::*};
+use futures_util::StreamExt;
+use reqwest::header::{ETAG, HeaderName, LAST_MODIFIED};
+use reqwest::{Method, StatusCode};
+use serde_json::json;
+use tokio::io::AsyncWrite;
+use tokio_util::sync::CancellationToken;
+
+use super::batch::BatchReport;
+use super::endpoint::Endpoint;
+use super::http::{
+    RequestSpec, error_from_response, read_body, send_json, send_json_with_meta, send_request,
+    send_stream, send_success, stream_with_retry, write_body,
+};
+use crate::{
+    CloudAIClient, Result, ResumePolicy, StreamOptionsLocal, TwcError,
+    stream::TwcStream,
+    types::{self, *},
+};
 
 /// Extension trait for agent client operations
 pub trait AgentClientExt {
@@ -22,6 +40,22 @@ pub trait AgentClientExt {
         request: AgentCallRequest,
     ) -> impl std::future::Future<Output = Result<AgentCallResponse>> + Send;
 
+    /// Continue a dialog: call the agent with `previous.id` as the parent
+    /// message of `message`
+    fn call_agent_chain(
+        &self,
+        agent_access_id: &str,
+        previous: &AgentCallResponse,
+        message: &str,
+    ) -> impl std::future::Future<Output = Result<AgentCallResponse>> + Send;
+
+    /// [`call_agent`](Self::call_agent) that also returns response metadata
+    fn call_agent_with_meta(
+        &self,
+        agent_access_id: &str,
+        request: AgentCallRequest,
+    ) -> impl std::future::Future<Output = Result<WithMeta<AgentCallResponse>>> + Send;
+
     /// OpenAI-compatible chat completions
     ///
     /// POST /api/v1/cloud-ai/agents/{agent_access_id}/v1/chat/completions
@@ -31,6 +65,101 @@ pub trait AgentClientExt {
         request: ChatCompletionRequest,
     ) -> impl std::future::Future<Output = Result<ChatCompletionResponse>> + Send;
 
+    /// [`chat_completions`](Self::chat_completions) that also returns
+    /// response metadata (status, headers such as rate limits, latency)
+    fn chat_completions_with_meta(
+        &self,
+        agent_access_id: &str,
+        request: ChatCompletionRequest,
+    ) -> impl std::future::Future<Output = Result<WithMeta<ChatCompletionResponse>>> + Send;
+
+    /// Chat completions that can be aborted with a cancellation token
+    ///
+    /// When the token fires, the in-flight request is dropped and
+    /// [`TwcError::Cancelled`] is returned.
+    fn chat_completions_cancellable(
+        &self,
+        agent_access_id: &str,
+        request: ChatCompletionRequest,
+        token: CancellationToken,
+    ) -> impl std::future::Future<Output = Result<ChatCompletionResponse>> + Send;
+
+    /// OpenAI-compatible chat completions, streamed as server-sent events
+    ///
+    /// POST /api/v1/cloud-ai/agents/{agent_access_id}/v1/chat/completions
+    ///
+    /// Forces `stream: true` on the request. The stream is not subject to the
+    /// client's overall timeout; it fails with [`TwcError::Timeout`] only when
+    /// no data arrives within the configured stream idle timeout.
+    fn chat_completions_stream(
+        &self,
+        agent_access_id: &str,
+        request: ChatCompletionRequest,
+    ) -> impl std::future::Future<Output = Result<TwcStream<ChatCompletionStreamResponse>>> + Send;
+
+    /// [`chat_completions_stream`](Self::chat_completions_stream) with
+    /// client-side stream options, e.g. a short idle timeout for interactive UIs
+    ///
+    /// With a [`ResumePolicy`](crate::ResumePolicy) other than `Off`, a
+    /// dropped connection ends the stream with [`TwcError::StreamInterrupted`]
+    /// carrying the text of the first choice received so far, or first
+    /// re-issues the request asking the model to continue.
+    fn chat_completions_stream_with_options(
+        &self,
+        agent_access_id: &str,
+        request: ChatCompletionRequest,
+        options: StreamOptionsLocal,
+    ) -> impl std::future::Future<Output = Result<TwcStream<ChatCompletionStreamResponse>>> + Send;
+
+    /// Stream only the text of a chat completion
+    ///
+    /// Built on [`chat_completions_stream`](Self::chat_completions_stream):
+    /// yields the non-empty content deltas of the first choice and skips
+    /// role-only and metadata chunks. A refusal ends the stream with
+    /// [`TwcError::Refusal`]. Use
+    /// [`TextStreamExt::collect_text`](crate::stream::TextStreamExt::collect_text)
+    /// to get the whole text at once.
+    fn chat_completions_stream_text(
+        &self,
+        agent_access_id: &str,
+        request: ChatCompletionRequest,
+    ) -> impl std::future::Future<Output = Result<TwcStream<String>>> + Send;
+
+    /// Run many chat completions with bounded concurrency
+    ///
+    /// Results are returned in the same order as `requests`, one per item,
+    /// so a failure of one item never hides the others. At most
+    /// `concurrency` requests are in flight at once (a value of 0 is treated
+    /// as 1). Dropping the returned future aborts all in-flight items.
+    fn chat_completions_batch(
+        &self,
+        agent_access_id: &str,
+        requests: Vec<ChatCompletionRequest>,
+        concurrency: usize,
+    ) -> impl std::future::Future<Output = Vec<Result<ChatCompletionResponse>>> + Send;
+
+    /// Like [`chat_completions_batch`](Self::chat_completions_batch), calling
+    /// `progress(completed, total)` each time an item finishes
+    fn chat_completions_batch_with_progress<F>(
+        &self,
+        agent_access_id: &str,
+        requests: Vec<ChatCompletionRequest>,
+        concurrency: usize,
+        progress: F,
+    ) -> impl std::future::Future<Output = Vec<Result<ChatCompletionResponse>>> + Send
+    where
+        F: Fn(usize, usize) + Send + Sync;
+
+    /// Like [`chat_completions_batch`](Self::chat_completions_batch),
+    /// returning a [`BatchReport`] that separates successes from failures,
+    /// totals their usage and can retry the failed items
+    fn chat_completions_batch_report(
+        &self,
+        agent_access_id: &str,
+        requests: Vec<ChatCompletionRequest>,
+        concurrency: usize,
+    ) -> impl std::future::Future<Output = BatchReport> + Send;
+
     /// OpenAI-compatible text completions (legacy)
     ///
     /// POST /api/v1/cloud-ai/agents/{agent_access_id}/v1/completions
@@ -38,8 +167,21 @@ pub trait AgentClientExt {
     fn text_completions(
         &self,
         agent_access_id: &str,
-        request: TextCompletionRequest,
-    ) -> impl std::future::Future<Output = Result<TextCompletionResponse>> + Send;
+        request: types::TextCompletionRequest,
+    ) -> impl std::future::Future<Output = Result<types::TextCompletionResponse>> + Send;
+
+    /// OpenAI-compatible text completions (legacy), streamed as server-sent events
+    ///
+    /// POST /api/v1/cloud-ai/agents/{agent_access_id}/v1/completions
+    ///
+    /// Forces `stream: true` on the request. Each yielded chunk carries the
+    /// text delta of a single choice.
+    #[deprecated(since = "0.1.0", note = "Use chat_completions instead")]
+    fn text_completions_stream(
+        &self,
+        agent_access_id: &str,
+        request: types::TextCompletionRequest,
+    ) -> impl std::future::Future<Output = Result<TwcStream<types::TextCompletionChunk>>> + Send;
 
     /// List available models
     ///
@@ -49,6 +191,20 @@ pub trait AgentClientExt {
         agent_access_id: &str,
     ) -> impl std::future::Future<Output = Result<ModelsResponse>> + Send;
 
+    /// Check that the token and the agent are valid, without generating
+    /// anything
+    ///
+    /// GET /api/v1/cloud-ai/agents/{agent_access_id}/v1/models
+    ///
+    /// Meant for readiness probes. A bad token fails with
+    /// [`TwcError::Unauthorized`], a suspended agent with
+    /// [`TwcError::Forbidden`] and an unknown one with
+    /// [`TwcError::NotFound`].
+    fn ping(
+        &self,
+        agent_access_id: &str,
+    ) -> impl std::future::Future<Output = Result<PingReport>> + Send;
+
     /// Get widget embed JavaScript code
     ///
     /// GET /api/v1/cloud-ai/agents/{agent_access_id}/embed.js
@@ -59,6 +215,36 @@ pub trait AgentClientExt {
         referer: &str,
         origin: &str,
     ) -> impl std::future::Future<Output = Result<String>> + Send;
+
+    /// Get widget embed JavaScript code, optionally authenticated and
+    /// revalidating a cached copy
+    ///
+    /// GET /api/v1/cloud-ai/agents/{agent_access_id}/embed.js
+    ///
+    /// With [`EmbedOptions::cached`], an unchanged script comes back as
+    /// `not_modified` without a body, so a widget server can poll cheaply.
+    fn get_embed_code_with_options(
+        &self,
+        agent_access_id: &str,
+        options: EmbedOptions,
+    ) -> impl std::future::Future<Output = Result<EmbedCode>> + Send;
+
+    /// Write the widget embed script to `writer` as it arrives
+    ///
+    /// GET /api/v1/cloud-ai/agents/{agent_access_id}/embed.js
+    ///
+    /// Sends the same request as
+    /// [`get_embed_code_with_options`](Self::get_embed_code_with_options).
+    /// A `304 Not Modified` writes nothing and comes back with that status;
+    /// other error statuses are reported as the matching [`TwcError`].
+    fn get_embed_code_to<W>(
+        &self,
+        agent_access_id: &str,
+        options: EmbedOptions,
+        writer: &mut W,
+    ) -> impl std::future::Future<Output = Result<Download>> + Send
+    where
+        W: AsyncWrite + Unpin + Send + ?Sized;
 }
 
 impl AgentClientExt for CloudAIClient {
@@ -67,23 +253,31 @@ impl AgentClientExt for CloudAIClient {
         agent_access_id: &str,
         request: AgentCallRequest,
     ) -> Result<AgentCallResponse> {
-        let url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/call",
-            self.config.base_url, agent_access_id
-        );
-
-        let response = self
-            .config
-            .http_client
-            .post(&url)
-            .header(AUTHORIZATION, self.config.auth_header())
-            .header("x-proxy-source", "twcai-rust")
-            .json(&request)
-            .send()
+        self.call_agent_with_meta(agent_access_id, request)
             .await
-            .map_err(TwcError::Http)?;
+            .map(|response| response.data)
+    }
+
+    async fn call_agent_chain(
+        &self,
+        agent_access_id: &str,
+        previous: &AgentCallResponse,
+        message: &str,
+    ) -> Result<AgentCallResponse> {
+        let request = AgentCallRequest::new(message).parent(&previous.id);
+        self.call_agent(agent_access_id, request).await
+    }
+
+    async fn call_agent_with_meta(
+        &self,
+        agent_access_id: &str,
+        request: AgentCallRequest,
+    ) -> Result<WithMeta<AgentCallResponse>> {
+        let spec = RequestSpec::agent(Method::POST, agent_access_id, Endpoint::Call)
+            .proxy_source()
+            .json(&request)?;
 
-        handle_response(response).await
+        send_json_with_meta(&self.config, spec).await
     }
 
     async fn chat_completions(
@@ -91,66 +285,235 @@ impl AgentClientExt for CloudAIClient {
         agent_access_id: &str,
         request: ChatCompletionRequest,
     ) -> Result<ChatCompletionResponse> {
-        let url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/v1/chat/completions",
-            self.config.base_url, agent_access_id
-        );
-
-        let response = self
-            .config
-            .http_client
-            .post(&url)
-            .header(AUTHORIZATION, self.config.auth_header())
-            .header("x-proxy-source", "twcai-rust")
-            .json(&request)
-            .send()
+        self.chat_completions_with_meta(agent_access_id, request)
             .await
-            .map_err(TwcError::Http)?;
+            .map(|response| response.data)
+    }
+
+    async fn chat_completions_with_meta(
+        &self,
+        agent_access_id: &str,
+        mut request: ChatCompletionRequest,
+    ) -> Result<WithMeta<ChatCompletionResponse>> {
+        self.config.apply_chat_defaults(&mut request);
+        if let Some(stop) = &request.stop {
+            stop.validate()?;
+        }
+
+        let spec = |request: &ChatCompletionRequest| {
+            RequestSpec::agent(Method::POST, agent_access_id, Endpoint::ChatCompletions)
+                .proxy_source()
+                .json(request)
+        };
 
-        handle_response(response).await
+        match send_json_with_meta(&self.config, spec(&request)?).await {
+            Err(error) if self.config.token_limit_fallback => {
+                match token_limit_fallback(&request, &error) {
+                    Some(request) => send_json_with_meta(&self.config, spec(&request)?).await,
+                    None => Err(error),
+                }
+            }
+            result => result,
+        }
+    }
+
+    async fn chat_completions_cancellable(
+        &self,
+        agent_access_id: &str,
+        request: ChatCompletionRequest,
+        token: CancellationToken,
+    ) -> Result<ChatCompletionResponse> {
+        tokio::select! {
+            _ = token.cancelled() => Err(TwcError::Cancelled),
+            result = self.chat_completions(agent_access_id, request) => result,
+        }
+    }
+
+    async fn chat_completions_batch(
+        &self,
+        agent_access_id: &str,
+        requests: Vec<ChatCompletionRequest>,
+        concurrency: usize,
+    ) -> Vec<Result<ChatCompletionResponse>> {
+        self.chat_completions_batch_with_progress(agent_access_id, requests, concurrency, |_, _| {})
+            .await
+    }
+
+    async fn chat_completions_batch_with_progress<F>(
+        &self,
+        agent_access_id: &str,
+        requests: Vec<ChatCompletionRequest>,
+        concurrency: usize,
+        progress: F,
+    ) -> Vec<Result<ChatCompletionResponse>>
+    where
+        F: Fn(usize, usize) + Send + Sync,
+    {
+        let total = requests.len();
+        let mut results: Vec<Option<Result<ChatCompletionResponse>>> =
+            (0..total).map(|_| None).collect();
+
+        let mut completions = futures_util::stream::iter(requests.into_iter().enumerate())
+            .map(|(index, request)| async move {
+                (index, self.chat_completions(agent_access_id, request).await)
+            })
+            .buffer_unordered(concurrency.max(1));
+
+        let mut completed = 0;
+        while let Some((index, result)) = completions.next().await {
+            completed += 1;
+            progress(completed, total);
+            results[index] = Some(result);
+        }
+
+        results
+            .into_iter()
+            .map(|result| result.expect("every batch item yields a result"))
+            .collect()
+    }
+
+    async fn chat_completions_batch_report(
+        &self,
+        agent_access_id: &str,
+        requests: Vec<ChatCompletionRequest>,
+        concurrency: usize,
+    ) -> BatchReport {
+        let items = requests.into_iter().enumerate().collect();
+        BatchReport::run(self, agent_access_id, items, concurrency).await
+    }
+
+    async fn chat_completions_stream(
+        &self,
+        agent_access_id: &str,
+        request: ChatCompletionRequest,
+    ) -> Result<TwcStream<ChatCompletionStreamResponse>> {
+        let options = StreamOptionsLocal::idle_timeout(self.config.stream_idle_timeout);
+        self.chat_completions_stream_with_options(agent_access_id, request, options)
+            .await
+    }
+
+    async fn chat_completions_stream_with_options(
+        &self,
+        agent_access_id: &str,
+        mut request: ChatCompletionRequest,
+        options: StreamOptionsLocal,
+    ) -> Result<TwcStream<ChatCompletionStreamResponse>> {
+        self.config.apply_chat_defaults(&mut request);
+        if let Some(stop) = &request.stop {
+            stop.validate()?;
+        }
+        request.stream = Some(true);
+
+        let chunks =
+            open_chat_stream(self, agent_access_id, &request, options.idle_timeout).await?;
+        let max_attempts = match options.resume_policy {
+            ResumePolicy::Off => return Ok(chunks),
+            ResumePolicy::Surface => 0,
+            ResumePolicy::Continue { max_attempts } => max_attempts,
+        };
+
+        let state = ChatResumeState {
+            client: self.clone(),
+            agent_access_id: agent_access_id.to_string(),
+            request,
+            idle_timeout: options.idle_timeout,
+            partial_text: String::new(),
+            attempts_left: max_attempts,
+            inner: Some(chunks),
+        };
+        Ok(TwcStream::new(futures_util::stream::unfold(
+            state,
+            next_chat_resumable,
+        )))
+    }
+
+    async fn chat_completions_stream_text(
+        &self,
+        agent_access_id: &str,
+        request: ChatCompletionRequest,
+    ) -> Result<TwcStream<String>> {
+        let chunks = self
+            .chat_completions_stream(agent_access_id, request)
+            .await?;
+        Ok(crate::stream::chat_text(chunks))
     }
 
     #[allow(deprecated)]
     async fn text_completions(
         &self,
         agent_access_id: &str,
-        request: TextCompletionRequest,
-    ) -> Result<TextCompletionResponse> {
-        let url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/v1/completions",
-            self.config.base_url, agent_access_id
-        );
-
-        let response = self
-            .config
-            .http_client
-            .post(&url)
-            .header(AUTHORIZATION, self.config.auth_header())
-            .header("x-proxy-source", "twcai-rust")
-            .json(&request)
-            .send()
-            .await
-            .map_err(TwcError::Http)?;
+        request: types::TextCompletionRequest,
+    ) -> Result<types::TextCompletionResponse> {
+        if let Some(stop) = &request.stop {
+            stop.validate()?;
+        }
+
+        let spec = RequestSpec::agent(Method::POST, agent_access_id, Endpoint::TextCompletions)
+            .proxy_source()
+            .json(&request)?;
 
-        handle_response(response).await
+        send_json(&self.config, spec).await
+    }
+
+    #[allow(deprecated)]
+    async fn text_completions_stream(
+        &self,
+        agent_access_id: &str,
+        mut request: types::TextCompletionRequest,
+    ) -> Result<TwcStream<types::TextCompletionChunk>> {
+        if let Some(stop) = &request.stop {
+            stop.validate()?;
+        }
+
+        request.stream = Some(true);
+
+        let spec = RequestSpec::agent(Method::POST, agent_access_id, Endpoint::TextCompletions)
+            .proxy_source()
+            .streaming()
+            .json(&request)?;
+
+        let events = send_stream::<TextCompletionStreamResponse>(
+            &self.config,
+            spec,
+            self.config.stream_idle_timeout,
+        )
+        .await?;
+        let chunks = events.flat_map(|item| {
+            let chunks: Vec<Result<types::TextCompletionChunk>> = match item {
+                Ok(response) => response
+                    .choices
+                    .into_iter()
+                    .map(|choice| {
+                        Ok(types::TextCompletionChunk {
+                            text_delta: choice.text,
+                            index: choice.index,
+                            finish_reason: choice.finish_reason,
+                            logprobs: choice.logprobs,
+                        })
+                    })
+                    .collect(),
+                Err(e) => vec![Err(e)],
+            };
+            futures_util::stream::iter(chunks)
+        });
+
+        Ok(TwcStream::new(chunks))
     }
 
     async fn list_models(&self, agent_access_id: &str) -> Result<ModelsResponse> {
-        let url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/v1/models",
-            self.config.base_url, agent_access_id
-        );
-
-        let response = self
-            .config
-            .http_client
-            .get(&url)
-            .header(AUTHORIZATION, self.config.auth_header())
-            .send()
-            .await
-            .map_err(TwcError::Http)?;
+        let spec = RequestSpec::agent(Method::GET, agent_access_id, Endpoint::Models);
 
-        handle_response(response).await
+        send_json(&self.config, spec).await
+    }
+
+    async fn ping(&self, agent_access_id: &str) -> Result<PingReport> {
+        let spec = RequestSpec::agent(Method::GET, agent_access_id, Endpoint::Models);
+        let models: WithMeta<ModelsResponse> = send_json_with_meta(&self.config, spec).await?;
+
+        Ok(PingReport {
+            latency: models.meta.latency,
+            models_available: models.data.data.len(),
+        })
     }
 
     async fn get_embed_code(
@@ -160,143 +523,281 @@ impl AgentClientExt for CloudAIClient {
         referer: &str,
         origin: &str,
     ) -> Result<String> {
-        let mut url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/embed.js",
-            self.config.base_url, agent_access_id
-        );
+        let options = EmbedOptions {
+            collapsed,
+            ..EmbedOptions::new(referer, origin)
+        };
+        let code = self
+            .get_embed_code_with_options(agent_access_id, options)
+            .await?;
+        Ok(code.script.unwrap_or_default())
+    }
 
-        if let Some(collapsed) = collapsed {
-            url.push_str(&format!("?collapsed={}", collapsed));
+    async fn get_embed_code_with_options(
+        &self,
+        agent_access_id: &str,
+        options: EmbedOptions,
+    ) -> Result<EmbedCode> {
+        let spec = embed_spec(agent_access_id, &options)?;
+
+        let response = send_request(&self.config, spec).await?;
+        let header = |name: HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(EmbedCode {
+                script: None,
+                etag: etag.or(options.if_none_match),
+                last_modified: last_modified.or(options.if_modified_since),
+                not_modified: true,
+            });
+        }
+        if !response.status().is_success() {
+            return Err(error_from_response(response, &self.config).await);
         }
 
-        let response = self
-            .config
-            .http_client
-            .get(&url)
-            .header("referer", referer)
-            .header("origin", origin)
-            .send()
-            .await
-            .map_err(TwcError::Http)?;
+        let body = read_body(response, self.config.max_response_bytes).await?;
+        Ok(EmbedCode {
+            script: Some(String::from_utf8_lossy(&body).into_owned()),
+            etag,
+            last_modified,
+            not_modified: false,
+        })
+    }
 
-        let status = response.status();
-        if status.is_success() {
-            response.text().await.map_err(TwcError::Http)
-        } else {
-            Err(TwcError::from_status(status, None))
+    async fn get_embed_code_to<W>(
+        &self,
+        agent_access_id: &str,
+        options: EmbedOptions,
+        writer: &mut W,
+    ) -> Result<Download>
+    where
+        W: AsyncWrite + Unpin + Send + ?Sized,
+    {
+        let spec = embed_spec(agent_access_id, &options)?;
+
+        let response = send_request(&self.config, spec).await?;
+        if !response.status().is_success() && response.status() != StatusCode::NOT_MODIFIED {
+            return Err(error_from_response(response, &self.config).await);
         }
+        write_body(response, writer).await
     }
 }
 
-/// Handle HTTP response and parse JSON or return appropriate error
-async fn handle_response<T: serde::de::DeserializeOwned>(response: reqwest::Response) -> Result<T> {
-    let status = response.status();
-
-    if status.is_success() {
-        response.json::<T>().await.map_err(TwcError::Http)
-    } else {
-        let text = response.text().await.ok();
-        Err(TwcError::from_status(status, text))
+/// Request for the widget embed script described by `options`
+fn embed_spec(agent_access_id: &str, options: &EmbedOptions) -> Result<RequestSpec> {
+    let mut spec = RequestSpec::agent(Method::GET, agent_access_id, Endpoint::EmbedScript)
+        .header("accept", "application/javascript");
+    if !options.authenticated {
+        spec = spec.unauthenticated();
+    }
+    let headers = [
+        ("referer", &options.referer),
+        ("origin", &options.origin),
+        ("if-none-match", &options.if_none_match),
+        ("if-modified-since", &options.if_modified_since),
+    ];
+    for (name, value) in headers {
+        if let Some(value) = value {
+            spec = spec.header(name, value.clone());
+        }
     }
+    spec.query(&json!({ "collapsed": options.collapsed }))
 }
 
-/// Request for text completions (legacy)
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
-pub struct TextCompletionRequest {
-    /// The prompt to generate completions for
-    pub prompt: String,
-    /// The model to use for completion
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub model: Option<String>,
-    /// Maximum number of tokens to generate
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub max_tokens: Option<u32>,
-    /// Sampling temperature (0-2)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub temperature: Option<f32>,
-    /// Nucleus sampling parameter (0-1)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub top_p: Option<f32>,
-    /// How many completions to generate
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub n: Option<u32>,
-    /// Whether to stream back partial progress
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub stream: Option<bool>,
-    /// Include log probabilities on most likely tokens
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub logprobs: Option<u32>,
-    /// Echo back the prompt in addition to completion
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub echo: Option<bool>,
-    /// Up to 4 sequences where API stops generating further tokens
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub stop: Option<Vec<String>>,
-    /// Presence penalty (-2.0 to 2.0)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub presence_penalty: Option<f32>,
-    /// Frequency penalty (-2.0 to 2.0)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub frequency_penalty: Option<f32>,
-    /// Generates best_of completions server-side and returns the "best"
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub best_of: Option<u32>,
-    /// Unique identifier representing your end-user
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub user: Option<String>,
+/// Open a chat completion stream for a prepared `request`, retrying once with
+/// the token limit moved when the server rejects its field
+async fn open_chat_stream(
+    client: &CloudAIClient,
+    agent_access_id: &str,
+    request: &ChatCompletionRequest,
+    idle_timeout: Duration,
+) -> Result<TwcStream<ChatCompletionStreamResponse>> {
+    let build = |request: &ChatCompletionRequest| {
+        RequestSpec::agent(Method::POST, agent_access_id, Endpoint::ChatCompletions)
+            .proxy_source()
+            .streaming()
+            .json(request)
+            .map(|spec| spec.idempotent(&client.config))
+    };
+
+    let spec = build(request)?;
+    let (spec, response) = match send_success(&client.config, spec.clone()).await {
+        Err(error) if client.config.token_limit_fallback => {
+            match token_limit_fallback(request, &error) {
+                Some(request) => {
+                    let spec = build(&request)?;
+                    (spec.clone(), send_success(&client.config, spec).await?)
+                }
+                None => return Err(error),
+            }
+        }
+        result => (spec, result?),
+    };
+
+    Ok(stream_with_retry(
+        &client.config,
+        spec,
+        response,
+        idle_timeout,
+    ))
+}
+
+/// User message sent after the partial answer when a chat stream is continued
+const CONTINUE_PROMPT: &str =
+    "Your previous answer was cut off. Continue exactly where it stopped without repeating it.";
+
+/// State of a chat stream with a [`ResumePolicy`] other than `Off`
+struct ChatResumeState {
+    client: CloudAIClient,
+    agent_access_id: String,
+    request: ChatCompletionRequest,
+    idle_timeout: Duration,
+    /// Content of the first choice received so far, over all attempts
+    partial_text: String,
+    attempts_left: u32,
+    /// Current connection; `None` once the stream has ended
+    inner: Option<TwcStream<ChatCompletionStreamResponse>>,
+}
+
+/// Next chunk of a resumable chat stream, re-issuing the request or ending
+/// with [`TwcError::StreamInterrupted`] when the connection drops
+async fn next_chat_resumable(
+    mut state: ChatResumeState,
+) -> Option<(Result<ChatCompletionStreamResponse>, ChatResumeState)> {
+    loop {
+        let mut error = match state.inner.as_mut()?.next().await {
+            None => return None,
+            Some(Ok(chunk)) => {
+                let content = chunk
+                    .choices
+                    .iter()
+                    .find(|choice| choice.index == 0)
+                    .and_then(|choice| choice.delta.content.as_deref());
+                if let Some(content) = content {
+                    state.partial_text.push_str(content);
+                }
+                return Some((Ok(chunk), state));
+            }
+            Some(Err(e)) if !e.is_broken_connection() => {
+                return Some((Err(e), state));
+            }
+            Some(Err(e)) => e,
+        };
+
+        state.inner = None;
+        while state.attempts_left > 0 && state.inner.is_none() {
+            state.attempts_left -= 1;
+            let mut request = state.request.clone();
+            request
+                .messages
+                .push(ChatMessage::assistant(state.partial_text.clone()));
+            request.messages.push(ChatMessage::user(CONTINUE_PROMPT));
+            match open_chat_stream(
+                &state.client,
+                &state.agent_access_id,
+                &request,
+                state.idle_timeout,
+            )
+            .await
+            {
+                Ok(inner) => state.inner = Some(inner),
+                Err(e) => error = e,
+            }
+        }
+
+        if state.inner.is_none() {
+            let error = TwcError::StreamInterrupted {
+                partial_text: std::mem::take(&mut state.partial_text),
+                source: Box::new(error),
+            };
+            return Some((Err(error), state));
+        }
+    }
 }
 
-/// Log probabilities for text completion
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
-pub struct TextCompletionLogprobs {
-    /// Tokens chosen by the model
-    pub tokens: Vec<String>,
-    /// Log probability of each token
-    pub token_logprobs: Vec<f32>,
-    /// Top logprobs for each token
-    pub top_logprobs: serde_json::Value,
-    /// Character offsets for each token
-    pub text_offset: Vec<u32>,
+/// The request with its token limit moved to the other field, when `error`
+/// rejected the field the limit was sent in
+fn token_limit_fallback(
+    request: &ChatCompletionRequest,
+    error: &TwcError,
+) -> Option<ChatCompletionRequest> {
+    let TwcError::InvalidRequest(message) = error.inner() else {
+        return None;
+    };
+    let (rejected, replacement) = match (request.max_tokens, request.max_completion_tokens) {
+        (Some(_), None) => ("max_tokens", "max_completion_tokens"),
+        (None, Some(_)) => ("max_completion_tokens", "max_tokens"),
+        _ => return None,
+    };
+    if !names_field(message, rejected) {
+        return None;
+    }
+
+    // Exactly one of the fields is set, so swapping them moves the limit
+    let mut retry = request.clone();
+    retry.max_tokens = request.max_completion_tokens;
+    retry.max_completion_tokens = request.max_tokens;
+    crate::logging::token_limit_fallback(rejected, replacement);
+    Some(retry)
 }
 
-/// Choice in text completion response
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
-pub struct TextCompletionChoice {
-    /// The generated text
-    pub text: String,
-    /// The index of this choice
-    pub index: u32,
-    /// Log probability information
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub logprobs: Option<TextCompletionLogprobs>,
-    /// The reason the model stopped generating tokens
-    pub finish_reason: String,
+/// Whether `message` mentions `field` as a whole word
+///
+/// `max_tokens` must not match inside e.g. `max_tokens_per_minute`.
+fn names_field(message: &str, field: &str) -> bool {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    message.match_indices(field).any(|(start, _)| {
+        let before = message[..start].chars().next_back();
+        let after = message[start + field.len()..].chars().next();
+        !before.is_some_and(is_word) && !after.is_some_and(is_word)
+    })
 }
 
-/// Token usage for text completion
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
-pub struct TextCompletionUsage {
-    /// Number of tokens in the prompt
-    pub prompt_tokens: u32,
-    /// Number of tokens in the generated completion
-    pub completion_tokens: u32,
-    /// Total number of tokens used
-    pub total_tokens: u32,
+/// Choice in a streamed text completion chunk (wire format)
+#[derive(Debug, Clone, serde::Deserialize)]
+struct TextCompletionStreamChoice {
+    text: String,
+    index: u32,
+    #[serde(default)]
+    logprobs: Option<types::TextCompletionLogprobs>,
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
-/// Text completion response (legacy)
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
-pub struct TextCompletionResponse {
-    /// Unique identifier for the completion
-    pub id: String,
-    /// Object type - always "text_completion"
-    pub object: String,
-    /// Unix timestamp when the completion was created
-    pub created: i64,
-    /// The model used for completion
-    pub model: String,
-    /// Array of completion choices
-    pub choices: Vec<TextCompletionChoice>,
-    /// Usage statistics for the completion
-    pub usage: TextCompletionUsage,
+/// Streamed text completion payload (wire format)
+#[derive(Debug, Clone, serde::Deserialize)]
+struct TextCompletionStreamResponse {
+    choices: Vec<TextCompletionStreamChoice>,
 }
+
+/// Moved to [`types::TextCompletionRequest`]
+#[deprecated(since = "0.1.2", note = "Use twcai::types::TextCompletionRequest")]
+pub type TextCompletionRequest = types::TextCompletionRequest;
+
+/// Moved to [`types::TextCompletionResponse`]
+#[deprecated(since = "0.1.2", note = "Use twcai::types::TextCompletionResponse")]
+pub type TextCompletionResponse = types::TextCompletionResponse;
+
+/// Moved to [`types::TextCompletionChoice`]
+#[deprecated(since = "0.1.2", note = "Use twcai::types::TextCompletionChoice")]
+pub type TextCompletionChoice = types::TextCompletionChoice;
+
+/// Moved to [`types::TextCompletionLogprobs`]
+#[deprecated(since = "0.1.2", note = "Use twcai::types::TextCompletionLogprobs")]
+pub type TextCompletionLogprobs = types::TextCompletionLogprobs;
+
+/// Moved to [`types::TextCompletionUsage`]
+#[deprecated(since = "0.1.2", note = "Use twcai::types::TextCompletionUsage")]
+pub type TextCompletionUsage = types::TextCompletionUsage;
+
+/// Moved to [`types::TextCompletionChunk`]
+#[deprecated(since = "0.1.2", note = "Use twcai::types::TextCompletionChunk")]
+pub type TextCompletionChunk = types::TextCompletionChunk;
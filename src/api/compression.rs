@@ -0,0 +1,54 @@
+//! Optional gzip compression of JSON request bodies
+
+use std::io::Write;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use reqwest::header::{CONTENT_ENCODING, CONTENT_TYPE};
+use reqwest::{RequestBuilder, StatusCode};
+
+use crate::{ClientConfig, Result, TwcError};
+
+/// Bodies smaller than this are always sent uncompressed
+pub(crate) const COMPRESSION_THRESHOLD: usize = 64 * 1024;
+
+/// Attach serialized JSON, gzipping it when compression is enabled and it
+/// exceeds [`COMPRESSION_THRESHOLD`]
+///
+/// Returns the builder and whether the body was compressed.
+pub(crate) fn json_body(
+    config: &ClientConfig,
+    builder: RequestBuilder,
+    json: Vec<u8>,
+) -> Result<(RequestBuilder, bool)> {
+    let builder = builder.header(CONTENT_TYPE, "application/json");
+
+    if !config.compress_requests || json.len() <= COMPRESSION_THRESHOLD {
+        return Ok((builder.body(json), false));
+    }
+
+    let mut encoder = GzEncoder::new(Vec::with_capacity(json.len() / 4), Compression::default());
+    encoder.write_all(&json).map_err(compression_error)?;
+    let compressed = encoder.finish().map_err(compression_error)?;
+
+    Ok((
+        builder.header(CONTENT_ENCODING, "gzip").body(compressed),
+        true,
+    ))
+}
+
+fn compression_error(e: std::io::Error) -> TwcError {
+    TwcError::InvalidRequest(format!("Failed to compress request body: {}", e))
+}
+
+/// Translate a 415 on a compressed request into an actionable error
+pub(crate) fn check_unsupported_encoding(status: StatusCode, compressed: bool) -> Result<()> {
+    if compressed && status == StatusCode::UNSUPPORTED_MEDIA_TYPE {
+        return Err(TwcError::InvalidRequest(
+            "Server rejected the gzip-compressed request body (415); \
+             disable compress_requests on the client builder"
+                .to_string(),
+        ));
+    }
+    Ok(())
+}
@@ -6,16 +6,30 @@
 //! - Updating conversations
 //! - Deleting conversations
 //! - Managing conversation items
+//! - Exporting conversations to JSON or Markdown
+//! - Importing conversations from exports or message arrays
+//! - Sending a user message and getting the agent reply in one call
+//! - Finding or creating the conversation of an external key
 
-use reqwest::header::AUTHORIZATION;
+use reqwest::Method;
+use serde_json::Value;
 
+use super::endpoint::Endpoint;
+use super::http::{RequestSpec, send_json};
+use super::responses::ResponsesExt;
 use crate::{
+    CloudAIClient, ConversationIndex, RequestOptions, Result, TwcError,
+    export::{self, ExportFormat, ImportSource},
+    pagination::{Page, Paginator},
     types::*,
-    CloudAIClient,
-    Result,
-    TwcError,
 };
 
+/// Page size used when walking every item of a conversation
+const EXPORT_PAGE_SIZE: u32 = 100;
+
+/// Maximum number of items accepted by a single create request
+const MAX_ITEMS_PER_REQUEST: usize = CreateItemsRequest::MAX_ITEMS;
+
 /// Extension trait for conversations API operations
 pub trait ConversationsExt {
     /// Create a new conversation
@@ -27,6 +41,15 @@ pub trait ConversationsExt {
         request: CreateConversationRequest,
     ) -> impl std::future::Future<Output = Result<Conversation>> + Send;
 
+    /// [`create_conversation`](Self::create_conversation) with per-call
+    /// options, e.g. an idempotency key
+    fn create_conversation_with_options(
+        &self,
+        agent_access_id: &str,
+        request: CreateConversationRequest,
+        options: RequestOptions,
+    ) -> impl std::future::Future<Output = Result<Conversation>> + Send;
+
     /// Get an existing conversation
     ///
     /// GET /api/v1/cloud-ai/agents/{agent_access_id}/v1/conversations/{conversation_id}
@@ -65,6 +88,32 @@ pub trait ConversationsExt {
         query: Option<ListItemsQuery>,
     ) -> impl std::future::Future<Output = Result<ConversationItemList>> + Send;
 
+    /// List items in a conversation as a [`Page`]
+    ///
+    /// Same request as [`list_conversation_items`](Self::list_conversation_items),
+    /// with the cursor of the next page resolved.
+    fn list_conversation_items_page(
+        &self,
+        agent_access_id: &str,
+        conversation_id: &str,
+        query: Option<ListItemsQuery>,
+    ) -> impl std::future::Future<Output = Result<Page<ConversationItem>>> + Send;
+
+    /// Stream every item of a conversation, fetching pages as needed
+    ///
+    /// `query.after` sets the starting point; later pages continue from the
+    /// previous page's cursor. Items come oldest first unless `query.order`
+    /// says otherwise; the order is always sent rather than left to the
+    /// server default. Items seen on an earlier page are skipped, so
+    /// messages appended while the stream runs cannot repeat an item; call
+    /// [`Paginator::snapshot`] to also leave those messages out.
+    fn stream_conversation_items(
+        &self,
+        agent_access_id: &str,
+        conversation_id: &str,
+        query: Option<ListItemsQuery>,
+    ) -> Paginator<ConversationItem>;
+
     /// Create items in a conversation
     ///
     /// POST /api/v1/cloud-ai/agents/{agent_access_id}/v1/conversations/{conversation_id}/items
@@ -96,6 +145,72 @@ pub trait ConversationsExt {
         conversation_id: &str,
         item_id: &str,
     ) -> impl std::future::Future<Output = Result<Conversation>> + Send;
+
+    /// Export a conversation with all of its items
+    ///
+    /// Follows item pagination oldest-first and renders either a stable JSON
+    /// document or a Markdown transcript (see [`ExportFormat`]). Items and
+    /// content parts this crate does not model are kept as raw JSON.
+    fn export_conversation(
+        &self,
+        agent_access_id: &str,
+        conversation_id: &str,
+        format: ExportFormat,
+    ) -> impl std::future::Future<Output = Result<String>> + Send;
+
+    /// Import a conversation from an export document or a messages array
+    ///
+    /// The first 20 items are sent with the conversation, the rest are added
+    /// in chunks of 20. `system` and `developer` messages are merged into a
+    /// single leading `system` item. The source is validated up front: every
+    /// malformed entry is listed by index in one `InvalidRequest` error and
+    /// nothing is created.
+    fn import_conversation(
+        &self,
+        agent_access_id: &str,
+        source: ImportSource,
+    ) -> impl std::future::Future<Output = Result<Conversation>> + Send;
+
+    /// Append a user message to a conversation and get the agent reply
+    ///
+    /// Creates a response bound to the conversation, so the user message and
+    /// the reply are both stored in it.
+    fn send_to_conversation(
+        &self,
+        agent_access_id: &str,
+        conversation_id: &str,
+        text: &str,
+    ) -> impl std::future::Future<Output = Result<ConversationTurn>> + Send;
+
+    /// Get the conversation `index` maps `key` to, or create one from `init`
+    ///
+    /// `key` is a metadata key and value, such as an external ticket id. A
+    /// created conversation gets it stamped on its metadata and is stored in
+    /// `index`; an indexed conversation that no longer exists is replaced.
+    /// Calls for the same agent and key through clones of one client run one
+    /// at a time, so concurrent deliveries of a webhook create a single
+    /// conversation.
+    fn find_or_create_conversation<I: ConversationIndex>(
+        &self,
+        agent_access_id: &str,
+        key: (&str, &str),
+        init: CreateConversationRequest,
+        index: &I,
+    ) -> impl std::future::Future<Output = Result<Conversation>> + Send;
+
+    /// [`find_or_create_conversation`](Self::find_or_create_conversation)
+    /// with per-call options
+    ///
+    /// The request id is sent with the lookup and the create; an
+    /// idempotency key only with the create.
+    fn find_or_create_conversation_with_options<I: ConversationIndex>(
+        &self,
+        agent_access_id: &str,
+        key: (&str, &str),
+        init: CreateConversationRequest,
+        index: &I,
+        options: RequestOptions,
+    ) -> impl std::future::Future<Output = Result<Conversation>> + Send;
 }
 
 impl ConversationsExt for CloudAIClient {
@@ -104,23 +219,21 @@ impl ConversationsExt for CloudAIClient {
         agent_access_id: &str,
         request: CreateConversationRequest,
     ) -> Result<Conversation> {
-        let url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/v1/conversations",
-            self.config.base_url,
-            agent_access_id
-        );
-
-        let response = self
-            .config
-            .http_client
-            .post(&url)
-            .header(AUTHORIZATION, self.config.auth_header())
-            .json(&request)
-            .send()
+        self.create_conversation_with_options(agent_access_id, request, RequestOptions::default())
             .await
-            .map_err(TwcError::Http)?;
+    }
 
-        handle_response(response).await
+    async fn create_conversation_with_options(
+        &self,
+        agent_access_id: &str,
+        request: CreateConversationRequest,
+        options: RequestOptions,
+    ) -> Result<Conversation> {
+        let spec = RequestSpec::agent(Method::POST, agent_access_id, Endpoint::Conversations)
+            .json(&request)?
+            .options(&options);
+
+        send_json(&self.config, spec).await
     }
 
     async fn get_conversation(
@@ -128,23 +241,13 @@ impl ConversationsExt for CloudAIClient {
         agent_access_id: &str,
         conversation_id: &str,
     ) -> Result<Conversation> {
-        let url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/v1/conversations/{}",
-            self.config.base_url,
+        let spec = RequestSpec::agent(
+            Method::GET,
             agent_access_id,
-            conversation_id
+            Endpoint::Conversation(conversation_id),
         );
 
-        let response = self
-            .config
-            .http_client
-            .get(&url)
-            .header(AUTHORIZATION, self.config.auth_header())
-            .send()
-            .await
-            .map_err(TwcError::Http)?;
-
-        handle_response(response).await
+        send_json(&self.config, spec).await
     }
 
     async fn update_conversation(
@@ -153,24 +256,14 @@ impl ConversationsExt for CloudAIClient {
         conversation_id: &str,
         request: UpdateConversationRequest,
     ) -> Result<Conversation> {
-        let url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/v1/conversations/{}",
-            self.config.base_url,
+        let spec = RequestSpec::agent(
+            Method::POST,
             agent_access_id,
-            conversation_id
-        );
-
-        let response = self
-            .config
-            .http_client
-            .post(&url)
-            .header(AUTHORIZATION, self.config.auth_header())
-            .json(&request)
-            .send()
-            .await
-            .map_err(TwcError::Http)?;
+            Endpoint::Conversation(conversation_id),
+        )
+        .json(&request)?;
 
-        handle_response(response).await
+        send_json(&self.config, spec).await
     }
 
     async fn delete_conversation(
@@ -178,23 +271,13 @@ impl ConversationsExt for CloudAIClient {
         agent_access_id: &str,
         conversation_id: &str,
     ) -> Result<ConversationDeleted> {
-        let url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/v1/conversations/{}",
-            self.config.base_url,
+        let spec = RequestSpec::agent(
+            Method::DELETE,
             agent_access_id,
-            conversation_id
+            Endpoint::Conversation(conversation_id),
         );
 
-        let response = self
-            .config
-            .http_client
-            .delete(&url)
-            .header(AUTHORIZATION, self.config.auth_header())
-            .send()
-            .await
-            .map_err(TwcError::Http)?;
-
-        handle_response(response).await
+        send_json(&self.config, spec).await
     }
 
     async fn list_conversation_items(
@@ -203,32 +286,54 @@ impl ConversationsExt for CloudAIClient {
         conversation_id: &str,
         query: Option<ListItemsQuery>,
     ) -> Result<ConversationItemList> {
-        let mut url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/v1/conversations/{}/items",
-            self.config.base_url,
+        let spec = RequestSpec::agent(
+            Method::GET,
             agent_access_id,
-            conversation_id
-        );
+            Endpoint::ConversationItems(conversation_id),
+        )
+        .query(&query)?;
 
-        if let Some(q) = query {
-            let query_string = serde_urlencoded::to_string(&q)
-                .map_err(|e| TwcError::InvalidRequest(e.to_string()))?;
-            if !query_string.is_empty() {
-                url.push('?');
-                url.push_str(&query_string);
-            }
-        }
+        send_json(&self.config, spec).await
+    }
 
-        let response = self
-            .config
-            .http_client
-            .get(&url)
-            .header(AUTHORIZATION, self.config.auth_header())
-            .send()
+    async fn list_conversation_items_page(
+        &self,
+        agent_access_id: &str,
+        conversation_id: &str,
+        query: Option<ListItemsQuery>,
+    ) -> Result<Page<ConversationItem>> {
+        self.list_conversation_items(agent_access_id, conversation_id, query)
             .await
-            .map_err(TwcError::Http)?;
+            .map(Page::from)
+    }
 
-        handle_response(response).await
+    fn stream_conversation_items(
+        &self,
+        agent_access_id: &str,
+        conversation_id: &str,
+        query: Option<ListItemsQuery>,
+    ) -> Paginator<ConversationItem> {
+        let client = self.clone();
+        let agent_access_id = agent_access_id.to_string();
+        let conversation_id = conversation_id.to_string();
+        let mut query = query.unwrap_or_default();
+        query.order.get_or_insert_with(|| "asc".to_string());
+
+        Paginator::new(move |cursor| {
+            let client = client.clone();
+            let agent_access_id = agent_access_id.clone();
+            let conversation_id = conversation_id.clone();
+            let query = ListItemsQuery {
+                after: cursor.or_else(|| query.after.clone()),
+                ..query.clone()
+            };
+            async move {
+                client
+                    .list_conversation_items_page(&agent_access_id, &conversation_id, Some(query))
+                    .await
+            }
+        })
+        .dedup_by(|item| item.id.clone())
     }
 
     async fn create_conversation_items(
@@ -238,33 +343,15 @@ impl ConversationsExt for CloudAIClient {
         request: CreateItemsRequest,
         query: Option<CreateItemsQuery>,
     ) -> Result<ConversationItemList> {
-        let mut url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/v1/conversations/{}/items",
-            self.config.base_url,
+        let spec = RequestSpec::agent(
+            Method::POST,
             agent_access_id,
-            conversation_id
-        );
+            Endpoint::ConversationItems(conversation_id),
+        )
+        .query(&query)?
+        .json(&request)?;
 
-        if let Some(q) = query {
-            let query_string = serde_urlencoded::to_string(&q)
-                .map_err(|e| TwcError::InvalidRequest(e.to_string()))?;
-            if !query_string.is_empty() {
-                url.push('?');
-                url.push_str(&query_string);
-            }
-        }
-
-        let response = self
-            .config
-            .http_client
-            .post(&url)
-            .header(AUTHORIZATION, self.config.auth_header())
-            .json(&request)
-            .send()
-            .await
-            .map_err(TwcError::Http)?;
-
-        handle_response(response).await
+        send_json(&self.config, spec).await
     }
 
     async fn get_conversation_item(
@@ -274,33 +361,14 @@ impl ConversationsExt for CloudAIClient {
         item_id: &str,
         query: Option<GetItemQuery>,
     ) -> Result<ConversationItem> {
-        let mut url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/v1/conversations/{}/items/{}",
-            self.config.base_url,
+        let spec = RequestSpec::agent(
+            Method::GET,
             agent_access_id,
-            conversation_id,
-            item_id
-        );
-
-        if let Some(q) = query {
-            let query_string = serde_urlencoded::to_string(&q)
-                .map_err(|e| TwcError::InvalidRequest(e.to_string()))?;
-            if !query_string.is_empty() {
-                url.push('?');
-                url.push_str(&query_string);
-            }
-        }
-
-        let response = self
-            .config
-            .http_client
-            .get(&url)
-            .header(AUTHORIZATION, self.config.auth_header())
-            .send()
-            .await
-            .map_err(TwcError::Http)?;
+            Endpoint::ConversationItem(conversation_id, item_id),
+        )
+        .query(&query)?;
 
-        handle_response(response).await
+        send_json(&self.config, spec).await
     }
 
     async fn delete_conversation_item(
@@ -309,37 +377,196 @@ impl ConversationsExt for CloudAIClient {
         conversation_id: &str,
         item_id: &str,
     ) -> Result<Conversation> {
-        let url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/v1/conversations/{}/items/{}",
-            self.config.base_url,
+        let spec = RequestSpec::agent(
+            Method::DELETE,
             agent_access_id,
-            conversation_id,
-            item_id
+            Endpoint::ConversationItem(conversation_id, item_id),
         );
 
-        let response = self
+        send_json(&self.config, spec).await
+    }
+
+    async fn export_conversation(
+        &self,
+        agent_access_id: &str,
+        conversation_id: &str,
+        format: ExportFormat,
+    ) -> Result<String> {
+        let conversation = self
+            .get_conversation(agent_access_id, conversation_id)
+            .await?;
+        let items = list_all_items_raw(self, agent_access_id, conversation_id).await?;
+        export::render(&conversation, &items, format)
+    }
+
+    async fn import_conversation(
+        &self,
+        agent_access_id: &str,
+        source: ImportSource,
+    ) -> Result<Conversation> {
+        let plan = export::prepare_import(source)?;
+        let mut items = plan.items.into_iter();
+
+        let initial: Vec<_> = items.by_ref().take(MAX_ITEMS_PER_REQUEST).collect();
+        let request = CreateConversationRequest {
+            items: (!initial.is_empty()).then_some(initial),
+            metadata: plan.metadata,
+        };
+        let conversation = self.create_conversation(agent_access_id, request).await?;
+
+        let remaining: Vec<CreateItemRequest> = items
+            .map(|item| CreateItemRequest {
+                item_type: item.item_type,
+                role: item.role,
+                content: item.content,
+            })
+            .collect();
+
+        for chunk in remaining.chunks(MAX_ITEMS_PER_REQUEST) {
+            let request = CreateItemsRequest {
+                items: chunk.to_vec(),
+            };
+            self.create_conversation_items(agent_access_id, &conversation.id, request, None)
+                .await?;
+        }
+
+        Ok(conversation)
+    }
+
+    async fn send_to_conversation(
+        &self,
+        agent_access_id: &str,
+        conversation_id: &str,
+        text: &str,
+    ) -> Result<ConversationTurn> {
+        let request = CreateResponseRequest {
+            input: Some(ResponseInput::Text(text.to_string())),
+            conversation: Some(conversation_id.into()),
+            ..Default::default()
+        };
+
+        let response = self.create_response(agent_access_id, request).await?;
+
+        Ok(ConversationTurn {
+            items: response.output_items().to_vec(),
+            text: response.output_text(),
+            response,
+        })
+    }
+
+    async fn find_or_create_conversation<I: ConversationIndex>(
+        &self,
+        agent_access_id: &str,
+        key: (&str, &str),
+        init: CreateConversationRequest,
+        index: &I,
+    ) -> Result<Conversation> {
+        self.find_or_create_conversation_with_options(
+            agent_access_id,
+            key,
+            init,
+            index,
+            RequestOptions::default(),
+        )
+        .await
+    }
+
+    async fn find_or_create_conversation_with_options<I: ConversationIndex>(
+        &self,
+        agent_access_id: &str,
+        key: (&str, &str),
+        mut init: CreateConversationRequest,
+        index: &I,
+        options: RequestOptions,
+    ) -> Result<Conversation> {
+        let (key, value) = key;
+        let _guard = self
             .config
-            .http_client
-            .delete(&url)
-            .header(AUTHORIZATION, self.config.auth_header())
-            .send()
-            .await
-            .map_err(TwcError::Http)?;
+            .conversation_locks
+            .lock(format!("{}\0{}\0{}", agent_access_id, key, value))
+            .await;
+
+        if let Some(conversation_id) = index.lookup(key, value).await? {
+            let lookup = RequestOptions {
+                request_id: options.request_id.clone(),
+                ..Default::default()
+            };
+            let spec = RequestSpec::agent(
+                Method::GET,
+                agent_access_id,
+                Endpoint::Conversation(&conversation_id),
+            )
+            .options(&lookup);
+            match send_json(&self.config, spec).await {
+                Err(e) if matches!(e.inner(), TwcError::NotFound(_)) => {}
+                found => return found,
+            }
+        }
+
+        match init
+            .metadata
+            .get_or_insert_with(|| Value::Object(Default::default()))
+        {
+            Value::Object(metadata) => {
+                metadata.insert(key.to_string(), Value::String(value.to_string()));
+            }
+            _ => {
+                return Err(TwcError::InvalidRequest(
+                    "conversation metadata must be an object".to_string(),
+                ));
+            }
+        }
 
-        handle_response(response).await
+        let conversation = self
+            .create_conversation_with_options(agent_access_id, init, options)
+            .await?;
+        index.store(key, value, &conversation.id).await?;
+        Ok(conversation)
     }
 }
 
-/// Handle HTTP response and parse JSON or return appropriate error
-async fn handle_response<T: serde::de::DeserializeOwned>(
-    response: reqwest::Response,
-) -> Result<T> {
-    let status = response.status();
-
-    if status.is_success() {
-        response.json::<T>().await.map_err(TwcError::Http)
-    } else {
-        let text = response.text().await.ok();
-        Err(TwcError::from_status(status, text))
+/// Fetch every item of a conversation, oldest first, as raw JSON
+async fn list_all_items_raw(
+    client: &CloudAIClient,
+    agent_access_id: &str,
+    conversation_id: &str,
+) -> Result<Vec<Value>> {
+    let mut items = Vec::new();
+    let mut after: Option<String> = None;
+
+    loop {
+        let query = ListItemsQuery {
+            after: after.take(),
+            limit: Some(EXPORT_PAGE_SIZE),
+            order: Some("asc".to_string()),
+            ..Default::default()
+        };
+        let spec = RequestSpec::agent(
+            Method::GET,
+            agent_access_id,
+            Endpoint::ConversationItems(conversation_id),
+        )
+        .query(&query)?;
+        let mut page: Value = send_json(&client.config, spec).await?;
+
+        let has_more = page
+            .get("has_more")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let last_id = page
+            .get("last_id")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+
+        if let Some(Value::Array(data)) = page.get_mut("data").map(Value::take) {
+            items.extend(data);
+        }
+
+        match last_id {
+            Some(last_id) if has_more && !last_id.is_empty() => after = Some(last_id),
+            _ => break,
+        }
     }
+
+    Ok(items)
 }
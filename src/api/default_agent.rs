@@ -0,0 +1,71 @@
+//! Agent calls without an explicit agent id
+//!
+//! Provides the most common agent operations against the client's default
+//! agent, set with `ClientBuilder::default_agent` or `TWCAI_AGENT_ID`.
+
+use super::{AgentClientExt, ResponsesExt};
+use crate::{CloudAIClient, Result, TwcError, stream::TwcStream, types::*};
+
+/// Extension trait for operations on the client's default agent
+///
+/// Every method fails with [`TwcError::Configuration`] when no default agent
+/// is configured.
+pub trait DefaultAgentExt {
+    /// [`call_agent`](AgentClientExt::call_agent) on the default agent
+    fn call(
+        &self,
+        request: AgentCallRequest,
+    ) -> impl std::future::Future<Output = Result<AgentCallResponse>> + Send;
+
+    /// [`chat_completions`](AgentClientExt::chat_completions) on the default agent
+    fn chat(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> impl std::future::Future<Output = Result<ChatCompletionResponse>> + Send;
+
+    /// [`chat_completions_stream`](AgentClientExt::chat_completions_stream)
+    /// on the default agent
+    fn chat_stream(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> impl std::future::Future<Output = Result<TwcStream<ChatCompletionStreamResponse>>> + Send;
+
+    /// [`create_response`](ResponsesExt::create_response) on the default agent
+    fn respond(
+        &self,
+        request: CreateResponseRequest,
+    ) -> impl std::future::Future<Output = Result<Response>> + Send;
+}
+
+impl DefaultAgentExt for CloudAIClient {
+    async fn call(&self, request: AgentCallRequest) -> Result<AgentCallResponse> {
+        self.call_agent(require_default_agent(self)?, request).await
+    }
+
+    async fn chat(&self, request: ChatCompletionRequest) -> Result<ChatCompletionResponse> {
+        self.chat_completions(require_default_agent(self)?, request)
+            .await
+    }
+
+    async fn chat_stream(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<TwcStream<ChatCompletionStreamResponse>> {
+        self.chat_completions_stream(require_default_agent(self)?, request)
+            .await
+    }
+
+    async fn respond(&self, request: CreateResponseRequest) -> Result<Response> {
+        self.create_response(require_default_agent(self)?, request)
+            .await
+    }
+}
+
+fn require_default_agent(client: &CloudAIClient) -> Result<&str> {
+    client.default_agent().ok_or_else(|| {
+        TwcError::Configuration(
+            "No default agent configured; set TWCAI_AGENT_ID or use ClientBuilder::default_agent"
+                .to_string(),
+        )
+    })
+}
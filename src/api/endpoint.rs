@@ -0,0 +1,160 @@
+//! Routes of the API endpoints
+//!
+//! Every request path is rendered from an [`Endpoint`], so path segments,
+//! id encoding and the join with the configured base URLs live in one place.
+
+use std::borrow::Cow;
+use std::fmt::Write;
+
+use crate::ClientConfig;
+
+/// Prefix of the agent API below the base URL
+pub(crate) const AGENTS_PREFIX: &str = "/api/v1/cloud-ai/agents";
+
+/// An API route with its path parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Endpoint<'a> {
+    /// `/call` of an agent
+    Call,
+    /// `/v1/chat/completions` of an agent
+    ChatCompletions,
+    /// `/v1/completions` of an agent
+    TextCompletions,
+    /// `/v1/models` of an agent
+    Models,
+    /// `/embed.js` of an agent
+    EmbedScript,
+    /// `/usage` of an agent
+    Usage,
+    /// `/v1/conversations` of an agent
+    Conversations,
+    /// `/v1/conversations/{conversation_id}` of an agent
+    Conversation(&'a str),
+    /// `/v1/conversations/{conversation_id}/items` of an agent
+    ConversationItems(&'a str),
+    /// `/v1/conversations/{conversation_id}/items/{item_id}` of an agent
+    ConversationItem(&'a str, &'a str),
+    /// `/v1/responses` of an agent
+    Responses,
+    /// `/v1/responses/{response_id}` of an agent
+    Response(&'a str),
+    /// `/v1/responses/{response_id}/input_items` of an agent
+    ResponseInputItems(&'a str),
+    /// `/v1/responses/{response_id}/cancel` of an agent
+    ResponseCancel(&'a str),
+    /// `/v1/files` of an agent
+    Files,
+    /// `/v1/files/{file_id}` of an agent
+    File(&'a str),
+    /// `/agents` of the control-plane API
+    Agents,
+    /// `/agents/{agent_id}` of the control-plane API
+    Agent(u64),
+    /// `/knowledge-bases/{knowledge_base_id}/documents` of the control-plane API
+    KnowledgeDocuments(u64),
+    /// `/knowledge-bases/{knowledge_base_id}/documents/{document_id}` of the
+    /// control-plane API
+    KnowledgeDocument(u64, u64),
+}
+
+impl Endpoint<'_> {
+    /// Whether the route belongs to the control-plane API rather than to an
+    /// agent
+    pub(crate) fn is_control_plane(&self) -> bool {
+        matches!(
+            self,
+            Endpoint::Agents
+                | Endpoint::Agent(_)
+                | Endpoint::KnowledgeDocuments(_)
+                | Endpoint::KnowledgeDocument(..)
+        )
+    }
+
+    /// Path below the agent or the control-plane prefix, ids percent-encoded;
+    /// routes without parameters are not allocated
+    pub(crate) fn path(&self) -> Cow<'static, str> {
+        let path = match *self {
+            Endpoint::Call => return Cow::Borrowed("/call"),
+            Endpoint::ChatCompletions => return Cow::Borrowed("/v1/chat/completions"),
+            Endpoint::TextCompletions => return Cow::Borrowed("/v1/completions"),
+            Endpoint::Models => return Cow::Borrowed("/v1/models"),
+            Endpoint::EmbedScript => return Cow::Borrowed("/embed.js"),
+            Endpoint::Usage => return Cow::Borrowed("/usage"),
+            Endpoint::Conversations => return Cow::Borrowed("/v1/conversations"),
+            Endpoint::Responses => return Cow::Borrowed("/v1/responses"),
+            Endpoint::Files => return Cow::Borrowed("/v1/files"),
+            Endpoint::Agents => return Cow::Borrowed("/agents"),
+            Endpoint::Conversation(conversation_id) => {
+                format!("/v1/conversations/{}", encode_segment(conversation_id))
+            }
+            Endpoint::ConversationItems(conversation_id) => {
+                format!(
+                    "/v1/conversations/{}/items",
+                    encode_segment(conversation_id)
+                )
+            }
+            Endpoint::ConversationItem(conversation_id, item_id) => format!(
+                "/v1/conversations/{}/items/{}",
+                encode_segment(conversation_id),
+                encode_segment(item_id)
+            ),
+            Endpoint::Response(response_id) => {
+                format!("/v1/responses/{}", encode_segment(response_id))
+            }
+            Endpoint::ResponseInputItems(response_id) => {
+                format!("/v1/responses/{}/input_items", encode_segment(response_id))
+            }
+            Endpoint::ResponseCancel(response_id) => {
+                format!("/v1/responses/{}/cancel", encode_segment(response_id))
+            }
+            Endpoint::File(file_id) => format!("/v1/files/{}", encode_segment(file_id)),
+            Endpoint::Agent(agent_id) => format!("/agents/{}", agent_id),
+            Endpoint::KnowledgeDocuments(knowledge_base_id) => {
+                format!("/knowledge-bases/{}/documents", knowledge_base_id)
+            }
+            Endpoint::KnowledgeDocument(knowledge_base_id, document_id) => format!(
+                "/knowledge-bases/{}/documents/{}",
+                knowledge_base_id, document_id
+            ),
+        };
+        Cow::Owned(path)
+    }
+}
+
+/// Length of the agent URL of `path` when no byte of `agent_access_id`
+/// needs encoding
+pub(crate) fn agent_url_len(config: &ClientConfig, agent_access_id: &str, path: &str) -> usize {
+    config.agents_url.len() + 1 + agent_access_id.len() + path.len()
+}
+
+/// Append the URL of `path` below the agent API of `agent_access_id` to `url`
+pub(crate) fn push_agent_url(
+    url: &mut String,
+    config: &ClientConfig,
+    agent_access_id: &str,
+    path: &str,
+) {
+    url.push_str(&config.agents_url);
+    url.push('/');
+    push_encoded_segment(url, agent_access_id);
+    url.push_str(path);
+}
+
+/// Percent-encode every byte of `segment` outside the URI unreserved set,
+/// so ids cannot add path segments, queries or fragments
+fn encode_segment(segment: &str) -> String {
+    let mut encoded = String::with_capacity(segment.len());
+    push_encoded_segment(&mut encoded, segment);
+    encoded
+}
+
+/// Append `segment` to `url`, encoded as [`encode_segment`] does
+fn push_encoded_segment(url: &mut String, segment: &str) {
+    for byte in segment.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            url.push(char::from(byte));
+        } else {
+            let _ = write!(url, "%{:02X}", byte);
+        }
+    }
+}
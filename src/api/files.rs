@@ -0,0 +1,140 @@
+//! AI Agents Files API endpoints (OpenAI-compatible)
+//!
+//! Provides methods for:
+//! - Uploading files (buffered or streamed from an `AsyncRead`)
+//! - Listing files
+//! - Getting files
+//! - Deleting files
+
+use reqwest::Method;
+use reqwest::multipart::{Form, Part};
+use tokio::io::AsyncRead;
+use tokio_util::io::ReaderStream;
+
+use super::endpoint::Endpoint;
+use super::http::{RequestSpec, send_json};
+use crate::{CloudAIClient, Result, types::*};
+
+/// Extension trait for files API operations
+pub trait FilesExt {
+    /// Upload a file from memory
+    ///
+    /// POST /api/v1/cloud-ai/agents/{agent_access_id}/v1/files
+    ///
+    /// The returned ID can be passed in `AgentCallRequest::file_ids`.
+    fn upload_file(
+        &self,
+        agent_access_id: &str,
+        filename: &str,
+        bytes: Vec<u8>,
+        purpose: &str,
+    ) -> impl std::future::Future<Output = Result<FileObject>> + Send;
+
+    /// Upload a file streamed from a reader, without buffering it
+    ///
+    /// POST /api/v1/cloud-ai/agents/{agent_access_id}/v1/files
+    ///
+    /// Pass `length` when the size is known so the part carries it. The
+    /// upload is not bounded by the client's overall timeout, since large
+    /// files can legitimately take longer.
+    fn upload_file_stream<R>(
+        &self,
+        agent_access_id: &str,
+        filename: &str,
+        reader: R,
+        length: Option<u64>,
+        purpose: &str,
+    ) -> impl std::future::Future<Output = Result<FileObject>> + Send
+    where
+        R: AsyncRead + Send + Sync + 'static;
+
+    /// List uploaded files
+    ///
+    /// GET /api/v1/cloud-ai/agents/{agent_access_id}/v1/files
+    fn list_files(
+        &self,
+        agent_access_id: &str,
+    ) -> impl std::future::Future<Output = Result<FileList>> + Send;
+
+    /// Get an uploaded file
+    ///
+    /// GET /api/v1/cloud-ai/agents/{agent_access_id}/v1/files/{file_id}
+    fn get_file(
+        &self,
+        agent_access_id: &str,
+        file_id: &str,
+    ) -> impl std::future::Future<Output = Result<FileObject>> + Send;
+
+    /// Delete an uploaded file
+    ///
+    /// DELETE /api/v1/cloud-ai/agents/{agent_access_id}/v1/files/{file_id}
+    fn delete_file(
+        &self,
+        agent_access_id: &str,
+        file_id: &str,
+    ) -> impl std::future::Future<Output = Result<FileDeleted>> + Send;
+}
+
+impl FilesExt for CloudAIClient {
+    async fn upload_file(
+        &self,
+        agent_access_id: &str,
+        filename: &str,
+        bytes: Vec<u8>,
+        purpose: &str,
+    ) -> Result<FileObject> {
+        let part = Part::bytes(bytes).file_name(filename.to_string());
+        let form = Form::new()
+            .text("purpose", purpose.to_string())
+            .part("file", part);
+        let spec =
+            RequestSpec::agent(Method::POST, agent_access_id, Endpoint::Files).multipart(form);
+
+        send_json(&self.config, spec).await
+    }
+
+    async fn upload_file_stream<R>(
+        &self,
+        agent_access_id: &str,
+        filename: &str,
+        reader: R,
+        length: Option<u64>,
+        purpose: &str,
+    ) -> Result<FileObject>
+    where
+        R: AsyncRead + Send + Sync + 'static,
+    {
+        let body = reqwest::Body::wrap_stream(ReaderStream::new(reader));
+        let part = match length {
+            Some(length) => Part::stream_with_length(body, length),
+            None => Part::stream(body),
+        }
+        .file_name(filename.to_string());
+        let form = Form::new()
+            .text("purpose", purpose.to_string())
+            .part("file", part);
+        let spec = RequestSpec::agent(Method::POST, agent_access_id, Endpoint::Files)
+            .multipart(form)
+            .untimed();
+
+        send_json(&self.config, spec).await
+    }
+
+    async fn list_files(&self, agent_access_id: &str) -> Result<FileList> {
+        let spec = RequestSpec::agent(Method::GET, agent_access_id, Endpoint::Files);
+
+        send_json(&self.config, spec).await
+    }
+
+    async fn get_file(&self, agent_access_id: &str, file_id: &str) -> Result<FileObject> {
+        let spec = RequestSpec::agent(Method::GET, agent_access_id, Endpoint::File(file_id));
+
+        send_json(&self.config, spec).await
+    }
+
+    async fn delete_file(&self, agent_access_id: &str, file_id: &str) -> Result<FileDeleted> {
+        let spec = RequestSpec::agent(Method::DELETE, agent_access_id, Endpoint::File(file_id));
+
+        send_json(&self.config, spec).await
+    }
+}
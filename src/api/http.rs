@@ -0,0 +1,965 @@
+//! Shared HTTP request sending and response handling for API endpoints
+
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use bytes::{Bytes, BytesMut};
+use futures_util::StreamExt;
+use reqwest::header::{ACCEPT, CONTENT_TYPE, ETAG};
+use reqwest::multipart::Form;
+use reqwest::{Method, StatusCode};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use super::compression::{check_unsupported_encoding, json_body};
+use super::endpoint::{self, Endpoint};
+use crate::{
+    ClientConfig, RequestOptions, Result, TwcError, logging, metrics,
+    stream::{TwcStream, json_stream},
+    types::{AttemptOutcome, AttemptRecord, Download, ResponseMeta, WarmUpReport, WithMeta},
+};
+
+/// Default cap on buffered response bodies (32 MiB)
+pub(crate) const DEFAULT_MAX_RESPONSE_BYTES: usize = 32 * 1024 * 1024;
+
+/// Upper bound on the time a warm-up request may take
+const WARM_UP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Delay before the first retry; doubles with each further attempt
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(200);
+
+/// Upper bound on the delay between retries
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// Most attempts kept in a request's history
+const MAX_ATTEMPT_RECORDS: usize = 16;
+
+/// Header deduplicating repeated creates server-side
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+/// API a [`RequestSpec`] is addressed to
+#[derive(Debug, Clone)]
+enum Api {
+    /// Agent API under `/api/v1/cloud-ai/agents/{agent_access_id}`
+    Agent(String),
+    /// Control-plane API (see [`ClientConfig::control_plane_url`])
+    ControlPlane,
+}
+
+/// Description of an API request, turned into HTTP by [`send_request`]
+#[derive(Debug, Clone)]
+pub(crate) struct RequestSpec {
+    method: Method,
+    api: Api,
+    path: Cow<'static, str>,
+    query: String,
+    body: Option<Body>,
+    headers: Vec<(&'static str, String)>,
+    request_id: Option<String>,
+    authenticated: bool,
+    streaming: bool,
+    timed: bool,
+}
+
+/// Body of a [`RequestSpec`]
+#[derive(Debug, Clone)]
+enum Body {
+    /// Serialized JSON, compressed when large
+    Json(Vec<u8>),
+    /// Multipart form; a form is sent at most once, so clones of the spec
+    /// share it and only the first send gets it
+    Multipart(Arc<Mutex<Option<Form>>>),
+}
+
+impl RequestSpec {
+    /// Request to `endpoint` of the agent `agent_access_id`
+    pub(crate) fn agent(method: Method, agent_access_id: &str, endpoint: Endpoint<'_>) -> Self {
+        debug_assert!(
+            !endpoint.is_control_plane(),
+            "{:?} is not an agent route",
+            endpoint
+        );
+        Self::new(method, Api::Agent(agent_access_id.to_string()), endpoint)
+    }
+
+    /// Request to `endpoint` of the control-plane API
+    pub(crate) fn control_plane(method: Method, endpoint: Endpoint<'_>) -> Self {
+        debug_assert!(
+            endpoint.is_control_plane(),
+            "{:?} is an agent route",
+            endpoint
+        );
+        Self::new(method, Api::ControlPlane, endpoint)
+    }
+
+    fn new(method: Method, api: Api, endpoint: Endpoint<'_>) -> Self {
+        Self {
+            method,
+            api,
+            path: endpoint.path(),
+            query: String::new(),
+            body: None,
+            headers: Vec::new(),
+            request_id: None,
+            authenticated: true,
+            streaming: false,
+            timed: true,
+        }
+    }
+
+    /// Set the query string from a struct or map (see [`query_string`]);
+    /// `None` sets no query
+    pub(crate) fn query<Q: Serialize>(mut self, query: &Q) -> Result<Self> {
+        self.query = query_string(query)?;
+        Ok(self)
+    }
+
+    /// Send `body` as JSON
+    pub(crate) fn json<B: Serialize>(mut self, body: &B) -> Result<Self> {
+        self.body = Some(Body::Json(serde_json::to_vec(body)?));
+        Ok(self)
+    }
+
+    /// Send `form` as `multipart/form-data`
+    pub(crate) fn multipart(mut self, form: Form) -> Self {
+        self.body = Some(Body::Multipart(Arc::new(Mutex::new(Some(form)))));
+        self
+    }
+
+    /// Add a request header
+    pub(crate) fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
+    /// Apply per-call [`RequestOptions`]
+    pub(crate) fn options(mut self, options: &RequestOptions) -> Self {
+        if let Some(key) = &options.idempotency_key {
+            self = self.header(IDEMPOTENCY_KEY, key.clone());
+        }
+        if let Some(request_id) = &options.request_id {
+            self.request_id = Some(request_id.clone());
+        }
+        self
+    }
+
+    /// Give a POST a generated `Idempotency-Key` when retries are enabled and
+    /// none is set, so that every re-send of the spec carries the same key
+    pub(crate) fn idempotent(self, config: &ClientConfig) -> Self {
+        let keyed = self
+            .headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case(IDEMPOTENCY_KEY));
+        if config.max_retries == 0 || self.method != Method::POST || keyed {
+            return self;
+        }
+        self.header(IDEMPOTENCY_KEY, uuid::Uuid::new_v4().to_string())
+    }
+
+    /// Correlation id sent with the request: the one set through
+    /// [`RequestOptions`], or else the current tracing span's id if the
+    /// client opted in to it
+    fn request_id(&self, config: &ClientConfig) -> Option<String> {
+        self.request_id
+            .clone()
+            .or_else(|| config.request_id_from_span.then(current_span_id).flatten())
+    }
+
+    /// Mark the request as coming from this client (call, chat and text
+    /// completion endpoints)
+    pub(crate) fn proxy_source(self) -> Self {
+        self.header("x-proxy-source", "twcai-rust")
+    }
+
+    /// Send the request without credentials, unless an auth style was set
+    /// explicitly (see [`ClientBuilder::auth_style`](crate::ClientBuilder::auth_style))
+    pub(crate) fn unauthenticated(mut self) -> Self {
+        self.authenticated = false;
+        self
+    }
+
+    /// Ask for a server-sent event stream; streams have no overall timeout
+    pub(crate) fn streaming(mut self) -> Self {
+        self.streaming = true;
+        self.untimed()
+    }
+
+    /// Send without the client's overall timeout, e.g. for large uploads
+    pub(crate) fn untimed(mut self) -> Self {
+        self.timed = false;
+        self
+    }
+
+    /// Render the URL into a single buffer sized up front
+    fn url(&self, config: &ClientConfig) -> String {
+        let base_len = match &self.api {
+            Api::Agent(agent_access_id) => {
+                endpoint::agent_url_len(config, agent_access_id, &self.path)
+            }
+            Api::ControlPlane => config.control_plane_base.len() + self.path.len(),
+        };
+        let query_len = if self.query.is_empty() {
+            0
+        } else {
+            1 + self.query.len()
+        };
+        let mut url = String::with_capacity(base_len + query_len);
+        match &self.api {
+            Api::Agent(agent_access_id) => {
+                endpoint::push_agent_url(&mut url, config, agent_access_id, &self.path)
+            }
+            Api::ControlPlane => {
+                url.push_str(&config.control_plane_base);
+                url.push_str(&self.path);
+            }
+        }
+        if !self.query.is_empty() {
+            url.push('?');
+            url.push_str(&self.query);
+        }
+        url
+    }
+
+    /// Build the request; also returns whether the body was compressed
+    fn into_builder(self, config: &ClientConfig) -> Result<(reqwest::RequestBuilder, bool)> {
+        let url = self.url(config);
+        let mut builder = config.http_client.request(self.method, &url);
+
+        if self.authenticated || config.auth_style.is_some() {
+            builder = config.authorize(builder);
+        }
+        if self.streaming {
+            builder = builder.header(ACCEPT, "text/event-stream");
+        }
+        if self.timed {
+            builder = builder.timeout(config.timeout);
+        }
+        if let Some(request_id) = self.request_id(config) {
+            builder = builder.header(config.request_id_header.clone(), request_id);
+        }
+        for (name, value) in self.headers {
+            builder = builder.header(name, value);
+        }
+
+        match self.body {
+            Some(Body::Json(json)) => json_body(config, builder, json),
+            Some(Body::Multipart(form)) => {
+                let form = form.lock().unwrap_or_else(|e| e.into_inner()).take();
+                let form = form.ok_or_else(|| {
+                    TwcError::InvalidRequest("multipart body was already sent".to_string())
+                })?;
+                Ok((builder.multipart(form), false))
+            }
+            None => Ok((builder, false)),
+        }
+    }
+}
+
+/// Send the request described by `spec` and return the response whatever
+/// its status
+///
+/// The spec goes through [`RequestSpec::idempotent`] first, so the retries
+/// of a POST repeat one `Idempotency-Key`.
+pub(crate) async fn send_request(
+    config: &ClientConfig,
+    spec: RequestSpec,
+) -> Result<reqwest::Response> {
+    let (builder, compressed) = spec.idempotent(config).into_builder(config)?;
+    let response = send(config, builder).await?;
+    check_unsupported_encoding(response.status(), compressed)?;
+    Ok(response)
+}
+
+/// Send the request described by `spec`, turning a non-success status
+/// into the matching [`TwcError`]
+pub(crate) async fn send_success(
+    config: &ClientConfig,
+    spec: RequestSpec,
+) -> Result<reqwest::Response> {
+    let request_id = spec.request_id(config);
+    let result = async {
+        let response = send_request(config, spec).await?;
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(error_from_response(response, config).await)
+        }
+    };
+    tag_request_id(result.await, request_id)
+}
+
+/// Send the request described by `spec` and decode its JSON response
+///
+/// Non-success statuses become the matching [`TwcError`].
+pub(crate) async fn send_json<T: DeserializeOwned>(
+    config: &ClientConfig,
+    spec: RequestSpec,
+) -> Result<T> {
+    let request_id = spec.request_id(config);
+    let result = async {
+        let response = send_request(config, spec).await?;
+        handle_response(response, config).await
+    };
+    tag_request_id(result.await, request_id)
+}
+
+/// Like [`send_json`] for a conditional request: `None` when the server
+/// answers `304 Not Modified`, else the decoded body and its `ETag`
+pub(crate) async fn send_json_if_modified<T: DeserializeOwned>(
+    config: &ClientConfig,
+    spec: RequestSpec,
+) -> Result<Option<(T, Option<String>)>> {
+    let request_id = spec.request_id(config);
+    let result = async {
+        let response = send_request(config, spec).await?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let data = handle_response(response, config).await?;
+        Ok(Some((data, etag)))
+    };
+    tag_request_id(result.await, request_id)
+}
+
+/// Send the request described by `spec` to an endpoint documented to
+/// return nothing, discarding any body of a successful response
+pub(crate) async fn send_expect_empty(config: &ClientConfig, spec: RequestSpec) -> Result<()> {
+    send_success(config, spec).await.map(drop)
+}
+
+/// Like [`send_json`], but also keep status, headers and latency
+pub(crate) async fn send_json_with_meta<T: DeserializeOwned>(
+    config: &ClientConfig,
+    spec: RequestSpec,
+) -> Result<WithMeta<T>> {
+    let request_id = spec.request_id(config);
+    let started = Instant::now();
+    let result = async {
+        let response = send_request(config, spec).await?;
+        handle_response_with_meta(response, config, started).await
+    };
+    tag_request_id(result.await, request_id)
+}
+
+/// Send a streaming request and decode its server-sent events as JSON
+///
+/// Failures before the first event are retried as [`stream_with_retry`]
+/// describes.
+pub(crate) async fn send_stream<T>(
+    config: &ClientConfig,
+    spec: RequestSpec,
+    idle_timeout: Duration,
+) -> Result<TwcStream<T>>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let spec = spec.idempotent(config);
+    let response = send_success(config, spec.clone()).await?;
+    Ok(stream_with_retry(config, spec, response, idle_timeout))
+}
+
+/// Decode the events of `response`, the answer to `spec`, re-sending `spec`
+/// when the stream breaks off before its first event
+///
+/// `spec` should have gone through [`RequestSpec::idempotent`] before its
+/// first send, so that re-sends repeat its `Idempotency-Key`.
+///
+/// A connection error or idle timeout before anything was yielded is
+/// retried with the usual backoff, one attempt per re-send. Re-sends share
+/// the attempt count and total deadline of the send that produced
+/// `response`, and end in [`TwcError::RetriesExhausted`] once those are used
+/// up. Once an event has been yielded, errors are passed on as they are: a
+/// request whose output the caller has started to see is never repeated, so
+/// nothing is generated or billed twice.
+pub(crate) fn stream_with_retry<T>(
+    config: &ClientConfig,
+    spec: RequestSpec,
+    response: reqwest::Response,
+    idle_timeout: Duration,
+) -> TwcStream<T>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let history = response
+        .extensions()
+        .get::<AttemptHistory>()
+        .cloned()
+        .unwrap_or_else(|| AttemptHistory {
+            records: Vec::new(),
+            attempts: 1,
+            started: Instant::now(),
+        });
+    let stream = json_stream(response, idle_timeout);
+    if config.max_retries == 0 {
+        return stream;
+    }
+
+    let state = StreamStart {
+        config: config.clone(),
+        spec,
+        idle_timeout,
+        stream,
+        history,
+        started: false,
+    };
+    TwcStream::new(futures_util::stream::unfold(state, next_after_start))
+}
+
+/// State of a stream that is re-sent while it fails before its first event
+struct StreamStart<T> {
+    config: ClientConfig,
+    spec: RequestSpec,
+    idle_timeout: Duration,
+    stream: TwcStream<T>,
+    /// Attempts so far, starting with those of the original send
+    history: AttemptHistory,
+    /// Whether an item has been yielded; no retries are made after that
+    started: bool,
+}
+
+async fn next_after_start<T>(mut state: StreamStart<T>) -> Option<(Result<T>, StreamStart<T>)>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    loop {
+        let error = match state.stream.next().await? {
+            Err(e) if !state.started && e.is_broken_connection() => e,
+            item => {
+                state.started = true;
+                return Some((item, state));
+            }
+        };
+
+        let request_id = state.spec.request_id(&state.config);
+        match tag_request_id(resend(&mut state, error).await, request_id) {
+            Ok(stream) => state.stream = stream,
+            Err(e) => {
+                state.started = true;
+                return Some((Err(e), state));
+            }
+        }
+    }
+}
+
+/// Re-send the spec of a stream that broke off with `error` before its first
+/// event, one attempt at a time, until one succeeds, fails for good or the
+/// retry budget is used up
+async fn resend<T>(state: &mut StreamStart<T>, mut error: TwcError) -> Result<TwcStream<T>>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let config = &state.config;
+    let history = &mut state.history;
+    let deadline = config.total_deadline.map(|budget| history.started + budget);
+
+    loop {
+        let delay = retry_delay(history.attempts - 1);
+        let fits = deadline.is_none_or(|deadline| Instant::now() + delay < deadline);
+        if history.attempts > config.max_retries || !fits {
+            return Err(TwcError::RetriesExhausted {
+                attempts: history.attempts,
+                elapsed: history.started.elapsed(),
+                last: Box::new(error),
+                history: history.records.clone(),
+            });
+        }
+
+        let (builder, compressed) = state.spec.clone().into_builder(config)?;
+        let mut request = builder.build()?;
+        if let Some(record) = history.records.last_mut() {
+            record.retry_delay = Some(delay);
+        }
+        logging::retry(
+            request.method(),
+            request.url().path(),
+            history.attempts,
+            delay,
+            &error,
+        );
+        tokio::time::sleep(delay).await;
+
+        if let (Some(deadline), Some(timeout)) = (deadline, request.timeout_mut()) {
+            *timeout = (*timeout).min(deadline.saturating_duration_since(Instant::now()));
+        }
+        let attempt_started = (SystemTime::now(), Instant::now());
+        let result = execute(config, request).await;
+        record_attempt(&mut history.records, attempt_started, &result);
+        history.attempts += 1;
+
+        error = match result {
+            Ok(response) => {
+                check_unsupported_encoding(response.status(), compressed)?;
+                if response.status().is_success() {
+                    return Ok(json_stream(response, state.idle_timeout));
+                }
+                error_from_response(response, config).await
+            }
+            Err(e) => e,
+        };
+        if !error.is_retryable() {
+            return Err(error);
+        }
+    }
+}
+
+/// Attach the request id a call was sent with to its error
+fn tag_request_id<T>(result: Result<T>, request_id: Option<String>) -> Result<T> {
+    match (result, request_id) {
+        (Err(source), Some(request_id)) => Err(TwcError::WithRequestId {
+            request_id,
+            source: Box::new(source),
+        }),
+        (result, _) => result,
+    }
+}
+
+/// Id of the current tracing span, used as the request id when none is set
+/// and [`ClientBuilder::request_id_from_span`](crate::ClientBuilder::request_id_from_span)
+/// is on
+#[cfg(feature = "tracing")]
+fn current_span_id() -> Option<String> {
+    tracing::Span::current()
+        .id()
+        .map(|id| format!("{:016x}", id.into_u64()))
+}
+
+#[cfg(not(feature = "tracing"))]
+fn current_span_id() -> Option<String> {
+    None
+}
+
+/// Send a request built from `builder`, logging it and its outcome
+///
+/// Retryable failures (see [`TwcError::is_retryable`]) are repeated up to
+/// `config.max_retries` times, as long as the body can be replayed. With a
+/// total deadline, each attempt's timeout is cut to the remaining budget and
+/// no retry is started that would end past it. A retryable failure that
+/// ends the retries is returned as [`TwcError::RetriesExhausted`].
+pub(crate) async fn send(
+    config: &ClientConfig,
+    builder: reqwest::RequestBuilder,
+) -> Result<reqwest::Response> {
+    let mut request = builder.build()?;
+    let started = Instant::now();
+    let deadline = config.total_deadline.map(|budget| started + budget);
+    let mut attempt = 0;
+    let mut history = Vec::new();
+
+    loop {
+        if let (Some(deadline), Some(timeout)) = (deadline, request.timeout_mut()) {
+            *timeout = (*timeout).min(deadline.saturating_duration_since(Instant::now()));
+        }
+        let retry = if attempt < config.max_retries {
+            request.try_clone()
+        } else {
+            None
+        };
+        let can_retry = attempt > 0 || retry.is_some();
+        let attempt_started = (SystemTime::now(), Instant::now());
+        let result = execute(config, request).await;
+        record_attempt(&mut history, attempt_started, &result);
+
+        let retryable = match &result {
+            Ok(response) => TwcError::from_status(response.status(), None).is_retryable(),
+            Err(e) => e.is_retryable(),
+        };
+        if !retryable {
+            return with_history(result, history, attempt + 1, started);
+        }
+
+        let delay = retry_delay(attempt);
+        let fits = deadline.is_none_or(|deadline| Instant::now() + delay < deadline);
+        let Some(next) = retry.filter(|_| fits) else {
+            if !can_retry {
+                return with_history(result, history, attempt + 1, started);
+            }
+            let last = match result {
+                Ok(response) => error_from_response(response, config).await,
+                Err(e) => e,
+            };
+            return Err(TwcError::RetriesExhausted {
+                attempts: attempt + 1,
+                elapsed: started.elapsed(),
+                last: Box::new(last),
+                history,
+            });
+        };
+
+        if let Some(record) = history.last_mut() {
+            record.retry_delay = Some(delay);
+        }
+        let (method, path) = (next.method(), next.url().path());
+        match &result {
+            Ok(response) => {
+                let error = TwcError::from_status(response.status(), None);
+                logging::retry(method, path, attempt + 1, delay, &error);
+            }
+            Err(e) => logging::retry(method, path, attempt + 1, delay, e),
+        }
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+        request = next;
+    }
+}
+
+/// Attempts of a request, carried from [`send`] in the response extensions
+/// to the response metadata and to [`stream_with_retry`]
+#[derive(Clone)]
+struct AttemptHistory {
+    records: Vec<AttemptRecord>,
+    /// Attempts made, including any dropped from `records`
+    attempts: u32,
+    /// Start of the first attempt
+    started: Instant,
+}
+
+/// Append the outcome of an attempt started at `started` to `history`,
+/// dropping the oldest record once it holds [`MAX_ATTEMPT_RECORDS`]
+fn record_attempt(
+    history: &mut Vec<AttemptRecord>,
+    started: (SystemTime, Instant),
+    result: &Result<reqwest::Response>,
+) {
+    if history.len() == MAX_ATTEMPT_RECORDS {
+        history.remove(0);
+    }
+    history.push(AttemptRecord {
+        started_at: started.0,
+        duration: started.1.elapsed(),
+        outcome: match result {
+            Ok(response) => AttemptOutcome::Status(response.status()),
+            Err(e) => AttemptOutcome::Error(e.to_string()),
+        },
+        retry_delay: None,
+    });
+}
+
+/// Attach `history` of the `attempts` made since `started` to a successful
+/// `result`
+fn with_history(
+    result: Result<reqwest::Response>,
+    history: Vec<AttemptRecord>,
+    attempts: u32,
+    started: Instant,
+) -> Result<reqwest::Response> {
+    result.map(|mut response| {
+        response.extensions_mut().insert(AttemptHistory {
+            records: history,
+            attempts,
+            started,
+        });
+        response
+    })
+}
+
+/// Backoff before retry number `attempt` (counting from 0)
+fn retry_delay(attempt: u32) -> Duration {
+    RETRY_BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(RETRY_MAX_DELAY)
+}
+
+/// Execute a single attempt of `request`
+///
+/// With a circuit breaker, the attempt fails fast while the circuit is open
+/// and reports its outcome. With a concurrency limit, it then waits for a
+/// free slot and the response keeps it until dropped (see [`RequestPermit`](crate::limit::RequestPermit)).
+async fn execute(
+    config: &ClientConfig,
+    mut request: reqwest::Request,
+) -> Result<reqwest::Response> {
+    let pass = match &config.circuit_breaker {
+        Some(breaker) => Some(breaker.admit()?),
+        None => None,
+    };
+    let permit = match &config.concurrency {
+        Some(limit) => Some(limit.acquire().await?),
+        None => None,
+    };
+
+    for interceptor in config.interceptors.iter() {
+        interceptor.on_request(&mut request);
+    }
+
+    let method = request.method().clone();
+    let path = request.url().path().to_string();
+
+    logging::request(&request);
+    let info = metrics::RequestInfo::new(&request);
+    let started = Instant::now();
+
+    let result = dispatch(config, request).await;
+    if let Some(pass) = pass {
+        pass.record(match &result {
+            Ok(response) => TwcError::from_status(response.status(), None).is_retryable(),
+            Err(e) => e.is_retryable(),
+        });
+    }
+
+    match result {
+        Ok(mut response) => {
+            if let Some(permit) = permit {
+                response.extensions_mut().insert(permit);
+            }
+            let elapsed = started.elapsed();
+            logging::response(&method, &path, response.status(), elapsed);
+            metrics::request(&info, Some(response.status()), elapsed);
+            for interceptor in config.interceptors.iter() {
+                interceptor.on_response(&response, elapsed);
+            }
+            Ok(response)
+        }
+        Err(e) => {
+            let elapsed = started.elapsed();
+            logging::failure(&method, &path, &e, elapsed);
+            metrics::request(&info, None, elapsed);
+            Err(e)
+        }
+    }
+}
+
+/// Send an unauthenticated `HEAD` to the base URL, so the next request finds
+/// a pooled connection
+///
+/// Bypasses retries, interceptors and the concurrency limit; the timeout is
+/// the client's, capped at [`WARM_UP_TIMEOUT`].
+pub(crate) async fn warm_up(config: &ClientConfig) -> Result<WarmUpReport> {
+    let request = config
+        .http_client
+        .head(&*config.base_url)
+        .timeout(config.timeout.min(WARM_UP_TIMEOUT))
+        .build()?;
+
+    let started = Instant::now();
+    let response = dispatch(config, request).await?;
+    Ok(WarmUpReport {
+        latency: started.elapsed(),
+        status: response.status(),
+    })
+}
+
+/// Hand `request` to the layered transport, or straight to reqwest
+async fn dispatch(config: &ClientConfig, request: reqwest::Request) -> Result<reqwest::Response> {
+    #[cfg(feature = "tower")]
+    if let Some(transport) = &config.transport {
+        return tower::ServiceExt::oneshot(transport.clone(), request).await;
+    }
+
+    config
+        .http_client
+        .execute(request)
+        .await
+        .map_err(TwcError::from_transport)
+}
+
+/// Handle HTTP response and parse JSON or return appropriate error
+///
+/// An empty or whitespace-only success body, as sent with `204 No Content`
+/// and by some proxies, decodes as JSON `null`: that yields `()`, `None` or
+/// [`Value::Null`], and a decode error for types that need content.
+pub(crate) async fn handle_response<T: DeserializeOwned>(
+    response: reqwest::Response,
+    config: &ClientConfig,
+) -> Result<T> {
+    if response.status().is_success() {
+        let body = read_body(response, config.max_response_bytes).await?;
+        if body.iter().all(u8::is_ascii_whitespace) {
+            return serde_json::from_slice(b"null").map_err(|e| TwcError::decode(e, &body));
+        }
+        logging::usage(&body);
+        metrics::usage(&body);
+        if let Some(tracker) = &config.cost_tracker {
+            tracker.observe(&body);
+        }
+        serde_json::from_slice(&body).map_err(|e| TwcError::decode(e, &body))
+    } else {
+        Err(error_from_response(response, config).await)
+    }
+}
+
+/// Like [`handle_response`], but also keep status, headers and the latency
+/// measured from `started`
+pub(crate) async fn handle_response_with_meta<T: DeserializeOwned>(
+    response: reqwest::Response,
+    config: &ClientConfig,
+    started: Instant,
+) -> Result<WithMeta<T>> {
+    let status = response.status();
+    let headers = response.headers().clone();
+    let attempts = response
+        .extensions()
+        .get::<AttemptHistory>()
+        .map(|history| history.records.clone())
+        .unwrap_or_default();
+    let data = handle_response(response, config).await?;
+
+    Ok(WithMeta {
+        data,
+        meta: ResponseMeta {
+            status,
+            headers,
+            latency: started.elapsed(),
+            attempts,
+        },
+    })
+}
+
+/// Build the error for a non-success response, reading at most
+/// `config.max_response_bytes` of its body for the message
+pub(crate) async fn error_from_response(
+    response: reqwest::Response,
+    config: &ClientConfig,
+) -> TwcError {
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    match read_body(response, config.max_response_bytes).await {
+        Ok(body) => TwcError::from_body(
+            status,
+            content_type.as_deref(),
+            &body,
+            config.max_error_message_len,
+        ),
+        Err(e @ TwcError::ResponseTooLarge { .. }) => e,
+        Err(_) => TwcError::from_status(status, None),
+    }
+}
+
+/// Buffer a response body, aborting once it grows past `max_bytes`
+///
+/// The body is copied at most once: a body arriving in a single chunk is
+/// returned as is, others are gathered into a buffer sized from
+/// `Content-Length` when the server sends one.
+pub(crate) async fn read_body(mut response: reqwest::Response, max_bytes: usize) -> Result<Bytes> {
+    let mut capacity = 0;
+    if let Some(length) = response.content_length() {
+        let length = usize::try_from(length).unwrap_or(usize::MAX);
+        if length > max_bytes {
+            return Err(TwcError::ResponseTooLarge {
+                limit: max_bytes,
+                received: length,
+            });
+        }
+        capacity = length;
+    }
+
+    let Some(first) = response.chunk().await? else {
+        return Ok(Bytes::new());
+    };
+    if first.len() > max_bytes {
+        return Err(TwcError::ResponseTooLarge {
+            limit: max_bytes,
+            received: first.len(),
+        });
+    }
+    let Some(second) = response.chunk().await? else {
+        return Ok(first);
+    };
+
+    let mut body = BytesMut::with_capacity(capacity.max(first.len() + second.len()));
+    body.extend_from_slice(&first);
+    let mut next = Some(second);
+    while let Some(chunk) = next {
+        let received = body.len() + chunk.len();
+        if received > max_bytes {
+            return Err(TwcError::ResponseTooLarge {
+                limit: max_bytes,
+                received,
+            });
+        }
+        body.extend_from_slice(&chunk);
+        next = response.chunk().await?;
+    }
+
+    Ok(body.freeze())
+}
+
+/// Write a response body to `writer` chunk by chunk, without buffering it
+///
+/// The body is not subject to `max_response_bytes`, as it is never held in
+/// memory as a whole.
+pub(crate) async fn write_body<W>(
+    mut response: reqwest::Response,
+    writer: &mut W,
+) -> Result<Download>
+where
+    W: AsyncWrite + Unpin + Send + ?Sized,
+{
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let mut bytes = 0;
+    while let Some(chunk) = response.chunk().await? {
+        writer.write_all(&chunk).await?;
+        bytes += chunk.len() as u64;
+    }
+    writer.flush().await?;
+
+    Ok(Download {
+        status,
+        content_type,
+        bytes,
+    })
+}
+
+/// Encode a query struct as a URL query string (without the leading `?`)
+///
+/// `None` fields are skipped and sequences become repeated `key[]=value`
+/// pairs, which `serde_urlencoded` cannot express. A `None` query encodes
+/// as the empty string.
+pub(crate) fn query_string<T: Serialize>(query: &T) -> Result<String> {
+    let fields = match serde_json::to_value(query)? {
+        Value::Object(fields) => fields,
+        Value::Null => return Ok(String::new()),
+        _ => {
+            return Err(TwcError::InvalidRequest(
+                "Query parameters must be a struct or map".to_string(),
+            ));
+        }
+    };
+
+    let mut encoder = url::form_urlencoded::Serializer::new(String::new());
+    for (key, value) in &fields {
+        match value {
+            Value::Null => {}
+            Value::Array(values) => {
+                let key = format!("{}[]", key);
+                for value in values {
+                    encoder.append_pair(&key, &query_value(&key, value)?);
+                }
+            }
+            value => {
+                encoder.append_pair(key, &query_value(key, value)?);
+            }
+        }
+    }
+
+    Ok(encoder.finish())
+}
+
+fn query_value(key: &str, value: &Value) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        Value::Bool(b) => Ok(b.to_string()),
+        _ => Err(TwcError::InvalidRequest(format!(
+            "Query parameter {} must be a scalar or a list of scalars",
+            key
+        ))),
+    }
+}
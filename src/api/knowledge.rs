@@ -0,0 +1,111 @@
+//! Knowledge base API endpoints (Timeweb Cloud control plane)
+//!
+//! Provides methods for:
+//! - Listing knowledge base documents
+//! - Uploading documents
+//! - Deleting documents
+//!
+//! These endpoints are served from the control-plane URL and prefix
+//! (see [`ClientBuilder::control_plane_url`](crate::ClientBuilder::control_plane_url)).
+
+use reqwest::Method;
+use reqwest::multipart::{Form, Part};
+use serde::Deserialize;
+
+use super::endpoint::Endpoint;
+use super::http::{RequestSpec, send_expect_empty, send_json};
+use crate::{CloudAIClient, Result, TwcError, types::*};
+
+/// Extension trait for knowledge base operations
+pub trait KnowledgeExt {
+    /// List documents of a knowledge base
+    ///
+    /// GET /api/v1/cloud-ai/knowledge-bases/{knowledge_base_id}/documents
+    fn list_knowledge_documents(
+        &self,
+        knowledge_base_id: u64,
+    ) -> impl std::future::Future<Output = Result<Vec<KnowledgeDocument>>> + Send;
+
+    /// Upload a document to a knowledge base
+    ///
+    /// POST /api/v1/cloud-ai/knowledge-bases/{knowledge_base_id}/documents
+    ///
+    /// The document is indexed asynchronously; poll
+    /// [`list_knowledge_documents`](Self::list_knowledge_documents) for its status.
+    fn upload_knowledge_document(
+        &self,
+        knowledge_base_id: u64,
+        name: &str,
+        bytes: Vec<u8>,
+        mime_type: &str,
+    ) -> impl std::future::Future<Output = Result<KnowledgeDocument>> + Send;
+
+    /// Delete a document from a knowledge base
+    ///
+    /// DELETE /api/v1/cloud-ai/knowledge-bases/{knowledge_base_id}/documents/{document_id}
+    fn delete_knowledge_document(
+        &self,
+        knowledge_base_id: u64,
+        document_id: u64,
+    ) -> impl std::future::Future<Output = Result<()>> + Send;
+}
+
+#[derive(Deserialize)]
+struct DocumentsEnvelope {
+    documents: Vec<KnowledgeDocument>,
+}
+
+#[derive(Deserialize)]
+struct DocumentEnvelope {
+    document: KnowledgeDocument,
+}
+
+impl KnowledgeExt for CloudAIClient {
+    async fn list_knowledge_documents(
+        &self,
+        knowledge_base_id: u64,
+    ) -> Result<Vec<KnowledgeDocument>> {
+        let spec = RequestSpec::control_plane(
+            Method::GET,
+            Endpoint::KnowledgeDocuments(knowledge_base_id),
+        );
+        let envelope: DocumentsEnvelope = send_json(&self.config, spec).await?;
+
+        Ok(envelope.documents)
+    }
+
+    async fn upload_knowledge_document(
+        &self,
+        knowledge_base_id: u64,
+        name: &str,
+        bytes: Vec<u8>,
+        mime_type: &str,
+    ) -> Result<KnowledgeDocument> {
+        let part = Part::bytes(bytes)
+            .file_name(name.to_string())
+            .mime_str(mime_type)
+            .map_err(|e| TwcError::InvalidRequest(format!("Invalid MIME type: {}", e)))?;
+        let form = Form::new().part("file", part);
+        let spec = RequestSpec::control_plane(
+            Method::POST,
+            Endpoint::KnowledgeDocuments(knowledge_base_id),
+        )
+        .multipart(form);
+        let envelope: DocumentEnvelope = send_json(&self.config, spec).await?;
+
+        Ok(envelope.document)
+    }
+
+    async fn delete_knowledge_document(
+        &self,
+        knowledge_base_id: u64,
+        document_id: u64,
+    ) -> Result<()> {
+        let spec = RequestSpec::control_plane(
+            Method::DELETE,
+            Endpoint::KnowledgeDocument(knowledge_base_id, document_id),
+        );
+
+        send_expect_empty(&self.config, spec).await
+    }
+}
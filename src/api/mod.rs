@@ -1,9 +1,24 @@
 //! API endpoint implementations
 
+pub mod agents;
+pub mod batch;
 pub mod client;
+mod compression;
 pub mod conversations;
+pub mod default_agent;
+pub(crate) mod endpoint;
+pub mod files;
+pub(crate) mod http;
+pub mod knowledge;
 pub mod responses;
+pub mod stats;
 
+pub use agents::AgentsAdminExt;
+pub use batch::BatchReport;
 pub use client::AgentClientExt;
 pub use conversations::ConversationsExt;
+pub use default_agent::DefaultAgentExt;
+pub use files::FilesExt;
+pub use knowledge::KnowledgeExt;
 pub use responses::ResponsesExt;
+pub use stats::StatsExt;
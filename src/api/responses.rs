@@ -2,19 +2,37 @@
 //!
 //! Provides methods for:
 //! - Creating responses
-//! - Getting responses
+//! - Getting responses, and waiting for background ones with conditional polls
+//! - Listing the input items of responses
 //! - Deleting responses
 //! - Cancelling responses
+//! - Cancellable creation that fires `cancel_response` server-side
+//! - Streaming responses, with transparent resume after dropped connections
+//!   and server-side cancellation on abort
 
-use reqwest::header::AUTHORIZATION;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use futures_util::StreamExt;
+use reqwest::Method;
+use tokio::io::AsyncWrite;
+use tokio_util::sync::CancellationToken;
+
+use super::endpoint::Endpoint;
+use super::http::{
+    RequestSpec, send_expect_empty, send_json, send_json_if_modified, send_json_with_meta,
+    send_stream, send_success, write_body,
+};
 use crate::{
+    CloudAIClient, RequestOptions, Result, StreamOptionsLocal, TwcError,
+    pagination::{Page, Paginator},
+    stream::TwcStream,
     types::*,
-    CloudAIClient,
-    Result,
-    TwcError,
 };
 
+/// Interval between status checks while waiting on a background response
+const RESPONSE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 /// Extension trait for responses API operations
 pub trait ResponsesExt {
     /// Create a new response
@@ -26,9 +44,80 @@ pub trait ResponsesExt {
         request: CreateResponseRequest,
     ) -> impl std::future::Future<Output = Result<Response>> + Send;
 
+    /// [`create_response`](Self::create_response) that also returns
+    /// response metadata
+    fn create_response_with_meta(
+        &self,
+        agent_access_id: &str,
+        request: CreateResponseRequest,
+    ) -> impl std::future::Future<Output = Result<WithMeta<Response>>> + Send;
+
+    /// [`create_response`](Self::create_response) with per-call options,
+    /// e.g. an idempotency key
+    fn create_response_with_options(
+        &self,
+        agent_access_id: &str,
+        request: CreateResponseRequest,
+        options: RequestOptions,
+    ) -> impl std::future::Future<Output = Result<Response>> + Send;
+
+    /// Create a response that can be aborted with a cancellation token
+    ///
+    /// When the token fires, the local request is dropped and
+    /// [`TwcError::Cancelled`] is returned. For background requests
+    /// (`background: Some(true)`) the created response is polled until it
+    /// finishes; if the token fires once its id is known, `cancel_response`
+    /// is also sent on a best-effort basis.
+    fn create_response_cancellable(
+        &self,
+        agent_access_id: &str,
+        request: CreateResponseRequest,
+        token: CancellationToken,
+    ) -> impl std::future::Future<Output = Result<Response>> + Send;
+
+    /// Create a response streamed as server-sent events
+    ///
+    /// POST /api/v1/cloud-ai/agents/{agent_access_id}/v1/responses
+    ///
+    /// Forces `stream: true` on the request. Like other streams, it is only
+    /// bounded by the client's stream idle timeout.
+    fn create_response_stream(
+        &self,
+        agent_access_id: &str,
+        request: CreateResponseRequest,
+    ) -> impl std::future::Future<Output = Result<TwcStream<ResponseStreamEvent>>> + Send;
+
+    /// [`create_response_stream`](Self::create_response_stream) with
+    /// client-side stream options, e.g. a short idle timeout for interactive UIs
+    fn create_response_stream_with_options(
+        &self,
+        agent_access_id: &str,
+        request: CreateResponseRequest,
+        options: StreamOptionsLocal,
+    ) -> impl std::future::Future<Output = Result<TwcStream<ResponseStreamEvent>>> + Send;
+
+    /// Create a streamed response that resumes after dropped connections
+    ///
+    /// On a transport error (including the idle timeout) the stream reconnects
+    /// with `GET /responses/{id}?stream=true&starting_after=N`, where `N` is
+    /// the last sequence number seen, and skips any replayed events. At most
+    /// `max_reconnects` reconnects are made before the error is surfaced.
+    /// Resuming needs the response id from the first event and a response
+    /// the server keeps around, e.g. one created with `background: true`.
+    fn resilient_response_stream(
+        &self,
+        agent_access_id: &str,
+        request: CreateResponseRequest,
+        max_reconnects: u32,
+    ) -> impl std::future::Future<Output = Result<TwcStream<ResponseStreamEvent>>> + Send;
+
     /// Get an existing response
     ///
     /// GET /api/v1/cloud-ai/agents/{agent_access_id}/v1/responses/{response_id}
+    ///
+    /// Fails with [`TwcError::InvalidRequest`] when the query asks for
+    /// `stream: true`; use [`get_response_stream`](Self::get_response_stream)
+    /// for that.
     fn get_response(
         &self,
         agent_access_id: &str,
@@ -36,6 +125,85 @@ pub trait ResponsesExt {
         query: Option<GetResponseQuery>,
     ) -> impl std::future::Future<Output = Result<Response>> + Send;
 
+    /// Write the JSON of an existing response to `writer` as it arrives
+    ///
+    /// GET /api/v1/cloud-ai/agents/{agent_access_id}/v1/responses/{response_id}
+    ///
+    /// For responses too large to parse comfortably, e.g. with `include`
+    /// expansions: the body is never held in memory, and is not subject to
+    /// `max_response_bytes`. Error statuses are reported as the matching
+    /// [`TwcError`] before anything is written. Like
+    /// [`get_response`](Self::get_response), rejects `stream: true`.
+    fn get_response_raw_to<W>(
+        &self,
+        agent_access_id: &str,
+        response_id: &str,
+        query: Option<GetResponseQuery>,
+        writer: &mut W,
+    ) -> impl std::future::Future<Output = Result<Download>> + Send
+    where
+        W: AsyncWrite + Unpin + Send + ?Sized;
+
+    /// Wait until a response has finished generating
+    ///
+    /// GET /api/v1/cloud-ai/agents/{agent_access_id}/v1/responses/{response_id}
+    ///
+    /// Polls every 500 ms while the status is `queued` or `in_progress`.
+    /// Once the server tags a reply with an `ETag`, later polls send it in
+    /// `If-None-Match` and a `304 Not Modified` counts as "no change", so an
+    /// unchanged response is not downloaded again. Servers without ETags are
+    /// polled with plain requests.
+    fn wait_for_response(
+        &self,
+        agent_access_id: &str,
+        response_id: &str,
+    ) -> impl std::future::Future<Output = Result<Response>> + Send;
+
+    /// Stream the events of an existing response
+    ///
+    /// GET /api/v1/cloud-ai/agents/{agent_access_id}/v1/responses/{response_id}?stream=true
+    ///
+    /// Forces `stream: true` on the query; set `starting_after` to replay only
+    /// the events after a known sequence number.
+    fn get_response_stream(
+        &self,
+        agent_access_id: &str,
+        response_id: &str,
+        query: Option<GetResponseQuery>,
+    ) -> impl std::future::Future<Output = Result<TwcStream<ResponseStreamEvent>>> + Send;
+
+    /// List the input items a response was generated from
+    ///
+    /// GET /api/v1/cloud-ai/agents/{agent_access_id}/v1/responses/{response_id}/input_items
+    fn list_response_input_items(
+        &self,
+        agent_access_id: &str,
+        response_id: &str,
+        query: Option<ListItemsQuery>,
+    ) -> impl std::future::Future<Output = Result<ResponseInputItemList>> + Send;
+
+    /// List the input items of a response as a [`Page`]
+    ///
+    /// Same request as [`list_response_input_items`](Self::list_response_input_items),
+    /// with the cursor of the next page resolved.
+    fn list_response_input_items_page(
+        &self,
+        agent_access_id: &str,
+        response_id: &str,
+        query: Option<ListItemsQuery>,
+    ) -> impl std::future::Future<Output = Result<Page<ResponseInputItem>>> + Send;
+
+    /// Stream every input item of a response, fetching pages as needed
+    ///
+    /// `query.after` sets the starting point; later pages continue from the
+    /// previous page's cursor.
+    fn stream_response_input_items(
+        &self,
+        agent_access_id: &str,
+        response_id: &str,
+        query: Option<ListItemsQuery>,
+    ) -> Paginator<ResponseInputItem>;
+
     /// Delete a response
     ///
     /// DELETE /api/v1/cloud-ai/agents/{agent_access_id}/v1/responses/{response_id}
@@ -61,23 +229,125 @@ impl ResponsesExt for CloudAIClient {
         agent_access_id: &str,
         request: CreateResponseRequest,
     ) -> Result<Response> {
-        let url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/v1/responses",
-            self.config.base_url,
-            agent_access_id
-        );
+        self.create_response_with_meta(agent_access_id, request)
+            .await
+            .map(|response| response.data)
+    }
+
+    async fn create_response_with_meta(
+        &self,
+        agent_access_id: &str,
+        mut request: CreateResponseRequest,
+    ) -> Result<WithMeta<Response>> {
+        self.config.apply_response_defaults(&mut request);
+        if let Some(conversation) = &request.conversation {
+            conversation.validate()?;
+        }
+
+        let spec = RequestSpec::agent(Method::POST, agent_access_id, Endpoint::Responses)
+            .json(&request)?;
 
-        let response = self
-            .config
-            .http_client
-            .post(&url)
-            .header(AUTHORIZATION, self.config.auth_header())
-            .json(&request)
-            .send()
+        send_json_with_meta(&self.config, spec).await
+    }
+
+    async fn create_response_with_options(
+        &self,
+        agent_access_id: &str,
+        mut request: CreateResponseRequest,
+        options: RequestOptions,
+    ) -> Result<Response> {
+        self.config.apply_response_defaults(&mut request);
+        if let Some(conversation) = &request.conversation {
+            conversation.validate()?;
+        }
+
+        let spec = RequestSpec::agent(Method::POST, agent_access_id, Endpoint::Responses)
+            .json(&request)?
+            .options(&options);
+
+        send_json(&self.config, spec).await
+    }
+
+    async fn create_response_cancellable(
+        &self,
+        agent_access_id: &str,
+        request: CreateResponseRequest,
+        token: CancellationToken,
+    ) -> Result<Response> {
+        let background = request.background == Some(true);
+
+        let current = tokio::select! {
+            _ = token.cancelled() => return Err(TwcError::Cancelled),
+            result = self.create_response(agent_access_id, request) => result?,
+        };
+
+        if !background || is_terminal_status(&current.status) {
+            return Ok(current);
+        }
+
+        tokio::select! {
+            _ = token.cancelled() => {
+                let _ = self.cancel_response(agent_access_id, &current.id).await;
+                Err(TwcError::Cancelled)
+            }
+            result = async {
+                tokio::time::sleep(RESPONSE_POLL_INTERVAL).await;
+                self.wait_for_response(agent_access_id, &current.id).await
+            } => result,
+        }
+    }
+
+    async fn create_response_stream(
+        &self,
+        agent_access_id: &str,
+        request: CreateResponseRequest,
+    ) -> Result<TwcStream<ResponseStreamEvent>> {
+        let options = StreamOptionsLocal::idle_timeout(self.config.stream_idle_timeout);
+        self.create_response_stream_with_options(agent_access_id, request, options)
             .await
-            .map_err(TwcError::Http)?;
+    }
+
+    async fn create_response_stream_with_options(
+        &self,
+        agent_access_id: &str,
+        mut request: CreateResponseRequest,
+        options: StreamOptionsLocal,
+    ) -> Result<TwcStream<ResponseStreamEvent>> {
+        self.config.apply_response_defaults(&mut request);
+        if let Some(conversation) = &request.conversation {
+            conversation.validate()?;
+        }
+        request.stream = Some(true);
+
+        let spec = RequestSpec::agent(Method::POST, agent_access_id, Endpoint::Responses)
+            .streaming()
+            .json(&request)?;
+
+        let events = send_stream(&self.config, spec, options.idle_timeout).await?;
+        Ok(cancel_on_abort(self, agent_access_id, None, events))
+    }
+
+    async fn resilient_response_stream(
+        &self,
+        agent_access_id: &str,
+        request: CreateResponseRequest,
+        max_reconnects: u32,
+    ) -> Result<TwcStream<ResponseStreamEvent>> {
+        let inner = self
+            .create_response_stream(agent_access_id, request)
+            .await?;
 
-        handle_response(response).await
+        let state = ResumeState {
+            client: self.clone(),
+            agent_access_id: agent_access_id.to_string(),
+            response_id: None,
+            last_sequence: None,
+            reconnects_left: max_reconnects,
+            inner: Some(inner),
+        };
+
+        let events = TwcStream::new(futures_util::stream::unfold(state, next_resumable));
+        Ok(cancel_on_abort(self, agent_access_id, None, events))
     }
 
     async fn get_response(
@@ -86,98 +356,286 @@ impl ResponsesExt for CloudAIClient {
         response_id: &str,
         query: Option<GetResponseQuery>,
     ) -> Result<Response> {
-        let mut url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/v1/responses/{}",
-            self.config.base_url,
+        if query.as_ref().and_then(|q| q.stream) == Some(true) {
+            return Err(TwcError::InvalidRequest(
+                "get_response cannot parse a streamed reply; use get_response_stream for stream=true"
+                    .to_string(),
+            ));
+        }
+
+        let spec = RequestSpec::agent(
+            Method::GET,
             agent_access_id,
-            response_id
-        );
+            Endpoint::Response(response_id),
+        )
+        .query(&query)?;
+
+        send_json(&self.config, spec).await
+    }
 
-        if let Some(q) = query {
-            let query_string = serde_urlencoded::to_string(&q)
-                .map_err(|e| TwcError::InvalidRequest(e.to_string()))?;
-            if !query_string.is_empty() {
-                url.push('?');
-                url.push_str(&query_string);
+    async fn get_response_raw_to<W>(
+        &self,
+        agent_access_id: &str,
+        response_id: &str,
+        query: Option<GetResponseQuery>,
+        writer: &mut W,
+    ) -> Result<Download>
+    where
+        W: AsyncWrite + Unpin + Send + ?Sized,
+    {
+        if query.as_ref().and_then(|q| q.stream) == Some(true) {
+            return Err(TwcError::InvalidRequest(
+                "get_response_raw_to cannot write a streamed reply; use get_response_stream for \
+                 stream=true"
+                    .to_string(),
+            ));
+        }
+
+        let spec = RequestSpec::agent(
+            Method::GET,
+            agent_access_id,
+            Endpoint::Response(response_id),
+        )
+        .query(&query)?;
+
+        let response = send_success(&self.config, spec).await?;
+        write_body(response, writer).await
+    }
+
+    async fn wait_for_response(
+        &self,
+        agent_access_id: &str,
+        response_id: &str,
+    ) -> Result<Response> {
+        let mut etag: Option<String> = None;
+        loop {
+            let mut spec = RequestSpec::agent(
+                Method::GET,
+                agent_access_id,
+                Endpoint::Response(response_id),
+            );
+            if let Some(etag) = &etag {
+                spec = spec.header("if-none-match", etag.clone());
             }
+
+            // `None` is a 304: the response has not changed since the last poll
+            if let Some((response, tag)) =
+                send_json_if_modified::<Response>(&self.config, spec).await?
+            {
+                if is_terminal_status(&response.status) {
+                    return Ok(response);
+                }
+                etag = tag;
+            }
+            tokio::time::sleep(RESPONSE_POLL_INTERVAL).await;
         }
+    }
 
-        let response = self
-            .config
-            .http_client
-            .get(&url)
-            .header(AUTHORIZATION, self.config.auth_header())
-            .send()
-            .await
-            .map_err(TwcError::Http)?;
+    async fn get_response_stream(
+        &self,
+        agent_access_id: &str,
+        response_id: &str,
+        query: Option<GetResponseQuery>,
+    ) -> Result<TwcStream<ResponseStreamEvent>> {
+        let query = GetResponseQuery {
+            stream: Some(true),
+            ..query.unwrap_or_default()
+        };
+        let spec = RequestSpec::agent(
+            Method::GET,
+            agent_access_id,
+            Endpoint::Response(response_id),
+        )
+        .streaming()
+        .query(&query)?;
 
-        handle_response(response).await
+        let events = send_stream(&self.config, spec, self.config.stream_idle_timeout).await?;
+        Ok(cancel_on_abort(
+            self,
+            agent_access_id,
+            Some(response_id.to_string()),
+            events,
+        ))
     }
 
-    async fn delete_response(
+    async fn list_response_input_items(
         &self,
         agent_access_id: &str,
         response_id: &str,
-    ) -> Result<()> {
-        let url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/v1/responses/{}",
-            self.config.base_url,
+        query: Option<ListItemsQuery>,
+    ) -> Result<ResponseInputItemList> {
+        let spec = RequestSpec::agent(
+            Method::GET,
             agent_access_id,
-            response_id
-        );
+            Endpoint::ResponseInputItems(response_id),
+        )
+        .query(&query)?;
 
-        let response = self
-            .config
-            .http_client
-            .delete(&url)
-            .header(AUTHORIZATION, self.config.auth_header())
-            .send()
-            .await
-            .map_err(TwcError::Http)?;
+        send_json(&self.config, spec).await
+    }
 
-        let status = response.status();
-        if status.is_success() || status.as_u16() == 204 {
-            Ok(())
-        } else {
-            Err(TwcError::from_status(status, None))
-        }
+    async fn list_response_input_items_page(
+        &self,
+        agent_access_id: &str,
+        response_id: &str,
+        query: Option<ListItemsQuery>,
+    ) -> Result<Page<ResponseInputItem>> {
+        self.list_response_input_items(agent_access_id, response_id, query)
+            .await
+            .map(Page::from)
     }
 
-    async fn cancel_response(
+    fn stream_response_input_items(
         &self,
         agent_access_id: &str,
         response_id: &str,
-    ) -> Result<Response> {
-        let url = format!(
-            "{}/api/v1/cloud-ai/agents/{}/v1/responses/{}/cancel",
-            self.config.base_url,
+        query: Option<ListItemsQuery>,
+    ) -> Paginator<ResponseInputItem> {
+        let client = self.clone();
+        let agent_access_id = agent_access_id.to_string();
+        let response_id = response_id.to_string();
+        let query = query.unwrap_or_default();
+
+        Paginator::new(move |cursor| {
+            let client = client.clone();
+            let agent_access_id = agent_access_id.clone();
+            let response_id = response_id.clone();
+            let query = ListItemsQuery {
+                after: cursor.or_else(|| query.after.clone()),
+                ..query.clone()
+            };
+            async move {
+                client
+                    .list_response_input_items_page(&agent_access_id, &response_id, Some(query))
+                    .await
+            }
+        })
+    }
+
+    async fn delete_response(&self, agent_access_id: &str, response_id: &str) -> Result<()> {
+        let spec = RequestSpec::agent(
+            Method::DELETE,
             agent_access_id,
-            response_id
+            Endpoint::Response(response_id),
         );
 
-        let response = self
-            .config
-            .http_client
-            .post(&url)
-            .header(AUTHORIZATION, self.config.auth_header())
-            .send()
-            .await
-            .map_err(TwcError::Http)?;
+        send_expect_empty(&self.config, spec).await
+    }
+
+    async fn cancel_response(&self, agent_access_id: &str, response_id: &str) -> Result<Response> {
+        let spec = RequestSpec::agent(
+            Method::POST,
+            agent_access_id,
+            Endpoint::ResponseCancel(response_id),
+        );
 
-        handle_response(response).await
+        send_json(&self.config, spec).await
     }
 }
 
-/// Handle HTTP response and parse JSON or return appropriate error
-async fn handle_response<T: serde::de::DeserializeOwned>(
-    response: reqwest::Response,
-) -> Result<T> {
-    let status = response.status();
-
-    if status.is_success() {
-        response.json::<T>().await.map_err(TwcError::Http)
-    } else {
-        let text = response.text().await.ok();
-        Err(TwcError::from_status(status, text))
+/// Whether a response status means generation has finished
+fn is_terminal_status(status: &str) -> bool {
+    !matches!(status, "queued" | "in_progress")
+}
+
+/// Make [`TwcStream::abort`] cancel the streamed response server-side
+///
+/// The response id is `known_id`, or taken from the first event that
+/// carries it; the stream cannot be cancelled before either is available.
+fn cancel_on_abort(
+    client: &CloudAIClient,
+    agent_access_id: &str,
+    known_id: Option<String>,
+    events: TwcStream<ResponseStreamEvent>,
+) -> TwcStream<ResponseStreamEvent> {
+    let response_id = Arc::new(Mutex::new(known_id));
+    let seen = response_id.clone();
+    let events = events.inspect(move |event| {
+        let mut seen = seen.lock().unwrap_or_else(|e| e.into_inner());
+        if seen.is_none()
+            && let Ok(event) = event
+        {
+            *seen = event.response_id().map(str::to_string);
+        }
+    });
+
+    let client = client.clone();
+    let agent_access_id = agent_access_id.to_string();
+    TwcStream::new(events).on_abort(move || async move {
+        let response_id = response_id
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        if let Some(response_id) = response_id {
+            let _ = client.cancel_response(&agent_access_id, &response_id).await;
+        }
+    })
+}
+
+/// State of a [`ResponsesExt::resilient_response_stream`]
+struct ResumeState {
+    client: CloudAIClient,
+    agent_access_id: String,
+    response_id: Option<String>,
+    last_sequence: Option<u32>,
+    reconnects_left: u32,
+    /// Current connection; `None` once the stream has ended
+    inner: Option<TwcStream<ResponseStreamEvent>>,
+}
+
+async fn next_resumable(
+    mut state: ResumeState,
+) -> Option<(Result<ResponseStreamEvent>, ResumeState)> {
+    loop {
+        let next = state.inner.as_mut()?.next().await;
+
+        let mut error = match next {
+            None => return None,
+            Some(Ok(event)) => {
+                if let Some(sequence) = event.sequence_number() {
+                    if state.last_sequence.is_some_and(|last| sequence <= last) {
+                        continue;
+                    }
+                    state.last_sequence = Some(sequence);
+                }
+                if state.response_id.is_none() {
+                    state.response_id = event.response_id().map(str::to_string);
+                }
+                return Some((Ok(event), state));
+            }
+            Some(Err(e)) => e,
+        };
+
+        // Bad events are skipped by the connection itself; only a lost
+        // connection needs replacing
+        if !error.is_broken_connection() {
+            return Some((Err(error), state));
+        }
+
+        state.inner = None;
+        loop {
+            let resumable = error.is_broken_connection();
+            let response_id = match state.response_id.clone() {
+                Some(id) if resumable && state.reconnects_left > 0 => id,
+                _ => return Some((Err(error), state)),
+            };
+
+            state.reconnects_left -= 1;
+            let query = GetResponseQuery {
+                starting_after: state.last_sequence,
+                ..Default::default()
+            };
+            let reconnected = state
+                .client
+                .get_response_stream(&state.agent_access_id, &response_id, Some(query))
+                .await;
+            match reconnected {
+                Ok(inner) => {
+                    state.inner = Some(inner);
+                    break;
+                }
+                Err(e) => error = e,
+            }
+        }
     }
 }
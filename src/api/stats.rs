@@ -0,0 +1,50 @@
+//! AI Agents usage statistics endpoints
+//!
+//! Provides methods for:
+//! - Daily token and request usage of an agent over a date range
+
+use reqwest::Method;
+
+use super::endpoint::Endpoint;
+use super::http::{RequestSpec, send_json};
+use crate::{CloudAIClient, Result, TwcError, types::*};
+
+/// Extension trait for usage statistics
+pub trait StatsExt {
+    /// Get daily usage of an agent between two dates (both inclusive)
+    ///
+    /// GET /api/v1/cloud-ai/agents/{agent_access_id}/usage?from=YYYY-MM-DD&to=YYYY-MM-DD
+    ///
+    /// With the `chrono` feature, `chrono::NaiveDate` can be passed directly.
+    fn get_agent_usage(
+        &self,
+        agent_access_id: &str,
+        from: impl Into<UsageDate> + Send,
+        to: impl Into<UsageDate> + Send,
+    ) -> impl std::future::Future<Output = Result<AgentUsageReport>> + Send;
+}
+
+impl StatsExt for CloudAIClient {
+    async fn get_agent_usage(
+        &self,
+        agent_access_id: &str,
+        from: impl Into<UsageDate> + Send,
+        to: impl Into<UsageDate> + Send,
+    ) -> Result<AgentUsageReport> {
+        let query = UsageQuery {
+            from: from.into(),
+            to: to.into(),
+        };
+        if query.from > query.to {
+            return Err(TwcError::InvalidRequest(format!(
+                "Usage range starts after it ends: {} > {}",
+                query.from, query.to
+            )));
+        }
+
+        let spec =
+            RequestSpec::agent(Method::GET, agent_access_id, Endpoint::Usage).query(&query)?;
+
+        send_json(&self.config, spec).await
+    }
+}
@@ -0,0 +1,144 @@
+//! Circuit breaker shared by all clones of a client
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::{Result, TwcError};
+
+/// Settings of the client's circuit breaker
+///
+/// Enabled with [`ClientBuilder::circuit_breaker`](crate::ClientBuilder::circuit_breaker).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive retryable failures (see [`TwcError::is_retryable`]) after
+    /// which the circuit opens
+    pub failure_threshold: u32,
+    /// How long an open circuit rejects requests before letting probes through
+    pub open_duration: Duration,
+    /// Probe requests allowed in flight while the circuit is half-open
+    pub half_open_max: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(30),
+            half_open_max: 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum State {
+    /// Requests pass; counts consecutive failures
+    Closed { failures: u32 },
+    /// Requests fail fast until the cool-down ends
+    Open { until: Instant },
+    /// A limited number of probes decide whether to close again
+    HalfOpen { probes: u32 },
+}
+
+/// Circuit breaker state machine
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: Mutex<State>,
+}
+
+/// Permission for one attempt to pass the breaker
+///
+/// Report its outcome with [`record`](Self::record); a pass dropped without
+/// an outcome (e.g. a cancelled probe) frees its probe slot.
+pub(crate) struct Pass<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(State::Closed { failures: 0 }),
+        }
+    }
+
+    /// Let an attempt through, or fail with [`TwcError::CircuitOpen`]
+    pub(crate) fn admit(&self) -> Result<Pass<'_>> {
+        let mut state = self.lock();
+        let probe = match *state {
+            State::Closed { .. } => false,
+            State::Open { until } => {
+                let now = Instant::now();
+                if now < until {
+                    return Err(TwcError::CircuitOpen(until - now));
+                }
+                *state = State::HalfOpen { probes: 1 };
+                true
+            }
+            State::HalfOpen { ref mut probes } => {
+                if *probes >= self.config.half_open_max {
+                    return Err(TwcError::CircuitOpen(Duration::ZERO));
+                }
+                *probes += 1;
+                true
+            }
+        };
+        Ok(Pass {
+            breaker: self,
+            probe,
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn open(&self, state: &mut State) {
+        *state = State::Open {
+            until: Instant::now() + self.config.open_duration,
+        };
+    }
+}
+
+impl Pass<'_> {
+    /// Record whether the attempt failed in a retryable way
+    pub(crate) fn record(mut self, failed: bool) {
+        let breaker = self.breaker;
+        let mut state = breaker.lock();
+        match *state {
+            State::Closed { ref mut failures } => {
+                if !failed {
+                    *failures = 0;
+                } else {
+                    *failures += 1;
+                    if *failures >= breaker.config.failure_threshold {
+                        breaker.open(&mut state);
+                    }
+                }
+            }
+            State::HalfOpen { .. } if self.probe => {
+                if failed {
+                    breaker.open(&mut state);
+                } else {
+                    *state = State::Closed { failures: 0 };
+                }
+            }
+            // Outcome of an attempt admitted before the circuit changed state
+            _ => {}
+        }
+        self.probe = false;
+    }
+}
+
+impl Drop for Pass<'_> {
+    fn drop(&mut self) {
+        if self.probe
+            && let State::HalfOpen { probes } = &mut *self.breaker.lock()
+        {
+            *probes = probes.saturating_sub(1);
+        }
+    }
+}
@@ -2,9 +2,33 @@
 
 use std::sync::Arc;
 
-use reqwest::header::{self, HeaderMap, HeaderValue};
+use reqwest::header::{self, HeaderMap, HeaderName, HeaderValue};
 
-use crate::{ClientConfig, Result, TwcError};
+use crate::api::endpoint::AGENTS_PREFIX;
+use crate::api::http::DEFAULT_MAX_RESPONSE_BYTES;
+use crate::breaker::CircuitBreaker;
+use crate::limit::ConcurrencyLimit;
+use crate::pricing::CostTracker;
+use crate::types::{CompatMode, NormalizePolicy, WarmUpReport};
+use crate::{CircuitBreakerConfig, ClientConfig, RequestInterceptor, Result, TwcError};
+
+/// Default base URL of the Timeweb Cloud control-plane API
+const DEFAULT_CONTROL_PLANE_URL: &str = "https://api.timeweb.cloud";
+
+/// Default path prefix of cloud-ai resources on the control-plane API
+const DEFAULT_CONTROL_PLANE_PREFIX: &str = "/api/v1/cloud-ai";
+
+/// Default `User-Agent` header
+const DEFAULT_USER_AGENT: &str = concat!("twcai/", env!("CARGO_PKG_VERSION"));
+
+/// Default cap on error messages taken from response bodies, in characters
+const DEFAULT_MAX_ERROR_MESSAGE_LEN: usize = 500;
+
+/// Default timeout for establishing a connection
+const DEFAULT_CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Default header carrying request ids
+const DEFAULT_REQUEST_ID_HEADER: &str = "x-request-id";
 
 /// Main client for Timeweb Cloud AI API
 #[derive(Clone)]
@@ -12,19 +36,155 @@ pub struct CloudAIClient {
     pub(crate) config: ClientConfig,
 }
 
+/// Sampling parameters applied to requests that leave them unset
+///
+/// Used by `chat_completions` and `create_response` (and their streaming
+/// variants); explicit per-request values always win.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ChatDefaults {
+    /// Sampling temperature
+    pub temperature: Option<f32>,
+    /// Maximum tokens to generate (`max_output_tokens` for responses)
+    pub max_completion_tokens: Option<u32>,
+    /// Nucleus sampling probability mass
+    pub top_p: Option<f32>,
+}
+
+/// How the token is attached to requests
+///
+/// Set with [`ClientBuilder::auth_style`] for deployments behind gateways
+/// that do not accept `Authorization: Bearer`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum AuthStyle {
+    /// `Authorization: Bearer <token>`
+    #[default]
+    Bearer,
+    /// The bare token in the header `name`, e.g. `X-API-Key`, or `Cookie`
+    /// with a token of the form `session=<value>`
+    Header {
+        /// Header name
+        name: String,
+    },
+    /// The token in the query parameter `name` of every request URL
+    ///
+    /// URLs may show up in logs and in transport error messages, so prefer
+    /// a header where the gateway allows it.
+    QueryParam {
+        /// Query parameter name
+        name: String,
+    },
+}
+
 /// Builder for CloudAIClient
 pub struct ClientBuilder {
     base_url: Option<String>,
+    control_plane_url: String,
+    control_plane_prefix: String,
     token: Option<String>,
     timeout: Option<std::time::Duration>,
+    connect_timeout: Option<std::time::Duration>,
+    stream_idle_timeout: Option<std::time::Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    pool_idle_timeout: Option<Option<std::time::Duration>>,
+    tcp_keepalive: Option<std::time::Duration>,
+    warm_up_on_build: bool,
+    http_version: Option<HttpVersion>,
+    compress_requests: bool,
+    max_response_bytes: usize,
+    max_error_message_len: usize,
+    default_model: Option<String>,
+    chat_defaults: ChatDefaults,
+    normalize_messages: Option<NormalizePolicy>,
+    compat_mode: CompatMode,
+    token_limit_fallback: bool,
+    default_agent: Option<String>,
+    max_retries: u32,
+    total_deadline: Option<std::time::Duration>,
+    max_concurrent_requests: Option<usize>,
+    queue_timeout: Option<std::time::Duration>,
+    hold_permit_while_streaming: bool,
+    circuit_breaker: Option<CircuitBreakerConfig>,
+    user_agent: String,
+    default_headers: Vec<(String, String)>,
+    request_id_header: String,
+    request_id_from_span: bool,
+    auth_style: Option<AuthStyle>,
+    proxy: Option<String>,
+    proxy_auth: Option<(String, String)>,
+    proxy_bypass: Option<String>,
+    no_proxy: bool,
+    root_certificates: Vec<Vec<u8>>,
+    accept_invalid_certs: bool,
+    min_tls_version: Option<crate::TlsVersion>,
+    resolve_overrides: Vec<(String, std::net::SocketAddr)>,
+    #[cfg(unix)]
+    unix_socket: Option<std::path::PathBuf>,
+    interceptors: Vec<Arc<dyn RequestInterceptor>>,
+    cost_tracker: Option<CostTracker>,
+    #[cfg(feature = "tower")]
+    base_transport: Option<crate::transport::BoxTransport>,
+    #[cfg(feature = "tower")]
+    layers: Vec<crate::transport::LayerFn>,
+}
+
+/// HTTP protocol version preference
+#[derive(Clone, Copy)]
+enum HttpVersion {
+    Http1Only,
+    Http2PriorKnowledge,
 }
 
 impl Default for ClientBuilder {
     fn default() -> Self {
         Self {
             base_url: Some("https://agent.timeweb.cloud".to_string()),
+            control_plane_url: DEFAULT_CONTROL_PLANE_URL.to_string(),
+            control_plane_prefix: DEFAULT_CONTROL_PLANE_PREFIX.to_string(),
             token: None,
             timeout: Some(std::time::Duration::from_secs(120)),
+            connect_timeout: None,
+            stream_idle_timeout: None,
+            pool_max_idle_per_host: None,
+            pool_idle_timeout: None,
+            tcp_keepalive: None,
+            warm_up_on_build: false,
+            http_version: None,
+            compress_requests: false,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            max_error_message_len: DEFAULT_MAX_ERROR_MESSAGE_LEN,
+            default_model: None,
+            chat_defaults: ChatDefaults::default(),
+            normalize_messages: None,
+            compat_mode: CompatMode::Current,
+            token_limit_fallback: false,
+            default_agent: None,
+            max_retries: 0,
+            total_deadline: None,
+            max_concurrent_requests: None,
+            queue_timeout: None,
+            hold_permit_while_streaming: false,
+            circuit_breaker: None,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            default_headers: Vec::new(),
+            request_id_header: DEFAULT_REQUEST_ID_HEADER.to_string(),
+            request_id_from_span: false,
+            auth_style: None,
+            proxy: None,
+            proxy_auth: None,
+            proxy_bypass: None,
+            no_proxy: false,
+            root_certificates: Vec::new(),
+            accept_invalid_certs: false,
+            min_tls_version: None,
+            resolve_overrides: Vec::new(),
+            #[cfg(unix)]
+            unix_socket: None,
+            interceptors: Vec::new(),
+            cost_tracker: None,
+            #[cfg(feature = "tower")]
+            base_transport: None,
+            #[cfg(feature = "tower")]
+            layers: Vec::new(),
         }
     }
 }
@@ -41,6 +201,24 @@ impl ClientBuilder {
         self
     }
 
+    /// Set the base URL of the control-plane API
+    ///
+    /// Used by knowledge base and account-level endpoints, which are served
+    /// from a different host than the agents API. Defaults to
+    /// `https://api.timeweb.cloud`.
+    pub fn control_plane_url(mut self, url: impl Into<String>) -> Self {
+        self.control_plane_url = url.into();
+        self
+    }
+
+    /// Set the path prefix of cloud-ai resources on the control-plane API
+    ///
+    /// Defaults to `/api/v1/cloud-ai`.
+    pub fn control_plane_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.control_plane_prefix = prefix.into();
+        self
+    }
+
     /// Set the authentication token
     pub fn token(mut self, token: impl Into<String>) -> Self {
         self.token = Some(token.into());
@@ -48,13 +226,504 @@ impl ClientBuilder {
     }
 
     /// Set the request timeout
+    ///
+    /// Applies to the whole of each non-streaming request. Streaming requests
+    /// are bounded by [`stream_idle_timeout`](Self::stream_idle_timeout) instead.
     pub fn timeout(mut self, duration: std::time::Duration) -> Self {
         self.timeout = Some(duration);
         self
     }
 
+    /// Set the timeout for establishing a connection (10 s by default)
+    ///
+    /// Separate from [`timeout`](Self::timeout), so an unreachable host fails
+    /// fast with [`TwcError::Connect`] while slow answers still get the full
+    /// overall timeout.
+    pub fn connect_timeout(mut self, duration: std::time::Duration) -> Self {
+        self.connect_timeout = Some(duration);
+        self
+    }
+
+    /// Set the maximum time a stream may go without receiving data
+    ///
+    /// Defaults to the request timeout.
+    pub fn stream_idle_timeout(mut self, duration: std::time::Duration) -> Self {
+        self.stream_idle_timeout = Some(duration);
+        self
+    }
+
+    /// Set the maximum number of idle pooled connections kept per host
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// Set how long idle pooled connections are kept alive
+    ///
+    /// Pass `None` to keep idle connections indefinitely.
+    pub fn pool_idle_timeout(mut self, duration: Option<std::time::Duration>) -> Self {
+        self.pool_idle_timeout = Some(duration);
+        self
+    }
+
+    /// Enable TCP keep-alive probes with the given interval
+    pub fn tcp_keepalive(mut self, duration: std::time::Duration) -> Self {
+        self.tcp_keepalive = Some(duration);
+        self
+    }
+
+    /// Open a connection to the base URL while building (see
+    /// [`CloudAIClient::warm_up`])
+    ///
+    /// [`build_async`](Self::build_async) waits for the warm-up;
+    /// [`build`](Self::build) runs it in the background when called inside
+    /// a Tokio runtime. A failed warm-up does not fail the build.
+    pub fn warm_up_on_build(mut self, warm_up: bool) -> Self {
+        self.warm_up_on_build = warm_up;
+        self
+    }
+
+    /// Only use HTTP/1.1
+    pub fn http1_only(mut self) -> Self {
+        self.http_version = Some(HttpVersion::Http1Only);
+        self
+    }
+
+    /// Use HTTP/2 without negotiation
+    ///
+    /// Only works against servers known to speak HTTP/2.
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.http_version = Some(HttpVersion::Http2PriorKnowledge);
+        self
+    }
+
+    /// Gzip-compress large JSON request bodies
+    ///
+    /// Off by default because the server may not accept compressed bodies.
+    /// When enabled, every JSON request body above 64 KiB, whichever
+    /// endpoint it goes to, is compressed and sent with
+    /// `Content-Encoding: gzip`. Multipart uploads are never compressed.
+    pub fn compress_requests(mut self, enabled: bool) -> Self {
+        self.compress_requests = enabled;
+        self
+    }
+
+    /// Set the maximum size of a buffered response body
+    ///
+    /// Reads of larger bodies (successful or not) are aborted with
+    /// [`TwcError::ResponseTooLarge`]. Defaults to 32 MiB. Streaming
+    /// responses are not limited.
+    pub fn max_response_bytes(mut self, max: usize) -> Self {
+        self.max_response_bytes = max;
+        self
+    }
+
+    /// Set the maximum length of error messages taken from response bodies
+    ///
+    /// Longer messages are cut and end with `…`. HTML error pages, as
+    /// returned by load balancers, are reduced to their text first.
+    /// Defaults to 500 characters.
+    pub fn max_error_message_len(mut self, max: usize) -> Self {
+        self.max_error_message_len = max;
+        self
+    }
+
+    /// Set the model used when a request does not name one
+    pub fn default_model(mut self, model: impl Into<String>) -> Self {
+        self.default_model = Some(model.into());
+        self
+    }
+
+    /// Set sampling parameters used when a request leaves them unset
+    pub fn default_chat_params(mut self, defaults: ChatDefaults) -> Self {
+        self.chat_defaults = defaults;
+        self
+    }
+
+    /// Normalize the messages of every chat request before sending it
+    ///
+    /// Applies [`normalize_messages`](crate::types::ChatCompletionRequest::normalize_messages)
+    /// with `policy`, for models that reject system messages after the first
+    /// turn. Off by default.
+    pub fn normalize_messages(mut self, policy: NormalizePolicy) -> Self {
+        self.normalize_messages = Some(policy);
+        self
+    }
+
+    /// Set which field chat requests carry their token limit in
+    ///
+    /// In [`CompatMode::Legacy`], a `max_completion_tokens` limit (set on the
+    /// request or by [`default_chat_params`](Self::default_chat_params)) is
+    /// sent as `max_tokens`. Defaults to [`CompatMode::Current`].
+    pub fn compat_mode(mut self, mode: CompatMode) -> Self {
+        self.compat_mode = mode;
+        self
+    }
+
+    /// Re-send a chat request rejected for its token limit field with the
+    /// other field
+    ///
+    /// When a request carrying only one of `max_tokens` and
+    /// `max_completion_tokens` fails with a 400 whose message names that
+    /// field, the limit is moved to the other field and the request is sent
+    /// once more. The switch is logged. Off by default.
+    pub fn token_limit_fallback(mut self, enabled: bool) -> Self {
+        self.token_limit_fallback = enabled;
+        self
+    }
+
+    /// Set the agent used by [`DefaultAgentExt`](crate::api::DefaultAgentExt)
+    pub fn default_agent(mut self, agent_access_id: impl Into<String>) -> Self {
+        self.default_agent = Some(agent_access_id.into());
+        self
+    }
+
+    /// Set how many times a failed request is retried
+    ///
+    /// Requests failing with a connection error, a timeout, 408 or a 5xx
+    /// status are repeated after an exponential backoff starting at 200 ms.
+    /// Requests with a streamed (non-replayable) body are never retried.
+    /// When the retries run out, the last failure is wrapped in
+    /// [`TwcError::RetriesExhausted`]. Defaults to 0.
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Bound the total time spent on a request and its retries
+    ///
+    /// Each attempt's timeout is cut to the time left, and no retry is
+    /// started whose backoff would end past the deadline. Streaming requests
+    /// keep running past it once their headers have arrived.
+    pub fn total_deadline(mut self, budget: std::time::Duration) -> Self {
+        self.total_deadline = Some(budget);
+        self
+    }
+
+    /// Limit how many requests are in flight at once
+    ///
+    /// Shared by all clones of the client. Further requests wait for a free
+    /// slot (see [`queue_timeout`](Self::queue_timeout)); each retry attempt
+    /// queues again. A slot is held until the response body has been read,
+    /// or for streams until the response headers arrive (see
+    /// [`hold_permit_while_streaming`](Self::hold_permit_while_streaming)).
+    /// Must be at least 1.
+    pub fn max_concurrent_requests(mut self, max: usize) -> Self {
+        self.max_concurrent_requests = Some(max);
+        self
+    }
+
+    /// Fail with [`TwcError::QueueTimeout`] when no request slot frees up
+    /// within `wait`
+    ///
+    /// Only applies with [`max_concurrent_requests`](Self::max_concurrent_requests).
+    /// Without it, requests wait indefinitely.
+    pub fn queue_timeout(mut self, wait: std::time::Duration) -> Self {
+        self.queue_timeout = Some(wait);
+        self
+    }
+
+    /// Keep a streaming request's slot until the stream is dropped
+    ///
+    /// Off by default, so long-lived streams do not starve other requests.
+    pub fn hold_permit_while_streaming(mut self, hold: bool) -> Self {
+        self.hold_permit_while_streaming = hold;
+        self
+    }
+
+    /// Fail fast while the API keeps failing
+    ///
+    /// After `failure_threshold` consecutive retryable failures (connection
+    /// errors, timeouts, 408 and 5xx) requests fail with
+    /// [`TwcError::CircuitOpen`] without being sent. Once `open_duration` has
+    /// passed, up to `half_open_max` probe requests go through: a probe that
+    /// does not fail that way closes the circuit, a failing one opens it
+    /// again. The state is shared by all clones of the client.
+    pub fn circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.circuit_breaker = Some(config);
+        self
+    }
+
+    /// Set the `User-Agent` header sent with every request
+    ///
+    /// Defaults to `twcai/<crate version>`.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Add a header sent with every request
+    ///
+    /// Headers set by an endpoint itself take precedence, e.g. `Accept` on
+    /// streaming and embed code requests. An invalid name or value fails
+    /// [`build`](Self::build).
+    pub fn default_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.default_headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Set the header that carries request ids
+    ///
+    /// Defaults to `x-request-id`. See
+    /// [`RequestOptions::request_id`](crate::RequestOptions::request_id).
+    pub fn request_id_header(mut self, name: impl Into<String>) -> Self {
+        self.request_id_header = name.into();
+        self
+    }
+
+    /// Send the current `tracing` span's id as the request id of calls that
+    /// set none (off by default)
+    ///
+    /// Span ids are local to the process and may be reused, and errors of
+    /// such calls come back wrapped in
+    /// [`TwcError::WithRequestId`](crate::TwcError::WithRequestId), so match
+    /// on [`TwcError::inner`](crate::TwcError::inner).
+    #[cfg(feature = "tracing")]
+    pub fn request_id_from_span(mut self, enabled: bool) -> Self {
+        self.request_id_from_span = enabled;
+        self
+    }
+
+    /// Set how the token is sent (`Authorization: Bearer` by default)
+    ///
+    /// Once set, even to [`AuthStyle::Bearer`], the style also applies to
+    /// [`get_embed_code`](crate::api::AgentClientExt::get_embed_code), which
+    /// otherwise sends no credentials.
+    pub fn auth_style(mut self, style: AuthStyle) -> Self {
+        self.auth_style = Some(style);
+        self
+    }
+
+    /// Send all requests through the HTTP(S) proxy at `url`
+    ///
+    /// Without this, proxies are taken from the `HTTP_PROXY`, `HTTPS_PROXY`
+    /// and `ALL_PROXY` environment variables (honouring `NO_PROXY`). An
+    /// explicit proxy replaces them.
+    pub fn proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Authenticate to the [`proxy`](Self::proxy) with basic credentials
+    pub fn proxy_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.proxy_auth = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Reach the given hosts directly instead of through the
+    /// [`proxy`](Self::proxy)
+    ///
+    /// Takes a comma-separated list in `NO_PROXY` format, e.g.
+    /// `"localhost,.internal.example,10.0.0.0/8"`.
+    pub fn proxy_bypass(mut self, hosts: impl Into<String>) -> Self {
+        self.proxy_bypass = Some(hosts.into());
+        self
+    }
+
+    /// Never use a proxy, ignoring the environment and [`proxy`](Self::proxy)
+    pub fn no_proxy(mut self) -> Self {
+        self.no_proxy = true;
+        self
+    }
+
+    /// Trust an additional root certificate
+    ///
+    /// Accepts PEM (possibly a bundle of several certificates) or DER bytes,
+    /// e.g. the CA of a corporate TLS-inspecting proxy. The system roots stay
+    /// trusted. Unparseable bytes fail [`build`](Self::build).
+    pub fn add_root_certificate(mut self, pem_or_der: impl Into<Vec<u8>>) -> Self {
+        self.root_certificates.push(pem_or_der.into());
+        self
+    }
+
+    /// Accept any server certificate, including self-signed and expired ones
+    ///
+    /// **Dangerous**: this disables certificate validation entirely and
+    /// makes every connection open to interception. Only use it against
+    /// local test servers; prefer [`add_root_certificate`](Self::add_root_certificate).
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Refuse TLS versions older than `version`
+    pub fn min_tls_version(mut self, version: crate::TlsVersion) -> Self {
+        self.min_tls_version = Some(version);
+        self
+    }
+
+    /// Connect to `addr` whenever a request targets `host`
+    ///
+    /// Overrides DNS for that host only; the URL, `Host` header and TLS
+    /// server name are unchanged. A port of `0` keeps the port from the URL.
+    pub fn resolve(mut self, host: impl Into<String>, addr: std::net::SocketAddr) -> Self {
+        self.resolve_overrides.push((host.into(), addr));
+        self
+    }
+
+    /// Send all requests over the Unix domain socket at `path`
+    ///
+    /// The host in `base_url` is then only used for the `Host` header (and
+    /// TLS, for `https` URLs). Proxy and [`resolve`](Self::resolve) settings
+    /// are ignored.
+    #[cfg(unix)]
+    pub fn unix_socket(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.unix_socket = Some(path.into());
+        self
+    }
+
+    /// Register a hook run around every request
+    ///
+    /// Interceptors run in the order they were registered.
+    pub fn interceptor(mut self, interceptor: Arc<dyn RequestInterceptor>) -> Self {
+        self.interceptors.push(interceptor);
+        self
+    }
+
+    /// Record the cost of every buffered response with `tracker`
+    ///
+    /// Responses that report a model and token usage are priced with the
+    /// tracker's [`PriceTable`](crate::pricing::PriceTable); keep a clone of
+    /// the tracker to read the totals. Streamed responses are not recorded.
+    pub fn cost_tracker(mut self, tracker: CostTracker) -> Self {
+        self.cost_tracker = Some(tracker);
+        self
+    }
+
+    /// Replace the HTTP transport with another tower service
+    ///
+    /// Requests never reach the network unless `service` sends them, which
+    /// makes this the hook for canned responses in tests (see
+    /// `testing::FixtureTransport` with the `testing` feature). Layers added
+    /// with [`layer`](Self::layer) wrap it.
+    #[cfg(feature = "tower")]
+    pub fn transport<S>(mut self, service: S) -> Self
+    where
+        S: tower::Service<reqwest::Request, Response = reqwest::Response, Error = TwcError>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        self.base_transport = Some(crate::transport::BoxTransport::new(service));
+        self
+    }
+
+    /// Wrap the HTTP transport in a tower layer
+    ///
+    /// All endpoints are dispatched through the layered service. Layers are
+    /// applied in call order, so the last one added is the outermost.
+    #[cfg(feature = "tower")]
+    pub fn layer<L>(mut self, layer: L) -> Self
+    where
+        L: tower::Layer<crate::transport::BoxTransport> + Send + Sync + 'static,
+        L::Service: tower::Service<reqwest::Request, Response = reqwest::Response, Error = TwcError>
+            + Clone
+            + Send
+            + Sync
+            + 'static,
+        <L::Service as tower::Service<reqwest::Request>>::Future: Send + 'static,
+    {
+        self.layers.push(Box::new(move |inner| {
+            crate::transport::BoxTransport::new(layer.layer(inner))
+        }));
+        self
+    }
+
+    /// Create a builder from environment variables
+    ///
+    /// Reads:
+    /// - `TWCAI_API_TOKEN` (required)
+    /// - `TWCAI_BASE_URL` (defaults to `https://agent.timeweb.cloud`)
+    /// - `TWCAI_AGENT_ID`, the [default agent](Self::default_agent)
+    /// - `TWCAI_TIMEOUT_SECS`, the request timeout in whole seconds
+    /// - `TWCAI_MAX_RETRIES`, see [`max_retries`](Self::max_retries)
+    ///
+    /// The last three count as unset when empty.
+    ///
+    /// The builder can be customized further before calling
+    /// [`build`](Self::build).
+    pub fn from_env() -> Result<Self> {
+        let token = std::env::var("TWCAI_API_TOKEN").map_err(|_| {
+            TwcError::Configuration("TWCAI_API_TOKEN environment variable not set".to_string())
+        })?;
+
+        let mut builder = Self::new().token(token);
+        if let Ok(base_url) = std::env::var("TWCAI_BASE_URL") {
+            builder = builder.base_url(base_url);
+        }
+        if let Some(agent) = env_var("TWCAI_AGENT_ID")? {
+            builder = builder.default_agent(agent);
+        }
+        if let Some(secs) = parse_env_var::<u64>("TWCAI_TIMEOUT_SECS", "a whole number of seconds")?
+        {
+            builder = builder.timeout(std::time::Duration::from_secs(secs));
+        }
+        if let Some(retries) = parse_env_var::<u32>("TWCAI_MAX_RETRIES", "a non-negative integer")?
+        {
+            builder = builder.max_retries(retries);
+        }
+
+        Ok(builder)
+    }
+
+    /// Create a builder from a named profile in a configuration file
+    ///
+    /// `path` defaults to `~/.config/twcai/config.toml` (honouring
+    /// `XDG_CONFIG_HOME`). Files ending in `.json` are parsed as JSON, all
+    /// others as TOML. Profiles live under `[profiles.<name>]` and may set
+    /// `base_url`, `token` or `token_file` (a secret file, relative to the
+    /// configuration file), `agent_id` and `timeout` (whole seconds).
+    /// Unknown keys are ignored with a warning.
+    #[cfg(feature = "config-file")]
+    pub fn from_profile(path: Option<std::path::PathBuf>, profile: &str) -> Result<Self> {
+        let profile = crate::profile::load(path, profile)?;
+
+        let mut builder = Self::new();
+        if let Some(base_url) = profile.base_url {
+            builder = builder.base_url(base_url);
+        }
+        if let Some(token) = profile.token {
+            builder = builder.token(token);
+        }
+        if let Some(agent) = profile.agent_id {
+            builder = builder.default_agent(agent);
+        }
+        if let Some(secs) = profile.timeout {
+            builder = builder.timeout(std::time::Duration::from_secs(secs));
+        }
+
+        Ok(builder)
+    }
+
     /// Build the client
     pub fn build(self) -> Result<CloudAIClient> {
+        let warm_up = self.warm_up_on_build;
+        let client = self.build_config().map(CloudAIClient::from_config)?;
+        if warm_up && let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let client = client.clone();
+            runtime.spawn(async move {
+                let _ = client.warm_up().await;
+            });
+        }
+        Ok(client)
+    }
+
+    /// Build the client, waiting for the warm-up if
+    /// [`warm_up_on_build`](Self::warm_up_on_build) is set
+    pub async fn build_async(self) -> Result<CloudAIClient> {
+        let warm_up = self.warm_up_on_build;
+        let client = self.build_config().map(CloudAIClient::from_config)?;
+        if warm_up {
+            let _ = client.warm_up().await;
+        }
+        Ok(client)
+    }
+
+    /// Build only the configuration, e.g. to create several clients from
+    /// with [`CloudAIClient::from_config`]
+    pub fn build_config(self) -> Result<ClientConfig> {
         let base_url = self
             .base_url
             .ok_or_else(|| TwcError::Configuration("Base URL is required".to_string()))?;
@@ -64,24 +733,181 @@ impl ClientBuilder {
             .ok_or_else(|| TwcError::Configuration("Token is required".to_string()))?;
 
         let mut headers = HeaderMap::new();
-        headers.insert(
-            header::ACCEPT,
-            HeaderValue::from_static("application/json"),
-        );
+        headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+        for (name, value) in &self.default_headers {
+            let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                TwcError::Configuration(format!("Invalid default header name: {:?}", name))
+            })?;
+            let value = HeaderValue::from_str(value).map_err(|_| {
+                TwcError::Configuration(format!("Invalid value for default header {}", name))
+            })?;
+            headers.insert(name, value);
+        }
+
+        let request_id_header =
+            HeaderName::from_bytes(self.request_id_header.as_bytes()).map_err(|_| {
+                TwcError::Configuration(format!(
+                    "Invalid request id header name: {:?}",
+                    self.request_id_header
+                ))
+            })?;
 
-        let http_client = reqwest::Client::builder()
-            .timeout(self.timeout.unwrap_or(std::time::Duration::from_secs(120)))
+        if let Some(AuthStyle::Header { name }) = &self.auth_style
+            && HeaderName::from_bytes(name.as_bytes()).is_err()
+        {
+            return Err(TwcError::Configuration(format!(
+                "Invalid auth header name: {:?}",
+                name
+            )));
+        }
+        if let Some(AuthStyle::QueryParam { name }) = &self.auth_style
+            && name.is_empty()
+        {
+            return Err(TwcError::Configuration(
+                "Auth query parameter name must not be empty".to_string(),
+            ));
+        }
+
+        let mut http_builder = reqwest::Client::builder()
             .default_headers(headers)
-            .build()
-            .map_err(TwcError::Http)?;
+            .user_agent(self.user_agent);
+        if self.no_proxy {
+            http_builder = http_builder.no_proxy();
+        } else if let Some(url) = &self.proxy {
+            let mut proxy = reqwest::Proxy::all(url)
+                .map_err(|_| TwcError::Configuration("Invalid proxy URL".to_string()))?;
+            if let Some((username, password)) = &self.proxy_auth {
+                proxy = proxy.basic_auth(username, password);
+            }
+            if let Some(hosts) = &self.proxy_bypass {
+                proxy = proxy.no_proxy(reqwest::NoProxy::from_string(hosts));
+            }
+            http_builder = http_builder.proxy(proxy);
+        } else if self.proxy_auth.is_some() || self.proxy_bypass.is_some() {
+            return Err(TwcError::Configuration(
+                "proxy_auth and proxy_bypass require an explicit proxy".to_string(),
+            ));
+        }
+        for bytes in &self.root_certificates {
+            let certificates = if bytes.trim_ascii_start().starts_with(b"-----BEGIN") {
+                reqwest::Certificate::from_pem_bundle(bytes)
+            } else {
+                reqwest::Certificate::from_der(bytes).map(|certificate| vec![certificate])
+            }
+            .map_err(|_| TwcError::Configuration("Invalid root certificate".to_string()))?;
+            for certificate in certificates {
+                http_builder = http_builder.add_root_certificate(certificate);
+            }
+        }
+        if self.accept_invalid_certs {
+            http_builder = http_builder.danger_accept_invalid_certs(true);
+        }
+        if let Some(version) = self.min_tls_version {
+            http_builder = http_builder.min_tls_version(version);
+        }
+        for (host, addr) in &self.resolve_overrides {
+            http_builder = http_builder.resolve(host, *addr);
+        }
+        #[cfg(unix)]
+        if let Some(path) = self.unix_socket {
+            http_builder = http_builder.unix_socket(path);
+        }
+        http_builder =
+            http_builder.connect_timeout(self.connect_timeout.unwrap_or(DEFAULT_CONNECT_TIMEOUT));
+        if let Some(max) = self.pool_max_idle_per_host {
+            http_builder = http_builder.pool_max_idle_per_host(max);
+        }
+        if let Some(idle_timeout) = self.pool_idle_timeout {
+            http_builder = http_builder.pool_idle_timeout(idle_timeout);
+        }
+        if let Some(keepalive) = self.tcp_keepalive {
+            http_builder = http_builder.tcp_keepalive(keepalive);
+        }
+        match self.http_version {
+            Some(HttpVersion::Http1Only) => http_builder = http_builder.http1_only(),
+            Some(HttpVersion::Http2PriorKnowledge) => {
+                http_builder = http_builder.http2_prior_knowledge()
+            }
+            None => {}
+        }
+        let http_client = http_builder.build().map_err(TwcError::Http)?;
+
+        let timeout = self.timeout.unwrap_or(std::time::Duration::from_secs(120));
+
+        if let Some(breaker) = &self.circuit_breaker
+            && (breaker.failure_threshold == 0 || breaker.half_open_max == 0)
+        {
+            return Err(TwcError::Configuration(
+                "circuit breaker failure_threshold and half_open_max must be at least 1"
+                    .to_string(),
+            ));
+        }
+
+        let concurrency = match self.max_concurrent_requests {
+            Some(0) => {
+                return Err(TwcError::Configuration(
+                    "max_concurrent_requests must be at least 1".to_string(),
+                ));
+            }
+            Some(max) => Some(ConcurrencyLimit::new(
+                max,
+                self.queue_timeout,
+                self.hold_permit_while_streaming,
+            )),
+            None => None,
+        };
+
+        #[cfg(feature = "tower")]
+        let transport = (self.base_transport.is_some() || !self.layers.is_empty()).then(|| {
+            let base = self.base_transport.unwrap_or_else(|| {
+                crate::transport::BoxTransport::new(crate::transport::HttpService::new(
+                    http_client.clone(),
+                ))
+            });
+            self.layers
+                .into_iter()
+                .fold(base, |service, layer| layer(service))
+        });
 
-        let config = ClientConfig {
+        let control_plane_prefix = self.control_plane_prefix.trim_end_matches('/');
+        let agents_url = format!("{}{}", base_url.trim_end_matches('/'), AGENTS_PREFIX);
+        let control_plane_base = format!("{}{}", self.control_plane_url, control_plane_prefix);
+        let auth_header = crate::auth_header(self.auth_style.as_ref(), &token);
+
+        Ok(ClientConfig {
             base_url: Arc::from(base_url.into_boxed_str()),
+            control_plane_url: Arc::from(self.control_plane_url.into_boxed_str()),
+            agents_url: Arc::from(agents_url.into_boxed_str()),
+            control_plane_base: Arc::from(control_plane_base.into_boxed_str()),
             token: Arc::from(token.into_boxed_str()),
+            auth_header,
             http_client,
-        };
-
-        Ok(CloudAIClient { config })
+            timeout,
+            stream_idle_timeout: self.stream_idle_timeout.unwrap_or(timeout),
+            compress_requests: self.compress_requests,
+            max_response_bytes: self.max_response_bytes,
+            max_error_message_len: self.max_error_message_len,
+            default_model: self.default_model.map(Arc::from),
+            chat_defaults: self.chat_defaults,
+            normalize_messages: self.normalize_messages,
+            compat_mode: self.compat_mode,
+            token_limit_fallback: self.token_limit_fallback,
+            default_agent: self.default_agent.map(Arc::from),
+            max_retries: self.max_retries,
+            total_deadline: self.total_deadline,
+            interceptors: self.interceptors.into(),
+            cost_tracker: self.cost_tracker,
+            request_id_header,
+            request_id_from_span: self.request_id_from_span,
+            auth_style: self.auth_style,
+            concurrency,
+            circuit_breaker: self
+                .circuit_breaker
+                .map(|config| Arc::new(CircuitBreaker::new(config))),
+            conversation_locks: Arc::default(),
+            #[cfg(feature = "tower")]
+            transport,
+        })
     }
 }
 
@@ -92,26 +918,87 @@ impl CloudAIClient {
     }
 
     /// Create a client from environment variables
-    /// 
+    ///
     /// Uses TWCAI_BASE_URL (optional, defaults to https://agent.timeweb.cloud)
-    /// and TWCAI_API_TOKEN (required)
+    /// and TWCAI_API_TOKEN (required), plus the optional variables read by
+    /// [`ClientBuilder::from_env`]
     pub fn from_env() -> Result<Self> {
-        let base_url = std::env::var("TWCAI_BASE_URL")
-            .unwrap_or_else(|_| "https://agent.timeweb.cloud".to_string());
-        
-        let token = std::env::var("TWCAI_API_TOKEN")
-            .map_err(|_| TwcError::Configuration(
-                "TWCAI_API_TOKEN environment variable not set".to_string()
-            ))?;
+        ClientBuilder::from_env()?.build()
+    }
 
-        Self::builder()
-            .base_url(base_url)
-            .token(token)
-            .build()
+    /// Create a client from a configuration built with
+    /// [`ClientConfig::builder`]
+    pub fn from_config(config: ClientConfig) -> Self {
+        CloudAIClient { config }
     }
 
     /// Get the client configuration
     pub fn config(&self) -> &ClientConfig {
         &self.config
     }
+
+    /// Open a connection to the base URL ahead of the first request
+    ///
+    /// Sends an unauthenticated `HEAD` request, so the TCP and TLS setup is
+    /// paid now and the next call reuses the pooled connection. Any response
+    /// status counts as success; only a failure to connect is an error.
+    pub async fn warm_up(&self) -> Result<WarmUpReport> {
+        crate::api::http::warm_up(&self.config).await
+    }
+
+    /// Derive a client that authenticates with `token`
+    ///
+    /// The derived client shares this client's connection pool and all other
+    /// settings, so serving many tenants does not open a pool per token.
+    pub fn with_token(&self, token: impl Into<String>) -> CloudAIClient {
+        let mut config = self.config.clone();
+        config.set_token(Arc::from(token.into().into_boxed_str()));
+        CloudAIClient { config }
+    }
+
+    /// The transport API requests are dispatched through, as a tower service
+    ///
+    /// Includes the service set with [`ClientBuilder::transport`] and every
+    /// layer added with [`ClientBuilder::layer`].
+    #[cfg(feature = "tower")]
+    pub fn transport(&self) -> crate::transport::BoxTransport {
+        match &self.config.transport {
+            Some(transport) => transport.clone(),
+            None => crate::transport::BoxTransport::new(crate::transport::HttpService::new(
+                self.config.http_client.clone(),
+            )),
+        }
+    }
+
+    /// Agent used by [`DefaultAgentExt`](crate::api::DefaultAgentExt), if set
+    pub fn default_agent(&self) -> Option<&str> {
+        self.config.default_agent.as_deref()
+    }
+}
+
+/// Read an environment variable, treating unset and empty as absent
+fn env_var(name: &str) -> Result<Option<String>> {
+    match std::env::var(name) {
+        Ok(value) if value.trim().is_empty() => Ok(None),
+        Ok(value) => Ok(Some(value)),
+        Err(std::env::VarError::NotPresent) => Ok(None),
+        Err(std::env::VarError::NotUnicode(_)) => Err(TwcError::Configuration(format!(
+            "{} environment variable is not valid UTF-8",
+            name
+        ))),
+    }
+}
+
+/// Read and parse an environment variable, naming it in the error
+fn parse_env_var<T: std::str::FromStr>(name: &str, expected: &str) -> Result<Option<T>> {
+    env_var(name)?
+        .map(|value| {
+            value.trim().parse().map_err(|_| {
+                TwcError::Configuration(format!(
+                    "{} environment variable must be {}, got {:?}",
+                    name, expected, value
+                ))
+            })
+        })
+        .transpose()
 }
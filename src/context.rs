@@ -0,0 +1,111 @@
+//! Fitting conversation history into a token budget
+//!
+//! Token counts come from the [`tokens`](crate::tokens) estimator, so the
+//! budget is approximate. Summarizing dropped history is out of scope.
+
+use crate::tokens::{REPLY_PRIMING_TOKENS, estimate_message_tokens};
+use crate::types::{ChatMessage, Role};
+
+/// How [`fit_messages`] chooses which messages to drop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TruncationStrategy {
+    /// Drop the oldest turns until the rest fit
+    DropOldest,
+    /// Keep at most the last `n` non-system messages, then drop the oldest
+    /// turns if they still do not fit
+    KeepLast(usize),
+}
+
+/// Drop history so that `messages` fit into `max_tokens`
+///
+/// The budget covers the estimated prompt, including per-message and reply
+/// priming overhead (see
+/// [`estimate_request_tokens`](crate::tokens::estimate_request_tokens)).
+///
+/// Rules:
+/// - system and developer messages are never dropped;
+/// - history is dropped a turn at a time, a turn being a user message with
+///   the assistant and tool messages that answer it, so pairs stay together;
+/// - the latest turn is only split when it alone exceeds the budget, and its
+///   last message is always kept.
+///
+/// The result may still exceed `max_tokens` when the kept messages alone do.
+/// Images, audio and files count with the flat costs of the estimator.
+pub fn fit_messages(
+    messages: Vec<ChatMessage>,
+    max_tokens: usize,
+    strategy: TruncationStrategy,
+) -> Vec<ChatMessage> {
+    let costs: Vec<usize> = messages.iter().map(estimate_message_tokens).collect();
+    let mut keep = vec![true; messages.len()];
+
+    let history: Vec<usize> = (0..messages.len())
+        .filter(|&i| !is_pinned(&messages[i]))
+        .collect();
+
+    let window_start = match strategy {
+        TruncationStrategy::DropOldest => 0,
+        TruncationStrategy::KeepLast(n) => {
+            let mut start = history.len().saturating_sub(n);
+            // Do not start the window with the answer to a dropped question
+            while start > 0 && start < history.len() && messages[history[start]].role != Role::User
+            {
+                start += 1;
+            }
+            if start == history.len() && n > 0 {
+                start = history.len() - 1;
+            }
+            start
+        }
+    };
+    for &i in &history[..window_start] {
+        keep[i] = false;
+    }
+
+    let mut turns = split_turns(&messages, &history[window_start..]);
+    let mut total = REPLY_PRIMING_TOKENS
+        + (0..messages.len())
+            .filter(|&i| keep[i])
+            .map(|i| costs[i])
+            .sum::<usize>();
+
+    while total > max_tokens && turns.len() > 1 {
+        for i in turns.remove(0) {
+            keep[i] = false;
+            total -= costs[i];
+        }
+    }
+
+    if let Some(last) = turns.first() {
+        for &i in &last[..last.len() - 1] {
+            if total <= max_tokens {
+                break;
+            }
+            keep[i] = false;
+            total -= costs[i];
+        }
+    }
+
+    messages
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(message, keep)| keep.then_some(message))
+        .collect()
+}
+
+/// Messages that are never dropped
+fn is_pinned(message: &ChatMessage) -> bool {
+    matches!(message.role, Role::System | Role::Developer)
+}
+
+/// Group history indices into turns, each starting at a user message
+fn split_turns(messages: &[ChatMessage], history: &[usize]) -> Vec<Vec<usize>> {
+    let mut turns: Vec<Vec<usize>> = Vec::new();
+    for &i in history {
+        match turns.last_mut() {
+            Some(turn) if messages[i].role != Role::User => turn.push(i),
+            _ => turns.push(vec![i]),
+        }
+    }
+    turns
+}
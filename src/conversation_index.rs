@@ -0,0 +1,86 @@
+//! Index from external keys to conversations
+//!
+//! The API cannot list or search conversations, so
+//! [`find_or_create_conversation`](crate::api::ConversationsExt::find_or_create_conversation)
+//! looks conversations up in a [`ConversationIndex`] the caller provides.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, Weak};
+
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+use crate::Result;
+
+/// Store mapping a metadata key and value to a conversation id
+///
+/// Implement it over a database or cache shared by every process that
+/// handles the same keys; [`MemoryConversationIndex`] covers one process.
+pub trait ConversationIndex: Send + Sync {
+    /// Conversation id stored for `key` = `value`, if any
+    fn lookup(&self, key: &str, value: &str)
+    -> impl Future<Output = Result<Option<String>>> + Send;
+
+    /// Store `conversation_id` for `key` = `value`, replacing a previous id
+    fn store(
+        &self,
+        key: &str,
+        value: &str,
+        conversation_id: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// [`ConversationIndex`] kept in memory
+#[derive(Debug, Default)]
+pub struct MemoryConversationIndex {
+    ids: Mutex<HashMap<(String, String), String>>,
+}
+
+impl MemoryConversationIndex {
+    /// Create an empty index
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ConversationIndex for MemoryConversationIndex {
+    async fn lookup(&self, key: &str, value: &str) -> Result<Option<String>> {
+        let ids = self.ids.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(ids.get(&(key.to_string(), value.to_string())).cloned())
+    }
+
+    async fn store(&self, key: &str, value: &str, conversation_id: &str) -> Result<()> {
+        let mut ids = self.ids.lock().unwrap_or_else(|e| e.into_inner());
+        ids.insert(
+            (key.to_string(), value.to_string()),
+            conversation_id.to_string(),
+        );
+        Ok(())
+    }
+}
+
+/// Locks held while a key is looked up and its conversation created, shared
+/// by all clones of a client
+#[derive(Debug, Default)]
+pub(crate) struct KeyedLocks {
+    locks: Mutex<HashMap<String, Weak<AsyncMutex<()>>>>,
+}
+
+impl KeyedLocks {
+    /// Wait until no other task holds the lock of `key`
+    pub(crate) async fn lock(&self, key: String) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.locks.lock().unwrap_or_else(|e| e.into_inner());
+            locks.retain(|_, lock| lock.strong_count() > 0);
+            match locks.get(&key).and_then(Weak::upgrade) {
+                Some(lock) => lock,
+                None => {
+                    let lock = Arc::new(AsyncMutex::new(()));
+                    locks.insert(key, Arc::downgrade(&lock));
+                    lock
+                }
+            }
+        };
+        lock.lock_owned().await
+    }
+}
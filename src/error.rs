@@ -2,6 +2,9 @@
 
 use thiserror::Error;
 
+/// Length of the body snippet kept on [`TwcError::Decode`], in bytes
+const DECODE_SNIPPET_BYTES: usize = 256;
+
 /// Result type alias for TWCai operations
 pub type Result<T> = std::result::Result<T, TwcError>;
 
@@ -12,10 +15,29 @@ pub enum TwcError {
     #[error("HTTP request failed: {0}")]
     Http(#[from] reqwest::Error),
 
+    /// No connection to the server could be established: it is unreachable,
+    /// refused the connection, did not answer within the connect timeout, or
+    /// failed the TLS handshake
+    #[error("Connection failed: {0}")]
+    Connect(#[source] reqwest::Error),
+
     /// JSON serialization/deserialization failed
     #[error("JSON error: {0}")]
     Json(#[from] serde_json::Error),
 
+    /// A response body could not be decoded as the expected JSON
+    #[error("Failed to decode response: {source} (body starts with {snippet:?})")]
+    Decode {
+        /// The decoding error
+        source: serde_json::Error,
+        /// Start of the body, invalid UTF-8 replaced, for diagnosis
+        snippet: String,
+    },
+
+    /// Writing a downloaded body failed
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
     /// URL parsing failed
     #[error("URL error: {0}")]
     Url(#[from] url::ParseError),
@@ -32,6 +54,26 @@ pub enum TwcError {
     #[error("Access forbidden - domain not whitelisted or agent suspended")]
     Forbidden,
 
+    /// Account balance exhausted (402)
+    #[error("Payment required: {0}")]
+    PaymentRequired(String),
+
+    /// Server timed out waiting for the request (408)
+    #[error("Request timeout: {0}")]
+    RequestTimeout(String),
+
+    /// Request conflicts with the current state of the resource (409)
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    /// Request body too large, e.g. oversized multimodal payload (413)
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    /// Request was well-formed but semantically invalid (422)
+    #[error("Unprocessable entity: {0}")]
+    UnprocessableEntity(String),
+
     /// Invalid request parameters
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
@@ -43,29 +85,279 @@ pub enum TwcError {
         status: u16,
         /// Error message from server
         message: String,
+        /// `Content-Type` of the error body, e.g. `text/html` for a page
+        /// served by a load balancer
+        content_type: Option<String>,
     },
 
     /// Client configuration error
     #[error("Client configuration error: {0}")]
     Configuration(String),
 
+    /// Stream produced no data within the idle timeout
+    #[error("No data received for {0:?}")]
+    Timeout(std::time::Duration),
+
+    /// Response body exceeded the configured size limit
+    #[error("Response body too large: received {received} bytes, limit is {limit}")]
+    ResponseTooLarge {
+        /// Configured maximum body size in bytes
+        limit: usize,
+        /// Bytes received (or announced) when the read was aborted
+        received: usize,
+    },
+
     /// Response cancelled
     #[error("Response was cancelled")]
     Cancelled,
+
+    /// No request slot became free within the queue timeout
+    #[error("No request slot free after waiting {0:?}")]
+    QueueTimeout(std::time::Duration),
+
+    /// Retries ended without success: every allowed attempt failed, or the
+    /// total deadline left no room for another
+    #[error("Giving up after {attempts} attempts in {elapsed:?}: {last}")]
+    RetriesExhausted {
+        /// Number of attempts made
+        attempts: u32,
+        /// Time from the first attempt to giving up
+        elapsed: std::time::Duration,
+        /// Error of the last attempt
+        last: Box<TwcError>,
+        /// Record of every attempt, the most recent 16 at most
+        history: Vec<crate::types::AttemptRecord>,
+    },
+
+    /// Error of a call sent with a request id (see
+    /// [`RequestOptions::request_id`](crate::RequestOptions::request_id))
+    ///
+    /// The message names only the id; the error itself is the
+    /// [`source`](std::error::Error::source).
+    #[error("request id {request_id}")]
+    WithRequestId {
+        /// Request id the call was sent with
+        request_id: String,
+        /// The error itself
+        source: Box<TwcError>,
+    },
+
+    /// Circuit breaker is open after repeated failures; holds the time left
+    /// until probe requests are let through
+    #[error("Circuit breaker open, retry in {0:?}")]
+    CircuitOpen(std::time::Duration),
+
+    /// A server-sent event could not be read, e.g. because of invalid UTF-8;
+    /// the stream goes on with the next event
+    #[error("Malformed server-sent event: {0}")]
+    InvalidEvent(String),
+
+    /// The model refused to answer; holds the refusal text
+    #[error("Model refused to answer: {0}")]
+    Refusal(String),
+
+    /// A stream broke off after delivering part of the answer (see
+    /// [`ResumePolicy`](crate::ResumePolicy))
+    #[error("Stream interrupted after {} bytes of text: {source}", .partial_text.len())]
+    StreamInterrupted {
+        /// Text received before the interruption
+        partial_text: String,
+        /// The transport error that ended the stream
+        source: Box<TwcError>,
+    },
 }
 
 impl TwcError {
-    /// Create error from HTTP status code and optional message
+    /// Create error from HTTP status code and optional response body
+    ///
+    /// JSON bodies of the form `{"error": {"message": ...}}`, `{"error": ...}`,
+    /// `{"message": ...}` or `{"detail": ...}` are reduced to their message.
     pub(crate) fn from_status(status: reqwest::StatusCode, message: Option<String>) -> Self {
+        Self::from_message(status, message.map(|body| extract_message(&body)))
+    }
+
+    /// Create error from a response's status, content type and body
+    ///
+    /// Like [`from_status`](Self::from_status), but HTML pages (as served by
+    /// load balancers and proxies) are reduced to their text, and the message
+    /// is cut to `max_message_len` characters. The content type is kept on
+    /// [`TwcError::ServerError`].
+    pub(crate) fn from_body(
+        status: reqwest::StatusCode,
+        content_type: Option<&str>,
+        body: &[u8],
+        max_message_len: usize,
+    ) -> Self {
+        let body = String::from_utf8_lossy(body);
+        let html = match content_type {
+            Some(content_type) => content_type.to_ascii_lowercase().contains("html"),
+            None => body.trim_start().starts_with('<'),
+        };
+        let message = if html {
+            html_text(&body)
+        } else {
+            extract_message(&body)
+        };
+
+        let mut error = Self::from_message(status, Some(truncate(message, max_message_len)));
+        if let TwcError::ServerError {
+            content_type: slot, ..
+        } = &mut error
+        {
+            *slot = content_type.map(str::to_string);
+        }
+        error
+    }
+
+    /// Decoding error of the response `body`, keeping its first
+    /// [`DECODE_SNIPPET_BYTES`] bytes
+    pub(crate) fn decode(source: serde_json::Error, body: &[u8]) -> Self {
+        let snippet = &body[..body.len().min(DECODE_SNIPPET_BYTES)];
+        TwcError::Decode {
+            source,
+            snippet: String::from_utf8_lossy(snippet).into_owned(),
+        }
+    }
+
+    /// Classify an error of sending a request: connection failures become
+    /// [`TwcError::Connect`], everything else [`TwcError::Http`]
+    pub(crate) fn from_transport(error: reqwest::Error) -> Self {
+        if error.is_connect() {
+            TwcError::Connect(error)
+        } else {
+            TwcError::Http(error)
+        }
+    }
+
+    fn from_message(status: reqwest::StatusCode, message: Option<String>) -> Self {
+        let message = message.filter(|message| !message.is_empty());
+        let or = |default: &str| message.clone().unwrap_or_else(|| default.to_string());
+
         match status.as_u16() {
             401 => TwcError::Unauthorized,
+            402 => TwcError::PaymentRequired(or("Payment required")),
             403 => TwcError::Forbidden,
-            404 => TwcError::NotFound(message.unwrap_or_else(|| "Resource not found".to_string())),
+            404 => TwcError::NotFound(or("Resource not found")),
+            408 => TwcError::RequestTimeout(or("Request timeout")),
+            409 => TwcError::Conflict(or("Conflict")),
+            413 => TwcError::PayloadTooLarge(or("Payload too large")),
+            422 => TwcError::UnprocessableEntity(or("Unprocessable entity")),
             500..=599 => TwcError::ServerError {
                 status: status.as_u16(),
-                message: message.unwrap_or_else(|| "Internal server error".to_string()),
+                message: or(status.canonical_reason().unwrap_or("Server error")),
+                content_type: None,
             },
-            _ => TwcError::InvalidRequest(message.unwrap_or_else(|| "Bad request".to_string())),
+            _ => TwcError::InvalidRequest(or("Bad request")),
+        }
+    }
+
+    /// Whether repeating the same request may succeed
+    ///
+    /// True for timeouts, connection failures, 408 and 5xx responses. Client
+    /// errors such as 402, 413 and 422 are never retryable.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            TwcError::WithRequestId { source, .. } => source.is_retryable(),
+            TwcError::Http(e) => e.is_timeout() || e.is_connect(),
+            TwcError::Connect(_) => true,
+            TwcError::Timeout(_) | TwcError::RequestTimeout(_) => true,
+            TwcError::ServerError { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the error broke off a connection or a stream rather than
+    /// coming from the server: transport and connect failures and idle
+    /// timeouts, wrapped in a request id or not
+    pub(crate) fn is_broken_connection(&self) -> bool {
+        matches!(
+            self.inner(),
+            TwcError::Http(_) | TwcError::Connect(_) | TwcError::Timeout(_)
+        )
+    }
+
+    /// Request id the failed call was sent with, if any
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            TwcError::WithRequestId { request_id, .. } => Some(request_id),
+            _ => None,
+        }
+    }
+
+    /// The error without the request id it may be wrapped with
+    ///
+    /// Handy for matching on the kind of failure:
+    /// `matches!(err.inner(), TwcError::NotFound(_))`.
+    pub fn inner(&self) -> &TwcError {
+        match self {
+            TwcError::WithRequestId { source, .. } => source.inner(),
+            other => other,
         }
     }
 }
+
+/// Text of an HTML page: tags, scripts and styles dropped, common entities
+/// decoded and whitespace collapsed
+fn html_text(html: &str) -> String {
+    // ASCII lowercasing keeps byte offsets, so indices carry over to `html`
+    let lower = html.to_ascii_lowercase();
+    let mut text = String::new();
+    let mut pos = 0;
+
+    while let Some(offset) = lower[pos..].find('<') {
+        let start = pos + offset;
+        text.push_str(&html[pos..start]);
+        text.push(' ');
+
+        let element_end = ["script", "style"]
+            .iter()
+            .find(|name| lower[start + 1..].starts_with(*name))
+            .and_then(|name| lower[start..].find(&format!("</{}", name)))
+            .map_or(start, |end| start + end);
+        pos = lower[element_end..]
+            .find('>')
+            .map_or(html.len(), |end| element_end + end + 1);
+    }
+    text.push_str(&html[pos..]);
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Cut `message` to at most `max_len` characters, the last of them a `…`
+/// marking the cut
+fn truncate(message: String, max_len: usize) -> String {
+    if message.chars().nth(max_len).is_none() {
+        return message;
+    }
+    let Some(kept) = max_len.checked_sub(1) else {
+        return String::new();
+    };
+    let end = message.char_indices().nth(kept).map_or(0, |(end, _)| end);
+    format!("{}…", &message[..end])
+}
+
+/// Pull a human-readable message out of an error body
+fn extract_message(body: &str) -> String {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(body) else {
+        return body.trim().to_string();
+    };
+
+    let message = value
+        .pointer("/error/message")
+        .or_else(|| value.get("error"))
+        .or_else(|| value.get("message"))
+        .or_else(|| value.get("detail"));
+
+    match message {
+        Some(serde_json::Value::String(message)) => message.clone(),
+        Some(other) if !other.is_null() => other.to_string(),
+        _ => body.trim().to_string(),
+    }
+}
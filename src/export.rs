@@ -0,0 +1,246 @@
+//! Conversation export to portable JSON and Markdown, and import back
+//!
+//! Items are rendered from their raw JSON so that content types this crate
+//! does not model are preserved instead of dropped.
+
+use serde_json::{Value, json};
+
+use crate::{
+    Result, TwcError,
+    types::{Conversation, ConversationItemContent, ConversationItemMessage},
+};
+
+/// Identifier written into JSON exports
+pub const EXPORT_FORMAT_VERSION: &str = "twcai.conversation.v1";
+
+/// Output format for [`export_conversation`](crate::api::ConversationsExt::export_conversation)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Stable JSON document: conversation header plus ordered items
+    Json,
+    /// Human-readable Markdown transcript
+    Markdown,
+}
+
+/// Render a conversation and its items (oldest first) in the given format
+pub fn render(
+    conversation: &Conversation,
+    items: &[Value],
+    format: ExportFormat,
+) -> Result<String> {
+    match format {
+        ExportFormat::Json => render_json(conversation, items),
+        ExportFormat::Markdown => Ok(render_markdown(conversation, items)),
+    }
+}
+
+fn render_json(conversation: &Conversation, items: &[Value]) -> Result<String> {
+    let document = json!({
+        "format": EXPORT_FORMAT_VERSION,
+        "conversation": conversation,
+        "items": items,
+    });
+    Ok(serde_json::to_string_pretty(&document)?)
+}
+
+fn render_markdown(conversation: &Conversation, items: &[Value]) -> String {
+    let mut out = format!("# Conversation {}\n\n", conversation.id);
+    out.push_str(&format!("Created at: {}\n", conversation.created_at));
+
+    if let Some(metadata) = &conversation.metadata {
+        out.push_str("\nMetadata:\n\n");
+        push_json_block(&mut out, &metadata.to_value());
+    }
+
+    for item in items {
+        out.push('\n');
+        let is_message = item.get("type").and_then(Value::as_str) == Some("message");
+        let role = item.get("role").and_then(Value::as_str);
+
+        match (is_message, role) {
+            (true, Some(role)) => {
+                out.push_str(&format!("## {}\n", capitalize(role)));
+                let parts = item
+                    .get("content")
+                    .and_then(Value::as_array)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                for part in parts {
+                    out.push('\n');
+                    match part.get("text").and_then(Value::as_str) {
+                        Some(text) => push_fenced(&mut out, "text", text),
+                        None => push_json_block(&mut out, part),
+                    }
+                }
+            }
+            _ => {
+                let item_type = item
+                    .get("type")
+                    .and_then(Value::as_str)
+                    .unwrap_or("unknown");
+                out.push_str(&format!("## Item ({})\n\n", item_type));
+                push_json_block(&mut out, item);
+            }
+        }
+    }
+
+    out
+}
+
+fn push_json_block(out: &mut String, value: &Value) {
+    let pretty = serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string());
+    push_fenced(out, "json", &pretty);
+}
+
+/// Append `content` in a code fence longer than any backtick run inside it
+fn push_fenced(out: &mut String, lang: &str, content: &str) {
+    let longest_run = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat((longest_run + 1).max(3));
+
+    out.push_str(&format!("{}{}\n{}\n{}\n", fence, lang, content, fence));
+}
+
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Source for [`import_conversation`](crate::api::ConversationsExt::import_conversation)
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImportSource {
+    /// Document produced by [`ExportFormat::Json`]
+    Export(Value),
+    /// OpenAI-style messages array (`[{"role": ..., "content": ...}]`)
+    Messages(Vec<Value>),
+}
+
+impl ImportSource {
+    /// Parse either an export document or a messages array from JSON text
+    pub fn from_json(json: &str) -> Result<Self> {
+        match serde_json::from_str(json)? {
+            Value::Array(messages) => Ok(Self::Messages(messages)),
+            document @ Value::Object(_) => Ok(Self::Export(document)),
+            _ => Err(TwcError::InvalidRequest(
+                "Import source must be an export document or a messages array".to_string(),
+            )),
+        }
+    }
+}
+
+/// Conversation contents ready to be recreated through the API
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ImportPlan {
+    pub metadata: Option<Value>,
+    pub items: Vec<ConversationItemMessage>,
+}
+
+/// Validate an import source and convert it into message items
+///
+/// Only message items with text content can be recreated. `system` and
+/// `developer` messages are joined in order, separated by a blank line,
+/// into a single `system` item placed first. Every malformed entry is
+/// reported with its index in one [`TwcError::InvalidRequest`].
+pub(crate) fn prepare_import(source: ImportSource) -> Result<ImportPlan> {
+    let (metadata, entries) = match source {
+        ImportSource::Export(document) => parse_export_document(document)?,
+        ImportSource::Messages(messages) => (None, messages),
+    };
+
+    let mut system_parts = Vec::new();
+    let mut items = Vec::new();
+    let mut errors = Vec::new();
+
+    for (index, entry) in entries.iter().enumerate() {
+        match parse_entry(entry) {
+            Ok((role, texts)) if role == "system" || role == "developer" => {
+                system_parts.extend(texts)
+            }
+            Ok((role, texts)) => items.push(message_item(role, texts)),
+            Err(reason) => errors.push(format!("entry {}: {}", index, reason)),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(TwcError::InvalidRequest(format!(
+            "Cannot import conversation: {}",
+            errors.join("; ")
+        )));
+    }
+
+    if !system_parts.is_empty() {
+        items.insert(0, message_item("system", vec![system_parts.join("\n\n")]));
+    }
+
+    Ok(ImportPlan { metadata, items })
+}
+
+fn parse_export_document(mut document: Value) -> Result<(Option<Value>, Vec<Value>)> {
+    let format = document.get("format").and_then(Value::as_str);
+    if format != Some(EXPORT_FORMAT_VERSION) {
+        return Err(TwcError::InvalidRequest(format!(
+            "Unsupported export format {:?}, expected {:?}",
+            format.unwrap_or_default(),
+            EXPORT_FORMAT_VERSION
+        )));
+    }
+
+    let metadata = document
+        .pointer_mut("/conversation/metadata")
+        .map(Value::take)
+        .filter(|m| !m.is_null());
+
+    match document.get_mut("items").map(Value::take) {
+        Some(Value::Array(items)) => Ok((metadata, items)),
+        _ => Err(TwcError::InvalidRequest(
+            "Export document has no items array".to_string(),
+        )),
+    }
+}
+
+/// Extract role and text parts from a single message entry
+fn parse_entry(entry: &Value) -> std::result::Result<(&str, Vec<String>), String> {
+    match entry.get("type").and_then(Value::as_str) {
+        None | Some("message") => {}
+        Some(item_type) => return Err(format!("item type {:?} cannot be imported", item_type)),
+    }
+
+    let role = match entry.get("role").and_then(Value::as_str) {
+        Some(role @ ("user" | "assistant" | "system" | "developer")) => role,
+        Some(role) => return Err(format!("unsupported role {:?}", role)),
+        None => return Err("missing role".to_string()),
+    };
+
+    let texts = match entry.get("content") {
+        Some(Value::String(text)) => vec![text.clone()],
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| match part.get("text").and_then(Value::as_str) {
+                Some(text) => Ok(text.to_string()),
+                None => Err(format!("content part {} has no text", i)),
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?,
+        _ => return Err("missing content".to_string()),
+    };
+
+    if texts.is_empty() {
+        return Err("empty content".to_string());
+    }
+
+    Ok((role, texts))
+}
+
+fn message_item(role: &str, texts: Vec<String>) -> ConversationItemMessage {
+    let content = texts
+        .into_iter()
+        .map(|text| match role {
+            "assistant" => ConversationItemContent::output_text(text),
+            _ => ConversationItemContent::input_text(text),
+        })
+        .collect();
+
+    ConversationItemMessage::new(role, content)
+}
@@ -0,0 +1,23 @@
+//! Hooks around every outgoing API request
+
+use std::time::Duration;
+
+/// Hook invoked around every HTTP request the client sends
+///
+/// Registered with [`ClientBuilder::interceptor`](crate::ClientBuilder::interceptor).
+/// Interceptors run in registration order, once per attempt, so a retried
+/// request passes through them again.
+pub trait RequestInterceptor: Send + Sync {
+    /// Inspect or modify a request (headers, body) just before it is sent
+    fn on_request(&self, request: &mut reqwest::Request) {
+        let _ = request;
+    }
+
+    /// Observe a response and the time it took to arrive
+    ///
+    /// Called once the status and headers are received, before the body is
+    /// read. Not called when the request fails without a response.
+    fn on_response(&self, response: &reqwest::Response, elapsed: Duration) {
+        let _ = (response, elapsed);
+    }
+}
@@ -4,6 +4,8 @@
 //! - Chat completions with multimodal support (text, image, audio)
 //! - Response management
 //! - Conversation lifecycle management
+//! - Server-sent event streaming
+//! - Cursor pagination of list endpoints
 //!
 //! # Example
 //! ```
@@ -31,29 +33,255 @@
 #![warn(missing_docs)]
 
 pub mod api;
+mod breaker;
 mod client;
+pub mod context;
+mod conversation_index;
 mod error;
+pub mod export;
+mod interceptor;
+mod limit;
+mod logging;
+mod metrics;
+mod options;
+pub mod pagination;
+pub mod pricing;
+#[cfg(feature = "config-file")]
+mod profile;
+pub mod sse;
+pub mod stream;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod tokens;
+#[cfg(feature = "tower")]
+pub mod transport;
 pub mod types;
 
-pub use client::{ClientBuilder, CloudAIClient};
+pub use breaker::CircuitBreakerConfig;
+pub use client::{AuthStyle, ChatDefaults, ClientBuilder, CloudAIClient};
+pub use conversation_index::{ConversationIndex, MemoryConversationIndex};
 pub use error::{Result, TwcError};
+pub use interceptor::RequestInterceptor;
+pub use options::{RequestOptions, ResumePolicy, StreamOptionsLocal};
+pub use reqwest::tls::Version as TlsVersion;
+pub use tokio_util::sync::CancellationToken;
 
 use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::header::{AUTHORIZATION, HeaderName, HeaderValue};
 
 /// Shared HTTP client configuration
+///
+/// Built by [`ClientBuilder`] and read through [`CloudAIClient::config`];
+/// the token itself is never exposed, only [`token_hint`](Self::token_hint).
 #[derive(Clone)]
+#[non_exhaustive]
 pub struct ClientConfig {
     /// Base URL for API requests
-    pub base_url: Arc<str>,
+    pub(crate) base_url: Arc<str>,
+    /// Base URL of the Timeweb Cloud control-plane API (knowledge bases, agents)
+    pub(crate) control_plane_url: Arc<str>,
+    /// `base_url` joined with the agent API prefix, rendered once
+    pub(crate) agents_url: Arc<str>,
+    /// `control_plane_url` joined with the cloud-ai path prefix, rendered once
+    pub(crate) control_plane_base: Arc<str>,
     /// Authentication token
-    pub token: Arc<str>,
+    pub(crate) token: Arc<str>,
+    /// Auth header rendered from the token, replaced along with it; `None`
+    /// for query-parameter auth and for tokens that are not valid header
+    /// values
+    pub(crate) auth_header: Option<(HeaderName, HeaderValue)>,
     /// HTTP client instance
-    pub http_client: reqwest::Client,
+    pub(crate) http_client: reqwest::Client,
+    /// Overall timeout applied to each non-streaming request
+    pub(crate) timeout: Duration,
+    /// Maximum gap between chunks before a streaming request is abandoned
+    pub(crate) stream_idle_timeout: Duration,
+    /// Whether large JSON request bodies are gzip-compressed
+    pub(crate) compress_requests: bool,
+    /// Maximum size of a buffered response body in bytes
+    pub(crate) max_response_bytes: usize,
+    /// Maximum length of an error message taken from a response body, in
+    /// characters
+    pub(crate) max_error_message_len: usize,
+    /// Model used when a request does not name one
+    pub(crate) default_model: Option<Arc<str>>,
+    /// Sampling parameters used when a request leaves them unset
+    pub(crate) chat_defaults: ChatDefaults,
+    /// Message rewrites applied to chat requests before sending, if any
+    pub(crate) normalize_messages: Option<types::NormalizePolicy>,
+    /// Field chat requests carry their token limit in
+    pub(crate) compat_mode: types::CompatMode,
+    /// Whether a chat request rejected for its token limit field is re-sent
+    /// with the other field
+    pub(crate) token_limit_fallback: bool,
+    /// Agent used by the `DefaultAgentExt` methods
+    pub(crate) default_agent: Option<Arc<str>>,
+    /// How many times a failed request is retried
+    pub(crate) max_retries: u32,
+    /// Time budget for a request including all its retries
+    pub(crate) total_deadline: Option<Duration>,
+    /// Hooks run around every request, in registration order
+    pub(crate) interceptors: Arc<[Arc<dyn RequestInterceptor>]>,
+    /// Tracker recording the cost of every buffered response, if any
+    pub(crate) cost_tracker: Option<pricing::CostTracker>,
+    /// Limit on requests in flight, shared by all clones of the client
+    pub(crate) concurrency: Option<limit::ConcurrencyLimit>,
+    /// Header carrying the request id of a call
+    pub(crate) request_id_header: reqwest::header::HeaderName,
+    /// Whether calls without a request id send the current tracing span's id
+    pub(crate) request_id_from_span: bool,
+    /// How the token is sent; `None` for the default bearer scheme
+    pub(crate) auth_style: Option<AuthStyle>,
+    /// Circuit breaker, shared by all clones of the client
+    pub(crate) circuit_breaker: Option<Arc<breaker::CircuitBreaker>>,
+    /// Locks single-flighting find-or-create per key, shared by all clones
+    /// of the client
+    pub(crate) conversation_locks: Arc<conversation_index::KeyedLocks>,
+    /// Layered transport requests are dispatched through, if any layers were added
+    #[cfg(feature = "tower")]
+    pub(crate) transport: Option<transport::BoxTransport>,
 }
 
 impl ClientConfig {
-    /// Create authorization header value
-    pub(crate) fn auth_header(&self) -> String {
-        format!("Bearer {}", self.token)
+    /// Create a builder for a configuration; the same builder as
+    /// [`CloudAIClient::builder`], finished with
+    /// [`build_config`](ClientBuilder::build_config)
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    /// Base URL for API requests
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Base URL of the Timeweb Cloud control-plane API
+    pub fn control_plane_url(&self) -> &str {
+        &self.control_plane_url
+    }
+
+    /// The token masked for logs: its last four characters, or only
+    /// asterisks if it is shorter than eight
+    pub fn token_hint(&self) -> String {
+        let chars: Vec<char> = self.token.chars().collect();
+        if chars.len() < 8 {
+            return "****".to_string();
+        }
+        let tail: String = chars[chars.len() - 4..].iter().collect();
+        format!("****{}", tail)
+    }
+
+    /// HTTP client requests are sent with
+    pub fn http_client(&self) -> &reqwest::Client {
+        &self.http_client
+    }
+
+    /// Overall timeout applied to each non-streaming request
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Maximum gap between chunks before a streaming request is abandoned
+    pub fn stream_idle_timeout(&self) -> Duration {
+        self.stream_idle_timeout
+    }
+
+    /// How many times a failed request is retried
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Time budget for a request including all its retries, if any
+    pub fn total_deadline(&self) -> Option<Duration> {
+        self.total_deadline
+    }
+
+    /// Model used when a request does not name one
+    pub fn default_model(&self) -> Option<&str> {
+        self.default_model.as_deref()
+    }
+
+    /// Sampling parameters used when a request leaves them unset
+    pub fn chat_defaults(&self) -> ChatDefaults {
+        self.chat_defaults
+    }
+
+    /// Tracker recording the cost of every buffered response, if any
+    pub fn cost_tracker(&self) -> Option<&pricing::CostTracker> {
+        self.cost_tracker.as_ref()
+    }
+
+    /// Attach the token to `builder` as the configured [`AuthStyle`] says
+    ///
+    /// Header styles reuse the cached header; a token that cannot be cached
+    /// goes through reqwest, which reports it when the request is built.
+    pub(crate) fn authorize(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match (&self.auth_style, &self.auth_header) {
+            (Some(AuthStyle::QueryParam { name }), _) => builder.query(&[(name, &*self.token)]),
+            (_, Some((name, value))) => builder.header(name.clone(), value.clone()),
+            (None | Some(AuthStyle::Bearer), None) => builder.bearer_auth(&self.token),
+            (Some(AuthStyle::Header { name }), None) => builder.header(name.as_str(), &*self.token),
+        }
     }
+
+    /// Replace the token and the auth header rendered from it
+    pub(crate) fn set_token(&mut self, token: Arc<str>) {
+        self.auth_header = auth_header(self.auth_style.as_ref(), &token);
+        self.token = token;
+    }
+
+    /// Fill the fields `request` leaves unset from the client defaults and
+    /// normalize its messages if the client is configured to
+    pub(crate) fn apply_chat_defaults(&self, request: &mut types::ChatCompletionRequest) {
+        if let Some(policy) = self.normalize_messages {
+            request.normalize_messages(policy);
+        }
+        if request.model.is_none() {
+            request.model = self.default_model.as_deref().map(str::to_string);
+        }
+        request.temperature = request.temperature.or(self.chat_defaults.temperature);
+        request.top_p = request.top_p.or(self.chat_defaults.top_p);
+        if request.max_completion_tokens.is_none() && request.max_tokens.is_none() {
+            request.max_completion_tokens = self.chat_defaults.max_completion_tokens;
+        }
+        if self.compat_mode == types::CompatMode::Legacy && request.max_tokens.is_none() {
+            request.max_tokens = request.max_completion_tokens.take();
+        }
+    }
+
+    /// Fill the fields `request` leaves unset from the client defaults
+    pub(crate) fn apply_response_defaults(&self, request: &mut types::CreateResponseRequest) {
+        if request.model.is_none() {
+            request.model = self.default_model.as_deref().map(str::to_string);
+        }
+        request.temperature = request.temperature.or(self.chat_defaults.temperature);
+        request.top_p = request.top_p.or(self.chat_defaults.top_p);
+        request.max_output_tokens = request
+            .max_output_tokens
+            .or(self.chat_defaults.max_completion_tokens);
+    }
+}
+
+/// Header sending `token` as `style` says, marked sensitive so it is
+/// redacted from debug output; `None` for query-parameter auth or a token
+/// that is not a valid header value
+pub(crate) fn auth_header(
+    style: Option<&AuthStyle>,
+    token: &str,
+) -> Option<(HeaderName, HeaderValue)> {
+    let (name, mut value) = match style {
+        None | Some(AuthStyle::Bearer) => (
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).ok()?,
+        ),
+        Some(AuthStyle::Header { name }) => (
+            HeaderName::from_bytes(name.as_bytes()).ok()?,
+            HeaderValue::from_str(token).ok()?,
+        ),
+        Some(AuthStyle::QueryParam { .. }) => return None,
+    };
+    value.set_sensitive(true);
+    Some((name, value))
 }
@@ -0,0 +1,69 @@
+//! Client-wide limit on in-flight requests
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{Result, TwcError};
+
+/// Semaphore bounding the requests in flight across all clones of a client
+#[derive(Clone)]
+pub(crate) struct ConcurrencyLimit {
+    semaphore: Arc<Semaphore>,
+    queue_timeout: Option<Duration>,
+    hold_while_streaming: bool,
+}
+
+/// Slot taken by one request attempt
+///
+/// Stored in the response extensions, so it is released once the response
+/// (and with it a buffered body) is dropped.
+#[derive(Clone)]
+pub(crate) struct RequestPermit {
+    _permit: Arc<OwnedSemaphorePermit>,
+    hold_while_streaming: bool,
+}
+
+impl ConcurrencyLimit {
+    pub(crate) fn new(
+        max_in_flight: usize,
+        queue_timeout: Option<Duration>,
+        hold_while_streaming: bool,
+    ) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_in_flight)),
+            queue_timeout,
+            hold_while_streaming,
+        }
+    }
+
+    /// Wait for a free slot, failing with [`TwcError::QueueTimeout`] after
+    /// the queue timeout
+    pub(crate) async fn acquire(&self) -> Result<RequestPermit> {
+        let acquire = self.semaphore.clone().acquire_owned();
+        let permit = match self.queue_timeout {
+            Some(wait) => tokio::time::timeout(wait, acquire)
+                .await
+                .map_err(|_| TwcError::QueueTimeout(wait))?,
+            None => acquire.await,
+        }
+        .expect("request semaphore is never closed");
+
+        Ok(RequestPermit {
+            _permit: Arc::new(permit),
+            hold_while_streaming: self.hold_while_streaming,
+        })
+    }
+}
+
+impl RequestPermit {
+    /// Take the permit of a streaming response, if it should stay held
+    /// until the stream ends; otherwise it is released here
+    pub(crate) fn for_stream(response: &mut reqwest::Response) -> Option<Self> {
+        response
+            .extensions_mut()
+            .remove::<Self>()
+            .filter(|permit| permit.hold_while_streaming)
+    }
+}
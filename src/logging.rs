@@ -0,0 +1,122 @@
+//! Optional request/response logging
+//!
+//! Each backend is compiled in only when its feature is enabled. Request
+//! headers are never logged, so the API token cannot leak into log output.
+
+use std::time::Duration;
+
+use reqwest::{Method, StatusCode};
+
+/// Record an outgoing request
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+pub(crate) fn request(request: &reqwest::Request) {
+    #[cfg(feature = "log")]
+    {
+        let body_len = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map_or(0, <[u8]>::len);
+        log::debug!(
+            target: "twcai",
+            "-> {} {} ({} bytes)",
+            request.method(),
+            request.url().path(),
+            body_len
+        );
+    }
+}
+
+/// Record the status and latency of a response
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+pub(crate) fn response(method: &Method, path: &str, status: StatusCode, elapsed: Duration) {
+    #[cfg(feature = "log")]
+    {
+        if status.is_success() {
+            log::debug!(target: "twcai", "<- {} {} {} in {:?}", method, path, status, elapsed);
+        } else {
+            log::warn!(target: "twcai", "<- {} {} {} in {:?}", method, path, status, elapsed);
+        }
+    }
+}
+
+/// Record a request that failed before a response arrived
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+pub(crate) fn failure(method: &Method, path: &str, error: &crate::TwcError, elapsed: Duration) {
+    #[cfg(feature = "log")]
+    {
+        let kind = match error {
+            crate::TwcError::Http(e) if e.is_timeout() => "timeout",
+            crate::TwcError::Connect(_) => "connect error",
+            crate::TwcError::Http(_) => "transport error",
+            _ => "transport layer error",
+        };
+        log::warn!(target: "twcai", "<- {} {} failed ({}) after {:?}", method, path, kind, elapsed);
+    }
+}
+
+/// Record a retryable failure of attempt `attempt` (counted from 1) that
+/// is retried after `delay`
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+pub(crate) fn retry(
+    method: &Method,
+    path: &str,
+    attempt: u32,
+    delay: Duration,
+    error: &crate::TwcError,
+) {
+    #[cfg(feature = "log")]
+    log::warn!(
+        target: "twcai",
+        "<- {} {} attempt {} failed ({}), retrying in {:?}",
+        method,
+        path,
+        attempt,
+        error,
+        delay
+    );
+}
+
+/// Record a chat request re-sent with its token limit in another field
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+pub(crate) fn token_limit_fallback(rejected: &str, replacement: &str) {
+    #[cfg(feature = "log")]
+    log::warn!(
+        target: "twcai",
+        "server rejected {}, retrying with {}",
+        rejected,
+        replacement
+    );
+}
+
+/// Warn about a configuration file key this crate does not know
+#[cfg(feature = "config-file")]
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+pub(crate) fn unknown_config_key(path: &std::path::Path, profile: &str, key: &str) {
+    #[cfg(feature = "log")]
+    log::warn!(
+        target: "twcai",
+        "unknown key {:?} in profile {:?} of {}",
+        key,
+        profile,
+        path.display()
+    );
+}
+
+/// Record token usage found in a successful JSON body
+#[cfg_attr(not(feature = "log"), allow(unused_variables))]
+pub(crate) fn usage(body: &[u8]) {
+    #[cfg(feature = "log")]
+    {
+        #[derive(serde::Deserialize)]
+        struct UsageProbe {
+            usage: Option<serde_json::Value>,
+        }
+
+        if !log::log_enabled!(target: "twcai", log::Level::Debug) {
+            return;
+        }
+        if let Ok(UsageProbe { usage: Some(usage) }) = serde_json::from_slice(body) {
+            log::debug!(target: "twcai", "<- usage {}", usage);
+        }
+    }
+}
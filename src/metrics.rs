@@ -0,0 +1,107 @@
+//! Optional metrics instrumentation through the `metrics` crate facade
+//!
+//! Compiled in only with the `metrics` feature. No exporter is bundled; the
+//! application installs whichever recorder it uses. Emitted metrics:
+//! - `twcai_requests_total{endpoint,status}`, counter
+//! - `twcai_request_duration_seconds{endpoint}`, histogram
+//! - `twcai_tokens_total{kind}`, counter with `kind` `prompt` or `completion`
+
+use std::time::Duration;
+
+#[cfg(feature = "metrics")]
+use reqwest::Method;
+use reqwest::StatusCode;
+
+/// Record a finished request; `status` is `None` when no response arrived
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn request(request: &RequestInfo, status: Option<StatusCode>, elapsed: Duration) {
+    #[cfg(feature = "metrics")]
+    {
+        let endpoint = endpoint_label(&request.method, &request.path);
+        let status = status.map_or_else(|| "error".to_string(), |s| s.as_u16().to_string());
+        metrics::counter!("twcai_requests_total", "endpoint" => endpoint.clone(), "status" => status)
+            .increment(1);
+        metrics::histogram!("twcai_request_duration_seconds", "endpoint" => endpoint)
+            .record(elapsed.as_secs_f64());
+    }
+}
+
+/// Record token usage found in a successful JSON body
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn usage(body: &[u8]) {
+    #[cfg(feature = "metrics")]
+    {
+        #[derive(serde::Deserialize)]
+        struct UsageProbe {
+            usage: Option<UsageCounts>,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct UsageCounts {
+            #[serde(alias = "input_tokens")]
+            prompt_tokens: Option<u64>,
+            #[serde(alias = "output_tokens")]
+            completion_tokens: Option<u64>,
+        }
+
+        if let Ok(UsageProbe { usage: Some(usage) }) = serde_json::from_slice(body) {
+            if let Some(prompt) = usage.prompt_tokens {
+                metrics::counter!("twcai_tokens_total", "kind" => "prompt").increment(prompt);
+            }
+            if let Some(completion) = usage.completion_tokens {
+                metrics::counter!("twcai_tokens_total", "kind" => "completion")
+                    .increment(completion);
+            }
+        }
+    }
+}
+
+/// Method and path of a request, captured before it is sent
+pub(crate) struct RequestInfo {
+    #[cfg(feature = "metrics")]
+    method: Method,
+    #[cfg(feature = "metrics")]
+    path: String,
+}
+
+impl RequestInfo {
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    pub(crate) fn new(request: &reqwest::Request) -> Self {
+        Self {
+            #[cfg(feature = "metrics")]
+            method: request.method().clone(),
+            #[cfg(feature = "metrics")]
+            path: request.url().path().to_string(),
+        }
+    }
+}
+
+/// Low-cardinality endpoint label: method plus path with ids replaced,
+/// e.g. `POST /agents/{id}/v1/chat/completions`
+#[cfg(feature = "metrics")]
+fn endpoint_label(method: &Method, path: &str) -> String {
+    const COLLECTIONS: [&str; 7] = [
+        "agents",
+        "conversations",
+        "responses",
+        "items",
+        "files",
+        "knowledge-bases",
+        "documents",
+    ];
+    const KEYWORDS: [&str; 4] = ["v1", "input_items", "cancel", "usage"];
+
+    let path = path.strip_prefix("/api/v1/cloud-ai").unwrap_or(path);
+    let mut label = format!("{} ", method);
+    let mut previous = "";
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        label.push('/');
+        if COLLECTIONS.contains(&previous) && !KEYWORDS.contains(&segment) {
+            label.push_str("{id}");
+        } else {
+            label.push_str(segment);
+        }
+        previous = segment;
+    }
+    label
+}
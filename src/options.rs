@@ -0,0 +1,108 @@
+//! Per-call request options
+
+use std::time::Duration;
+
+/// Options applied to a single API call
+///
+/// Passed to the `_with_options` variants of endpoints, e.g.
+/// [`create_response_with_options`](crate::api::ResponsesExt::create_response_with_options).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestOptions {
+    pub(crate) idempotency_key: Option<String>,
+    pub(crate) request_id: Option<String>,
+}
+
+impl RequestOptions {
+    /// Create empty options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send `key` as the `Idempotency-Key` header
+    ///
+    /// Lets the server recognise a repeated create as the same logical
+    /// request. Without it, a key is generated for POST requests whenever
+    /// retries are enabled (see
+    /// [`ClientBuilder::max_retries`](crate::ClientBuilder::max_retries)).
+    /// Every attempt of a call sends the same key.
+    pub fn idempotency_key(mut self, key: impl Into<String>) -> Self {
+        self.idempotency_key = Some(key.into());
+        self
+    }
+
+    /// Send `id` in the request id header (`x-request-id` unless changed
+    /// with [`ClientBuilder::request_id_header`](crate::ClientBuilder::request_id_header))
+    ///
+    /// Errors of the call are wrapped in [`TwcError::WithRequestId`](crate::TwcError::WithRequestId)
+    /// carrying the id, so they can be joined with server logs. With the
+    /// `tracing` feature, the current span's id can be sent when no id is
+    /// set (see `ClientBuilder::request_id_from_span`).
+    pub fn request_id(mut self, id: impl Into<String>) -> Self {
+        self.request_id = Some(id.into());
+        self
+    }
+}
+
+/// Client-side options of a single stream
+///
+/// Passed to the `_stream_with_options` endpoints. Unlike the wire-level
+/// `stream_options` of a request, nothing here is sent to the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamOptionsLocal {
+    /// Longest silence before the stream fails with
+    /// [`TwcError::Timeout`](crate::TwcError::Timeout) and closes; any data
+    /// resets it, `:` heartbeat comments included
+    pub idle_timeout: Duration,
+    /// What a chat stream does when its connection drops midway
+    pub resume_policy: ResumePolicy,
+}
+
+impl StreamOptionsLocal {
+    /// Idle timeout used by [`Default`], generous enough for slow models
+    pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+    /// Options with the given idle timeout
+    pub fn idle_timeout(idle_timeout: Duration) -> Self {
+        Self {
+            idle_timeout,
+            resume_policy: ResumePolicy::default(),
+        }
+    }
+
+    /// Set what a chat stream does when its connection drops midway
+    pub fn resume_policy(mut self, policy: ResumePolicy) -> Self {
+        self.resume_policy = policy;
+        self
+    }
+}
+
+impl Default for StreamOptionsLocal {
+    fn default() -> Self {
+        Self::idle_timeout(Self::DEFAULT_IDLE_TIMEOUT)
+    }
+}
+
+/// Handling of a chat stream whose connection drops after it started
+///
+/// Only transport failures count: [`TwcError::Http`](crate::TwcError::Http)
+/// and idle [`TwcError::Timeout`](crate::TwcError::Timeout). Other errors are
+/// yielded unchanged under every policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResumePolicy {
+    /// Yield the transport error as is
+    #[default]
+    Off,
+    /// End the stream with [`TwcError::StreamInterrupted`](crate::TwcError::StreamInterrupted),
+    /// which carries the text received so far
+    Surface,
+    /// Send the request again, up to `max_attempts` times, with the text
+    /// received so far as an assistant message and a user message asking to
+    /// continue; then behave like [`Surface`](Self::Surface)
+    ///
+    /// A heuristic: the model may repeat or rephrase the end of the partial
+    /// text, and every attempt is billed as a new request.
+    Continue {
+        /// Requests sent at most after the first one
+        max_attempts: u32,
+    },
+}
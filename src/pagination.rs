@@ -0,0 +1,167 @@
+//! Cursor pagination shared by list endpoints
+//!
+//! List endpoints return a page of items and a cursor (`last_id` with
+//! `has_more`) for the next page. [`Page`] is the endpoint-independent form
+//! of such a page, and [`Paginator`] walks every page as a stream of items.
+//!
+//! Pages are fetched one after another, not from a snapshot, so items
+//! appended or removed while a listing is walked can make pages overlap or
+//! shift. [`Paginator::dedup_by`] skips items seen on an earlier page and
+//! [`Paginator::stop_when`] ends the walk at the first item matching a
+//! predicate, such as one created after the walk started.
+
+use std::collections::{HashSet, VecDeque};
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::Stream;
+
+use crate::Result;
+
+/// One page of a cursor-paginated listing
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    /// Items of this page, in server order
+    pub items: Vec<T>,
+    /// Whether more items follow this page
+    pub has_more: bool,
+    /// Cursor to pass as `after` for the next page (`None` on the last page)
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Build a page from items and the id of its last item
+    ///
+    /// The cursor is kept only when `has_more` is set.
+    pub fn new(items: Vec<T>, has_more: bool, last_id: Option<String>) -> Self {
+        Self {
+            items,
+            has_more,
+            next_cursor: if has_more { last_id } else { None },
+        }
+    }
+}
+
+type PageFuture<T> = Pin<Box<dyn Future<Output = Result<Page<T>>> + Send>>;
+type FetchFn<T> = Box<dyn Fn(Option<String>) -> PageFuture<T> + Send + Sync>;
+type FilterFn<T> = Box<dyn FnMut(&T) -> bool + Send + Sync>;
+type StopFn<T> = Box<dyn Fn(&T) -> bool + Send + Sync>;
+
+/// Stream of every item of a paginated listing
+///
+/// Pages are fetched lazily, one at a time, by calling the fetch closure
+/// with the cursor of the previous page (`None` for the first). The stream
+/// ends after a page without a next cursor, and ends after yielding the
+/// first error.
+pub struct Paginator<T> {
+    fetch: FetchFn<T>,
+    filter: Option<FilterFn<T>>,
+    stop: Option<StopFn<T>>,
+    buffer: VecDeque<T>,
+    pending: Option<PageFuture<T>>,
+    cursor: Option<String>,
+    done: bool,
+}
+
+impl<T> Paginator<T> {
+    /// Create a paginator from a page fetching closure
+    pub fn new<F, Fut>(fetch: F) -> Self
+    where
+        F: Fn(Option<String>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Page<T>>> + Send + 'static,
+    {
+        Self {
+            fetch: Box::new(move |cursor| Box::pin(fetch(cursor))),
+            filter: None,
+            stop: None,
+            buffer: VecDeque::new(),
+            pending: None,
+            cursor: None,
+            done: false,
+        }
+    }
+
+    /// Skip items whose key was already yielded, such as items that shifted
+    /// onto the next page while the listing was walked
+    pub fn dedup_by<K, F>(mut self, key: F) -> Self
+    where
+        K: Eq + Hash + Send + Sync + 'static,
+        F: Fn(&T) -> K + Send + Sync + 'static,
+    {
+        let mut seen = HashSet::new();
+        self.filter = Some(Box::new(move |item| seen.insert(key(item))));
+        self
+    }
+
+    /// End the stream, without yielding it, at the first item matching
+    /// `predicate`
+    pub fn stop_when<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        self.stop = Some(Box::new(predicate));
+        self
+    }
+}
+
+impl<T> Unpin for Paginator<T> {}
+
+impl<T> Stream for Paginator<T> {
+    type Item = Result<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            if let Some(item) = this.buffer.pop_front() {
+                if this.stop.as_ref().is_some_and(|stop| stop(&item)) {
+                    this.buffer.clear();
+                    this.pending = None;
+                    this.done = true;
+                    return Poll::Ready(None);
+                }
+                if this.filter.as_mut().is_some_and(|filter| !filter(&item)) {
+                    continue;
+                }
+                return Poll::Ready(Some(Ok(item)));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+
+            let pending = match &mut this.pending {
+                Some(pending) => pending,
+                None => this.pending.insert((this.fetch)(this.cursor.take())),
+            };
+
+            match pending.as_mut().poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(result) => {
+                    this.pending = None;
+                    match result {
+                        Ok(page) => {
+                            this.buffer.extend(page.items);
+                            this.cursor = page.next_cursor;
+                            this.done = this.cursor.is_none();
+                        }
+                        Err(e) => {
+                            this.done = true;
+                            return Poll::Ready(Some(Err(e)));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Paginator<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Paginator")
+            .field("buffered", &self.buffer.len())
+            .field("cursor", &self.cursor)
+            .field("done", &self.done)
+            .finish()
+    }
+}
@@ -0,0 +1,259 @@
+//! Cost estimation from token usage
+//!
+//! A [`PriceTable`] maps model names to token prices; [`PriceTable::cost`]
+//! turns the usage of one request into a [`Cost`]. Tables are built in code
+//! or loaded from a JSON file (TOML with the `config-file` feature):
+//!
+//! ```toml
+//! currency = "RUB"
+//!
+//! [models.deepseek-reason]
+//! input = 50.0
+//! cached_input = 12.5
+//! output = 200.0
+//! ```
+//!
+//! Prices are per million tokens. A [`CostTracker`] registered with
+//! [`ClientBuilder::cost_tracker`](crate::ClientBuilder::cost_tracker)
+//! accumulates the cost of every response that reports usage.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{ResponseUsage, Usage};
+use crate::{Result, TwcError};
+
+/// Tokens the prices of a [`ModelPrice`] refer to
+const TOKENS_PER_PRICE: f64 = 1_000_000.0;
+
+/// Decimal places amounts are rounded to
+const AMOUNT_SCALE: f64 = 1_000_000.0;
+
+/// Token prices of one model, per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct ModelPrice {
+    /// Price of prompt tokens
+    pub input: f64,
+    /// Price of prompt tokens served from the cache; `input` when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_input: Option<f64>,
+    /// Price of completion tokens, reasoning tokens included
+    pub output: f64,
+}
+
+impl ModelPrice {
+    /// Prices of prompt and completion tokens
+    pub fn new(input: f64, output: f64) -> Self {
+        Self {
+            input,
+            cached_input: None,
+            output,
+        }
+    }
+
+    /// Set a discounted price for cached prompt tokens
+    pub fn cached_input(mut self, price: f64) -> Self {
+        self.cached_input = Some(price);
+        self
+    }
+}
+
+/// Per-model token prices in a single currency
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceTable {
+    /// Currency of all prices, e.g. `RUB`
+    pub currency: String,
+    /// Prices by exact model name
+    #[serde(default)]
+    pub models: HashMap<String, ModelPrice>,
+}
+
+impl PriceTable {
+    /// Empty table with prices in `currency`
+    pub fn new(currency: impl Into<String>) -> Self {
+        Self {
+            currency: currency.into(),
+            models: HashMap::new(),
+        }
+    }
+
+    /// Add or replace the prices of `model`
+    pub fn model(mut self, model: impl Into<String>, price: ModelPrice) -> Self {
+        self.models.insert(model.into(), price);
+        self
+    }
+
+    /// Load a table from a JSON file, or a TOML file with the `config-file`
+    /// feature (chosen by the `.json`/`.toml` extension)
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            TwcError::Configuration(format!("Cannot read {}: {}", path.display(), e))
+        })?;
+        let invalid = |e: String| {
+            TwcError::Configuration(format!("Invalid price table {}: {}", path.display(), e))
+        };
+
+        let is_toml = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+        if is_toml {
+            #[cfg(feature = "config-file")]
+            return toml::from_str(&contents).map_err(|e| invalid(e.to_string()));
+            #[cfg(not(feature = "config-file"))]
+            return Err(TwcError::Configuration(format!(
+                "Cannot load {}: TOML price tables need the config-file feature",
+                path.display()
+            )));
+        }
+        serde_json::from_str(&contents).map_err(|e| invalid(e.to_string()))
+    }
+
+    /// Cost of a request to `model` that used `usage`
+    ///
+    /// `None` for models missing from the table. Cached prompt tokens are
+    /// charged at `cached_input` when both the price and the detailed usage
+    /// are present. Amounts are rounded to six decimal places.
+    pub fn cost(&self, model: &str, usage: &Usage) -> Option<Cost> {
+        let price = self.models.get(model)?;
+        let (cached, cached_price) = match price.cached_input {
+            Some(cached_price) => (usage.cached_tokens().min(usage.prompt_tokens), cached_price),
+            None => (0, 0.0),
+        };
+        let full = usage.prompt_tokens - cached;
+
+        let input = round(
+            (f64::from(full) * price.input + f64::from(cached) * cached_price) / TOKENS_PER_PRICE,
+        );
+        let output = round(f64::from(usage.completion_tokens) * price.output / TOKENS_PER_PRICE);
+        Some(Cost {
+            input,
+            output,
+            total: round(input + output),
+            currency: self.currency.clone(),
+        })
+    }
+}
+
+/// Round `amount` to six decimal places
+fn round(amount: f64) -> f64 {
+    (amount * AMOUNT_SCALE).round() / AMOUNT_SCALE
+}
+
+/// Estimated cost of one or more requests
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Cost {
+    /// Cost of the prompt tokens
+    pub input: f64,
+    /// Cost of the completion tokens
+    pub output: f64,
+    /// `input` plus `output`
+    pub total: f64,
+    /// Currency of the amounts
+    pub currency: String,
+}
+
+impl std::ops::AddAssign<&Cost> for Cost {
+    fn add_assign(&mut self, other: &Cost) {
+        self.input = round(self.input + other.input);
+        self.output = round(self.output + other.output);
+        self.total = round(self.total + other.total);
+        if self.currency.is_empty() {
+            self.currency.clone_from(&other.currency);
+        }
+    }
+}
+
+/// Running cost totals, shared by all its clones
+///
+/// Feed it by hand with [`record`](Self::record), or register it with
+/// [`ClientBuilder::cost_tracker`](crate::ClientBuilder::cost_tracker) to
+/// record every response of a client that reports a model and usage.
+#[derive(Debug, Clone)]
+pub struct CostTracker {
+    table: Arc<PriceTable>,
+    totals: Arc<Mutex<Totals>>,
+}
+
+/// Accumulated state of a [`CostTracker`]
+#[derive(Debug, Default)]
+struct Totals {
+    by_model: HashMap<String, Cost>,
+    unpriced: u64,
+}
+
+impl CostTracker {
+    /// Tracker pricing usage with `table`
+    pub fn new(table: PriceTable) -> Self {
+        Self {
+            table: Arc::new(table),
+            totals: Arc::default(),
+        }
+    }
+
+    /// Add the cost of one request, returning it
+    ///
+    /// Requests to models missing from the table are counted in
+    /// [`unpriced`](Self::unpriced) and return `None`.
+    pub fn record(&self, model: &str, usage: &Usage) -> Option<Cost> {
+        let cost = self.table.cost(model, usage);
+        let mut totals = self.totals.lock().unwrap_or_else(|e| e.into_inner());
+        match &cost {
+            Some(cost) => *totals.by_model.entry(model.to_string()).or_default() += cost,
+            None => totals.unpriced += 1,
+        }
+        cost
+    }
+
+    /// Cost of every priced request so far
+    pub fn total(&self) -> Cost {
+        let totals = self.totals.lock().unwrap_or_else(|e| e.into_inner());
+        let mut total = Cost {
+            currency: self.table.currency.clone(),
+            ..Default::default()
+        };
+        for cost in totals.by_model.values() {
+            total += cost;
+        }
+        total
+    }
+
+    /// Cost of the requests to `model` so far, if any was priced
+    pub fn model_total(&self, model: &str) -> Option<Cost> {
+        let totals = self.totals.lock().unwrap_or_else(|e| e.into_inner());
+        totals.by_model.get(model).cloned()
+    }
+
+    /// Number of requests whose model is missing from the table
+    pub fn unpriced(&self) -> u64 {
+        self.totals
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .unpriced
+    }
+
+    /// Forget everything recorded so far
+    pub fn reset(&self) {
+        *self.totals.lock().unwrap_or_else(|e| e.into_inner()) = Totals::default();
+    }
+
+    /// Record the model and usage found in a successful JSON body, if any
+    pub(crate) fn observe(&self, body: &[u8]) {
+        #[derive(Deserialize)]
+        struct UsageProbe {
+            model: Option<String>,
+            usage: Option<ResponseUsage>,
+        }
+
+        if let Ok(UsageProbe {
+            model: Some(model),
+            usage: Some(usage),
+        }) = serde_json::from_slice(body)
+        {
+            self.record(&model, &usage.into());
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! Client configuration profiles loaded from a TOML or JSON file
+//!
+//! ```toml
+//! [profiles.prod]
+//! base_url = "https://agent.timeweb.cloud"
+//! token_file = "prod.token"
+//! agent_id = "agent-123"
+//! timeout = 60
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use serde::de::IgnoredAny;
+
+use crate::{Result, TwcError, logging};
+
+/// Contents of a configuration file
+#[derive(Debug, Deserialize)]
+struct ProfileFile {
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+}
+
+/// One named profile
+#[derive(Debug, Default, Deserialize)]
+pub(crate) struct Profile {
+    pub base_url: Option<String>,
+    pub token: Option<String>,
+    /// File holding the token, relative to the configuration file
+    pub token_file: Option<PathBuf>,
+    pub agent_id: Option<String>,
+    /// Request timeout in whole seconds
+    pub timeout: Option<u64>,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, IgnoredAny>,
+}
+
+/// Default configuration file: `$XDG_CONFIG_HOME/twcai/config.toml`, or
+/// `~/.config/twcai/config.toml`
+fn default_path() -> Result<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok_or_else(|| {
+            TwcError::Configuration(
+                "Cannot locate the configuration directory: neither XDG_CONFIG_HOME nor HOME is set"
+                    .to_string(),
+            )
+        })?;
+
+    Ok(config_dir.join("twcai").join("config.toml"))
+}
+
+/// Load profile `name` from `path` (or the default file), resolving
+/// `token_file` into `token`
+pub(crate) fn load(path: Option<PathBuf>, name: &str) -> Result<Profile> {
+    let path = match path {
+        Some(path) => path,
+        None => default_path()?,
+    };
+
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| TwcError::Configuration(format!("Cannot read {}: {}", path.display(), e)))?;
+
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let file: ProfileFile = if is_json {
+        serde_json::from_str(&contents).map_err(|e| invalid_file(&path, e))?
+    } else {
+        toml::from_str(&contents).map_err(|e| invalid_file(&path, e))?
+    };
+
+    let mut profiles = file.profiles;
+    let Some(mut profile) = profiles.remove(name) else {
+        let available: Vec<_> = profiles.keys().map(String::as_str).collect();
+        return Err(TwcError::Configuration(format!(
+            "Profile {:?} not found in {}; available profiles: {}",
+            name,
+            path.display(),
+            if available.is_empty() {
+                "none".to_string()
+            } else {
+                available.join(", ")
+            }
+        )));
+    };
+
+    for key in profile.unknown.keys() {
+        logging::unknown_config_key(&path, name, key);
+    }
+
+    if let Some(token_file) = profile.token_file.take() {
+        if profile.token.is_some() {
+            return Err(TwcError::Configuration(format!(
+                "Profile {:?} in {} sets both token and token_file",
+                name,
+                path.display()
+            )));
+        }
+        let token_path = match path.parent() {
+            Some(dir) => dir.join(&token_file),
+            None => token_file,
+        };
+        let token = std::fs::read_to_string(&token_path).map_err(|e| {
+            TwcError::Configuration(format!(
+                "Cannot read token file {}: {}",
+                token_path.display(),
+                e
+            ))
+        })?;
+        profile.token = Some(token.trim().to_string());
+    }
+
+    Ok(profile)
+}
+
+fn invalid_file(path: &Path, error: impl std::fmt::Display) -> TwcError {
+    TwcError::Configuration(format!(
+        "Invalid configuration file {}: {}",
+        path.display(),
+        error
+    ))
+}
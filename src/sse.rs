@@ -0,0 +1,169 @@
+//! Server-sent events parsing shared by streaming endpoints
+//!
+//! [`EventStream`] works on any byte stream and handles events split across
+//! chunks, multi-line `data:` fields, `\r\n` line endings, `:` comment
+//! heartbeats and a last event that is not followed by a blank line.
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+
+use crate::{Result, TwcError};
+
+/// A single server-sent event
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SseEvent {
+    /// Event name from the `event:` field, if any
+    pub event: Option<String>,
+    /// Event payload; multiple `data:` lines are joined with `\n`
+    pub data: String,
+    /// Last event ID from the `id:` field, if any
+    pub id: Option<String>,
+}
+
+/// Stream of server-sent events parsed from a byte stream
+///
+/// An event with a line that is not valid UTF-8 is yielded as
+/// [`TwcError::InvalidEvent`] and the stream goes on with the next event.
+/// An error of the byte stream ends it.
+pub struct EventStream<S> {
+    inner: S,
+    buffer: Vec<u8>,
+    pending: PendingEvent,
+    queue: VecDeque<Result<SseEvent>>,
+    finished: bool,
+}
+
+#[derive(Default)]
+struct PendingEvent {
+    event: Option<String>,
+    data: Vec<String>,
+    id: Option<String>,
+    malformed: bool,
+}
+
+impl PendingEvent {
+    fn take(&mut self) -> Option<Result<SseEvent>> {
+        let pending = std::mem::take(self);
+        if pending.malformed {
+            return Some(Err(TwcError::InvalidEvent(
+                "event contains invalid UTF-8".to_string(),
+            )));
+        }
+        if pending.data.is_empty() && pending.event.is_none() {
+            return None;
+        }
+        Some(Ok(SseEvent {
+            event: pending.event,
+            data: pending.data.join("\n"),
+            id: pending.id,
+        }))
+    }
+}
+
+impl<S> EventStream<S> {
+    /// Wrap a byte stream into an event stream
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            buffer: Vec::new(),
+            pending: PendingEvent::default(),
+            queue: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    fn process_buffer(&mut self) {
+        while let Some(pos) = self.buffer.iter().position(|b| *b == b'\n') {
+            let mut line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            line.pop();
+            self.process_raw_line(line);
+        }
+    }
+
+    /// Handle what is left once the byte stream ended: a last line without
+    /// a newline and an event without the closing blank line
+    fn finish(&mut self) {
+        if !self.buffer.is_empty() {
+            let line = std::mem::take(&mut self.buffer);
+            self.process_raw_line(line);
+        }
+        self.process_line("");
+    }
+
+    fn process_raw_line(&mut self, mut line: Vec<u8>) {
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        match String::from_utf8(line) {
+            Ok(line) => self.process_line(&line),
+            Err(_) => self.pending.malformed = true,
+        }
+    }
+
+    fn process_line(&mut self, line: &str) {
+        if line.is_empty() {
+            if let Some(event) = self.pending.take() {
+                self.queue.push_back(event);
+            }
+            return;
+        }
+
+        if line.starts_with(':') {
+            return;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+
+        match field {
+            "event" => self.pending.event = Some(value.to_string()),
+            "data" => self.pending.data.push(value.to_string()),
+            "id" => self.pending.id = Some(value.to_string()),
+            _ => {}
+        }
+    }
+}
+
+impl<S, E> Stream for EventStream<S>
+where
+    S: Stream<Item = std::result::Result<Bytes, E>> + Unpin,
+    E: Into<TwcError>,
+{
+    type Item = Result<SseEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(event) = this.queue.pop_front() {
+                return Poll::Ready(Some(event));
+            }
+
+            if this.finished {
+                return Poll::Ready(None);
+            }
+
+            match this.inner.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    this.buffer.extend_from_slice(&chunk);
+                    this.process_buffer();
+                }
+                Poll::Ready(Some(Err(e))) => {
+                    this.finished = true;
+                    return Poll::Ready(Some(Err(e.into())));
+                }
+                Poll::Ready(None) => {
+                    this.finish();
+                    this.finished = true;
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
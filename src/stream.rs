@@ -0,0 +1,206 @@
+//! Typed streams over server-sent events
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+
+use crate::types::ChatCompletionStreamResponse;
+use crate::{Result, TwcError, limit::RequestPermit, sse::EventStream};
+
+/// Stream of typed items returned by streaming endpoints
+///
+/// The stream owns the HTTP response: dropping it midway closes the
+/// connection, so the server can stop generating. [`abort`](Self::abort)
+/// does the same and, for streams of the responses API, also asks the server
+/// to cancel the response once its id is known.
+pub struct TwcStream<T> {
+    inner: Pin<Box<dyn Stream<Item = Result<T>> + Send>>,
+    on_abort: Option<AbortHook>,
+}
+
+/// Stream of chat completion chunks
+pub type ChatStream = TwcStream<ChatCompletionStreamResponse>;
+
+/// Best-effort cleanup run by [`TwcStream::abort`]
+type AbortHook = Box<dyn FnOnce() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+
+impl<T> TwcStream<T> {
+    /// Wrap any stream of results
+    pub fn new(stream: impl Stream<Item = Result<T>> + Send + 'static) -> Self {
+        Self {
+            inner: Box::pin(stream),
+            on_abort: None,
+        }
+    }
+
+    /// Run `hook` when the stream is aborted, after the connection is closed
+    pub(crate) fn on_abort<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.on_abort = Some(Box::new(move || Box::pin(hook())));
+        self
+    }
+
+    /// Stop the stream and close its connection
+    ///
+    /// For streams of the responses API whose response id has been seen,
+    /// `cancel_response` is also sent; its outcome is ignored.
+    pub async fn abort(self) {
+        let Self { inner, on_abort } = self;
+        drop(inner);
+        if let Some(hook) = on_abort {
+            hook().await;
+        }
+    }
+}
+
+impl<T> Stream for TwcStream<T> {
+    type Item = Result<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+/// Sentinel payload that terminates OpenAI-compatible streams
+const DONE: &str = "[DONE]";
+
+/// Fail a byte stream with [`TwcError::Timeout`] when no chunk arrives within `idle`
+///
+/// The deadline restarts with every chunk, so long-lived streams that keep
+/// producing data are never cut off.
+pub(crate) fn with_idle_timeout<S, E>(
+    inner: S,
+    idle: Duration,
+) -> impl Stream<Item = Result<Bytes>> + Send
+where
+    S: Stream<Item = std::result::Result<Bytes, E>> + Send + Unpin + 'static,
+    E: Into<TwcError>,
+{
+    futures_util::stream::unfold(Some(inner), move |state| async move {
+        let mut inner = state?;
+        match tokio::time::timeout(idle, inner.next()).await {
+            Ok(Some(Ok(chunk))) => Some((Ok(chunk), Some(inner))),
+            Ok(Some(Err(e))) => Some((Err(e.into()), None)),
+            Ok(None) => None,
+            Err(_) => Some((Err(TwcError::Timeout(idle)), None)),
+        }
+    })
+}
+
+/// Turn an SSE HTTP response into a stream of JSON-decoded payloads
+///
+/// The stream ends on the `[DONE]` sentinel or when the body ends, and
+/// stops after yielding the first transport error. Events that cannot be
+/// read or decoded are yielded as errors and the stream goes on with the
+/// next one. A concurrency slot held by the
+/// request is released now, or kept until the stream is dropped if the
+/// client is configured to hold it while streaming.
+pub(crate) fn json_stream<T>(
+    mut response: reqwest::Response,
+    idle_timeout: Duration,
+) -> TwcStream<T>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let permit = RequestPermit::for_stream(&mut response);
+    let bytes = with_idle_timeout(Box::pin(response.bytes_stream()), idle_timeout);
+    let events = EventStream::new(Box::pin(bytes));
+
+    let stream = futures_util::stream::unfold(Some((events, permit)), |state| async move {
+        let (mut events, permit) = state?;
+        loop {
+            match events.next().await? {
+                Ok(event) => {
+                    if event.data == DONE {
+                        return None;
+                    }
+                    if event.data.is_empty() {
+                        continue;
+                    }
+                    let item = serde_json::from_str::<T>(&event.data).map_err(TwcError::Json);
+                    return Some((item, Some((events, permit))));
+                }
+                Err(e @ TwcError::InvalidEvent(_)) => {
+                    return Some((Err(e), Some((events, permit))));
+                }
+                Err(e) => return Some((Err(e), None)),
+            }
+        }
+    });
+
+    TwcStream::new(stream)
+}
+
+/// Reduce a chat chunk stream to the text deltas of the first choice
+///
+/// Role-only and metadata chunks, other choices and empty deltas are
+/// skipped. Refusal deltas are gathered and surface as one
+/// [`TwcError::Refusal`] when the choice finishes or the stream ends.
+pub(crate) fn chat_text(chunks: TwcStream<ChatCompletionStreamResponse>) -> TwcStream<String> {
+    let stream = futures_util::stream::unfold(Some((chunks, String::new())), |state| async move {
+        let (mut chunks, mut refusal) = state?;
+        loop {
+            let Some(chunk) = chunks.next().await else {
+                if refusal.is_empty() {
+                    return None;
+                }
+                return Some((Err(TwcError::Refusal(refusal)), None));
+            };
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => return Some((Err(e), Some((chunks, refusal)))),
+            };
+            let Some(choice) = chunk.choices.into_iter().find(|choice| choice.index == 0) else {
+                continue;
+            };
+
+            if let Some(delta) = choice.delta.refusal {
+                refusal.push_str(&delta);
+            }
+            if choice.finish_reason.is_some() && !refusal.is_empty() {
+                return Some((Err(TwcError::Refusal(refusal)), None));
+            }
+            match choice.delta.content {
+                Some(content) if !content.is_empty() => {
+                    return Some((Ok(content), Some((chunks, refusal))));
+                }
+                _ => {}
+            }
+        }
+    });
+
+    TwcStream::new(stream)
+}
+
+/// Adapters for streams of text deltas, such as
+/// [`chat_completions_stream_text`](crate::api::AgentClientExt::chat_completions_stream_text)
+pub trait TextStreamExt: Stream<Item = Result<String>> + Sized {
+    /// Concatenate every delta into the full text, failing with the first error
+    fn collect_text(self) -> impl Future<Output = Result<String>> + Send
+    where
+        Self: Send,
+    {
+        async move {
+            let mut deltas = std::pin::pin!(self);
+            let mut text = String::new();
+            while let Some(delta) = deltas.next().await {
+                text.push_str(&delta?);
+            }
+            Ok::<_, TwcError>(text)
+        }
+    }
+}
+
+impl<S: Stream<Item = Result<String>>> TextStreamExt for S {}
@@ -0,0 +1,697 @@
+//! Canned-response transport for tests (requires the `testing` feature)
+//!
+//! [`FixtureTransport`] answers requests from a table of routes instead of
+//! the network. Plug it into a client with
+//! [`ClientBuilder::transport`](crate::ClientBuilder::transport); every
+//! request it receives is recorded, so tests can assert on what was sent.
+//! [`RecordingLayer`] captures a live session to fixture files that
+//! [`FixtureTransport::from_recordings`] replays.
+//!
+//! ```
+//! use serde_json::json;
+//! use twcai::CloudAIClient;
+//! use twcai::testing::{Fixture, FixtureTransport};
+//!
+//! let fixtures = FixtureTransport::new().route(
+//!     reqwest::Method::POST,
+//!     "/api/v1/cloud-ai/agents/*/call",
+//!     Fixture::json(200, &json!({"message": "Hi", "id": "msg-1"})),
+//! );
+//!
+//! let client = CloudAIClient::builder()
+//!     .token("test-token")
+//!     .transport(fixtures.clone())
+//!     .build()
+//!     .unwrap();
+//! ```
+//!
+//! Fixtures replace the network, so they cannot exercise it. Tests of
+//! connection reuse, proxies, TLS, connect errors, timeouts and streams cut
+//! mid-body still need a real server. Headers that reqwest adds while
+//! sending, the `User-Agent` and those from
+//! [`ClientBuilder::default_header`](crate::ClientBuilder::default_header),
+//! are not part of the recorded request. Streamed bodies such as multipart
+//! uploads are recorded empty, and routes cannot pick a response by request
+//! body.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use reqwest::header::{CONTENT_TYPE, HeaderMap};
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tower::Service;
+
+use crate::transport::BoxTransport;
+use crate::{Result, TwcError};
+
+/// Canned response served by a [`FixtureTransport`] route
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    status: StatusCode,
+    headers: Vec<(String, String)>,
+    body: Bytes,
+}
+
+impl Fixture {
+    /// Response with a raw body and content type
+    pub fn raw(status: u16, content_type: &str, body: impl Into<Bytes>) -> Self {
+        Self {
+            status: StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+            headers: vec![(CONTENT_TYPE.to_string(), content_type.to_string())],
+            body: body.into(),
+        }
+    }
+
+    /// JSON response
+    pub fn json(status: u16, body: &Value) -> Self {
+        Self::raw(status, "application/json", body.to_string())
+    }
+
+    /// Successful server-sent event stream, `body` being the raw event text
+    pub fn sse(body: impl Into<String>) -> Self {
+        Self::raw(200, "text/event-stream", body.into())
+    }
+
+    /// Successful response with the contents of a file
+    ///
+    /// The content type follows the extension: `.json` is JSON, `.sse` an
+    /// event stream, anything else plain text.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let body = std::fs::read(path).map_err(|e| {
+            TwcError::Configuration(format!("failed to read fixture {}: {}", path.display(), e))
+        })?;
+        let content_type = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => "application/json",
+            Some("sse") => "text/event-stream",
+            _ => "text/plain",
+        };
+        Ok(Self::raw(200, content_type, body))
+    }
+
+    /// Override the status code
+    pub fn status(mut self, status: u16) -> Self {
+        self.status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        self
+    }
+
+    /// Add a response header
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    fn to_response(&self) -> Result<reqwest::Response> {
+        let mut builder = http::Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        let response = builder
+            .body(self.body.clone())
+            .map_err(|e| TwcError::Configuration(format!("invalid fixture response: {}", e)))?;
+        Ok(reqwest::Response::from(response))
+    }
+}
+
+/// Request received by a [`FixtureTransport`]
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    /// HTTP method
+    pub method: Method,
+    /// URL path
+    pub path: String,
+    /// Raw query string, if any
+    pub query: Option<String>,
+    /// Request headers
+    pub headers: HeaderMap,
+    /// Request body (empty for streamed uploads)
+    pub body: Bytes,
+}
+
+impl RecordedRequest {
+    /// Parse the body as JSON
+    pub fn json(&self) -> Result<Value> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+#[derive(Debug)]
+struct Route {
+    method: Method,
+    pattern: Vec<String>,
+    fixture: Fixture,
+    once: bool,
+}
+
+impl Route {
+    fn matches(&self, method: &Method, path: &str) -> bool {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        self.method == *method
+            && self.pattern.len() == segments.len()
+            && self
+                .pattern
+                .iter()
+                .zip(&segments)
+                .all(|(pattern, segment)| is_wildcard(pattern) || pattern == segment)
+    }
+}
+
+/// `*` and `{name}` match any single path segment
+fn is_wildcard(segment: &str) -> bool {
+    segment == "*" || (segment.starts_with('{') && segment.ends_with('}'))
+}
+
+#[derive(Debug, Default)]
+struct State {
+    routes: Vec<Route>,
+    requests: Vec<RecordedRequest>,
+}
+
+/// Transport answering requests with canned [`Fixture`]s
+///
+/// Routes match on method and path (the query string is ignored), in
+/// registration order. Path patterns may use `*` or `{name}` for a single
+/// segment. A request matching no route fails with
+/// [`TwcError::Configuration`]. Clones share routes and recorded requests.
+#[derive(Debug, Clone, Default)]
+pub struct FixtureTransport {
+    state: Arc<Mutex<State>>,
+}
+
+impl FixtureTransport {
+    /// Create a transport without routes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `fixture` for every matching request
+    pub fn route(self, method: Method, pattern: &str, fixture: Fixture) -> Self {
+        self.push_route(method, pattern, fixture, false);
+        self
+    }
+
+    /// Serve `fixture` for the first matching request only
+    ///
+    /// Register several one-shot routes for the same path to script a
+    /// sequence of responses.
+    pub fn route_once(self, method: Method, pattern: &str, fixture: Fixture) -> Self {
+        self.push_route(method, pattern, fixture, true);
+        self
+    }
+
+    /// Load a session captured by [`RecordingLayer`]
+    ///
+    /// Every recording becomes a one-shot route for its exact method and
+    /// path, registered in recording order, so repeated calls to the same
+    /// endpoint replay their responses in sequence.
+    pub fn from_recordings(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let read_error = |path: &Path, e: std::io::Error| {
+            TwcError::Configuration(format!(
+                "failed to read recording {}: {}",
+                path.display(),
+                e
+            ))
+        };
+
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+            .map_err(|e| read_error(dir, e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+
+        let transport = Self::new();
+        for path in paths {
+            let contents = std::fs::read_to_string(&path).map_err(|e| read_error(&path, e))?;
+            let recording: Recording = serde_json::from_str(&contents).map_err(|e| {
+                TwcError::Configuration(format!("invalid recording {}: {}", path.display(), e))
+            })?;
+            let method = Method::from_bytes(recording.request.method.as_bytes()).map_err(|_| {
+                TwcError::Configuration(format!(
+                    "invalid method {:?} in recording {}",
+                    recording.request.method,
+                    path.display()
+                ))
+            })?;
+
+            let response = recording.response;
+            let fixture = Fixture {
+                status: StatusCode::from_u16(response.status)
+                    .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+                headers: response.headers.into_iter().collect(),
+                body: response.body.into_bytes(),
+            };
+            transport.push_route(method, &recording.request.path, fixture, true);
+        }
+        Ok(transport)
+    }
+
+    /// Requests received so far, in order
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.lock().requests.clone()
+    }
+
+    fn push_route(&self, method: Method, pattern: &str, fixture: Fixture, once: bool) {
+        self.lock().routes.push(Route {
+            method,
+            pattern: pattern
+                .trim_matches('/')
+                .split('/')
+                .map(str::to_string)
+                .collect(),
+            fixture,
+            once,
+        });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn respond(&self, request: reqwest::Request) -> Result<reqwest::Response> {
+        let recorded = RecordedRequest {
+            method: request.method().clone(),
+            path: request.url().path().to_string(),
+            query: request.url().query().map(str::to_string),
+            headers: request.headers().clone(),
+            body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(Bytes::copy_from_slice)
+                .unwrap_or_default(),
+        };
+
+        let mut state = self.lock();
+        let index = state
+            .routes
+            .iter()
+            .position(|route| route.matches(&recorded.method, &recorded.path));
+        let unmatched = format!("no fixture for {} {}", recorded.method, recorded.path);
+        state.requests.push(recorded);
+
+        let index = index.ok_or(TwcError::Configuration(unmatched))?;
+        let response = state.routes[index].fixture.to_response();
+        if state.routes[index].once {
+            state.routes.remove(index);
+        }
+        response
+    }
+}
+
+impl tower::Service<reqwest::Request> for FixtureTransport {
+    type Response = reqwest::Response;
+    type Error = TwcError;
+    type Future = std::future::Ready<Result<reqwest::Response>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: reqwest::Request) -> Self::Future {
+        std::future::ready(self.respond(request))
+    }
+}
+
+/// Assert that `actual` equals the JSON stored in the golden file at `path`
+///
+/// Objects are compared semantically, so key order and formatting of the
+/// file do not matter. With `TWCAI_UPDATE_GOLDEN` set, the file is
+/// (re)written from `actual` instead.
+///
+/// # Panics
+///
+/// When the file cannot be read or parsed, or the values differ.
+pub fn assert_json_golden(actual: &Value, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let pretty = serde_json::to_string_pretty(actual).expect("JSON value serializes");
+
+    if std::env::var_os("TWCAI_UPDATE_GOLDEN").is_some() {
+        std::fs::write(path, pretty + "\n")
+            .unwrap_or_else(|e| panic!("failed to write golden file {}: {}", path.display(), e));
+        return;
+    }
+
+    let contents = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("failed to read golden file {}: {}", path.display(), e));
+    let expected: Value = serde_json::from_str(&contents)
+        .unwrap_or_else(|e| panic!("invalid JSON in golden file {}: {}", path.display(), e));
+
+    if *actual != expected {
+        panic!(
+            "JSON does not match golden file {}\n--- expected\n{}\n--- actual\n{}",
+            path.display(),
+            serde_json::to_string_pretty(&expected).expect("JSON value serializes"),
+            pretty
+        );
+    }
+}
+
+/// Value written in place of redacted body fields
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Headers never written to recordings
+const SECRET_HEADERS: [&str; 4] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+
+/// Headers left out of recordings because they change between runs or no
+/// longer match the stored body
+const VOLATILE_HEADERS: [&str; 4] = ["connection", "content-length", "date", "transfer-encoding"];
+
+/// One request/response pair as stored on disk
+#[derive(Debug, Serialize, Deserialize)]
+struct Recording {
+    request: RecordingRequest,
+    response: RecordingResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordingRequest {
+    method: String,
+    path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    query: Option<String>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(flatten)]
+    body: RecordedBody,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RecordingResponse {
+    status: u16,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(flatten)]
+    body: RecordedBody,
+}
+
+/// Body stored as JSON when it parses, as text otherwise
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RecordedBody {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    json: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+}
+
+impl RecordedBody {
+    fn new(bytes: &[u8], redact: &[String]) -> Self {
+        if bytes.is_empty() {
+            return Self::default();
+        }
+        match serde_json::from_slice::<Value>(bytes) {
+            Ok(mut json) => {
+                for pointer in redact {
+                    redact_pointer(&mut json, pointer);
+                }
+                Self {
+                    json: Some(json),
+                    text: None,
+                }
+            }
+            Err(_) => Self {
+                json: None,
+                text: Some(String::from_utf8_lossy(bytes).into_owned()),
+            },
+        }
+    }
+
+    fn into_bytes(self) -> Bytes {
+        match (self.json, self.text) {
+            (Some(json), _) => Bytes::from(json.to_string()),
+            (None, Some(text)) => Bytes::from(text),
+            (None, None) => Bytes::new(),
+        }
+    }
+}
+
+/// Replace the value at a JSON pointer with [`REDACTED`]
+///
+/// A `*` segment matches every element of an array or member of an object.
+fn redact_pointer(value: &mut Value, pointer: &str) {
+    let segments: Vec<String> = pointer
+        .split('/')
+        .skip(1)
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect();
+    redact_segments(value, &segments);
+}
+
+fn redact_segments(value: &mut Value, segments: &[String]) {
+    let Some((first, rest)) = segments.split_first() else {
+        *value = Value::String(REDACTED.to_string());
+        return;
+    };
+
+    match value {
+        Value::Object(map) if first == "*" => {
+            map.values_mut()
+                .for_each(|child| redact_segments(child, rest));
+        }
+        Value::Array(items) if first == "*" => {
+            items
+                .iter_mut()
+                .for_each(|child| redact_segments(child, rest));
+        }
+        Value::Object(map) => {
+            if let Some(child) = map.get_mut(first.as_str()) {
+                redact_segments(child, rest);
+            }
+        }
+        Value::Array(items) => {
+            if let Some(child) = first.parse::<usize>().ok().and_then(|i| items.get_mut(i)) {
+                redact_segments(child, rest);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn recorded_headers(headers: &HeaderMap, redact: &[String]) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .filter(|(name, _)| {
+            let name = name.as_str();
+            !SECRET_HEADERS.contains(&name)
+                && !VOLATILE_HEADERS.contains(&name)
+                && !redact
+                    .iter()
+                    .any(|redacted| redacted.eq_ignore_ascii_case(name))
+        })
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+/// Query string with redacted parameters and redacted headers' names removed
+fn recorded_query(query: &str, config: &RecordingLayer) -> Option<String> {
+    let redacted = |name: &str| {
+        config.query_params.iter().any(|param| param == name)
+            || config
+                .headers
+                .iter()
+                .any(|header| header.eq_ignore_ascii_case(name))
+    };
+    let kept: Vec<&str> = query
+        .split('&')
+        .filter(|pair| !redacted(pair.split('=').next().unwrap_or_default()))
+        .collect();
+    (!kept.is_empty()).then(|| kept.join("&"))
+}
+
+/// Layer capturing every request/response pair to fixture files
+///
+/// Requests pass through to the wrapped transport unchanged. Each pair is
+/// written as pretty-printed JSON to `NNNN-<method>-<path>.json` in the
+/// recording directory, numbered in request order, ready for
+/// [`FixtureTransport::from_recordings`]. `Authorization`, `Cookie` and
+/// `Set-Cookie` headers are always stripped; more headers, query parameters
+/// and body fields (by JSON pointer) can be redacted. A client using
+/// `AuthStyle::QueryParam` should redact its token's parameter with
+/// [`redact_query_param`](Self::redact_query_param).
+///
+/// Response bodies are buffered in full before being handed back, so
+/// streaming responses arrive at once while recording.
+#[derive(Debug, Clone)]
+pub struct RecordingLayer {
+    dir: PathBuf,
+    headers: Vec<String>,
+    query_params: Vec<String>,
+    request_fields: Vec<String>,
+    response_fields: Vec<String>,
+}
+
+impl RecordingLayer {
+    /// Record into `dir`, which is created when missing
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            headers: Vec::new(),
+            query_params: Vec::new(),
+            request_fields: Vec::new(),
+            response_fields: Vec::new(),
+        }
+    }
+
+    /// Leave a header out of recorded requests and responses
+    ///
+    /// A request query parameter of the same name is left out as well.
+    pub fn redact_header(mut self, name: impl Into<String>) -> Self {
+        self.headers.push(name.into());
+        self
+    }
+
+    /// Leave a request query parameter out of recordings, e.g. the token's
+    /// parameter with `AuthStyle::QueryParam`
+    pub fn redact_query_param(mut self, name: impl Into<String>) -> Self {
+        self.query_params.push(name.into());
+        self
+    }
+
+    /// Redact a request body field, e.g. `/metadata/email`
+    ///
+    /// `*` segments match every array element or object member.
+    pub fn redact_request_field(mut self, pointer: impl Into<String>) -> Self {
+        self.request_fields.push(pointer.into());
+        self
+    }
+
+    /// Redact a response body field, e.g. `/id` or `/data/*/id`
+    pub fn redact_response_field(mut self, pointer: impl Into<String>) -> Self {
+        self.response_fields.push(pointer.into());
+        self
+    }
+}
+
+impl tower::Layer<BoxTransport> for RecordingLayer {
+    type Service = RecordingTransport;
+
+    fn layer(&self, inner: BoxTransport) -> RecordingTransport {
+        RecordingTransport {
+            inner,
+            shared: Arc::new(RecordingShared {
+                config: self.clone(),
+                sequence: AtomicUsize::new(0),
+            }),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RecordingShared {
+    config: RecordingLayer,
+    sequence: AtomicUsize,
+}
+
+impl RecordingShared {
+    async fn write(&self, sequence: usize, recording: &Recording) -> Result<()> {
+        let slug: String = recording
+            .request
+            .path
+            .trim_matches('/')
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .take(80)
+            .collect();
+        let path = self.config.dir.join(format!(
+            "{:04}-{}-{}.json",
+            sequence,
+            recording.request.method.to_ascii_lowercase(),
+            slug
+        ));
+
+        let write_error = |e: std::io::Error| {
+            TwcError::Configuration(format!(
+                "failed to write recording {}: {}",
+                path.display(),
+                e
+            ))
+        };
+        let contents = serde_json::to_string_pretty(recording)? + "\n";
+        tokio::fs::create_dir_all(&self.config.dir)
+            .await
+            .map_err(write_error)?;
+        tokio::fs::write(&path, contents).await.map_err(write_error)
+    }
+}
+
+/// Transport produced by [`RecordingLayer`]
+#[derive(Clone)]
+pub struct RecordingTransport {
+    inner: BoxTransport,
+    shared: Arc<RecordingShared>,
+}
+
+impl Service<reqwest::Request> for RecordingTransport {
+    type Response = reqwest::Response;
+    type Error = TwcError;
+    type Future = Pin<Box<dyn Future<Output = Result<reqwest::Response>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: reqwest::Request) -> Self::Future {
+        let config = &self.shared.config;
+        let sequence = self.shared.sequence.fetch_add(1, Ordering::Relaxed);
+        let recorded_request = RecordingRequest {
+            method: request.method().to_string(),
+            path: request.url().path().to_string(),
+            query: request
+                .url()
+                .query()
+                .and_then(|query| recorded_query(query, config)),
+            headers: recorded_headers(request.headers(), &config.headers),
+            body: RecordedBody::new(
+                request
+                    .body()
+                    .and_then(|body| body.as_bytes())
+                    .unwrap_or_default(),
+                &config.request_fields,
+            ),
+        };
+
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let shared = self.shared.clone();
+
+        Box::pin(async move {
+            let response = inner.call(request).await?;
+            let status = response.status();
+            let version = response.version();
+            let headers = response.headers().clone();
+            let body = response.bytes().await?;
+
+            let recording = Recording {
+                request: recorded_request,
+                response: RecordingResponse {
+                    status: status.as_u16(),
+                    headers: recorded_headers(&headers, &shared.config.headers),
+                    body: RecordedBody::new(&body, &shared.config.response_fields),
+                },
+            };
+            shared.write(sequence, &recording).await?;
+
+            let mut builder = http::Response::builder().status(status).version(version);
+            for (name, value) in &headers {
+                builder = builder.header(name, value);
+            }
+            let response = builder.body(body).map_err(|e| {
+                TwcError::Configuration(format!("invalid recorded response: {}", e))
+            })?;
+            Ok(reqwest::Response::from(response))
+        })
+    }
+}
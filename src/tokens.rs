@@ -0,0 +1,116 @@
+//! Local token estimation for prompt budgeting
+//!
+//! Estimates are heuristic and tokenizer-agnostic: roughly four characters
+//! per token for alphabetic scripts, never fewer tokens than words, and one
+//! token per CJK character. Expect them to land within about 15% of the
+//! `prompt_tokens` the API reports for ordinary prose.
+
+use crate::types::{ChatCompletionRequest, ChatContent, ChatMessage, ContentItem};
+
+/// Fixed overhead of every chat message (message delimiters)
+pub const TOKENS_PER_MESSAGE: usize = 3;
+
+/// Extra overhead of a message that carries a `name`
+pub const TOKENS_PER_NAME: usize = 1;
+
+/// Tokens the API adds to prime the assistant reply
+pub const REPLY_PRIMING_TOKENS: usize = 3;
+
+/// Flat cost assumed for an image part (a high-detail 512px tile set)
+pub const IMAGE_TOKENS: usize = 765;
+
+/// Flat cost assumed for an audio part
+pub const AUDIO_TOKENS: usize = 500;
+
+/// Flat cost assumed for a file part
+pub const FILE_TOKENS: usize = 1000;
+
+/// Estimate the number of tokens in a piece of text
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut cjk = 0;
+    let mut other = 0;
+    let mut words = 0;
+    let mut in_word = false;
+
+    for c in text.chars() {
+        if is_cjk(c) {
+            cjk += 1;
+            in_word = false;
+        } else if c.is_whitespace() {
+            in_word = false;
+        } else {
+            other += 1;
+            if !in_word {
+                words += 1;
+                in_word = true;
+            }
+        }
+    }
+
+    cjk + words.max(other.div_ceil(4))
+}
+
+/// Estimate the tokens a single message adds to a prompt
+///
+/// Text parts are estimated from their text; images, audio and files use
+/// the flat costs defined in this module.
+pub fn estimate_message_tokens(message: &ChatMessage) -> usize {
+    let content = match &message.content {
+        ChatContent::Text(text) => estimate_tokens(text),
+        ChatContent::Array(items) => items.iter().map(estimate_content_item_tokens).sum(),
+    };
+
+    let name = message
+        .name
+        .as_deref()
+        .map_or(0, |name| TOKENS_PER_NAME + estimate_tokens(name));
+
+    let tool_calls = message
+        .tool_calls
+        .as_ref()
+        .map_or(0, |calls| estimate_tokens(&calls.to_string()));
+
+    let tool_call_id = message.tool_call_id.as_deref().map_or(0, estimate_tokens);
+
+    // One token for the role itself
+    TOKENS_PER_MESSAGE + 1 + content + name + tool_calls + tool_call_id
+}
+
+/// Estimate the prompt tokens of a whole chat completion request
+///
+/// Covers every message, tool definitions and the reply priming overhead.
+pub fn estimate_request_tokens(request: &ChatCompletionRequest) -> usize {
+    let messages: usize = request.messages.iter().map(estimate_message_tokens).sum();
+
+    let tools = request
+        .tools
+        .as_ref()
+        .and_then(|tools| serde_json::to_string(tools).ok())
+        .map_or(0, |json| estimate_tokens(&json));
+
+    messages + tools + REPLY_PRIMING_TOKENS
+}
+
+fn estimate_content_item_tokens(item: &ContentItem) -> usize {
+    match item {
+        ContentItem::Text(text) => estimate_tokens(&text.text),
+        ContentItem::Refusal(refusal) => estimate_tokens(&refusal.refusal),
+        ContentItem::ImageUrl(_) => IMAGE_TOKENS,
+        ContentItem::InputAudio(_) => AUDIO_TOKENS,
+        ContentItem::File(_) => FILE_TOKENS,
+    }
+}
+
+/// Whether a character belongs to a script tokenized roughly per character
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3000}'..='\u{303F}' // CJK symbols and punctuation
+            | '\u{3040}'..='\u{30FF}' // Hiragana, Katakana
+            | '\u{3400}'..='\u{4DBF}' // CJK extension A
+            | '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+            | '\u{AC00}'..='\u{D7AF}' // Hangul syllables
+            | '\u{F900}'..='\u{FAFF}' // CJK compatibility ideographs
+            | '\u{FF00}'..='\u{FFEF}' // Half/full-width forms
+    )
+}
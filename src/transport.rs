@@ -0,0 +1,52 @@
+//! HTTP transport as a `tower::Service` (requires the `tower` feature)
+//!
+//! Every API call is dispatched through the client's transport, so layers
+//! added with [`ClientBuilder::layer`](crate::ClientBuilder::layer) (retries,
+//! concurrency limits, load shedding, ...) apply to all endpoints.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tower::util::BoxCloneSyncService;
+
+use crate::TwcError;
+
+/// Type-erased transport service used by the client
+pub type BoxTransport = BoxCloneSyncService<reqwest::Request, reqwest::Response, TwcError>;
+
+/// Base transport: executes requests on a `reqwest::Client`
+#[derive(Clone)]
+pub struct HttpService {
+    client: reqwest::Client,
+}
+
+impl HttpService {
+    /// Wrap a `reqwest::Client`
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+impl tower::Service<reqwest::Request> for HttpService {
+    type Response = reqwest::Response;
+    type Error = TwcError;
+    type Future = Pin<Box<dyn Future<Output = Result<reqwest::Response, TwcError>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: reqwest::Request) -> Self::Future {
+        let client = self.client.clone();
+        Box::pin(async move {
+            client
+                .execute(request)
+                .await
+                .map_err(TwcError::from_transport)
+        })
+    }
+}
+
+/// Layer application deferred until the base transport exists
+pub(crate) type LayerFn = Box<dyn FnOnce(BoxTransport) -> BoxTransport + Send + Sync>;
@@ -0,0 +1,25 @@
+//! Types for agents management API (Timeweb Cloud control plane)
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// AI agent on the account
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentInfo {
+    /// Agent ID in the control plane
+    pub id: u64,
+    /// Access ID used by the agents API (`agent_access_id`)
+    pub access_id: String,
+    /// Display name
+    pub name: String,
+    /// Model the agent runs on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Agent status, e.g. "active"
+    pub status: String,
+    /// Creation time (ISO 8601)
+    pub created_at: String,
+    /// Additional fields from API
+    #[serde(flatten)]
+    pub extra: Value,
+}
@@ -1,9 +1,10 @@
 //! Types for chat completions API
 
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 
 use super::common::*;
+use super::response::ServiceTier;
 
 /// Role of the message author
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -24,8 +25,12 @@ pub enum Role {
 }
 
 /// Content item for multimodal messages
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-#[serde(untagged)]
+///
+/// Serialized with its `type` tag derived from the variant. Deserialization
+/// also accepts parts without a tag, or with the Responses-style
+/// `input_text`/`output_text` tags, recognizing them by their fields.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum ContentItem {
     /// Text content
     Text(TextContent),
@@ -39,6 +44,45 @@ pub enum ContentItem {
     Refusal(RefusalContent),
 }
 
+impl ContentItem {
+    /// The `type` tag of this part
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ContentItem::Text(_) => "text",
+            ContentItem::ImageUrl(_) => "image_url",
+            ContentItem::InputAudio(_) => "input_audio",
+            ContentItem::File(_) => "file",
+            ContentItem::Refusal(_) => "refusal",
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ContentItem {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let value = Value::deserialize(deserializer)?;
+        let tag = match value.get("type").and_then(Value::as_str) {
+            Some("text" | "input_text" | "output_text") => "text",
+            Some(tag @ ("image_url" | "input_audio" | "file" | "refusal")) => tag,
+            // Untagged or unknown parts are recognized by their fields
+            _ => ["text", "image_url", "input_audio", "file", "refusal"]
+                .into_iter()
+                .find(|field| value.get(field).is_some())
+                .ok_or_else(|| D::Error::custom("unrecognized content item"))?,
+        };
+
+        let item = match tag {
+            "text" => serde_json::from_value(value).map(ContentItem::Text),
+            "image_url" => serde_json::from_value(value).map(ContentItem::ImageUrl),
+            "input_audio" => serde_json::from_value(value).map(ContentItem::InputAudio),
+            "file" => serde_json::from_value(value).map(ContentItem::File),
+            _ => serde_json::from_value(value).map(ContentItem::Refusal),
+        };
+        item.map_err(D::Error::custom)
+    }
+}
+
 /// Chat message content - can be string or array of content items
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(untagged)]
@@ -49,12 +93,114 @@ pub enum ChatContent {
     Array(Vec<ContentItem>),
 }
 
+impl ChatContent {
+    /// The plain text, or the first text part of a multimodal array
+    ///
+    /// Refusal parts are never returned; see [`ChatCompletionChoice::refusal`].
+    pub fn first_text(&self) -> Option<&str> {
+        match self {
+            ChatContent::Text(text) => Some(text),
+            ChatContent::Array(items) => items.iter().find_map(|item| match item {
+                ContentItem::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            }),
+        }
+    }
+
+    /// The plain text, or every text part of a multimodal array in order
+    pub fn iter_text_parts(&self) -> impl Iterator<Item = &str> + '_ {
+        let (text, items) = match self {
+            ChatContent::Text(text) => (Some(text.as_str()), &[][..]),
+            ChatContent::Array(items) => (None, items.as_slice()),
+        };
+        text.into_iter()
+            .chain(items.iter().filter_map(|item| match item {
+                ContentItem::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            }))
+    }
+
+    /// Number of characters across all text parts
+    pub fn len_chars(&self) -> usize {
+        self.iter_text_parts()
+            .map(|text| text.chars().count())
+            .sum()
+    }
+
+    /// Whether the content is empty text or an empty array
+    pub fn is_empty(&self) -> bool {
+        match self {
+            ChatContent::Text(text) => text.is_empty(),
+            ChatContent::Array(items) => items.is_empty(),
+        }
+    }
+}
+
+impl Default for ChatContent {
+    fn default() -> Self {
+        ChatContent::Text(String::new())
+    }
+}
+
+/// Text as-is; array parts on separate lines, with non-text parts shown as
+/// placeholders such as `[image: url]`
+impl std::fmt::Display for ChatContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let items = match self {
+            ChatContent::Text(text) => return f.write_str(text),
+            ChatContent::Array(items) => items,
+        };
+
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            match item {
+                ContentItem::Text(text) => f.write_str(&text.text)?,
+                ContentItem::ImageUrl(image) => {
+                    // Inline images would flood logs; keep only the media type
+                    let url = &image.image_url.url;
+                    match url.split_once(";base64,") {
+                        Some((media, _)) if url.starts_with("data:") => {
+                            write!(f, "[image: {};base64,...]", media)?
+                        }
+                        _ => write!(f, "[image: {}]", url)?,
+                    }
+                }
+                ContentItem::InputAudio(_) => f.write_str("[audio]")?,
+                ContentItem::File(file) => match &file.file {
+                    FilePart::ById { file_id } => write!(f, "[file: {}]", file_id)?,
+                    FilePart::Inline { filename, .. } => write!(f, "[file: {}]", filename)?,
+                },
+                ContentItem::Refusal(refusal) => write!(f, "[refused: {}]", refusal.refusal)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<&str> for ChatContent {
+    fn from(text: &str) -> Self {
+        ChatContent::Text(text.to_string())
+    }
+}
+
+impl From<String> for ChatContent {
+    fn from(text: String) -> Self {
+        ChatContent::Text(text)
+    }
+}
+
 /// Chat message
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ChatMessage {
     /// The role of the author of this message
     pub role: Role,
     /// The contents of the message
+    ///
+    /// Empty text when the API sends `null` or omits it, as it does for
+    /// assistant messages that only carry `tool_calls`.
+    #[serde(default, deserialize_with = "null_as_default")]
     pub content: ChatContent,
     /// The name of the author (required for function role)
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -68,6 +214,12 @@ pub struct ChatMessage {
     /// Tool call ID (required for tool role messages)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_call_id: Option<String>,
+    /// Refusal message of an assistant that declined to answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refusal: Option<String>,
+    /// Fields the typed ones do not cover, e.g. `annotations` on responses
+    #[serde(flatten, default, skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
 }
 
 impl ChatMessage {
@@ -80,6 +232,8 @@ impl ChatMessage {
             function_call: None,
             tool_calls: None,
             tool_call_id: None,
+            refusal: None,
+            extra: Map::new(),
         }
     }
 
@@ -92,6 +246,8 @@ impl ChatMessage {
             function_call: None,
             tool_calls: None,
             tool_call_id: None,
+            refusal: None,
+            extra: Map::new(),
         }
     }
 
@@ -104,9 +260,41 @@ impl ChatMessage {
             function_call: None,
             tool_calls: None,
             tool_call_id: None,
+            refusal: None,
+            extra: Map::new(),
         }
     }
 
+    /// Create a new developer text message (replaces `system` on newer backends)
+    pub fn developer(content: impl Into<String>) -> Self {
+        Self {
+            role: Role::Developer,
+            content: ChatContent::Text(content.into()),
+            name: None,
+            function_call: None,
+            tool_calls: None,
+            tool_call_id: None,
+            refusal: None,
+            extra: Map::new(),
+        }
+    }
+
+    /// Create a new user text message attributed to `name`
+    pub fn user_named(name: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            name: Some(name.into()),
+            ..Self::user(content)
+        }
+    }
+
+    /// Create a new user message with text and an attached file
+    pub fn user_with_file(text: impl Into<String>, file: FilePart) -> Self {
+        Self::user_multimodal(vec![
+            ContentItem::Text(TextContent::new(text)),
+            ContentItem::File(FileContent::new(file)),
+        ])
+    }
+
     /// Create a new multimodal user message
     pub fn user_multimodal(items: Vec<ContentItem>) -> Self {
         Self {
@@ -116,6 +304,8 @@ impl ChatMessage {
             function_call: None,
             tool_calls: None,
             tool_call_id: None,
+            refusal: None,
+            extra: Map::new(),
         }
     }
 }
@@ -127,8 +317,81 @@ pub struct ChatCompletionChoice {
     pub index: u32,
     /// A chat completion message generated by the model
     pub message: ChatMessage,
-    /// The reason the model stopped generating tokens
-    pub finish_reason: FinishReason,
+    /// The reason the model stopped generating tokens (`None` while
+    /// generation is still in progress)
+    #[serde(default)]
+    pub finish_reason: Option<FinishReason>,
+    /// Log probabilities of the generated tokens, when requested with `logprobs`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<ChatLogprobs>,
+    /// Fields the typed ones do not cover
+    #[serde(flatten, default, skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
+}
+
+impl ChatCompletionChoice {
+    /// The refusal message, if the model declined to answer
+    ///
+    /// Read from the message `refusal` field or a `refusal` content part.
+    pub fn refusal(&self) -> Option<&str> {
+        if let Some(refusal) = &self.message.refusal {
+            return Some(refusal);
+        }
+        match &self.message.content {
+            ChatContent::Array(items) => items.iter().find_map(|item| match item {
+                ContentItem::Refusal(refusal) => Some(refusal.refusal.as_str()),
+                _ => None,
+            }),
+            ChatContent::Text(_) => None,
+        }
+    }
+}
+
+/// Log probability information of a chat completion choice
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ChatLogprobs {
+    /// Log probabilities of the message content tokens
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub content: Vec<TokenLogprob>,
+    /// Log probabilities of the refusal tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refusal: Option<Vec<TokenLogprob>>,
+}
+
+/// Log probability of one generated token
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TokenLogprob {
+    /// The token
+    pub token: String,
+    /// Log probability of the token
+    pub logprob: f64,
+    /// UTF-8 bytes of the token, when it does not form valid text on its own
+    #[serde(default)]
+    pub bytes: Option<Vec<u8>>,
+    /// Most likely alternatives at this position (`top_logprobs` of the request)
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+/// Alternative token at a position, with its log probability
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TopLogprob {
+    /// The token
+    pub token: String,
+    /// Log probability of the token
+    pub logprob: f64,
+    /// UTF-8 bytes of the token
+    #[serde(default)]
+    pub bytes: Option<Vec<u8>>,
+}
+
+/// Deserialize `null` as the type's default value
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
 }
 
 /// Chat completion response
@@ -140,15 +403,90 @@ pub struct ChatCompletionResponse {
     pub object: String,
     /// The Unix timestamp (in seconds) of when the chat completion was created
     pub created: i64,
-    /// The model used for the chat completion
+    /// The model that served the request, as reported by the server; it may
+    /// be a dated version of the requested name
     pub model: String,
     /// A list of chat completion choices
     pub choices: Vec<ChatCompletionChoice>,
     /// Usage statistics for the completion request
     pub usage: Usage,
-    /// System fingerprint
+    /// Fingerprint of the backend configuration; a change means the model
+    /// behind the same name may have changed
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system_fingerprint: Option<String>,
+    /// Service tier the server processed the request with, when echoed back
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<ServiceTier>,
+    /// Fields the typed ones do not cover, e.g. `provider`
+    #[serde(flatten, default, skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
+}
+
+impl ChatCompletionResponse {
+    /// Whether `object` is `chat.completion`, as opposed to e.g. a
+    /// streaming chunk or an unrelated payload
+    pub fn is_chat_completion(&self) -> bool {
+        self.object == "chat.completion"
+    }
+
+    /// Answer text of the first choice
+    ///
+    /// `None` when there are no choices, the first choice is a refusal or it
+    /// carries no text.
+    pub fn first_text(&self) -> Option<&str> {
+        let choice = self.choices.first()?;
+        if choice.refusal().is_some() {
+            return None;
+        }
+        choice.message.content.first_text()
+    }
+
+    /// Whether the model refused to answer in the first choice
+    pub fn is_refusal(&self) -> bool {
+        self.choices
+            .first()
+            .is_some_and(|choice| choice.refusal().is_some())
+    }
+
+    /// The choice with the given `index` field (requests with `n` > 1)
+    pub fn choice(&self, index: u32) -> Option<&ChatCompletionChoice> {
+        self.choices.iter().find(|choice| choice.index == index)
+    }
+
+    /// Index and answer text of every choice, skipping refusals and choices
+    /// without text
+    pub fn choice_texts(&self) -> impl Iterator<Item = (u32, &str)> + '_ {
+        self.choices.iter().filter_map(|choice| {
+            if choice.refusal().is_some() {
+                return None;
+            }
+            let text = choice.message.content.first_text()?;
+            Some((choice.index, text))
+        })
+    }
+
+    /// The choice with the highest score, the earliest one on ties
+    ///
+    /// Scores that cannot be compared, such as `NaN`, never win.
+    pub fn best_by<F, S>(&self, mut score: F) -> Option<&ChatCompletionChoice>
+    where
+        F: FnMut(&ChatCompletionChoice) -> S,
+        S: PartialOrd,
+    {
+        let mut best: Option<(&ChatCompletionChoice, S)> = None;
+        for choice in &self.choices {
+            let value = score(choice);
+            let better = match &best {
+                Some((_, top)) => value.partial_cmp(top) == Some(std::cmp::Ordering::Greater),
+                // A score that cannot be compared to itself is NaN-like
+                None => value.partial_cmp(&value).is_some(),
+            };
+            if better {
+                best = Some((choice, value));
+            }
+        }
+        best.map(|(choice, _)| choice)
+    }
 }
 
 /// Delta content for streaming responses
@@ -160,6 +498,9 @@ pub struct StreamDelta {
     /// The role of the message author (only in first chunk)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub role: Option<String>,
+    /// The refusal message delta, when the model declines to answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refusal: Option<String>,
 }
 
 /// Choice in streaming chat completion response
@@ -184,13 +525,98 @@ pub struct ChatCompletionStreamResponse {
     pub created: i64,
     /// The model used for the chat completion
     pub model: String,
-    /// A list of chat completion choices
+    /// A list of chat completion choices (empty in the final usage chunk)
     pub choices: Vec<StreamChoice>,
+    /// Token usage, sent in a final chunk when `stream_options.include_usage` is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
     /// System fingerprint
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system_fingerprint: Option<String>,
 }
 
+/// Summary of a streamed chat completion, built chunk by chunk
+///
+/// Groups content deltas by `choices[].index`, so streams of requests with
+/// `n` > 1 are accumulated per choice, and keeps the usage of the final chunk
+/// (sent only when the request sets
+/// [`ChatCompletionRequest::with_stream_usage`]).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChatStreamSummary {
+    /// ID of the chat completion
+    pub id: String,
+    /// Model that generated the completion
+    pub model: String,
+    /// Concatenated content of the first choice
+    pub content: String,
+    /// Reason the first choice finished, once known
+    pub finish_reason: Option<FinishReason>,
+    /// Every choice seen so far, ordered by index
+    pub choices: Vec<ChoiceSummary>,
+    /// Token usage, once the usage chunk arrived
+    pub usage: Option<Usage>,
+}
+
+/// Accumulated content of one choice of a streamed chat completion
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChoiceSummary {
+    /// The index of the choice in the list of choices
+    pub index: u32,
+    /// Concatenated content of the choice
+    pub content: String,
+    /// Reason the choice finished, once known
+    pub finish_reason: Option<FinishReason>,
+}
+
+impl ChatStreamSummary {
+    /// Fold one chunk into the summary
+    pub fn push(&mut self, chunk: &ChatCompletionStreamResponse) {
+        if self.id.is_empty() {
+            self.id.clone_from(&chunk.id);
+            self.model.clone_from(&chunk.model);
+        }
+        for delta in &chunk.choices {
+            // Choices of one chunk may arrive in any order and interleave
+            // across chunks
+            let position = match self
+                .choices
+                .binary_search_by_key(&delta.index, |choice| choice.index)
+            {
+                Ok(position) => position,
+                Err(position) => {
+                    let choice = ChoiceSummary {
+                        index: delta.index,
+                        ..Default::default()
+                    };
+                    self.choices.insert(position, choice);
+                    position
+                }
+            };
+            let choice = &mut self.choices[position];
+            if let Some(content) = &delta.delta.content {
+                choice.content.push_str(content);
+                if delta.index == 0 {
+                    self.content.push_str(content);
+                }
+            }
+            if delta.finish_reason.is_some() {
+                choice.finish_reason.clone_from(&delta.finish_reason);
+                if delta.index == 0 {
+                    self.finish_reason.clone_from(&delta.finish_reason);
+                }
+            }
+        }
+        if chunk.usage.is_some() {
+            self.usage.clone_from(&chunk.usage);
+        }
+    }
+
+    /// The accumulated choice with the given index
+    pub fn choice(&self, index: u32) -> Option<&ChoiceSummary> {
+        self.choices.iter().find(|choice| choice.index == index)
+    }
+}
+
 /// Tool choice options
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(untagged)]
@@ -201,6 +627,12 @@ pub enum ToolChoice {
     Object(Value),
 }
 
+impl From<&str> for ToolChoice {
+    fn from(choice: &str) -> Self {
+        ToolChoice::Simple(choice.to_string())
+    }
+}
+
 /// Request body for chat completions
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct ChatCompletionRequest {
@@ -260,6 +692,141 @@ pub struct ChatCompletionRequest {
     /// An integer between 0 and 5 specifying the number of most likely tokens
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_logprobs: Option<u32>,
+    /// Reasoning effort for reasoning models
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<ReasoningEffort>,
+    /// Parameters the typed fields do not cover, sent as top-level keys
+    #[serde(flatten, default, skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
+}
+
+impl ChatCompletionRequest {
+    /// Limit the number of generated tokens
+    ///
+    /// Sets `max_completion_tokens`; clients in [`CompatMode::Legacy`] send
+    /// the limit as `max_tokens` instead.
+    pub fn with_token_limit(mut self, limit: u32) -> Self {
+        self.max_completion_tokens = Some(limit);
+        self.max_tokens = None;
+        self
+    }
+
+    /// Ask for a final stream chunk carrying token usage
+    ///
+    /// Sets `stream_options.include_usage`; only affects streaming requests.
+    pub fn with_stream_usage(mut self) -> Self {
+        self.stream_options = Some(StreamOptions::with_usage());
+        self
+    }
+
+    /// Send an additional top-level parameter, e.g. a vendor-specific hint
+    ///
+    /// The key must not name a typed field of [`ChatCompletionRequest`].
+    pub fn extra_param(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+
+    /// Rewrite `messages` according to `policy`
+    ///
+    /// Only system messages are moved or merged and only empty messages are
+    /// removed, so user, assistant and tool turns keep their relative order.
+    pub fn normalize_messages(&mut self, policy: NormalizePolicy) {
+        if policy.drop_empty {
+            self.messages.retain(|message| !is_empty_message(message));
+        }
+        if policy.merge_system {
+            merge_system_messages(&mut self.messages);
+        }
+        if policy.hoist_system
+            && let Some(position) = self.messages.iter().position(|m| m.role == Role::System)
+        {
+            let system = self.messages.remove(position);
+            self.messages.insert(0, system);
+        }
+    }
+}
+
+/// Which field a chat request carries its token limit in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompatMode {
+    /// `max_completion_tokens`, as current OpenAI-compatible backends expect
+    #[default]
+    Current,
+    /// `max_tokens`, for older backends that do not know `max_completion_tokens`
+    Legacy,
+}
+
+/// Rewrites applied by [`ChatCompletionRequest::normalize_messages`]
+///
+/// Some models reject a system message that is not the first one; each
+/// flag fixes one way a conversation can violate that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NormalizePolicy {
+    /// Move the first system message to the front
+    pub hoist_system: bool,
+    /// Merge all system messages into the first one, separated by a blank line
+    pub merge_system: bool,
+    /// Drop messages with empty content, except tool results and messages
+    /// carrying tool calls or a refusal
+    pub drop_empty: bool,
+}
+
+impl NormalizePolicy {
+    /// Every rewrite enabled
+    pub fn all() -> Self {
+        Self {
+            hoist_system: true,
+            merge_system: true,
+            drop_empty: true,
+        }
+    }
+}
+
+/// Whether a message carries nothing the model could use
+fn is_empty_message(message: &ChatMessage) -> bool {
+    message.content.is_empty()
+        && message.role != Role::Tool
+        && message.tool_calls.is_none()
+        && message.function_call.is_none()
+        && message.refusal.is_none()
+}
+
+/// Fold every system message into the first one, in order
+fn merge_system_messages(messages: &mut Vec<ChatMessage>) {
+    let mut merged: Vec<ChatMessage> = Vec::with_capacity(messages.len());
+    let mut first_system = None;
+    for message in messages.drain(..) {
+        if message.role == Role::System {
+            if let Some(index) = first_system {
+                append_content(&mut merged[index].content, message.content);
+                continue;
+            }
+            first_system = Some(merged.len());
+        }
+        merged.push(message);
+    }
+    *messages = merged;
+}
+
+/// Append `content` to `target`, keeping text as text when both are text
+fn append_content(target: &mut ChatContent, content: ChatContent) {
+    *target = match (std::mem::take(target), content) {
+        (ChatContent::Text(a), ChatContent::Text(b)) => ChatContent::Text(format!("{a}\n\n{b}")),
+        (a, b) => {
+            let mut items = into_items(a);
+            items.extend(into_items(b));
+            ChatContent::Array(items)
+        }
+    };
+}
+
+/// Content as a list of parts
+fn into_items(content: ChatContent) -> Vec<ContentItem> {
+    match content {
+        ChatContent::Text(text) => vec![ContentItem::Text(TextContent::new(text))],
+        ChatContent::Array(items) => items,
+    }
 }
 
 /// Stop sequence - can be a single string or array of strings
@@ -272,6 +839,56 @@ pub enum StopSequence {
     Multiple(Vec<String>),
 }
 
+impl StopSequence {
+    /// Maximum number of stop sequences accepted by the API
+    pub const MAX: usize = 4;
+
+    /// The stop sequences as a slice
+    pub fn as_slice(&self) -> &[String] {
+        match self {
+            StopSequence::Single(stop) => std::slice::from_ref(stop),
+            StopSequence::Multiple(stops) => stops,
+        }
+    }
+
+    /// Check that there are at most [`MAX`](Self::MAX) sequences
+    pub fn validate(&self) -> crate::Result<()> {
+        let count = self.as_slice().len();
+        if count > Self::MAX {
+            return Err(crate::TwcError::InvalidRequest(format!(
+                "At most {} stop sequences are allowed, got {}",
+                Self::MAX,
+                count
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl From<&str> for StopSequence {
+    fn from(stop: &str) -> Self {
+        StopSequence::Single(stop.to_string())
+    }
+}
+
+impl From<String> for StopSequence {
+    fn from(stop: String) -> Self {
+        StopSequence::Single(stop)
+    }
+}
+
+impl From<Vec<String>> for StopSequence {
+    fn from(stops: Vec<String>) -> Self {
+        StopSequence::Multiple(stops)
+    }
+}
+
+impl From<Vec<&str>> for StopSequence {
+    fn from(stops: Vec<&str>) -> Self {
+        StopSequence::Multiple(stops.into_iter().map(str::to_string).collect())
+    }
+}
+
 /// Response format union type
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(untagged)]
@@ -306,6 +923,43 @@ pub struct AgentCallRequest {
     /// Optional array of file IDs to attach to the message
     #[serde(skip_serializing_if = "Option::is_none")]
     pub file_ids: Option<Vec<String>>,
+    /// Parameters the typed fields do not cover, sent as top-level keys
+    #[serde(flatten, default, skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
+}
+
+impl AgentCallRequest {
+    /// Create a request with the given message
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: Some(message.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Continue the dialog after the given message ID
+    pub fn parent(mut self, message_id: impl Into<String>) -> Self {
+        self.parent_message_id = Some(message_id.into());
+        self
+    }
+
+    /// Attach files by their IDs
+    pub fn files<I, S>(mut self, file_ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.file_ids = Some(file_ids.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Send an additional top-level parameter, e.g. a vendor-specific hint
+    ///
+    /// The key must not name a typed field of [`AgentCallRequest`].
+    pub fn extra_param(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
 }
 
 /// Response from simple agent call
@@ -313,8 +967,14 @@ pub struct AgentCallRequest {
 pub struct AgentCallResponse {
     /// The response message from the agent
     pub message: String,
-    /// Unique ID of the message
+    /// Unique ID of the message, used as `parent_message_id` of the next call
     pub id: String,
     /// The reason why the response was finished
     pub finish_reason: Value,
+    /// Token usage, when reported by the API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    /// Additional fields from API
+    #[serde(flatten)]
+    pub extra: Value,
 }
@@ -1,9 +1,12 @@
 //! Common types shared across API modules
 
+use std::collections::BTreeMap;
+
+use base64::Engine as _;
 use serde::{Deserialize, Serialize};
 
 /// Token usage statistics
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct Usage {
     /// Number of tokens in the prompt
     pub prompt_tokens: u32,
@@ -11,6 +14,83 @@ pub struct Usage {
     pub completion_tokens: u32,
     /// Total number of tokens used in the request
     pub total_tokens: u32,
+    /// Breakdown of the prompt tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+    /// Breakdown of the completion tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion_tokens_details: Option<CompletionTokensDetails>,
+}
+
+impl Usage {
+    /// Prompt tokens served from the cache (0 when not reported)
+    pub fn cached_tokens(&self) -> u32 {
+        self.prompt_tokens_details
+            .as_ref()
+            .and_then(|d| d.cached_tokens)
+            .unwrap_or(0)
+    }
+
+    /// Completion tokens spent on reasoning (0 when not reported)
+    pub fn reasoning_tokens(&self) -> u32 {
+        self.completion_tokens_details
+            .as_ref()
+            .and_then(|d| d.reasoning_tokens)
+            .unwrap_or(0)
+    }
+}
+
+/// Adds the counts of another request, e.g. to total a batch
+impl std::ops::AddAssign<&Usage> for Usage {
+    fn add_assign(&mut self, other: &Usage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+        if let Some(details) = &other.prompt_tokens_details {
+            let total = self
+                .prompt_tokens_details
+                .get_or_insert_with(Default::default);
+            total.cached_tokens = add_counts(total.cached_tokens, details.cached_tokens);
+            total.audio_tokens = add_counts(total.audio_tokens, details.audio_tokens);
+        }
+        if let Some(details) = &other.completion_tokens_details {
+            let total = self
+                .completion_tokens_details
+                .get_or_insert_with(Default::default);
+            total.reasoning_tokens = add_counts(total.reasoning_tokens, details.reasoning_tokens);
+            total.audio_tokens = add_counts(total.audio_tokens, details.audio_tokens);
+        }
+    }
+}
+
+/// Sum of two optional counts, `None` only when neither is reported
+fn add_counts(a: Option<u32>, b: Option<u32>) -> Option<u32> {
+    match (a, b) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+    }
+}
+
+/// Breakdown of prompt (input) tokens
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct PromptTokensDetails {
+    /// Tokens read from the prompt cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_tokens: Option<u32>,
+    /// Audio input tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_tokens: Option<u32>,
+}
+
+/// Breakdown of completion (output) tokens
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct CompletionTokensDetails {
+    /// Tokens spent on reasoning before the visible answer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_tokens: Option<u32>,
+    /// Audio output tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_tokens: Option<u32>,
 }
 
 /// Function call definition
@@ -85,32 +165,64 @@ pub struct CustomTool {
 /// Text content item for multimodal messages
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct TextContent {
-    /// Content type - always "text"
-    #[serde(rename = "type")]
-    pub content_type: String,
     /// The text content
     pub text: String,
 }
 
+impl TextContent {
+    /// Text part with the given content
+    pub fn new(text: impl Into<String>) -> Self {
+        Self { text: text.into() }
+    }
+}
+
 /// Image URL specification
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ImageUrl {
     /// The URL of the image
     pub url: String,
-    /// The detail level of the image (low, high, auto)
-    pub detail: Option<String>,
+    /// The detail level of the image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<ImageDetail>,
+}
+
+/// Detail level at which the model looks at an image
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageDetail {
+    /// Low resolution, fewer tokens
+    Low,
+    /// High resolution, more tokens
+    High,
+    /// Let the model decide
+    Auto,
 }
 
 /// Image URL content item for multimodal messages
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ImageUrlContent {
-    /// Content type - always "image_url"
-    #[serde(rename = "type")]
-    pub content_type: String,
     /// Image URL object
     pub image_url: ImageUrl,
 }
 
+impl ImageUrlContent {
+    /// Image part for an `http(s)` or base64 `data:` URL
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            image_url: ImageUrl {
+                url: url.into(),
+                detail: None,
+            },
+        }
+    }
+
+    /// Set the detail level the model looks at the image with
+    pub fn detail(mut self, detail: ImageDetail) -> Self {
+        self.image_url.detail = Some(detail);
+        self
+    }
+}
+
 /// Input audio specification
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct InputAudio {
@@ -120,54 +232,324 @@ pub struct InputAudio {
     pub format: String,
 }
 
+impl InputAudio {
+    /// Default limit on raw audio size accepted by the constructors (25 MiB)
+    ///
+    /// Base64 grows the payload by a third, so larger clips are likely to be
+    /// rejected by the server with 413 anyway.
+    pub const DEFAULT_MAX_BYTES: usize = 25 * 1024 * 1024;
+
+    /// Encode raw audio bytes, rejecting clips above [`Self::DEFAULT_MAX_BYTES`]
+    pub fn from_bytes(bytes: &[u8], format: AudioFormat) -> crate::Result<Self> {
+        Self::from_bytes_with_limit(bytes, format, Self::DEFAULT_MAX_BYTES)
+    }
+
+    /// Encode raw audio bytes, rejecting clips above `max_bytes`
+    pub fn from_bytes_with_limit(
+        bytes: &[u8],
+        format: AudioFormat,
+        max_bytes: usize,
+    ) -> crate::Result<Self> {
+        check_audio_size(bytes.len() as u64, max_bytes)?;
+        Ok(Self {
+            data: base64::engine::general_purpose::STANDARD.encode(bytes),
+            format: format.as_str().to_string(),
+        })
+    }
+
+    /// Read and encode an audio file, detecting its format
+    ///
+    /// The format is sniffed from the file header, falling back to the
+    /// extension. Files above [`Self::DEFAULT_MAX_BYTES`] are rejected
+    /// before being read.
+    pub async fn from_path(path: impl AsRef<std::path::Path>) -> crate::Result<Self> {
+        Self::from_path_with_limit(path, Self::DEFAULT_MAX_BYTES).await
+    }
+
+    /// Read and encode an audio file, rejecting files above `max_bytes`
+    pub async fn from_path_with_limit(
+        path: impl AsRef<std::path::Path>,
+        max_bytes: usize,
+    ) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let io_error = |e: std::io::Error| {
+            crate::TwcError::InvalidRequest(format!(
+                "failed to read audio file {}: {}",
+                path.display(),
+                e
+            ))
+        };
+
+        let metadata = tokio::fs::metadata(path).await.map_err(io_error)?;
+        check_audio_size(metadata.len(), max_bytes)?;
+        let bytes = tokio::fs::read(path).await.map_err(io_error)?;
+
+        let format = AudioFormat::sniff(&bytes)
+            .or_else(|| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .and_then(AudioFormat::from_extension)
+            })
+            .ok_or_else(|| {
+                crate::TwcError::InvalidRequest(format!(
+                    "unsupported audio format in {}: expected wav, mp3, m4a, ogg, flac or webm",
+                    path.display()
+                ))
+            })?;
+
+        Self::from_bytes_with_limit(&bytes, format, max_bytes)
+    }
+}
+
+fn check_audio_size(len: u64, max_bytes: usize) -> crate::Result<()> {
+    if len > max_bytes as u64 {
+        return Err(crate::TwcError::PayloadTooLarge(format!(
+            "audio is {} bytes, limit is {}",
+            len, max_bytes
+        )));
+    }
+    Ok(())
+}
+
+/// Audio formats accepted in `input_audio` content
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    /// WAV (RIFF) audio
+    Wav,
+    /// MPEG-1/2 Layer III audio
+    Mp3,
+    /// AAC in an MPEG-4 container
+    M4a,
+    /// Ogg container (Vorbis or Opus)
+    Ogg,
+    /// Free Lossless Audio Codec
+    Flac,
+    /// WebM (Matroska) container
+    Webm,
+}
+
+impl AudioFormat {
+    /// Format name as sent in `input_audio.format`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AudioFormat::Wav => "wav",
+            AudioFormat::Mp3 => "mp3",
+            AudioFormat::M4a => "m4a",
+            AudioFormat::Ogg => "ogg",
+            AudioFormat::Flac => "flac",
+            AudioFormat::Webm => "webm",
+        }
+    }
+
+    /// Format for a file extension (case-insensitive), if supported
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "wav" | "wave" => Some(AudioFormat::Wav),
+            "mp3" => Some(AudioFormat::Mp3),
+            "m4a" | "mp4" => Some(AudioFormat::M4a),
+            "ogg" | "oga" | "opus" => Some(AudioFormat::Ogg),
+            "flac" => Some(AudioFormat::Flac),
+            "webm" => Some(AudioFormat::Webm),
+            _ => None,
+        }
+    }
+
+    /// Detect the format from the leading bytes of a file
+    pub fn sniff(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [
+                b'R',
+                b'I',
+                b'F',
+                b'F',
+                _,
+                _,
+                _,
+                _,
+                b'W',
+                b'A',
+                b'V',
+                b'E',
+                ..,
+            ] => Some(AudioFormat::Wav),
+            [b'I', b'D', b'3', ..] => Some(AudioFormat::Mp3),
+            // MPEG frame sync with a non-reserved layer
+            [0xFF, b, ..] if b & 0xE0 == 0xE0 && b & 0x06 != 0 => Some(AudioFormat::Mp3),
+            [_, _, _, _, b'f', b't', b'y', b'p', ..] => Some(AudioFormat::M4a),
+            [b'O', b'g', b'g', b'S', ..] => Some(AudioFormat::Ogg),
+            [b'f', b'L', b'a', b'C', ..] => Some(AudioFormat::Flac),
+            [0x1A, 0x45, 0xDF, 0xA3, ..] => Some(AudioFormat::Webm),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for AudioFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// Input audio content item for multimodal messages
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct InputAudioContent {
-    /// Content type - always "input_audio"
-    #[serde(rename = "type")]
-    pub content_type: String,
     /// Input audio object
     pub input_audio: InputAudio,
 }
 
+impl InputAudioContent {
+    /// Audio part for encoded audio (see [`InputAudio::from_bytes`])
+    pub fn new(input_audio: InputAudio) -> Self {
+        Self { input_audio }
+    }
+}
+
 /// File content item for multimodal messages
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FileContent {
-    /// Content type - always "file"
-    #[serde(rename = "type")]
-    pub content_type: String,
-    /// File object (OpenAI File type)
-    pub file: serde_json::Value,
+    /// File reference or inline document
+    pub file: FilePart,
+}
+
+impl FileContent {
+    /// File part for an uploaded or inline file
+    pub fn new(file: FilePart) -> Self {
+        Self { file }
+    }
+}
+
+/// File attached to a message, by ID or inline
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum FilePart {
+    /// A previously uploaded file
+    ById {
+        /// ID of the uploaded file
+        file_id: String,
+    },
+    /// An inline document
+    Inline {
+        /// Name of the file, including its extension
+        filename: String,
+        /// Contents as a base64 `data:` URL
+        file_data: String,
+    },
+}
+
+impl FilePart {
+    /// Reference a previously uploaded file
+    pub fn by_id(file_id: impl Into<String>) -> Self {
+        FilePart::ById {
+            file_id: file_id.into(),
+        }
+    }
+
+    /// Inline a document from memory, encoding it as a base64 `data:` URL
+    pub fn inline(filename: impl Into<String>, mime_type: &str, bytes: &[u8]) -> Self {
+        FilePart::Inline {
+            filename: filename.into(),
+            file_data: format!(
+                "data:{};base64,{}",
+                mime_type,
+                base64::engine::general_purpose::STANDARD.encode(bytes)
+            ),
+        }
+    }
+
+    /// Read a document and inline it
+    ///
+    /// The MIME type is guessed from the extension, defaulting to
+    /// `application/octet-stream`.
+    pub async fn inline_from_path(path: impl AsRef<std::path::Path>) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let bytes = tokio::fs::read(path).await.map_err(|e| {
+            crate::TwcError::InvalidRequest(format!(
+                "failed to read file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        let filename = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mime_type = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map_or("application/octet-stream", document_mime_type);
+
+        Ok(Self::inline(filename, mime_type, &bytes))
+    }
+}
+
+fn document_mime_type(ext: &str) -> &'static str {
+    match ext.to_ascii_lowercase().as_str() {
+        "pdf" => "application/pdf",
+        "txt" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "json" => "application/json",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        _ => "application/octet-stream",
+    }
 }
 
 /// Refusal content item
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct RefusalContent {
-    /// Content type - always "refusal"
-    #[serde(rename = "type")]
-    pub content_type: String,
     /// Refusal message
     pub refusal: String,
 }
 
+impl RefusalContent {
+    /// Refusal part with the given explanation
+    pub fn new(refusal: impl Into<String>) -> Self {
+        Self {
+            refusal: refusal.into(),
+        }
+    }
+}
+
 /// Stream options for streaming responses
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct StreamOptions {
     /// Whether to include usage information in streaming responses
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub include_usage: Option<bool>,
 }
 
+impl StreamOptions {
+    /// Options asking for a final chunk carrying token usage
+    pub fn with_usage() -> Self {
+        Self {
+            include_usage: Some(true),
+        }
+    }
+}
+
 /// Model information
+///
+/// Accepts `created_at` in place of `created`.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Model {
     /// Model identifier
     pub id: String,
     /// Object type, always "model"
+    #[serde(default = "model_object")]
     pub object: String,
-    /// Unix timestamp when the model was created
-    pub created: i64,
-    /// Organization that owns the model
-    pub owned_by: String,
+    /// Unix timestamp when the model was created, if reported
+    #[serde(default, alias = "created_at", skip_serializing_if = "Option::is_none")]
+    pub created: Option<i64>,
+    /// Organization that owns the model, if reported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owned_by: Option<String>,
+}
+
+fn model_object() -> String {
+    "model".to_string()
 }
 
 /// List of models response
@@ -192,3 +574,164 @@ pub enum FinishReason {
     /// Model called a tool
     ToolCalls,
 }
+
+/// Reasoning configuration for reasoning models
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct ReasoningConfig {
+    /// How much effort the model spends on reasoning
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effort: Option<ReasoningEffort>,
+    /// Whether and how detailed a reasoning summary is returned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<ReasoningSummary>,
+}
+
+/// Reasoning effort level
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningEffort {
+    /// As little reasoning as possible
+    Minimal,
+    /// Low effort
+    Low,
+    /// Medium effort (the API default)
+    Medium,
+    /// High effort
+    High,
+}
+
+/// Reasoning summary detail level
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReasoningSummary {
+    /// Most detailed summary the model supports
+    Auto,
+    /// Short summary
+    Concise,
+    /// Detailed summary
+    Detailed,
+}
+
+/// Additional output data requested with `include`
+///
+/// Covers the values documented for responses, conversation items and item
+/// creation. Anything else can be sent with [`Include::Custom`]; note that
+/// the server silently ignores unknown values.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Include {
+    /// `web_search_call.action.sources`: sources of web search tool calls
+    WebSearchCallActionSources,
+    /// `code_interpreter_call.outputs`: outputs of code interpreter calls
+    CodeInterpreterCallOutputs,
+    /// `computer_call_output.output.image_url`: image URLs of computer call outputs
+    ComputerCallOutputImageUrl,
+    /// `file_search_call.results`: results of file search calls
+    FileSearchCallResults,
+    /// `message.input_image.image_url`: image URLs of input messages
+    MessageInputImageUrl,
+    /// `message.output_text.logprobs`: log probabilities of output text
+    MessageOutputTextLogprobs,
+    /// `reasoning.encrypted_content`: encrypted reasoning for stateless use
+    ReasoningEncryptedContent,
+    /// Any other value
+    Custom(String),
+}
+
+impl Include {
+    /// Wire value of this option
+    pub fn as_str(&self) -> &str {
+        match self {
+            Include::WebSearchCallActionSources => "web_search_call.action.sources",
+            Include::CodeInterpreterCallOutputs => "code_interpreter_call.outputs",
+            Include::ComputerCallOutputImageUrl => "computer_call_output.output.image_url",
+            Include::FileSearchCallResults => "file_search_call.results",
+            Include::MessageInputImageUrl => "message.input_image.image_url",
+            Include::MessageOutputTextLogprobs => "message.output_text.logprobs",
+            Include::ReasoningEncryptedContent => "reasoning.encrypted_content",
+            Include::Custom(value) => value,
+        }
+    }
+}
+
+impl std::fmt::Display for Include {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<&str> for Include {
+    fn from(value: &str) -> Self {
+        match value {
+            "web_search_call.action.sources" => Include::WebSearchCallActionSources,
+            "code_interpreter_call.outputs" => Include::CodeInterpreterCallOutputs,
+            "computer_call_output.output.image_url" => Include::ComputerCallOutputImageUrl,
+            "file_search_call.results" => Include::FileSearchCallResults,
+            "message.input_image.image_url" => Include::MessageInputImageUrl,
+            "message.output_text.logprobs" => Include::MessageOutputTextLogprobs,
+            "reasoning.encrypted_content" => Include::ReasoningEncryptedContent,
+            other => Include::Custom(other.to_string()),
+        }
+    }
+}
+
+impl Serialize for Include {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Include {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Ok(Include::from(value.as_str()))
+    }
+}
+
+/// Flat string key-value metadata attached to an object (at most 16 pairs)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(transparent)]
+pub struct Metadata(pub BTreeMap<String, String>);
+
+impl Metadata {
+    /// Create empty metadata
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Value of `key`, if set
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(String::as_str)
+    }
+
+    /// Set `key` to `value`, returning the previous value
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.0.insert(key.into(), value.into())
+    }
+
+    /// Key-value pairs in key order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Number of pairs
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether no pair is set
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Metadata {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self(
+            iter.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
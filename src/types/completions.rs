@@ -0,0 +1,175 @@
+//! Types for the legacy text completions API
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::chat::StopSequence;
+
+/// Request for text completions (legacy)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct TextCompletionRequest {
+    /// The prompt to generate completions for
+    pub prompt: String,
+    /// The model to use for completion
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Maximum number of tokens to generate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Sampling temperature (0-2)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Nucleus sampling parameter (0-1)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// How many completions to generate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    /// Whether to stream back partial progress
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream: Option<bool>,
+    /// Include log probabilities on most likely tokens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<u32>,
+    /// Echo back the prompt in addition to completion
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub echo: Option<bool>,
+    /// Up to 4 sequences where API stops generating further tokens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<StopSequence>,
+    /// Presence penalty (-2.0 to 2.0)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    /// Frequency penalty (-2.0 to 2.0)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    /// Generates best_of completions server-side and returns the "best"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub best_of: Option<u32>,
+    /// Unique identifier representing your end-user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+impl TextCompletionRequest {
+    /// Create a request for the given prompt
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Use the given model
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Limit the number of generated tokens
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Set the sampling temperature (0-2)
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Set the nucleus sampling parameter (0-1)
+    pub fn top_p(mut self, top_p: f32) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    /// Generate `n` completions
+    pub fn n(mut self, n: u32) -> Self {
+        self.n = Some(n);
+        self
+    }
+
+    /// Stop at the given sequence or sequences
+    pub fn stop(mut self, stop: impl Into<StopSequence>) -> Self {
+        self.stop = Some(stop.into());
+        self
+    }
+
+    /// Set the end-user identifier
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+}
+
+/// Log probabilities for text completion
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TextCompletionLogprobs {
+    /// Tokens chosen by the model
+    pub tokens: Vec<String>,
+    /// Log probability of each token
+    pub token_logprobs: Vec<f32>,
+    /// Top logprobs for each token
+    pub top_logprobs: Value,
+    /// Character offsets for each token
+    pub text_offset: Vec<u32>,
+}
+
+/// Choice in text completion response
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TextCompletionChoice {
+    /// The generated text
+    pub text: String,
+    /// The index of this choice
+    pub index: u32,
+    /// Log probability information
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<TextCompletionLogprobs>,
+    /// The reason the model stopped generating tokens
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+}
+
+/// Token usage for text completion
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TextCompletionUsage {
+    /// Number of tokens in the prompt
+    pub prompt_tokens: u32,
+    /// Number of tokens in the generated completion
+    pub completion_tokens: u32,
+    /// Total number of tokens used
+    pub total_tokens: u32,
+}
+
+/// Text completion response (legacy)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TextCompletionResponse {
+    /// Unique identifier for the completion
+    pub id: String,
+    /// Object type - always "text_completion"
+    pub object: String,
+    /// Unix timestamp when the completion was created
+    pub created: i64,
+    /// The model used for completion
+    pub model: String,
+    /// Array of completion choices
+    pub choices: Vec<TextCompletionChoice>,
+    /// Usage statistics for the completion
+    pub usage: TextCompletionUsage,
+}
+
+/// Incremental piece of a streamed text completion
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TextCompletionChunk {
+    /// Text generated since the previous chunk for this choice
+    pub text_delta: String,
+    /// The index of the choice this delta belongs to
+    pub index: u32,
+    /// The reason the model stopped generating tokens (final chunk only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+    /// Log probability information for the delta
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<TextCompletionLogprobs>,
+}
@@ -1,16 +1,115 @@
 //! Types for conversations API (OpenAI-compatible)
 
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
+
+use crate::TwcError;
+use crate::pagination::{Page, Paginator};
 
-/// Content item for conversation messages
+use super::chat::{ChatContent, ChatMessage, ContentItem, Role};
+use super::common::{ImageDetail, Include, Metadata};
+use super::response::Response;
+
+/// Content part of a conversation item
+///
+/// Unknown part types are kept in [`ConversationItemContent::Other`].
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct ConversationItemContent {
-    /// Content type - "input_text", "output_text", etc.
-    #[serde(rename = "type")]
-    pub content_type: String,
-    /// Text content
-    pub text: String,
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConversationItemContent {
+    /// Text written by the user, system or developer
+    InputText {
+        /// Text content
+        text: String,
+    },
+    /// Text written by the assistant
+    OutputText {
+        /// Text content
+        text: String,
+    },
+    /// Image given by URL or by uploaded file
+    InputImage {
+        /// URL or base64 data URL of the image
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        image_url: Option<String>,
+        /// ID of an uploaded image file
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file_id: Option<String>,
+        /// Detail level at which the model looks at the image
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        detail: Option<ImageDetail>,
+    },
+    /// File given by ID, URL or inline data
+    InputFile {
+        /// ID of an uploaded file
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file_id: Option<String>,
+        /// URL of the file
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file_url: Option<String>,
+        /// Base64 data URL of the file
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        file_data: Option<String>,
+        /// Name of the file
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filename: Option<String>,
+    },
+    /// Any other part, kept as raw JSON
+    #[serde(untagged)]
+    Other(Value),
+}
+
+impl ConversationItemContent {
+    /// `input_text` part
+    pub fn input_text(text: impl Into<String>) -> Self {
+        Self::InputText { text: text.into() }
+    }
+
+    /// `output_text` part
+    pub fn output_text(text: impl Into<String>) -> Self {
+        Self::OutputText { text: text.into() }
+    }
+
+    /// `input_image` part with an image URL
+    pub fn input_image(image_url: impl Into<String>, detail: Option<ImageDetail>) -> Self {
+        Self::InputImage {
+            image_url: Some(image_url.into()),
+            file_id: None,
+            detail,
+        }
+    }
+
+    /// `input_file` part with an uploaded file ID
+    pub fn input_file(file_id: impl Into<String>) -> Self {
+        Self::InputFile {
+            file_id: Some(file_id.into()),
+            file_url: None,
+            file_data: None,
+            filename: None,
+        }
+    }
+
+    /// The `type` tag of this part
+    pub fn content_type(&self) -> &str {
+        match self {
+            Self::InputText { .. } => "input_text",
+            Self::OutputText { .. } => "output_text",
+            Self::InputImage { .. } => "input_image",
+            Self::InputFile { .. } => "input_file",
+            Self::Other(value) => value.get("type").and_then(Value::as_str).unwrap_or(""),
+        }
+    }
+
+    /// Text of an `input_text` or `output_text` part, or of an unknown part
+    /// with a `text` field
+    pub fn text(&self) -> Option<&str> {
+        match self {
+            Self::InputText { text } | Self::OutputText { text } => Some(text),
+            Self::Other(value) => value.get("text").and_then(Value::as_str),
+            _ => None,
+        }
+    }
 }
 
 /// Conversation item (message)
@@ -27,6 +126,32 @@ pub struct ConversationItem {
     pub role: String,
     /// Content of the item
     pub content: Vec<ConversationItemContent>,
+    /// Unix timestamp of creation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<i64>,
+    /// Additional fields from API
+    #[serde(flatten, default, skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
+}
+
+impl ConversationItem {
+    /// Text of all text-bearing content parts, concatenated
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(ConversationItemContent::text)
+            .collect()
+    }
+
+    /// Whether the item was written by the user
+    pub fn is_user(&self) -> bool {
+        self.role == "user"
+    }
+
+    /// Whether the item was written by the assistant
+    pub fn is_assistant(&self) -> bool {
+        self.role == "assistant"
+    }
 }
 
 /// Request to create a conversation
@@ -52,16 +177,44 @@ pub struct ConversationItemMessage {
     pub content: Vec<ConversationItemContentInput>,
 }
 
-/// Input content for conversation item
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct ConversationItemContentInput {
-    /// Content type - "input_text"
-    #[serde(rename = "type")]
-    pub content_type: String,
-    /// Text content
-    pub text: String,
+impl ConversationItemMessage {
+    /// Message item with `content`
+    pub fn new(role: impl Into<String>, content: Vec<ConversationItemContent>) -> Self {
+        Self {
+            item_type: "message".to_string(),
+            role: role.into(),
+            content,
+        }
+    }
+
+    /// User message with text
+    pub fn user(text: impl Into<String>) -> Self {
+        Self::new("user", vec![ConversationItemContent::input_text(text)])
+    }
+
+    /// Assistant message with text
+    pub fn assistant(text: impl Into<String>) -> Self {
+        Self::new(
+            "assistant",
+            vec![ConversationItemContent::output_text(text)],
+        )
+    }
+
+    /// User message with text and an image URL
+    pub fn user_with_image(text: impl Into<String>, image_url: impl Into<String>) -> Self {
+        Self::new(
+            "user",
+            vec![
+                ConversationItemContent::input_text(text),
+                ConversationItemContent::input_image(image_url, None),
+            ],
+        )
+    }
 }
 
+/// Input content for conversation item
+pub type ConversationItemContentInput = ConversationItemContent;
+
 /// Request to update a conversation
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct UpdateConversationRequest {
@@ -79,8 +232,75 @@ pub struct Conversation {
     /// Unix timestamp of creation
     pub created_at: i64,
     /// Set of 16 key-value pairs attached to the object
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub metadata: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ConversationMetadata>,
+}
+
+/// Metadata of a [`Conversation`] as returned by the server
+///
+/// Conversations created by other tooling may carry nested or non-string
+/// values; those are kept in [`MetadataRaw`](Self::MetadataRaw) rather than
+/// failing to parse.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum ConversationMetadata {
+    /// Flat string key-value pairs
+    Metadata(Metadata),
+    /// Any other JSON, as received
+    MetadataRaw(Value),
+}
+
+impl ConversationMetadata {
+    /// String value of `key`; `None` for missing keys and for values that
+    /// are not strings
+    pub fn get(&self, key: &str) -> Option<&str> {
+        match self {
+            ConversationMetadata::Metadata(metadata) => metadata.get(key),
+            ConversationMetadata::MetadataRaw(value) => value.get(key)?.as_str(),
+        }
+    }
+
+    /// The flat pairs, if the metadata has that shape
+    pub fn as_metadata(&self) -> Option<&Metadata> {
+        match self {
+            ConversationMetadata::Metadata(metadata) => Some(metadata),
+            ConversationMetadata::MetadataRaw(_) => None,
+        }
+    }
+
+    /// The metadata as JSON
+    pub fn to_value(&self) -> Value {
+        match self {
+            ConversationMetadata::Metadata(metadata) => {
+                serde_json::to_value(metadata).unwrap_or_default()
+            }
+            ConversationMetadata::MetadataRaw(value) => value.clone(),
+        }
+    }
+}
+
+impl From<Metadata> for ConversationMetadata {
+    fn from(metadata: Metadata) -> Self {
+        ConversationMetadata::Metadata(metadata)
+    }
+}
+
+impl<'de> Deserialize<'de> for ConversationMetadata {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        let flat = value
+            .as_object()
+            .filter(|map| map.values().all(Value::is_string))
+            .map(|map| {
+                map.iter()
+                    .filter_map(|(key, value)| Some((key.as_str(), value.as_str()?)))
+                    .collect()
+            });
+        Ok(match flat {
+            Some(metadata) => ConversationMetadata::Metadata(metadata),
+            None => ConversationMetadata::MetadataRaw(value),
+        })
+    }
 }
 
 /// Conversation deletion confirmation
@@ -109,6 +329,25 @@ pub struct ConversationItemList {
     pub has_more: bool,
 }
 
+impl From<ConversationItemList> for Page<ConversationItem> {
+    fn from(list: ConversationItemList) -> Self {
+        Page::new(list.data, list.has_more, Some(list.last_id))
+    }
+}
+
+impl Paginator<ConversationItem> {
+    /// End the stream at the first item created after this call
+    ///
+    /// Items appended to the conversation while it is walked are not
+    /// yielded. Items without `created_at` never end the stream.
+    pub fn snapshot(self) -> Self {
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs() as i64);
+        self.stop_when(move |item| item.created_at.is_some_and(|created| created > started))
+    }
+}
+
 /// Query parameters for listing conversation items
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct ListItemsQuery {
@@ -117,11 +356,11 @@ pub struct ListItemsQuery {
     pub after: Option<String>,
     /// Additional output data to include
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub include: Option<Vec<String>>,
+    pub include: Option<Vec<Include>>,
     /// Limit on number of objects (1-100, default 20)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub limit: Option<u32>,
-    /// Order to return items (asc or desc)
+    /// Order to return items (asc or desc); the server default is desc
     #[serde(skip_serializing_if = "Option::is_none")]
     pub order: Option<String>,
 }
@@ -133,6 +372,36 @@ pub struct CreateItemsRequest {
     pub items: Vec<CreateItemRequest>,
 }
 
+impl CreateItemsRequest {
+    /// Maximum number of items accepted by one request
+    pub const MAX_ITEMS: usize = 20;
+
+    /// Convert chat messages into requests of at most [`Self::MAX_ITEMS`] items
+    ///
+    /// Fails on the first message that cannot be converted, naming its index.
+    pub fn from_messages(messages: &[ChatMessage]) -> crate::Result<Vec<Self>> {
+        let items = messages
+            .iter()
+            .enumerate()
+            .map(|(i, message)| {
+                CreateItemRequest::try_from(message).map_err(|e| match e {
+                    TwcError::InvalidRequest(reason) => {
+                        TwcError::InvalidRequest(format!("message {}: {}", i, reason))
+                    }
+                    other => other,
+                })
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+
+        Ok(items
+            .chunks(Self::MAX_ITEMS)
+            .map(|chunk| Self {
+                items: chunk.to_vec(),
+            })
+            .collect())
+    }
+}
+
 /// Single item creation request
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct CreateItemRequest {
@@ -145,22 +414,76 @@ pub struct CreateItemRequest {
     pub content: Vec<ItemContentInput>,
 }
 
-/// Content input for item creation
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct ItemContentInput {
-    /// Content type - "input_text"
-    #[serde(rename = "type")]
-    pub content_type: String,
-    /// Text content
-    pub text: String,
+impl TryFrom<&ChatMessage> for CreateItemRequest {
+    type Error = TwcError;
+
+    /// Convert a text chat message into a conversation item
+    ///
+    /// User, system and developer text becomes `input_text`, assistant text
+    /// `output_text`, and image URLs of non-assistant messages `input_image`.
+    /// Tool and function messages, assistant tool calls and other content
+    /// parts are rejected with `TwcError::InvalidRequest`.
+    fn try_from(message: &ChatMessage) -> crate::Result<Self> {
+        let role = match message.role {
+            Role::User => "user",
+            Role::System => "system",
+            Role::Developer => "developer",
+            Role::Assistant => "assistant",
+            Role::Tool | Role::Function => {
+                return Err(TwcError::InvalidRequest(
+                    "tool and function messages cannot be stored as conversation items yet"
+                        .to_string(),
+                ));
+            }
+        };
+
+        if message.tool_calls.is_some() || message.function_call.is_some() {
+            return Err(TwcError::InvalidRequest(
+                "messages with tool calls cannot be stored as conversation items yet".to_string(),
+            ));
+        }
+
+        let text_part = |text: String| match message.role {
+            Role::Assistant => ConversationItemContent::output_text(text),
+            _ => ConversationItemContent::input_text(text),
+        };
+        let content = match &message.content {
+            ChatContent::Text(text) => vec![text_part(text.clone())],
+            ChatContent::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    ContentItem::Text(text) => Ok(text_part(text.text.clone())),
+                    ContentItem::ImageUrl(image) if message.role != Role::Assistant => {
+                        Ok(ConversationItemContent::input_image(
+                            image.image_url.url.clone(),
+                            image.image_url.detail,
+                        ))
+                    }
+                    other => Err(TwcError::InvalidRequest(format!(
+                        "multimodal content cannot be stored as conversation items yet (found {})",
+                        other.content_type()
+                    ))),
+                })
+                .collect::<crate::Result<Vec<_>>>()?,
+        };
+
+        Ok(Self {
+            item_type: "message".to_string(),
+            role: role.to_string(),
+            content,
+        })
+    }
 }
 
+/// Content input for item creation
+pub type ItemContentInput = ConversationItemContent;
+
 /// Query parameters for getting a conversation item
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct GetItemQuery {
     /// Additional output data to include in model response
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub include: Option<Vec<String>>,
+    pub include: Option<Vec<Include>>,
 }
 
 /// Query parameters for creating items
@@ -168,5 +491,16 @@ pub struct GetItemQuery {
 pub struct CreateItemsQuery {
     /// Additional fields to include in the response
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub include: Option<Vec<String>>,
+    pub include: Option<Vec<Include>>,
+}
+
+/// Result of appending a user message to a conversation and getting a reply
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversationTurn {
+    /// Response that produced the reply
+    pub response: Response,
+    /// Items the response added to the conversation
+    pub items: Vec<Value>,
+    /// Assistant reply text
+    pub text: String,
 }
@@ -0,0 +1,70 @@
+//! Types for the widget embed script
+
+/// Options of a widget embed script request
+///
+/// Passed to
+/// [`get_embed_code_with_options`](crate::api::AgentClientExt::get_embed_code_with_options).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EmbedOptions {
+    /// Whether the widget starts collapsed
+    pub collapsed: Option<bool>,
+    /// `Referer` header, the page embedding the widget
+    pub referer: Option<String>,
+    /// `Origin` header of the page embedding the widget
+    pub origin: Option<String>,
+    /// Send the client's token, for agents that are not publicly embeddable
+    ///
+    /// Off by default. An auth style set with
+    /// [`ClientBuilder::auth_style`](crate::ClientBuilder::auth_style)
+    /// authenticates the request either way.
+    pub authenticated: bool,
+    /// `ETag` of a cached script, sent as `If-None-Match`
+    pub if_none_match: Option<String>,
+    /// `Last-Modified` of a cached script, sent as `If-Modified-Since`
+    pub if_modified_since: Option<String>,
+}
+
+impl EmbedOptions {
+    /// Options for a widget on `origin`, embedded in the page `referer`
+    pub fn new(referer: impl Into<String>, origin: impl Into<String>) -> Self {
+        Self {
+            referer: Some(referer.into()),
+            origin: Some(origin.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Set whether the widget starts collapsed
+    pub fn collapsed(mut self, collapsed: bool) -> Self {
+        self.collapsed = Some(collapsed);
+        self
+    }
+
+    /// Set whether the client's token is sent
+    pub fn authenticated(mut self, authenticated: bool) -> Self {
+        self.authenticated = authenticated;
+        self
+    }
+
+    /// Revalidate `cached`: the server answers `304 Not Modified` when the
+    /// script has not changed since
+    pub fn cached(mut self, cached: &EmbedCode) -> Self {
+        self.if_none_match.clone_from(&cached.etag);
+        self.if_modified_since.clone_from(&cached.last_modified);
+        self
+    }
+}
+
+/// Widget embed script with its cache validators
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EmbedCode {
+    /// The script; `None` when not modified
+    pub script: Option<String>,
+    /// `ETag` of the script
+    pub etag: Option<String>,
+    /// `Last-Modified` date of the script
+    pub last_modified: Option<String>,
+    /// Whether the server answered `304 Not Modified`, so the cached
+    /// script is still current
+    pub not_modified: bool,
+}
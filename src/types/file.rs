@@ -0,0 +1,46 @@
+//! Types for files API (OpenAI-compatible)
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Uploaded file
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FileObject {
+    /// File ID, usable in `AgentCallRequest::file_ids`
+    pub id: String,
+    /// Object type - always "file"
+    #[serde(default)]
+    pub object: String,
+    /// Size of the file in bytes
+    pub bytes: u64,
+    /// Unix timestamp of upload
+    pub created_at: i64,
+    /// Name of the uploaded file
+    pub filename: String,
+    /// Intended purpose of the file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    /// Additional fields from API
+    #[serde(flatten)]
+    pub extra: Value,
+}
+
+/// List of uploaded files
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FileList {
+    /// Object type - always "list"
+    pub object: String,
+    /// Uploaded files
+    pub data: Vec<FileObject>,
+}
+
+/// File deletion confirmation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FileDeleted {
+    /// ID of the deleted file
+    pub id: String,
+    /// Object type
+    pub object: String,
+    /// Whether the file was deleted
+    pub deleted: bool,
+}
@@ -0,0 +1,38 @@
+//! Types for knowledge base API (Timeweb Cloud control plane)
+
+use serde::{Deserialize, Serialize};
+
+/// Indexing status of a knowledge base document
+///
+/// Unknown statuses are kept in [`KnowledgeDocumentStatus::Other`] so new
+/// indexing states do not break deserialization.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KnowledgeDocumentStatus {
+    /// Uploaded, waiting for indexing
+    Uploaded,
+    /// Being indexed
+    Indexing,
+    /// Indexed and available to the agent
+    Indexed,
+    /// Indexing failed
+    Error,
+    /// Any other status
+    #[serde(untagged)]
+    Other(String),
+}
+
+/// Document stored in a knowledge base
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct KnowledgeDocument {
+    /// Document ID
+    pub id: u64,
+    /// File name of the document
+    pub name: String,
+    /// Indexing status
+    pub status: KnowledgeDocumentStatus,
+    /// Size of the document in bytes
+    pub size: u64,
+    /// Upload time (ISO 8601)
+    pub created_at: String,
+}
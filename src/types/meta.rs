@@ -0,0 +1,161 @@
+//! Response metadata returned by the `*_with_meta` methods
+
+use std::time::{Duration, SystemTime};
+
+use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
+
+/// Parsed body together with the HTTP metadata of its response
+#[derive(Debug, Clone)]
+pub struct WithMeta<T> {
+    /// Parsed response body
+    pub data: T,
+    /// Status, headers and latency of the response
+    pub meta: ResponseMeta,
+}
+
+/// HTTP metadata of a successful response
+#[derive(Debug, Clone)]
+pub struct ResponseMeta {
+    /// HTTP status code
+    pub status: StatusCode,
+    /// Response headers
+    pub headers: HeaderMap,
+    /// Time from sending the request until the body was read
+    pub latency: Duration,
+    /// Every attempt made, the most recent 16 at most; more than one means
+    /// the call was retried
+    pub attempts: Vec<AttemptRecord>,
+}
+
+/// One attempt of a request, as recorded by the retry loop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttemptRecord {
+    /// When the attempt was sent
+    pub started_at: SystemTime,
+    /// Time until the response headers arrived or the attempt failed
+    pub duration: Duration,
+    /// How the attempt ended
+    pub outcome: AttemptOutcome,
+    /// Backoff waited before the next attempt; `None` for the last one
+    pub retry_delay: Option<Duration>,
+}
+
+/// How an [`AttemptRecord`] ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttemptOutcome {
+    /// The server answered with this status
+    Status(StatusCode),
+    /// No response arrived; holds the error message
+    Error(String),
+}
+
+/// Outcome of a response body written straight to a writer by the `*_to`
+/// methods
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Download {
+    /// HTTP status code
+    pub status: StatusCode,
+    /// `Content-Type` of the body, if the server sent one
+    pub content_type: Option<String>,
+    /// Number of bytes written
+    pub bytes: u64,
+}
+
+/// Result of [`ping`](crate::api::AgentClientExt::ping)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PingReport {
+    /// Round-trip time of the check
+    pub latency: Duration,
+    /// Number of models the agent offers
+    pub models_available: usize,
+}
+
+/// Result of [`CloudAIClient::warm_up`](crate::CloudAIClient::warm_up)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WarmUpReport {
+    /// Time to connect and receive the response headers
+    pub latency: Duration,
+    /// Status of the response; any status means the connection is pooled
+    pub status: StatusCode,
+}
+
+/// Rate limit state reported by the `x-ratelimit-*` headers
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RateLimitInfo {
+    /// Maximum requests allowed in the current window
+    pub limit_requests: Option<u64>,
+    /// Maximum tokens allowed in the current window
+    pub limit_tokens: Option<u64>,
+    /// Requests left in the current window
+    pub remaining_requests: Option<u64>,
+    /// Tokens left in the current window
+    pub remaining_tokens: Option<u64>,
+    /// Time until the request limit resets
+    pub reset_requests: Option<Duration>,
+    /// Time until the token limit resets
+    pub reset_tokens: Option<Duration>,
+}
+
+impl ResponseMeta {
+    /// Whether the call succeeded only after retrying
+    pub fn retried(&self) -> bool {
+        self.attempts.len() > 1
+    }
+
+    /// Value of a header as a string, if present and valid ASCII
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)?.to_str().ok()
+    }
+
+    /// Rate limit state, or `None` when no `x-ratelimit-*` header is present
+    pub fn ratelimit(&self) -> Option<RateLimitInfo> {
+        let count = |name| self.header(name).and_then(|v| v.trim().parse().ok());
+        let reset = |name| self.header(name).and_then(parse_reset);
+
+        let info = RateLimitInfo {
+            limit_requests: count("x-ratelimit-limit-requests"),
+            limit_tokens: count("x-ratelimit-limit-tokens"),
+            remaining_requests: count("x-ratelimit-remaining-requests"),
+            remaining_tokens: count("x-ratelimit-remaining-tokens"),
+            reset_requests: reset("x-ratelimit-reset-requests"),
+            reset_tokens: reset("x-ratelimit-reset-tokens"),
+        };
+
+        (info != RateLimitInfo::default()).then_some(info)
+    }
+}
+
+/// Parse reset values such as `20ms`, `1s`, `6m0s`, `1h2m3.5s` or plain seconds
+fn parse_reset(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    if let Ok(seconds) = value.parse::<f64>() {
+        return Duration::try_from_secs_f64(seconds).ok();
+    }
+
+    let mut total = 0.0;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let split = rest.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+        let (number, tail) = rest.split_at(split);
+        let number: f64 = number.parse().ok()?;
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        total += number
+            * match unit {
+                "h" => 3600.0,
+                "m" => 60.0,
+                "s" => 1.0,
+                "ms" => 0.001,
+                _ => return None,
+            };
+        rest = tail;
+    }
+
+    Duration::try_from_secs_f64(total).ok()
+}
@@ -1,11 +1,25 @@
 //! DTOs and type definitions for TWCai API
 
+pub mod agent;
 pub mod chat;
 pub mod common;
+pub mod completions;
 pub mod conversation;
+pub mod embed;
+pub mod file;
+pub mod knowledge;
+pub mod meta;
 pub mod response;
+pub mod stats;
 
+pub use agent::*;
 pub use chat::*;
 pub use common::*;
+pub use completions::*;
 pub use conversation::*;
+pub use embed::*;
+pub use file::*;
+pub use knowledge::*;
+pub use meta::*;
 pub use response::*;
+pub use stats::*;
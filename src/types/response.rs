@@ -1,7 +1,15 @@
 //! Types for responses API (OpenAI-compatible)
 
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
+
+use crate::pagination::Page;
+
+use super::chat::ToolChoice;
+use super::common::{
+    CompletionTokensDetails, Include, PromptTokensDetails, ReasoningConfig, Usage,
+};
+use super::conversation::Conversation;
 
 /// Request to create a response
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
@@ -26,7 +34,7 @@ pub struct CreateResponseRequest {
     pub metadata: Option<Value>,
     /// Tools available to the model
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub tools: Option<Value>,
+    pub tools: Option<Vec<ResponseTool>>,
     /// Whether to stream the response
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
@@ -41,7 +49,7 @@ pub struct CreateResponseRequest {
     pub text: Option<Value>,
     /// How the model should choose tools
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub tool_choice: Option<Value>,
+    pub tool_choice: Option<ToolChoice>,
     /// Allow model to execute tool calls in parallel
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parallel_tool_calls: Option<bool>,
@@ -53,10 +61,10 @@ pub struct CreateResponseRequest {
     pub previous_response_id: Option<String>,
     /// Conversation this response belongs to
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub conversation: Option<Value>,
+    pub conversation: Option<ConversationRef>,
     /// Additional output data to include in model response
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub include: Option<Vec<String>>,
+    pub include: Option<Vec<Include>>,
     /// Whether to store the generated response for later retrieval
     #[serde(skip_serializing_if = "Option::is_none")]
     pub store: Option<bool>,
@@ -68,10 +76,10 @@ pub struct CreateResponseRequest {
     pub top_logprobs: Option<u32>,
     /// Truncation strategy for model response
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub truncation: Option<String>,
+    pub truncation: Option<Truncation>,
     /// Service tier for request processing
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub service_tier: Option<String>,
+    pub service_tier: Option<ServiceTier>,
     /// Stable identifier for detecting policy violations
     #[serde(skip_serializing_if = "Option::is_none")]
     pub safety_identifier: Option<String>,
@@ -83,10 +91,423 @@ pub struct CreateResponseRequest {
     pub prompt: Option<Value>,
     /// Configuration for reasoning models (gpt-5 and o-series)
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub reasoning: Option<Value>,
+    pub reasoning: Option<ReasoningConfig>,
     /// Deprecated: use safety_identifier or prompt_cache_key
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
+    /// Parameters the typed fields do not cover, sent as top-level keys
+    #[serde(flatten, default, skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
+}
+
+impl CreateResponseRequest {
+    /// Send an additional top-level parameter, e.g. a vendor-specific hint
+    ///
+    /// The key must not name a typed field of [`CreateResponseRequest`].
+    pub fn extra_param(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.extra.insert(key.into(), value.into());
+        self
+    }
+
+    /// Start building a request with [`CreateResponseRequestBuilder`]
+    pub fn builder() -> CreateResponseRequestBuilder {
+        CreateResponseRequestBuilder::default()
+    }
+}
+
+/// Builder for [`CreateResponseRequest`]
+///
+/// Created by [`CreateResponseRequest::builder`]. Fields that cannot be sent
+/// together are reported by [`build`](Self::build) as
+/// [`TwcError::InvalidRequest`](crate::TwcError::InvalidRequest).
+#[derive(Debug, Clone, Default)]
+pub struct CreateResponseRequestBuilder {
+    request: CreateResponseRequest,
+    input_text: Option<String>,
+    input_messages: Vec<Value>,
+}
+
+impl CreateResponseRequestBuilder {
+    /// Use the given model
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.request.model = Some(model.into());
+        self
+    }
+
+    /// Set the system-level instructions
+    pub fn instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.request.instructions = Some(instructions.into());
+        self
+    }
+
+    /// Send a plain text input
+    pub fn input_text(mut self, text: impl Into<String>) -> Self {
+        self.input_text = Some(text.into());
+        self
+    }
+
+    /// Send a list of input items, replacing any added before
+    pub fn input_messages(mut self, messages: impl IntoIterator<Item = Value>) -> Self {
+        self.input_messages = messages.into_iter().collect();
+        self
+    }
+
+    /// Append a text message from `role` (`user`, `assistant`, `developer`)
+    pub fn input_message(mut self, role: impl Into<String>, text: impl Into<String>) -> Self {
+        self.input_messages.push(serde_json::json!({
+            "role": role.into(),
+            "content": text.into(),
+        }));
+        self
+    }
+
+    /// Limit the number of generated tokens
+    pub fn max_output_tokens(mut self, max_output_tokens: u32) -> Self {
+        self.request.max_output_tokens = Some(max_output_tokens);
+        self
+    }
+
+    /// Set the sampling temperature (0-2)
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.request.temperature = Some(temperature);
+        self
+    }
+
+    /// Set the nucleus sampling parameter (0-1)
+    pub fn top_p(mut self, top_p: f32) -> Self {
+        self.request.top_p = Some(top_p);
+        self
+    }
+
+    /// Attach string key-value metadata to the response
+    pub fn metadata<I, K, V>(mut self, metadata: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let metadata = metadata
+            .into_iter()
+            .map(|(key, value)| (key.into(), Value::String(value.into())))
+            .collect::<Map<_, _>>();
+        self.request.metadata = Some(Value::Object(metadata));
+        self
+    }
+
+    /// Make tools available to the model
+    pub fn tools(mut self, tools: impl IntoIterator<Item = ResponseTool>) -> Self {
+        self.request.tools = Some(tools.into_iter().collect());
+        self
+    }
+
+    /// Control how the model chooses tools, e.g. `"auto"` or `"required"`
+    pub fn tool_choice(mut self, tool_choice: impl Into<ToolChoice>) -> Self {
+        self.request.tool_choice = Some(tool_choice.into());
+        self
+    }
+
+    /// Allow or forbid parallel tool calls
+    pub fn parallel_tool_calls(mut self, parallel: bool) -> Self {
+        self.request.parallel_tool_calls = Some(parallel);
+        self
+    }
+
+    /// Continue from a previous response
+    pub fn previous_response_id(mut self, response_id: impl Into<String>) -> Self {
+        self.request.previous_response_id = Some(response_id.into());
+        self
+    }
+
+    /// Add the response to the conversation with the given id
+    pub fn conversation_id(mut self, conversation_id: impl Into<String>) -> Self {
+        self.request.conversation = Some(ConversationRef::Id(conversation_id.into()));
+        self
+    }
+
+    /// Add the response to a conversation, e.g. `&Conversation`
+    pub fn conversation(mut self, conversation: impl Into<ConversationRef>) -> Self {
+        self.request.conversation = Some(conversation.into());
+        self
+    }
+
+    /// Run the response in the background
+    pub fn background(mut self, background: bool) -> Self {
+        self.request.background = Some(background);
+        self
+    }
+
+    /// Whether to store the response for later retrieval
+    pub fn store(mut self, store: bool) -> Self {
+        self.request.store = Some(store);
+        self
+    }
+
+    /// Request additional output data
+    pub fn include(mut self, include: impl IntoIterator<Item = Include>) -> Self {
+        self.request.include = Some(include.into_iter().collect());
+        self
+    }
+
+    /// Set the text output configuration, e.g. a JSON schema format
+    pub fn text(mut self, text: Value) -> Self {
+        self.request.text = Some(text);
+        self
+    }
+
+    /// Set the truncation strategy
+    pub fn truncation(mut self, truncation: Truncation) -> Self {
+        self.request.truncation = Some(truncation);
+        self
+    }
+
+    /// Set the service tier
+    pub fn service_tier(mut self, service_tier: ServiceTier) -> Self {
+        self.request.service_tier = Some(service_tier);
+        self
+    }
+
+    /// Configure reasoning models
+    pub fn reasoning(mut self, reasoning: ReasoningConfig) -> Self {
+        self.request.reasoning = Some(reasoning);
+        self
+    }
+
+    /// Set the identifier used to detect policy violations
+    pub fn safety_identifier(mut self, safety_identifier: impl Into<String>) -> Self {
+        self.request.safety_identifier = Some(safety_identifier.into());
+        self
+    }
+
+    /// Set the key used to cache similar prompts
+    pub fn prompt_cache_key(mut self, prompt_cache_key: impl Into<String>) -> Self {
+        self.request.prompt_cache_key = Some(prompt_cache_key.into());
+        self
+    }
+
+    /// Send an additional top-level parameter
+    pub fn extra_param(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.request = self.request.extra_param(key, value);
+        self
+    }
+
+    /// Check the combination of fields and build the request
+    ///
+    /// Fails when both text and message input are set, when
+    /// `previous_response_id` is combined with a conversation, when the
+    /// conversation id is empty, or when a background response is not stored.
+    pub fn build(self) -> crate::Result<CreateResponseRequest> {
+        let mut request = self.request;
+        let invalid = |reason: &str| Err(crate::TwcError::InvalidRequest(reason.to_string()));
+
+        if self.input_text.is_some() && !self.input_messages.is_empty() {
+            return invalid("input_text and input_messages cannot both be set");
+        }
+        if request.previous_response_id.is_some() && request.conversation.is_some() {
+            return invalid("previous_response_id and conversation cannot both be set");
+        }
+        if let Some(conversation) = &request.conversation {
+            conversation.validate()?;
+        }
+        if request.background == Some(true) && request.store == Some(false) {
+            return invalid("background responses must be stored; remove store(false)");
+        }
+
+        request.input = match self.input_text {
+            Some(text) => Some(ResponseInput::Text(text)),
+            None if self.input_messages.is_empty() => None,
+            None => Some(ResponseInput::Messages(self.input_messages)),
+        };
+        Ok(request)
+    }
+}
+
+/// Conversation a response belongs to
+///
+/// The API accepts either the bare id or an object with an `id` field; both
+/// shapes are read and written as they came.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ConversationRef {
+    /// Conversation id, sent as a string
+    Id(String),
+    /// Conversation object, sent as `{"id": "..."}`
+    Object {
+        /// Conversation id
+        id: String,
+    },
+}
+
+impl ConversationRef {
+    /// ID of the conversation
+    pub fn id(&self) -> &str {
+        match self {
+            ConversationRef::Id(id) | ConversationRef::Object { id } => id,
+        }
+    }
+
+    /// Check that the conversation id is not empty
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.id().trim().is_empty() {
+            return Err(crate::TwcError::InvalidRequest(
+                "Conversation id must not be empty".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl From<&Conversation> for ConversationRef {
+    fn from(conversation: &Conversation) -> Self {
+        ConversationRef::Id(conversation.id.clone())
+    }
+}
+
+impl From<&str> for ConversationRef {
+    fn from(id: &str) -> Self {
+        ConversationRef::Id(id.to_string())
+    }
+}
+
+impl From<String> for ConversationRef {
+    fn from(id: String) -> Self {
+        ConversationRef::Id(id)
+    }
+}
+
+/// Tool available to the model in the responses API
+///
+/// Function tools are flat (`{"type": "function", "name": ...}`) unlike the
+/// nested chat [`Tool`](super::Tool), so the two are separate types. Built-in
+/// tools such as `web_search` are kept in [`ResponseTool::Other`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponseTool {
+    /// `function`: a function the model may call
+    Function(ResponseFunctionTool),
+    /// Any other tool, as its full JSON object
+    Other(Value),
+}
+
+impl ResponseTool {
+    /// Function tool taking arguments described by a JSON schema
+    pub fn function(name: impl Into<String>, parameters: Value) -> Self {
+        ResponseTool::Function(ResponseFunctionTool::new(name, parameters))
+    }
+}
+
+impl From<ResponseFunctionTool> for ResponseTool {
+    fn from(tool: ResponseFunctionTool) -> Self {
+        ResponseTool::Function(tool)
+    }
+}
+
+impl Serialize for ResponseTool {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::Error;
+
+        let tool = match self {
+            ResponseTool::Function(tool) => tool,
+            ResponseTool::Other(data) => return data.serialize(serializer),
+        };
+        let mut tool = serde_json::to_value(tool).map_err(S::Error::custom)?;
+        if let Value::Object(fields) = &mut tool {
+            fields.insert("type".to_string(), Value::from("function"));
+        }
+        tool.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ResponseTool {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let data = Value::deserialize(deserializer)?;
+        match data.get("type").and_then(Value::as_str) {
+            Some("function") => serde_json::from_value(data)
+                .map(ResponseTool::Function)
+                .map_err(D::Error::custom),
+            _ => Ok(ResponseTool::Other(data)),
+        }
+    }
+}
+
+/// Function tool of the responses API
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResponseFunctionTool {
+    /// Name of the function
+    pub name: String,
+    /// What the function does, used by the model to decide when to call it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// JSON schema of the arguments
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<Value>,
+    /// Whether the arguments must match the schema exactly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+impl ResponseFunctionTool {
+    /// Function taking arguments described by a JSON schema
+    pub fn new(name: impl Into<String>, parameters: Value) -> Self {
+        Self {
+            name: name.into(),
+            description: None,
+            parameters: Some(parameters),
+            strict: None,
+        }
+    }
+
+    /// Describe what the function does
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Require arguments that match the schema exactly
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = Some(strict);
+        self
+    }
+}
+
+/// Service tier used to process a request
+///
+/// Unknown tiers are kept in [`ServiceTier::Other`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceTier {
+    /// Let the project settings decide
+    Auto,
+    /// Standard processing
+    Default,
+    /// Cheaper, slower processing
+    Flex,
+    /// Faster processing at a higher price
+    Priority,
+    /// Any other tier
+    #[serde(untagged)]
+    Other(String),
+}
+
+/// What to do when the input exceeds the model's context window
+///
+/// Unknown strategies are kept in [`Truncation::Other`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Truncation {
+    /// Drop items from the start of the conversation to fit
+    Auto,
+    /// Fail the request with a 400 error
+    Disabled,
+    /// Any other strategy
+    #[serde(untagged)]
+    Other(String),
 }
 
 /// Input can be a string or array of messages
@@ -100,17 +521,69 @@ pub enum ResponseInput {
 }
 
 /// Token usage for response
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+///
+/// Accepts both chat-style (`prompt_tokens`) and Responses-style
+/// (`input_tokens`) field names.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct ResponseUsage {
     /// Number of tokens in the prompt
+    #[serde(alias = "input_tokens")]
     pub prompt_tokens: u32,
     /// Number of tokens in the response
+    #[serde(alias = "output_tokens")]
     pub completion_tokens: u32,
     /// Total number of tokens
     pub total_tokens: u32,
+    /// Breakdown of the prompt tokens
+    #[serde(
+        default,
+        alias = "input_tokens_details",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub prompt_tokens_details: Option<PromptTokensDetails>,
+    /// Breakdown of the response tokens
+    #[serde(
+        default,
+        alias = "output_tokens_details",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub completion_tokens_details: Option<CompletionTokensDetails>,
+}
+
+impl ResponseUsage {
+    /// Prompt tokens served from the cache (0 when not reported)
+    pub fn cached_tokens(&self) -> u32 {
+        self.prompt_tokens_details
+            .as_ref()
+            .and_then(|d| d.cached_tokens)
+            .unwrap_or(0)
+    }
+
+    /// Response tokens spent on reasoning (0 when not reported)
+    pub fn reasoning_tokens(&self) -> u32 {
+        self.completion_tokens_details
+            .as_ref()
+            .and_then(|d| d.reasoning_tokens)
+            .unwrap_or(0)
+    }
+}
+
+/// Chat-style usage with the same counts
+impl From<ResponseUsage> for Usage {
+    fn from(usage: ResponseUsage) -> Self {
+        Usage {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+            prompt_tokens_details: usage.prompt_tokens_details,
+            completion_tokens_details: usage.completion_tokens_details,
+        }
+    }
 }
 
 /// Response object (OpenAI-compatible)
+///
+/// Accepts the chat-style `created` in place of `created_at`.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Response {
     /// Unique identifier for the response
@@ -118,24 +591,75 @@ pub struct Response {
     /// Object type - always "response"
     pub object: String,
     /// Unix timestamp of creation
+    #[serde(alias = "created")]
     pub created_at: i64,
     /// Model identifier
     pub model: String,
     /// Response status
     pub status: String,
-    /// Token usage information
-    pub usage: ResponseUsage,
+    /// Token usage information; absent (or null) until the response completes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<ResponseUsage>,
     /// Additional fields from API
     #[serde(flatten)]
     pub extra: Value,
 }
 
+impl Response {
+    /// Service tier the server actually used, when echoed back
+    pub fn service_tier(&self) -> Option<ServiceTier> {
+        self.extra
+            .get("service_tier")
+            .and_then(|tier| ServiceTier::deserialize(tier).ok())
+    }
+
+    /// Total tokens used, 0 when usage is not reported
+    pub fn total_tokens(&self) -> u32 {
+        self.usage.as_ref().map_or(0, |usage| usage.total_tokens)
+    }
+
+    /// Output items produced by the response (`output` field)
+    pub fn output_items(&self) -> &[Value] {
+        self.extra
+            .get("output")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Reasoning summary texts of all `reasoning` items, separated by a blank
+    /// line (empty unless a summary was requested in [`ReasoningConfig`])
+    pub fn reasoning_summary(&self) -> String {
+        self.output_items()
+            .iter()
+            .filter(|item| item.get("type").and_then(Value::as_str) == Some("reasoning"))
+            .filter_map(|item| item.get("summary").and_then(Value::as_array))
+            .flatten()
+            .filter(|part| part.get("type").and_then(Value::as_str) == Some("summary_text"))
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// Concatenated text of all `output_text` parts of assistant messages
+    pub fn output_text(&self) -> String {
+        self.output_items()
+            .iter()
+            .filter(|item| item.get("type").and_then(Value::as_str) == Some("message"))
+            .filter_map(|item| item.get("content").and_then(Value::as_array))
+            .flatten()
+            .filter(|part| part.get("type").and_then(Value::as_str) == Some("output_text"))
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect()
+    }
+}
+
 /// Query parameters for getting a response
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
 pub struct GetResponseQuery {
     /// Additional fields to include in response
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub include: Option<Vec<String>>,
+    pub include: Option<Vec<Include>>,
     /// Enable stream obfuscation for side-channel attack protection
     #[serde(skip_serializing_if = "Option::is_none")]
     pub include_obfuscation: Option<bool>,
@@ -146,3 +670,498 @@ pub struct GetResponseQuery {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
 }
+
+/// Item of the input a response was generated from
+///
+/// Messages and function calls with their outputs are typed; every other
+/// item type is kept as [`ResponseInputItem::Other`] with its raw payload.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponseInputItem {
+    /// `message`: instructions, user input or earlier assistant output
+    Message(InputMessageItem),
+    /// `function_call`: a tool call made by the model earlier
+    FunctionCall(FunctionCallItem),
+    /// `function_call_output`: the result returned for a tool call
+    FunctionCallOutput(FunctionCallOutputItem),
+    /// Any other item type
+    Other {
+        /// Item type from the `type` field
+        item_type: String,
+        /// Full item payload
+        data: Value,
+    },
+}
+
+impl ResponseInputItem {
+    /// ID of the item, if it has one
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            Self::Message(item) => Some(&item.id),
+            Self::FunctionCall(item) => Some(&item.id),
+            Self::FunctionCallOutput(item) => Some(&item.id),
+            Self::Other { data, .. } => data.get("id").and_then(Value::as_str),
+        }
+    }
+}
+
+/// Message given to the model as input
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct InputMessageItem {
+    /// Unique ID of the item
+    pub id: String,
+    /// Role of the author: `user`, `assistant`, `system` or `developer`
+    pub role: String,
+    /// Status of the item
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Content parts (`input_text`, `output_text`, `input_image`, ...)
+    pub content: Vec<Value>,
+}
+
+impl InputMessageItem {
+    /// Text of all text parts, in order
+    pub fn text(&self) -> String {
+        self.content
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect()
+    }
+}
+
+/// Tool call made by the model
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FunctionCallItem {
+    /// Unique ID of the item
+    pub id: String,
+    /// ID linking the call to its output
+    pub call_id: String,
+    /// Name of the called function
+    pub name: String,
+    /// Arguments as a JSON string
+    pub arguments: String,
+    /// Status of the item
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+/// Result returned for a tool call
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FunctionCallOutputItem {
+    /// Unique ID of the item
+    pub id: String,
+    /// ID of the call this is the output of
+    pub call_id: String,
+    /// Output of the call, usually a string
+    pub output: Value,
+    /// Status of the item
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+impl Serialize for ResponseInputItem {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::Error;
+
+        let (item_type, item) = match self {
+            Self::Message(item) => ("message", serde_json::to_value(item)),
+            Self::FunctionCall(item) => ("function_call", serde_json::to_value(item)),
+            Self::FunctionCallOutput(item) => ("function_call_output", serde_json::to_value(item)),
+            Self::Other { data, .. } => return data.serialize(serializer),
+        };
+        let mut item = item.map_err(S::Error::custom)?;
+        if let Value::Object(fields) = &mut item {
+            fields.insert("type".to_string(), Value::from(item_type));
+        }
+        item.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ResponseInputItem {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let data = Value::deserialize(deserializer)?;
+        let item_type = data
+            .get("type")
+            .and_then(Value::as_str)
+            // Easy input messages may leave out the type
+            .unwrap_or("message")
+            .to_string();
+
+        let parsed = match item_type.as_str() {
+            "message" => serde_json::from_value(data).map(Self::Message),
+            "function_call" => serde_json::from_value(data).map(Self::FunctionCall),
+            "function_call_output" => serde_json::from_value(data).map(Self::FunctionCallOutput),
+            _ => return Ok(Self::Other { item_type, data }),
+        };
+
+        parsed.map_err(D::Error::custom)
+    }
+}
+
+/// List of the input items of a response
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResponseInputItemList {
+    /// Object type - always "list"
+    pub object: String,
+    /// Input items
+    pub data: Vec<ResponseInputItem>,
+    /// ID of the first item in the list
+    #[serde(default)]
+    pub first_id: Option<String>,
+    /// ID of the last item in the list
+    #[serde(default)]
+    pub last_id: Option<String>,
+    /// Whether there are more items available
+    pub has_more: bool,
+}
+
+impl From<ResponseInputItemList> for Page<ResponseInputItem> {
+    fn from(list: ResponseInputItemList) -> Self {
+        Page::new(list.data, list.has_more, list.last_id)
+    }
+}
+
+/// Event streamed by the responses API
+///
+/// Lifecycle events, output item events and the deltas of text, function
+/// call arguments and reasoning summaries are typed; every other event type
+/// is kept as [`ResponseStreamEvent::Other`] with its raw payload.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponseStreamEvent {
+    /// `response.created`
+    Created(ResponseLifecycleEvent),
+    /// `response.in_progress`
+    InProgress(ResponseLifecycleEvent),
+    /// `response.completed`
+    Completed(ResponseLifecycleEvent),
+    /// `response.failed`
+    Failed(ResponseLifecycleEvent),
+    /// `response.incomplete`
+    Incomplete(ResponseLifecycleEvent),
+    /// `response.output_text.delta`
+    OutputTextDelta(ResponseTextDeltaEvent),
+    /// `response.output_text.done`
+    OutputTextDone(ResponseTextDoneEvent),
+    /// `response.output_item.added`
+    OutputItemAdded(ResponseOutputItemEvent),
+    /// `response.output_item.done`
+    OutputItemDone(ResponseOutputItemEvent),
+    /// `response.function_call_arguments.delta`
+    FunctionCallArgumentsDelta(ResponseArgumentsDeltaEvent),
+    /// `response.function_call_arguments.done`
+    FunctionCallArgumentsDone(ResponseArgumentsDoneEvent),
+    /// `response.reasoning_summary_text.delta`
+    ReasoningSummaryTextDelta(ResponseSummaryDeltaEvent),
+    /// `response.reasoning_summary_text.done`
+    ReasoningSummaryTextDone(ResponseSummaryDoneEvent),
+    /// Any other event type
+    Other {
+        /// Event type from the `type` field
+        event: String,
+        /// Full event payload
+        data: Value,
+    },
+}
+
+/// Lifecycle event carrying a snapshot of the response
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResponseLifecycleEvent {
+    /// Position of the event in the stream
+    pub sequence_number: u32,
+    /// Response snapshot (usage is only present once the response finishes)
+    pub response: Value,
+}
+
+/// Incremental output text
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResponseTextDeltaEvent {
+    /// Position of the event in the stream
+    pub sequence_number: u32,
+    /// ID of the output item the text belongs to
+    pub item_id: String,
+    /// Index of the output item
+    pub output_index: u32,
+    /// Index of the content part within the item
+    pub content_index: u32,
+    /// Text fragment
+    pub delta: String,
+}
+
+/// Final output text of a content part
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResponseTextDoneEvent {
+    /// Position of the event in the stream
+    pub sequence_number: u32,
+    /// ID of the output item the text belongs to
+    pub item_id: String,
+    /// Index of the output item
+    pub output_index: u32,
+    /// Index of the content part within the item
+    pub content_index: u32,
+    /// Complete text of the content part
+    pub text: String,
+}
+
+/// Output item added to or finished in the response
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResponseOutputItemEvent {
+    /// Position of the event in the stream
+    pub sequence_number: u32,
+    /// Index of the output item
+    pub output_index: u32,
+    /// The item; complete in `response.output_item.done`
+    pub item: Value,
+}
+
+/// Incremental arguments of a function call
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResponseArgumentsDeltaEvent {
+    /// Position of the event in the stream
+    pub sequence_number: u32,
+    /// ID of the function call item
+    pub item_id: String,
+    /// Index of the output item
+    pub output_index: u32,
+    /// Fragment of the JSON arguments
+    pub delta: String,
+}
+
+/// Final arguments of a function call
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResponseArgumentsDoneEvent {
+    /// Position of the event in the stream
+    pub sequence_number: u32,
+    /// ID of the function call item
+    pub item_id: String,
+    /// Index of the output item
+    pub output_index: u32,
+    /// Complete JSON arguments
+    pub arguments: String,
+}
+
+/// Incremental text of a reasoning summary
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResponseSummaryDeltaEvent {
+    /// Position of the event in the stream
+    pub sequence_number: u32,
+    /// ID of the reasoning item
+    pub item_id: String,
+    /// Index of the output item
+    pub output_index: u32,
+    /// Index of the summary part within the item
+    pub summary_index: u32,
+    /// Text fragment
+    pub delta: String,
+}
+
+/// Final text of a reasoning summary part
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResponseSummaryDoneEvent {
+    /// Position of the event in the stream
+    pub sequence_number: u32,
+    /// ID of the reasoning item
+    pub item_id: String,
+    /// Index of the output item
+    pub output_index: u32,
+    /// Index of the summary part within the item
+    pub summary_index: u32,
+    /// Complete text of the summary part
+    pub text: String,
+}
+
+impl ResponseStreamEvent {
+    /// Position of the event in the stream, if the server sent one
+    pub fn sequence_number(&self) -> Option<u32> {
+        match self {
+            Self::Created(e)
+            | Self::InProgress(e)
+            | Self::Completed(e)
+            | Self::Failed(e)
+            | Self::Incomplete(e) => Some(e.sequence_number),
+            Self::OutputTextDelta(e) => Some(e.sequence_number),
+            Self::OutputTextDone(e) => Some(e.sequence_number),
+            Self::OutputItemAdded(e) | Self::OutputItemDone(e) => Some(e.sequence_number),
+            Self::FunctionCallArgumentsDelta(e) => Some(e.sequence_number),
+            Self::FunctionCallArgumentsDone(e) => Some(e.sequence_number),
+            Self::ReasoningSummaryTextDelta(e) => Some(e.sequence_number),
+            Self::ReasoningSummaryTextDone(e) => Some(e.sequence_number),
+            Self::Other { data, .. } => data
+                .get("sequence_number")
+                .and_then(Value::as_u64)
+                .and_then(|n| u32::try_from(n).ok()),
+        }
+    }
+
+    /// ID of the response, when the event carries a response snapshot
+    pub fn response_id(&self) -> Option<&str> {
+        let response = match self {
+            Self::Created(e)
+            | Self::InProgress(e)
+            | Self::Completed(e)
+            | Self::Failed(e)
+            | Self::Incomplete(e) => &e.response,
+            Self::Other { data, .. } => data.get("response")?,
+            _ => return None,
+        };
+        response.get("id").and_then(Value::as_str)
+    }
+}
+
+impl<'de> Deserialize<'de> for ResponseStreamEvent {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let data = Value::deserialize(deserializer)?;
+        let event = data
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| D::Error::missing_field("type"))?
+            .to_string();
+
+        let parsed = match event.as_str() {
+            "response.created" => serde_json::from_value(data).map(Self::Created),
+            "response.in_progress" => serde_json::from_value(data).map(Self::InProgress),
+            "response.completed" => serde_json::from_value(data).map(Self::Completed),
+            "response.failed" => serde_json::from_value(data).map(Self::Failed),
+            "response.incomplete" => serde_json::from_value(data).map(Self::Incomplete),
+            "response.output_text.delta" => serde_json::from_value(data).map(Self::OutputTextDelta),
+            "response.output_text.done" => serde_json::from_value(data).map(Self::OutputTextDone),
+            "response.output_item.added" => serde_json::from_value(data).map(Self::OutputItemAdded),
+            "response.output_item.done" => serde_json::from_value(data).map(Self::OutputItemDone),
+            "response.function_call_arguments.delta" => {
+                serde_json::from_value(data).map(Self::FunctionCallArgumentsDelta)
+            }
+            "response.function_call_arguments.done" => {
+                serde_json::from_value(data).map(Self::FunctionCallArgumentsDone)
+            }
+            "response.reasoning_summary_text.delta" => {
+                serde_json::from_value(data).map(Self::ReasoningSummaryTextDelta)
+            }
+            "response.reasoning_summary_text.done" => {
+                serde_json::from_value(data).map(Self::ReasoningSummaryTextDone)
+            }
+            _ => return Ok(Self::Other { event, data }),
+        };
+
+        parsed.map_err(D::Error::custom)
+    }
+}
+
+/// Response built from its stream of events
+///
+/// Lifecycle events provide the response snapshot (id, status, usage);
+/// output item events and deltas rebuild the `output` items, so a stream
+/// cut before `response.completed` still yields the text, function call
+/// arguments and reasoning summaries received so far.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ResponseStreamAccumulator {
+    snapshot: Option<Value>,
+    output: Vec<Value>,
+}
+
+impl ResponseStreamAccumulator {
+    /// Create an empty accumulator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fold one event into the response
+    pub fn push(&mut self, event: &ResponseStreamEvent) {
+        match event {
+            ResponseStreamEvent::Created(e)
+            | ResponseStreamEvent::InProgress(e)
+            | ResponseStreamEvent::Completed(e)
+            | ResponseStreamEvent::Failed(e)
+            | ResponseStreamEvent::Incomplete(e) => self.snapshot = Some(e.response.clone()),
+            ResponseStreamEvent::OutputItemAdded(e) | ResponseStreamEvent::OutputItemDone(e) => {
+                *self.item(e.output_index) = e.item.clone();
+            }
+            ResponseStreamEvent::OutputTextDelta(e) => {
+                let part = self.part(e.output_index, "content", e.content_index, "output_text");
+                append_text(part, "text", &e.delta);
+            }
+            ResponseStreamEvent::OutputTextDone(e) => {
+                let part = self.part(e.output_index, "content", e.content_index, "output_text");
+                part["text"] = Value::from(e.text.as_str());
+            }
+            ResponseStreamEvent::FunctionCallArgumentsDelta(e) => {
+                append_text(self.item(e.output_index), "arguments", &e.delta);
+            }
+            ResponseStreamEvent::FunctionCallArgumentsDone(e) => {
+                self.item(e.output_index)["arguments"] = Value::from(e.arguments.as_str());
+            }
+            ResponseStreamEvent::ReasoningSummaryTextDelta(e) => {
+                let part = self.part(e.output_index, "summary", e.summary_index, "summary_text");
+                append_text(part, "text", &e.delta);
+            }
+            ResponseStreamEvent::ReasoningSummaryTextDone(e) => {
+                let part = self.part(e.output_index, "summary", e.summary_index, "summary_text");
+                part["text"] = Value::from(e.text.as_str());
+            }
+            ResponseStreamEvent::Other { .. } => {}
+        }
+    }
+
+    /// The response with its output items
+    ///
+    /// Items rebuilt from the events replace the `output` of the last
+    /// snapshot. Fails with [`TwcError::Json`](crate::TwcError::Json) when no
+    /// lifecycle event was received.
+    pub fn into_response(self) -> crate::Result<Response> {
+        let mut response = self.snapshot.unwrap_or_default();
+        if !self.output.is_empty()
+            && let Value::Object(fields) = &mut response
+        {
+            fields.insert("output".to_string(), Value::Array(self.output));
+        }
+        Ok(serde_json::from_value(response)?)
+    }
+
+    /// Output item at `index`, created empty if missing
+    fn item(&mut self, index: u32) -> &mut Value {
+        let index = index as usize;
+        if self.output.len() <= index {
+            self.output.resize(index + 1, Value::Null);
+        }
+        let item = &mut self.output[index];
+        if !item.is_object() {
+            *item = Value::Object(Map::new());
+        }
+        item
+    }
+
+    /// Part `index` of the `parts` array of an output item, created with
+    /// `part_type` if missing
+    fn part(&mut self, item: u32, parts: &str, index: u32, part_type: &str) -> &mut Value {
+        let item = self.item(item);
+        if !item[parts].is_array() {
+            item[parts] = Value::Array(Vec::new());
+        }
+        let parts = item[parts]
+            .as_array_mut()
+            .expect("parts were just made an array");
+        let index = index as usize;
+        while parts.len() <= index {
+            parts.push(serde_json::json!({"type": part_type, "text": ""}));
+        }
+        &mut parts[index]
+    }
+}
+
+/// Append `delta` to the string field `field` of `value`
+fn append_text(value: &mut Value, field: &str, delta: &str) {
+    match value.get_mut(field) {
+        Some(Value::String(text)) => text.push_str(delta),
+        _ => value[field] = Value::from(delta),
+    }
+}
@@ -0,0 +1,149 @@
+//! Types for agent usage statistics
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::TwcError;
+
+/// Calendar date, written as `YYYY-MM-DD`
+///
+/// With the `chrono` feature it converts to and from `chrono::NaiveDate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UsageDate {
+    /// Year
+    pub year: i32,
+    /// Month (1-12)
+    pub month: u8,
+    /// Day of month (1-31)
+    pub day: u8,
+}
+
+impl UsageDate {
+    /// Create a date, checking that month and day are in range
+    pub fn new(year: i32, month: u8, day: u8) -> crate::Result<Self> {
+        let days_in_month = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+            2 => 28,
+            _ => {
+                return Err(TwcError::InvalidRequest(format!(
+                    "Invalid month: {}",
+                    month
+                )));
+            }
+        };
+        if day == 0 || day > days_in_month {
+            return Err(TwcError::InvalidRequest(format!(
+                "Invalid day {} for {:04}-{:02}",
+                day, year, month
+            )));
+        }
+        Ok(Self { year, month, day })
+    }
+}
+
+impl fmt::Display for UsageDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl FromStr for UsageDate {
+    type Err = TwcError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        let invalid =
+            || TwcError::InvalidRequest(format!("Invalid date {:?}, expected YYYY-MM-DD", s));
+        let mut parts = s.splitn(3, '-');
+        let mut next = || parts.next().ok_or_else(invalid);
+        let year = next()?.parse().map_err(|_| invalid())?;
+        let month = next()?.parse().map_err(|_| invalid())?;
+        let day = next()?.parse().map_err(|_| invalid())?;
+        Self::new(year, month, day)
+    }
+}
+
+impl Serialize for UsageDate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for UsageDate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        // Accept full timestamps by keeping only the date part
+        let date = s.split('T').next().unwrap_or_default();
+        date.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::NaiveDate> for UsageDate {
+    fn from(date: chrono::NaiveDate) -> Self {
+        use chrono::Datelike;
+
+        Self {
+            year: date.year(),
+            month: date.month() as u8,
+            day: date.day() as u8,
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<UsageDate> for chrono::NaiveDate {
+    fn from(date: UsageDate) -> Self {
+        chrono::NaiveDate::from_ymd_opt(date.year, date.month.into(), date.day.into())
+            .expect("UsageDate is always a valid calendar date")
+    }
+}
+
+/// Query parameters for agent usage statistics
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UsageQuery {
+    /// First day of the range (inclusive)
+    pub from: UsageDate,
+    /// Last day of the range (inclusive)
+    pub to: UsageDate,
+}
+
+/// Usage of an agent for one day
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DailyUsage {
+    /// Day the bucket covers
+    pub date: UsageDate,
+    /// Number of requests
+    pub requests: u64,
+    /// Prompt tokens consumed
+    pub prompt_tokens: u64,
+    /// Completion tokens generated
+    pub completion_tokens: u64,
+}
+
+/// Usage report of an agent over a date range
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentUsageReport {
+    /// First day of the range
+    pub from: UsageDate,
+    /// Last day of the range
+    pub to: UsageDate,
+    /// Daily buckets, oldest first
+    pub days: Vec<DailyUsage>,
+}
+
+impl AgentUsageReport {
+    /// Sum of all daily buckets as `(requests, prompt_tokens, completion_tokens)`
+    pub fn totals(&self) -> (u64, u64, u64) {
+        self.days.iter().fold((0, 0, 0), |(r, p, c), day| {
+            (
+                r + day.requests,
+                p + day.prompt_tokens,
+                c + day.completion_tokens,
+            )
+        })
+    }
+}
@@ -0,0 +1,434 @@
+//! Agent API tests for TWCai
+
+mod common;
+
+/// Agent call tests
+#[cfg(all(test, feature = "testing"))]
+mod agent_call {
+    use reqwest::Method;
+    use serde_json::json;
+    use twcai::TwcError;
+    use twcai::api::AgentClientExt;
+    use twcai::testing::{Fixture, FixtureTransport, assert_json_golden};
+    use twcai::types::*;
+
+    use crate::common::client_with;
+
+    const CALL_PATH: &str = "/api/v1/cloud-ai/agents/{agent_id}/call";
+
+    fn fixture(name: &str) -> std::path::PathBuf {
+        std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    #[tokio::test]
+    async fn test_call_agent_chain_sets_parent() {
+        let fixtures = FixtureTransport::new()
+            .route_once(
+                Method::POST,
+                CALL_PATH,
+                Fixture::from_file(fixture("agent_call/first.json")).unwrap(),
+            )
+            .route_once(
+                Method::POST,
+                CALL_PATH,
+                Fixture::json(
+                    200,
+                    &json!({"message": "Why did...", "id": "msg-2", "finish_reason": null}),
+                ),
+            );
+        let client = client_with(&fixtures);
+
+        let first = client
+            .call_agent("agent-1", AgentCallRequest::new("Hello"))
+            .await
+            .unwrap();
+        assert_eq!(first.message, "Hi! How can I help?");
+        assert_eq!(first.usage.as_ref().map(|u| u.total_tokens), Some(10));
+        assert_eq!(first.extra["created"], 1700000000);
+
+        let second = client
+            .call_agent_chain("agent-1", &first, "Tell me a joke")
+            .await
+            .unwrap();
+        assert_eq!(second.id, "msg-2");
+        assert!(second.usage.is_none());
+
+        let requests = fixtures.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].path, "/api/v1/cloud-ai/agents/agent-1/call");
+        assert_eq!(requests[0].json().unwrap(), json!({"message": "Hello"}));
+        assert_json_golden(
+            &requests[1].json().unwrap(),
+            fixture("golden/call_agent_chain.json"),
+        );
+        assert_eq!(requests[1].headers["authorization"], "Bearer test-token");
+    }
+
+    #[tokio::test]
+    async fn test_error_fixture_maps_status() {
+        let fixtures = FixtureTransport::new().route(
+            Method::POST,
+            CALL_PATH,
+            Fixture::json(402, &json!({"message": "Insufficient balance"})),
+        );
+
+        let err = client_with(&fixtures)
+            .call_agent("agent-1", AgentCallRequest::new("Hello"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TwcError::PaymentRequired(_)));
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_request_fails() {
+        let fixtures = FixtureTransport::new();
+
+        let err = client_with(&fixtures)
+            .call_agent("agent-1", AgentCallRequest::new("Hello"))
+            .await
+            .unwrap_err();
+        match err {
+            TwcError::Configuration(message) => {
+                assert!(message.contains("POST /api/v1/cloud-ai/agents/agent-1/call"))
+            }
+            other => panic!("expected Configuration, got {:?}", other),
+        }
+        assert_eq!(fixtures.requests().len(), 1);
+    }
+}
+
+/// Agents management tests
+#[cfg(all(test, feature = "testing"))]
+mod agents_admin {
+    use reqwest::Method;
+    use serde_json::json;
+    use twcai::api::AgentsAdminExt;
+    use twcai::testing::{Fixture, FixtureTransport};
+
+    use crate::common::{builder_with, client_with};
+
+    fn agent(id: u64) -> serde_json::Value {
+        json!({
+            "id": id,
+            "access_id": format!("access-{}", id),
+            "name": format!("Agent {}", id),
+            "model": "gpt-4.1",
+            "status": "active",
+            "created_at": "2025-01-01T10:00:00Z",
+            "description": "test agent"
+        })
+    }
+
+    #[tokio::test]
+    async fn test_list_agents_follows_offset_pagination() {
+        let first_page: Vec<_> = (1..=100).map(agent).collect();
+        let fixtures = FixtureTransport::new()
+            .route_once(
+                Method::GET,
+                "/custom/prefix/agents",
+                Fixture::json(200, &json!({"agents": first_page, "meta": {"total": 101}})),
+            )
+            .route_once(
+                Method::GET,
+                "/custom/prefix/agents",
+                Fixture::json(
+                    200,
+                    &json!({"agents": [agent(101)], "meta": {"total": 101}}),
+                ),
+            );
+
+        let client = builder_with(&fixtures)
+            .control_plane_prefix("/custom/prefix/")
+            .build()
+            .unwrap();
+
+        let agents = client.list_agents().await.unwrap();
+
+        let queries: Vec<_> = fixtures.requests().into_iter().map(|r| r.query).collect();
+        assert_eq!(
+            queries,
+            [
+                Some("limit=100&offset=0".to_string()),
+                Some("limit=100&offset=100".to_string())
+            ]
+        );
+        assert_eq!(agents.len(), 101);
+        assert_eq!(agents[100].access_id, "access-101");
+        assert_eq!(agents[0].extra["description"], "test agent");
+    }
+
+    #[tokio::test]
+    async fn test_get_agent() {
+        let fixtures = FixtureTransport::new().route(
+            Method::GET,
+            "/api/v1/cloud-ai/agents/42",
+            Fixture::json(200, &json!({"agent": agent(42)})),
+        );
+
+        let agent = client_with(&fixtures).get_agent(42).await.unwrap();
+        assert_eq!(agent.name, "Agent 42");
+        assert_eq!(agent.model.as_deref(), Some("gpt-4.1"));
+    }
+}
+
+/// Widget embed tests
+#[cfg(all(test, feature = "testing"))]
+mod embed {
+    use reqwest::Method;
+    use twcai::TwcError;
+    use twcai::api::AgentClientExt;
+    use twcai::testing::{Fixture, FixtureTransport};
+    use twcai::types::{EmbedCode, EmbedOptions};
+
+    use crate::common::client_with;
+
+    const EMBED_PATH: &str = "/api/v1/cloud-ai/agents/agent-1/embed.js";
+    const SCRIPT: &str = "window.twc = {};";
+
+    fn options() -> EmbedOptions {
+        EmbedOptions::new("https://site.example/page", "https://site.example")
+    }
+
+    fn script() -> Fixture {
+        Fixture::raw(200, "application/javascript", SCRIPT)
+    }
+
+    #[tokio::test]
+    async fn test_anonymous_by_default() {
+        let fixtures = FixtureTransport::new().route(
+            Method::GET,
+            EMBED_PATH,
+            script()
+                .header("etag", "\"v1\"")
+                .header("last-modified", "Wed, 01 Oct 2025 10:00:00 GMT"),
+        );
+
+        let code = client_with(&fixtures)
+            .get_embed_code_with_options("agent-1", options())
+            .await
+            .unwrap();
+
+        let headers = &fixtures.requests()[0].headers;
+        assert!(!headers.contains_key("authorization"));
+        assert_eq!(headers["referer"], "https://site.example/page");
+        assert_eq!(headers["origin"], "https://site.example");
+        assert_eq!(
+            code,
+            EmbedCode {
+                script: Some(SCRIPT.to_string()),
+                etag: Some("\"v1\"".to_string()),
+                last_modified: Some("Wed, 01 Oct 2025 10:00:00 GMT".to_string()),
+                not_modified: false,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_sends_token() {
+        let fixtures = FixtureTransport::new().route(Method::GET, EMBED_PATH, script());
+
+        let options = options().authenticated(true).collapsed(true);
+        let code = client_with(&fixtures)
+            .get_embed_code_with_options("agent-1", options)
+            .await
+            .unwrap();
+
+        let request = &fixtures.requests()[0];
+        assert!(
+            request
+                .query
+                .as_deref()
+                .is_some_and(|query| query.split('&').any(|pair| pair == "collapsed=true"))
+        );
+        assert_eq!(request.headers["authorization"], "Bearer test-token");
+        assert_eq!(code.script.as_deref(), Some(SCRIPT));
+    }
+
+    #[tokio::test]
+    async fn test_not_modified_keeps_cached_validators() {
+        let fixtures = FixtureTransport::new().route(
+            Method::GET,
+            EMBED_PATH,
+            Fixture::raw(304, "text/plain", ""),
+        );
+
+        let cached = EmbedCode {
+            script: Some(SCRIPT.to_string()),
+            etag: Some("\"v1\"".to_string()),
+            last_modified: Some("Wed, 01 Oct 2025 10:00:00 GMT".to_string()),
+            not_modified: false,
+        };
+        let code = client_with(&fixtures)
+            .get_embed_code_with_options("agent-1", options().cached(&cached))
+            .await
+            .unwrap();
+
+        let headers = &fixtures.requests()[0].headers;
+        assert_eq!(headers["if-none-match"], "\"v1\"");
+        assert_eq!(
+            headers["if-modified-since"],
+            "Wed, 01 Oct 2025 10:00:00 GMT"
+        );
+        assert!(code.not_modified);
+        assert_eq!(code.script, None);
+        assert_eq!(code.etag, cached.etag);
+        assert_eq!(code.last_modified, cached.last_modified);
+    }
+
+    #[tokio::test]
+    async fn test_forbidden_maps_to_error() {
+        let fixtures = FixtureTransport::new().route(
+            Method::GET,
+            EMBED_PATH,
+            Fixture::raw(
+                403,
+                "application/json",
+                r#"{"message": "Agent is not embeddable"}"#,
+            ),
+        );
+
+        let error = client_with(&fixtures)
+            .get_embed_code_with_options("agent-1", options())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error, TwcError::Forbidden));
+    }
+
+    #[tokio::test]
+    async fn test_plain_embed_code_returns_script() {
+        let fixtures = FixtureTransport::new().route(Method::GET, EMBED_PATH, script());
+
+        let script = client_with(&fixtures)
+            .get_embed_code(
+                "agent-1",
+                None,
+                "https://site.example/page",
+                "https://site.example",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(script, SCRIPT);
+    }
+}
+
+/// Health check tests
+#[cfg(test)]
+mod ping {
+    use mockito::Matcher;
+    use serde_json::json;
+    use twcai::api::{AgentClientExt, AgentsAdminExt};
+    use twcai::{CloudAIClient, TwcError};
+
+    use crate::common::builder_for;
+
+    const MODELS_PATH: &str = "/api/v1/cloud-ai/agents/agent-1/v1/models";
+
+    fn control_plane_client(server: &mockito::Server) -> CloudAIClient {
+        builder_for(server.url())
+            .control_plane_url(server.url())
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ping_reports_models() {
+        let mut server = mockito::Server::new_async().await;
+        let models = server
+            .mock("GET", MODELS_PATH)
+            .match_header("authorization", "Bearer test-token")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "object": "list",
+                    "data": [
+                        {"id": "gpt-4.1", "object": "model", "owned_by": "openai"},
+                        {"id": "gpt-4.1-mini", "object": "model", "owned_by": "openai"}
+                    ]
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let report = control_plane_client(&server).ping("agent-1").await.unwrap();
+
+        models.assert_async().await;
+        assert_eq!(report.models_available, 2);
+        assert!(report.latency > std::time::Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_ping_unauthorized() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", MODELS_PATH)
+            .with_status(401)
+            .with_body(r#"{"message":"invalid token"}"#)
+            .create_async()
+            .await;
+
+        let err = control_plane_client(&server)
+            .ping("agent-1")
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, TwcError::Unauthorized), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_ping_unknown_agent() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", MODELS_PATH)
+            .with_status(404)
+            .with_body(r#"{"message":"agent not found"}"#)
+            .create_async()
+            .await;
+
+        let err = control_plane_client(&server)
+            .ping("agent-1")
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(err, TwcError::NotFound(ref m) if m == "agent not found"),
+            "{:?}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_token() {
+        let mut server = mockito::Server::new_async().await;
+        let agents = server
+            .mock("GET", "/api/v1/cloud-ai/agents")
+            .match_query(Matcher::Exact("limit=1".to_string()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({"agents": [], "meta": {"total": 0}}).to_string())
+            .create_async()
+            .await;
+
+        control_plane_client(&server).verify_token().await.unwrap();
+        agents.assert_async().await;
+
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/cloud-ai/agents")
+            .match_query(Matcher::Any)
+            .with_status(401)
+            .create_async()
+            .await;
+
+        let err = control_plane_client(&server)
+            .verify_token()
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TwcError::Unauthorized), "{:?}", err);
+    }
+}
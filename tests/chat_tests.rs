@@ -0,0 +1,1666 @@
+//! Chat completion tests for TWCai
+
+mod common;
+
+/// Input audio tests
+#[cfg(test)]
+mod audio {
+    use std::path::PathBuf;
+
+    use serde_json::json;
+    use twcai::TwcError;
+    use twcai::types::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/audio")
+            .join(name)
+    }
+
+    #[tokio::test]
+    async fn test_from_path_sniffs_every_format() {
+        let cases = [
+            ("tone.wav", AudioFormat::Wav),
+            ("tone.mp3", AudioFormat::Mp3),
+            ("tone.m4a", AudioFormat::M4a),
+            ("tone.ogg", AudioFormat::Ogg),
+            ("tone.flac", AudioFormat::Flac),
+            ("tone.webm", AudioFormat::Webm),
+        ];
+
+        for (name, format) in cases {
+            let bytes = std::fs::read(fixture(name)).unwrap();
+            assert_eq!(AudioFormat::sniff(&bytes), Some(format), "{}", name);
+
+            let audio = InputAudio::from_path(fixture(name)).await.unwrap();
+            assert_eq!(audio.format, format.as_str(), "{}", name);
+            assert_eq!(audio, InputAudio::from_bytes(&bytes, format).unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_from_path_falls_back_to_extension() {
+        let audio = InputAudio::from_path(fixture("headerless.mp3"))
+            .await
+            .unwrap();
+        assert_eq!(audio.format, "mp3");
+    }
+
+    #[tokio::test]
+    async fn test_from_path_rejects_unknown_format() {
+        let err = InputAudio::from_path(fixture("noise.bin"))
+            .await
+            .unwrap_err();
+        match err {
+            TwcError::InvalidRequest(message) => assert!(message.contains("noise.bin")),
+            other => panic!("expected InvalidRequest, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_from_path_missing_file() {
+        let err = InputAudio::from_path(fixture("missing.wav"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TwcError::InvalidRequest(_)));
+    }
+
+    #[tokio::test]
+    async fn test_from_path_size_limit() {
+        let err = InputAudio::from_path_with_limit(fixture("tone.wav"), 16)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TwcError::PayloadTooLarge(_)));
+    }
+
+    #[test]
+    fn test_from_bytes_encodes_base64() {
+        let audio = InputAudio::from_bytes(b"RIFF", AudioFormat::Wav).unwrap();
+        assert_eq!(
+            serde_json::to_value(&audio).unwrap(),
+            json!({"data": "UklGRg==", "format": "wav"})
+        );
+    }
+
+    #[test]
+    fn test_from_bytes_size_limit() {
+        let bytes = [0u8; 8];
+        assert!(InputAudio::from_bytes_with_limit(&bytes, AudioFormat::Ogg, 8).is_ok());
+        let err = InputAudio::from_bytes_with_limit(&bytes, AudioFormat::Ogg, 7).unwrap_err();
+        assert!(matches!(err, TwcError::PayloadTooLarge(_)));
+    }
+
+    #[test]
+    fn test_from_extension() {
+        assert_eq!(AudioFormat::from_extension("WAV"), Some(AudioFormat::Wav));
+        assert_eq!(AudioFormat::from_extension("flac"), Some(AudioFormat::Flac));
+        assert_eq!(AudioFormat::from_extension("aiff"), None);
+    }
+
+    #[test]
+    fn test_format_serialization() {
+        assert_eq!(
+            serde_json::to_value(AudioFormat::M4a).unwrap(),
+            json!("m4a")
+        );
+        assert_eq!(AudioFormat::Webm.to_string(), "webm");
+    }
+}
+
+/// Chat content display and accessor tests
+#[cfg(test)]
+mod chat_content {
+    use twcai::types::*;
+
+    fn mixed() -> ChatContent {
+        ChatContent::Array(vec![
+            ContentItem::Text(TextContent::new("Look at this:")),
+            ContentItem::ImageUrl(ImageUrlContent::new("https://example.com/cat.png")),
+            ContentItem::ImageUrl(ImageUrlContent::new("data:image/png;base64,iVBORw0KGgo=")),
+            ContentItem::InputAudio(InputAudioContent::new(InputAudio {
+                data: String::new(),
+                format: "wav".to_string(),
+            })),
+            ContentItem::File(FileContent::new(FilePart::by_id("file-1"))),
+            ContentItem::Refusal(RefusalContent::new("I can't help with that.")),
+            ContentItem::Text(TextContent::new("Привет")),
+        ])
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(ChatContent::from("Hello").to_string(), "Hello");
+        assert_eq!(
+            mixed().to_string(),
+            "Look at this:\n\
+             [image: https://example.com/cat.png]\n\
+             [image: data:image/png;base64,...]\n\
+             [audio]\n\
+             [file: file-1]\n\
+             [refused: I can't help with that.]\n\
+             Привет"
+        );
+    }
+
+    #[test]
+    fn test_text_accessors() {
+        let content = mixed();
+        assert_eq!(
+            content.iter_text_parts().collect::<Vec<_>>(),
+            ["Look at this:", "Привет"]
+        );
+        assert_eq!(content.len_chars(), 19);
+        assert!(!content.is_empty());
+
+        let text = ChatContent::from(String::from("héllo"));
+        assert_eq!(text.iter_text_parts().collect::<Vec<_>>(), ["héllo"]);
+        assert_eq!(text.len_chars(), 5);
+
+        assert!(ChatContent::default().is_empty());
+        assert!(ChatContent::Array(vec![]).is_empty());
+        assert_eq!(ChatContent::Array(vec![]).to_string(), "");
+    }
+}
+
+/// Multiple choice (`n` > 1) tests
+#[cfg(test)]
+mod choices {
+    use twcai::types::*;
+
+    const TWO_CHOICES: &str = include_str!("fixtures/chat/two_choices.json");
+    #[cfg(feature = "testing")]
+    const TWO_CHOICE_STREAM: &str = include_str!("fixtures/sse/chat_two_choices.txt");
+
+    fn response() -> ChatCompletionResponse {
+        serde_json::from_str(TWO_CHOICES).unwrap()
+    }
+
+    #[test]
+    fn test_choice_by_index() {
+        let response = response();
+        let second = response.choice(1).unwrap();
+        assert_eq!(
+            second.message.content.first_text(),
+            Some("The capital of France is Paris.")
+        );
+        assert!(response.choice(2).is_none());
+    }
+
+    #[test]
+    fn test_choice_texts() {
+        let texts: Vec<_> = response().choice_texts().collect();
+        assert_eq!(
+            texts,
+            [(0, "Paris."), (1, "The capital of France is Paris.")]
+        );
+    }
+
+    #[test]
+    fn test_best_by() {
+        let response = response();
+        let longest = response
+            .best_by(|choice| choice.message.content.len_chars())
+            .unwrap();
+        assert_eq!(longest.index, 1);
+
+        // Ties keep the earliest choice
+        assert_eq!(response.best_by(|_| 1).unwrap().index, 0);
+        assert!(response.best_by(|_| f64::NAN).is_none());
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_stream_groups_deltas_by_choice_index() {
+        use futures_util::StreamExt;
+        use reqwest::Method;
+        use twcai::api::AgentClientExt;
+        use twcai::testing::{Fixture, FixtureTransport};
+
+        use crate::common::client_with;
+
+        let fixtures = FixtureTransport::new().route(
+            Method::POST,
+            "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            Fixture::sse(TWO_CHOICE_STREAM),
+        );
+        let client = client_with(&fixtures);
+
+        let request = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Capital of France?")],
+            n: Some(2),
+            ..Default::default()
+        };
+        let mut stream = client
+            .chat_completions_stream("agent-1", request)
+            .await
+            .unwrap();
+        let mut summary = ChatStreamSummary::default();
+        while let Some(chunk) = stream.next().await {
+            summary.push(&chunk.unwrap());
+        }
+
+        assert_eq!(fixtures.requests().len(), 1);
+        assert_eq!(summary.choices.len(), 2);
+        assert_eq!(summary.content, "Paris.");
+        assert_eq!(summary.finish_reason, Some(FinishReason::Stop));
+
+        let second = summary.choice(1).unwrap();
+        assert_eq!(second.content, "The capital of France is Paris.");
+        assert_eq!(second.finish_reason, Some(FinishReason::Length));
+    }
+}
+
+/// Client default parameter tests
+#[cfg(all(test, feature = "testing"))]
+mod defaults {
+    use reqwest::Method;
+    use serde_json::json;
+    use twcai::api::{AgentClientExt, ResponsesExt};
+    use twcai::testing::{Fixture, FixtureTransport};
+    use twcai::types::*;
+    use twcai::{ChatDefaults, CloudAIClient};
+
+    use crate::common::builder_with;
+
+    const CHAT_BODY: &str = r#"{
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "deepseek-reason",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "Hi"},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 3, "completion_tokens": 1, "total_tokens": 4}
+    }"#;
+
+    const CHAT_PATH: &str = "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions";
+
+    fn client_with_defaults(fixtures: &FixtureTransport) -> CloudAIClient {
+        builder_with(fixtures)
+            .default_model("deepseek-reason")
+            .default_chat_params(ChatDefaults {
+                temperature: Some(0.5),
+                max_completion_tokens: Some(256),
+                top_p: None,
+            })
+            .build()
+            .unwrap()
+    }
+
+    fn chat_fixtures() -> FixtureTransport {
+        FixtureTransport::new().route(
+            Method::POST,
+            CHAT_PATH,
+            Fixture::raw(200, "application/json", CHAT_BODY),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_defaults_fill_unset_fields() {
+        let fixtures = chat_fixtures();
+
+        let request = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hello")],
+            ..Default::default()
+        };
+        client_with_defaults(&fixtures)
+            .chat_completions("agent-1", request)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fixtures.requests()[0].json().unwrap(),
+            json!({
+                "model": "deepseek-reason",
+                "messages": [{"role": "user", "content": "Hello"}],
+                "temperature": 0.5,
+                "max_completion_tokens": 256
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_values_win_over_defaults() {
+        let fixtures = chat_fixtures();
+
+        // The legacy max_tokens also counts as an explicit limit
+        let request = ChatCompletionRequest {
+            model: Some("gpt-4.1".to_string()),
+            messages: vec![ChatMessage::user("Hello")],
+            temperature: Some(1.5),
+            max_tokens: Some(32),
+            ..Default::default()
+        };
+        client_with_defaults(&fixtures)
+            .chat_completions("agent-1", request)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fixtures.requests()[0].json().unwrap(),
+            json!({
+                "model": "gpt-4.1",
+                "messages": [{"role": "user", "content": "Hello"}],
+                "temperature": 1.5,
+                "max_tokens": 32
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_defaults_apply_to_responses() {
+        let fixtures = FixtureTransport::new().route(
+            Method::POST,
+            "/api/v1/cloud-ai/agents/agent-1/v1/responses",
+            Fixture::json(
+                200,
+                &json!({
+                    "id": "resp_1",
+                    "object": "response",
+                    "created_at": 1700000000,
+                    "model": "deepseek-reason",
+                    "status": "completed",
+                    "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+                }),
+            ),
+        );
+
+        let request = CreateResponseRequest {
+            input: Some(ResponseInput::Text("Hello".to_string())),
+            temperature: Some(0.0),
+            ..Default::default()
+        };
+        client_with_defaults(&fixtures)
+            .create_response("agent-1", request)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fixtures.requests()[0].json().unwrap(),
+            json!({
+                "model": "deepseek-reason",
+                "input": "Hello",
+                "temperature": 0.0,
+                "max_output_tokens": 256
+            })
+        );
+    }
+}
+
+/// Extra request parameter tests
+#[cfg(test)]
+mod extra_params {
+    use serde_json::json;
+    use twcai::types::*;
+
+    #[test]
+    fn test_chat_extra_serializes_at_top_level() {
+        let request = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hi")],
+            temperature: Some(0.5),
+            ..Default::default()
+        }
+        .extra_param("provider", json!({"order": ["azure", "openai"]}))
+        .extra_param("top_k", 40);
+
+        let value = serde_json::to_value(&request).unwrap();
+        assert_eq!(value["provider"], json!({"order": ["azure", "openai"]}));
+        assert_eq!(value["top_k"], 40);
+        assert_eq!(value["temperature"], 0.5);
+        assert!(value.get("extra").is_none());
+    }
+
+    #[test]
+    fn test_response_and_call_extra_serialize_at_top_level() {
+        let response = CreateResponseRequest {
+            input: Some(ResponseInput::Text("Hi".to_string())),
+            ..Default::default()
+        }
+        .extra_param("route", "fallback");
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            json!({"input": "Hi", "route": "fallback"})
+        );
+
+        let call = AgentCallRequest::new("Hi").extra_param("locale", "ru");
+        assert_eq!(
+            serde_json::to_value(&call).unwrap(),
+            json!({"message": "Hi", "locale": "ru"})
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys_round_trip_into_extra() {
+        let request: ChatCompletionRequest = serde_json::from_value(json!({
+            "messages": [],
+            "model": "gpt-4.1",
+            "top_k": 40
+        }))
+        .unwrap();
+
+        assert_eq!(request.model.as_deref(), Some("gpt-4.1"));
+        assert_eq!(request.extra.len(), 1);
+        assert_eq!(request.extra["top_k"], 40);
+        assert_eq!(
+            serde_json::to_string(&ChatCompletionRequest::default()).unwrap(),
+            r#"{"messages":[]}"#
+        );
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_extra_params_sent_on_the_wire() {
+        use reqwest::Method;
+        use twcai::api::AgentClientExt;
+        use twcai::testing::{Fixture, FixtureTransport};
+
+        use crate::common::client_with;
+
+        let fixtures = FixtureTransport::new().route(
+            Method::POST,
+            "/api/v1/cloud-ai/agents/agent-1/call",
+            Fixture::json(
+                200,
+                &json!({"message": "Привет", "id": "msg-1", "finish_reason": "stop"}),
+            ),
+        );
+        let client = client_with(&fixtures);
+
+        let reply = client
+            .call_agent(
+                "agent-1",
+                AgentCallRequest::new("Hello").extra_param("locale", "ru"),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fixtures.requests()[0].json().unwrap(),
+            json!({"message": "Hello", "locale": "ru"})
+        );
+        assert_eq!(reply.message, "Привет");
+    }
+}
+
+/// File content part tests
+#[cfg(test)]
+mod file_part {
+    use std::path::PathBuf;
+
+    use serde_json::json;
+    use twcai::TwcError;
+    use twcai::types::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/files")
+            .join(name)
+    }
+
+    #[test]
+    fn test_by_id_serialization() {
+        let part = FilePart::by_id("file-abc123");
+        let json = serde_json::to_value(&part).unwrap();
+        assert_eq!(json, json!({"file_id": "file-abc123"}));
+        assert_eq!(serde_json::from_value::<FilePart>(json).unwrap(), part);
+    }
+
+    #[test]
+    fn test_inline_serialization() {
+        let part = FilePart::inline("draconomicon.pdf", "application/pdf", b"%PDF");
+        let json = serde_json::to_value(&part).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "filename": "draconomicon.pdf",
+                "file_data": "data:application/pdf;base64,JVBERg=="
+            })
+        );
+        assert_eq!(serde_json::from_value::<FilePart>(json).unwrap(), part);
+    }
+
+    #[test]
+    fn test_user_with_file_message() {
+        let message = ChatMessage::user_with_file(
+            "What is the first dragon in the book?",
+            FilePart::by_id("file-abc123"),
+        );
+
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            json!({
+                "role": "user",
+                "content": [
+                    {"type": "text", "text": "What is the first dragon in the book?"},
+                    {"type": "file", "file": {"file_id": "file-abc123"}}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_file_content_item_deserializes() {
+        let content: ChatContent = serde_json::from_value(json!([
+            {"type": "file", "file": {"filename": "a.txt", "file_data": "data:text/plain;base64,aGk="}}
+        ]))
+        .unwrap();
+
+        match content {
+            ChatContent::Array(items) => match &items[0] {
+                ContentItem::File(file) => assert!(matches!(file.file, FilePart::Inline { .. })),
+                other => panic!("expected file part, got {:?}", other),
+            },
+            other => panic!("expected array content, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inline_from_path() {
+        let part = FilePart::inline_from_path(fixture("note.txt"))
+            .await
+            .unwrap();
+        assert_eq!(
+            part,
+            FilePart::Inline {
+                filename: "note.txt".to_string(),
+                file_data: "data:text/plain;base64,aGVsbG8=".to_string(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_inline_from_missing_path() {
+        let err = FilePart::inline_from_path(fixture("missing.pdf"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TwcError::InvalidRequest(_)));
+    }
+}
+
+/// Chat logprobs tests
+#[cfg(test)]
+mod logprobs {
+    use twcai::types::*;
+
+    const FIXTURE: &str = include_str!("fixtures/chat/logprobs.json");
+
+    #[test]
+    fn test_nested_logprobs_deserialize() {
+        let response: ChatCompletionResponse = serde_json::from_str(FIXTURE).unwrap();
+        let logprobs = response.choices[0].logprobs.as_ref().unwrap();
+
+        assert_eq!(logprobs.content.len(), 2);
+        assert_eq!(logprobs.refusal, None);
+
+        let first = &logprobs.content[0];
+        assert_eq!(first.token, "Yes");
+        assert_eq!(first.logprob, -0.0012);
+        assert_eq!(first.bytes.as_deref(), Some(&b"Yes"[..]));
+        assert_eq!(first.top_logprobs.len(), 2);
+        assert_eq!(first.top_logprobs[1].token, "No");
+        assert_eq!(first.top_logprobs[1].logprob, -6.73);
+
+        assert_eq!(logprobs.content[1].top_logprobs[1].bytes, None);
+    }
+
+    #[test]
+    fn test_null_logprobs_tolerated() {
+        let response: ChatCompletionResponse = serde_json::from_str(FIXTURE).unwrap();
+
+        assert_eq!(response.choices[1].logprobs, None);
+        assert_eq!(response.choices[2].logprobs, Some(ChatLogprobs::default()));
+    }
+
+    #[test]
+    fn test_logprobs_absent() {
+        let response: ChatCompletionResponse = serde_json::from_str(
+            r#"{
+                "id": "chatcmpl-1",
+                "object": "chat.completion",
+                "created": 1700000000,
+                "model": "deepseek-reason",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "Hi"},
+                    "finish_reason": "stop"
+                }],
+                "usage": {"prompt_tokens": 3, "completion_tokens": 1, "total_tokens": 4}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(response.choices[0].logprobs, None);
+        assert!(
+            !serde_json::to_string(&response)
+                .unwrap()
+                .contains("logprobs")
+        );
+    }
+
+    #[test]
+    fn test_logprobs_request_fields() {
+        let request = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Is it?")],
+            logprobs: Some(true),
+            top_logprobs: Some(2),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["logprobs"], true);
+        assert_eq!(json["top_logprobs"], 2);
+    }
+}
+
+/// Chat message serde tests
+#[cfg(test)]
+mod message {
+    use serde_json::json;
+    use twcai::types::*;
+
+    #[test]
+    fn test_developer_message() {
+        let message = ChatMessage::developer("Answer briefly");
+        let json = serde_json::to_value(&message).unwrap();
+
+        assert_eq!(
+            json,
+            json!({"role": "developer", "content": "Answer briefly"})
+        );
+        assert_eq!(
+            serde_json::from_value::<ChatMessage>(json).unwrap(),
+            message
+        );
+    }
+
+    #[test]
+    fn test_named_user_message() {
+        let message = ChatMessage::user_named("alice", "Hi all");
+        let json = serde_json::to_value(&message).unwrap();
+
+        assert_eq!(
+            json,
+            json!({"role": "user", "content": "Hi all", "name": "alice"})
+        );
+        assert_eq!(
+            serde_json::from_value::<ChatMessage>(json).unwrap(),
+            message
+        );
+    }
+
+    #[test]
+    fn test_message_without_name() {
+        let message: ChatMessage =
+            serde_json::from_value(json!({"role": "user", "content": "Hello"})).unwrap();
+
+        assert_eq!(message, ChatMessage::user("Hello"));
+        assert!(
+            serde_json::to_value(&message)
+                .unwrap()
+                .get("name")
+                .is_none()
+        );
+    }
+}
+
+/// Message normalization tests
+#[cfg(test)]
+mod normalize {
+    use serde_json::json;
+    use twcai::types::*;
+
+    fn request(messages: Vec<ChatMessage>) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            messages,
+            ..Default::default()
+        }
+    }
+
+    fn texts(request: &ChatCompletionRequest) -> Vec<String> {
+        request
+            .messages
+            .iter()
+            .map(|message| message.content.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_default_policy_changes_nothing() {
+        let messages = vec![
+            ChatMessage::user("Hi"),
+            ChatMessage::system("Be brief"),
+            ChatMessage::assistant(""),
+        ];
+        let mut normalized = request(messages.clone());
+        normalized.normalize_messages(NormalizePolicy::default());
+        assert_eq!(normalized.messages, messages);
+    }
+
+    #[test]
+    fn test_hoist_system() {
+        let mut request = request(vec![
+            ChatMessage::user("Hi"),
+            ChatMessage::assistant("Hello"),
+            ChatMessage::system("Be brief"),
+            ChatMessage::user("Bye"),
+        ]);
+        request.normalize_messages(NormalizePolicy {
+            hoist_system: true,
+            ..Default::default()
+        });
+        assert_eq!(texts(&request), ["Be brief", "Hi", "Hello", "Bye"]);
+        assert_eq!(request.messages[0].role, Role::System);
+    }
+
+    #[test]
+    fn test_hoist_moves_only_first_system_message() {
+        let mut request = request(vec![
+            ChatMessage::user("Hi"),
+            ChatMessage::system("One"),
+            ChatMessage::system("Two"),
+        ]);
+        request.normalize_messages(NormalizePolicy {
+            hoist_system: true,
+            ..Default::default()
+        });
+        assert_eq!(texts(&request), ["One", "Hi", "Two"]);
+    }
+
+    #[test]
+    fn test_merge_system() {
+        let mut request = request(vec![
+            ChatMessage::system("Be brief"),
+            ChatMessage::user("Hi"),
+            ChatMessage::system("Answer in French"),
+        ]);
+        request.normalize_messages(NormalizePolicy {
+            merge_system: true,
+            ..Default::default()
+        });
+        assert_eq!(texts(&request), ["Be brief\n\nAnswer in French", "Hi"]);
+    }
+
+    #[test]
+    fn test_merge_multimodal_system_messages() {
+        let mut request = request(vec![
+            ChatMessage::system("Be brief"),
+            ChatMessage {
+                role: Role::System,
+                ..ChatMessage::user_multimodal(vec![ContentItem::Text(TextContent::new("Cite"))])
+            },
+        ]);
+        request.normalize_messages(NormalizePolicy {
+            merge_system: true,
+            ..Default::default()
+        });
+        assert_eq!(request.messages.len(), 1);
+        let parts: Vec<_> = request.messages[0].content.iter_text_parts().collect();
+        assert_eq!(parts, ["Be brief", "Cite"]);
+    }
+
+    #[test]
+    fn test_drop_empty() {
+        let tool_call = ChatMessage {
+            tool_calls: Some(json!([{"id": "call_1", "type": "function"}])),
+            ..ChatMessage::assistant("")
+        };
+        let tool_result = ChatMessage {
+            role: Role::Tool,
+            tool_call_id: Some("call_1".to_string()),
+            ..ChatMessage::user("")
+        };
+        let mut request = request(vec![
+            ChatMessage::system(""),
+            ChatMessage::user("Hi"),
+            tool_call.clone(),
+            tool_result.clone(),
+            ChatMessage::assistant(""),
+        ]);
+        request.normalize_messages(NormalizePolicy {
+            drop_empty: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            request.messages,
+            [ChatMessage::user("Hi"), tool_call, tool_result]
+        );
+    }
+
+    #[test]
+    fn test_all_policies() {
+        let mut request = request(vec![
+            ChatMessage::user("Hi"),
+            ChatMessage::system(""),
+            ChatMessage::system("Be brief"),
+            ChatMessage::assistant("Hello"),
+            ChatMessage::system("Answer in French"),
+        ]);
+        request.normalize_messages(NormalizePolicy::all());
+        assert_eq!(
+            texts(&request),
+            ["Be brief\n\nAnswer in French", "Hi", "Hello"]
+        );
+    }
+
+    /// Every conversation of up to five messages drawn from system, user and
+    /// assistant turns, empty or not
+    fn conversations() -> impl Iterator<Item = Vec<ChatMessage>> {
+        const KINDS: usize = 6;
+        (0..=5u32).flat_map(|len| {
+            (0..KINDS.pow(len)).map(move |mut code| {
+                (0..len)
+                    .map(|position| {
+                        let kind = code % KINDS;
+                        code /= KINDS;
+                        let text = if kind % 2 == 0 {
+                            format!("turn {}", position)
+                        } else {
+                            String::new()
+                        };
+                        match kind / 2 {
+                            0 => ChatMessage::system(text),
+                            1 => ChatMessage::user(text),
+                            _ => ChatMessage::assistant(text),
+                        }
+                    })
+                    .collect()
+            })
+        })
+    }
+
+    fn dialog_turns(messages: &[ChatMessage]) -> Vec<&ChatMessage> {
+        messages
+            .iter()
+            .filter(|message| message.role != Role::System)
+            .collect()
+    }
+
+    #[test]
+    fn test_dialog_order_is_preserved() {
+        let policies = (0..8).map(|bits| NormalizePolicy {
+            hoist_system: bits & 1 != 0,
+            merge_system: bits & 2 != 0,
+            drop_empty: bits & 4 != 0,
+        });
+        for policy in policies {
+            for messages in conversations() {
+                let mut normalized = request(messages.clone());
+                normalized.normalize_messages(policy);
+
+                let expected: Vec<_> = dialog_turns(&messages)
+                    .into_iter()
+                    .filter(|message| !(policy.drop_empty && message.content.is_empty()))
+                    .collect();
+                assert_eq!(
+                    dialog_turns(&normalized.messages),
+                    expected,
+                    "{:?} on {:?}",
+                    policy,
+                    messages
+                );
+
+                let systems = normalized
+                    .messages
+                    .iter()
+                    .filter(|message| message.role == Role::System)
+                    .count();
+                if policy.merge_system {
+                    assert!(systems <= 1);
+                }
+                if policy.hoist_system && systems > 0 {
+                    assert_eq!(normalized.messages[0].role, Role::System);
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_client_normalizes_before_sending() {
+        use reqwest::Method;
+        use twcai::api::AgentClientExt;
+        use twcai::testing::{Fixture, FixtureTransport};
+
+        use crate::common::builder_with;
+
+        let fixtures = FixtureTransport::new().route(
+            Method::POST,
+            "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            Fixture::raw(
+                200,
+                "application/json",
+                include_str!("fixtures/conformance/chat_text.json"),
+            ),
+        );
+        let client = builder_with(&fixtures)
+            .normalize_messages(NormalizePolicy::all())
+            .build()
+            .unwrap();
+
+        client
+            .chat_completions(
+                "agent-1",
+                request(vec![
+                    ChatMessage::user("Hi"),
+                    ChatMessage::system("Be brief"),
+                ]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            fixtures.requests()[0].json().unwrap()["messages"],
+            json!([
+                {"role": "system", "content": "Be brief"},
+                {"role": "user", "content": "Hi"}
+            ])
+        );
+    }
+}
+
+/// Reasoning configuration tests
+#[cfg(test)]
+mod reasoning {
+    use serde_json::json;
+    use twcai::types::*;
+
+    #[test]
+    fn test_response_request_reasoning_golden() {
+        let request = CreateResponseRequest {
+            input: Some(ResponseInput::Text("Prove it".to_string())),
+            reasoning: Some(ReasoningConfig {
+                effort: Some(ReasoningEffort::High),
+                summary: Some(ReasoningSummary::Concise),
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({"input": "Prove it", "reasoning": {"effort": "high", "summary": "concise"}})
+        );
+    }
+
+    #[test]
+    fn test_reasoning_config_round_trip() {
+        for (effort, name) in [
+            (ReasoningEffort::Minimal, "minimal"),
+            (ReasoningEffort::Low, "low"),
+            (ReasoningEffort::Medium, "medium"),
+            (ReasoningEffort::High, "high"),
+        ] {
+            let config = ReasoningConfig {
+                effort: Some(effort),
+                summary: None,
+            };
+            let json = serde_json::to_value(config).unwrap();
+            assert_eq!(json, json!({"effort": name}));
+            assert_eq!(
+                serde_json::from_value::<ReasoningConfig>(json).unwrap(),
+                config
+            );
+        }
+
+        for (summary, name) in [
+            (ReasoningSummary::Auto, "auto"),
+            (ReasoningSummary::Concise, "concise"),
+            (ReasoningSummary::Detailed, "detailed"),
+        ] {
+            assert_eq!(serde_json::to_value(summary).unwrap(), json!(name));
+        }
+    }
+
+    #[test]
+    fn test_chat_reasoning_effort() {
+        let request = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Think")],
+            reasoning_effort: Some(ReasoningEffort::Low),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap()["reasoning_effort"],
+            "low"
+        );
+    }
+
+    #[test]
+    fn test_reasoning_summary_output() {
+        let response: Response = serde_json::from_value(json!({
+            "id": "resp_1",
+            "object": "response",
+            "created_at": 1700000000,
+            "model": "o4-mini",
+            "status": "completed",
+            "output": [
+                {
+                    "type": "reasoning",
+                    "id": "rs_1",
+                    "summary": [
+                        {"type": "summary_text", "text": "Checked small cases."},
+                        {"type": "summary_text", "text": "Generalized by induction."}
+                    ]
+                },
+                {
+                    "type": "message",
+                    "role": "assistant",
+                    "content": [{"type": "output_text", "text": "It holds."}]
+                }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            response.reasoning_summary(),
+            "Checked small cases.\n\nGeneralized by induction."
+        );
+        assert_eq!(response.output_text(), "It holds.");
+    }
+}
+
+/// Refusal tests
+#[cfg(test)]
+mod refusal {
+    use serde_json::json;
+    use twcai::types::*;
+
+    const FIXTURE: &str = include_str!("fixtures/chat/refusal.json");
+
+    fn response_with_message(message: serde_json::Value) -> ChatCompletionResponse {
+        serde_json::from_value(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1727000000,
+            "model": "test-model",
+            "choices": [{"index": 0, "message": message, "finish_reason": "stop"}],
+            "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_refusal_field_surfaced() {
+        let response: ChatCompletionResponse = serde_json::from_str(FIXTURE).unwrap();
+
+        assert!(response.is_refusal());
+        assert_eq!(
+            response.choices[0].refusal(),
+            Some("I'm sorry, I can't assist with that request.")
+        );
+        assert_eq!(response.first_text(), None);
+    }
+
+    #[test]
+    fn test_refusal_content_part_surfaced() {
+        let response = response_with_message(json!({
+            "role": "assistant",
+            "content": [{"type": "refusal", "refusal": "No."}]
+        }));
+
+        assert!(matches!(
+            &response.choices[0].message.content,
+            ChatContent::Array(items) if matches!(items[0], ContentItem::Refusal(_))
+        ));
+        assert!(response.is_refusal());
+        assert_eq!(response.choices[0].refusal(), Some("No."));
+        assert_eq!(response.first_text(), None);
+    }
+
+    #[test]
+    fn test_first_text_skips_refusal_parts() {
+        let content: ChatContent = serde_json::from_value(json!([
+            {"type": "refusal", "refusal": "Not that part."},
+            {"type": "text", "text": "But this one."}
+        ]))
+        .unwrap();
+
+        assert_eq!(content.first_text(), Some("But this one."));
+    }
+
+    #[test]
+    fn test_plain_answer_is_not_refusal() {
+        let response = response_with_message(json!({
+            "role": "assistant",
+            "content": "Paris."
+        }));
+
+        assert!(!response.is_refusal());
+        assert_eq!(response.choices[0].refusal(), None);
+        assert_eq!(response.first_text(), Some("Paris."));
+    }
+
+    #[test]
+    fn test_refusal_omitted_when_serializing_requests() {
+        let json = serde_json::to_value(ChatMessage::user("Hi")).unwrap();
+        assert_eq!(json, json!({"role": "user", "content": "Hi"}));
+    }
+}
+
+/// Service tier and truncation tests
+#[cfg(test)]
+mod service_tier {
+    use serde_json::json;
+    use twcai::types::*;
+
+    #[test]
+    fn test_serialization_casing() {
+        let request = CreateResponseRequest {
+            service_tier: Some(ServiceTier::Priority),
+            truncation: Some(Truncation::Auto),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({"service_tier": "priority", "truncation": "auto"})
+        );
+        assert_eq!(
+            serde_json::to_value(ServiceTier::Default).unwrap(),
+            json!("default")
+        );
+        assert_eq!(
+            serde_json::to_value(Truncation::Disabled).unwrap(),
+            json!("disabled")
+        );
+    }
+
+    #[test]
+    fn test_unknown_value_fallback() {
+        let tier: ServiceTier = serde_json::from_value(json!("scale")).unwrap();
+        assert_eq!(tier, ServiceTier::Other("scale".to_string()));
+        assert_eq!(serde_json::to_value(&tier).unwrap(), json!("scale"));
+
+        let truncation: Truncation = serde_json::from_value(json!("middle")).unwrap();
+        assert_eq!(truncation, Truncation::Other("middle".to_string()));
+    }
+
+    #[test]
+    fn test_response_echoes_service_tier() {
+        let response: Response = serde_json::from_value(json!({
+            "id": "resp_1",
+            "object": "response",
+            "created_at": 1700000000,
+            "model": "m",
+            "status": "completed",
+            "service_tier": "flex"
+        }))
+        .unwrap();
+        assert_eq!(response.service_tier(), Some(ServiceTier::Flex));
+
+        let response: Response = serde_json::from_value(json!({
+            "id": "resp_2",
+            "object": "response",
+            "created_at": 1700000000,
+            "model": "m",
+            "status": "completed"
+        }))
+        .unwrap();
+        assert_eq!(response.service_tier(), None);
+    }
+}
+
+/// Stop sequence tests
+#[cfg(test)]
+mod stop {
+    use serde_json::json;
+    use twcai::TwcError;
+    use twcai::types::*;
+
+    #[test]
+    fn test_single_round_trip() {
+        let stop = StopSequence::from("\n");
+        let json = serde_json::to_value(&stop).unwrap();
+        assert_eq!(json, json!("\n"));
+        assert_eq!(serde_json::from_value::<StopSequence>(json).unwrap(), stop);
+    }
+
+    #[test]
+    fn test_many_round_trip() {
+        let stop = StopSequence::from(vec!["\n", "END"]);
+        let json = serde_json::to_value(&stop).unwrap();
+        assert_eq!(json, json!(["\n", "END"]));
+        assert_eq!(serde_json::from_value::<StopSequence>(json).unwrap(), stop);
+    }
+
+    #[test]
+    fn test_request_stop_shapes() {
+        let request = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hi")],
+            stop: Some("END".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&request).unwrap()["stop"],
+            json!("END")
+        );
+
+        let request: ChatCompletionRequest =
+            serde_json::from_value(json!({"messages": [], "stop": ["a", "b"]})).unwrap();
+        assert_eq!(request.stop.unwrap().as_slice(), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_validate_limit() {
+        assert!(
+            StopSequence::from(vec!["a", "b", "c", "d"])
+                .validate()
+                .is_ok()
+        );
+        assert!(matches!(
+            StopSequence::from(vec!["a", "b", "c", "d", "e"]).validate(),
+            Err(TwcError::InvalidRequest(_))
+        ));
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_too_many_stops_rejected_before_sending() {
+        use twcai::api::AgentClientExt;
+        use twcai::testing::FixtureTransport;
+
+        use crate::common::client_with;
+
+        let fixtures = FixtureTransport::new();
+        let client = client_with(&fixtures);
+
+        let request = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hi")],
+            stop: Some(vec!["1", "2", "3", "4", "5"].into()),
+            ..Default::default()
+        };
+        let result = client.chat_completions("agent-1", request).await;
+
+        assert!(matches!(result, Err(TwcError::InvalidRequest(m)) if m.contains("4")));
+        assert!(fixtures.requests().is_empty());
+    }
+}
+
+/// Token limit field compatibility tests
+#[cfg(test)]
+mod token_limit {
+    use mockito::{Matcher, Mock, ServerGuard};
+    use serde_json::json;
+    use twcai::api::AgentClientExt;
+    use twcai::types::*;
+    use twcai::{ClientBuilder, TwcError};
+
+    use crate::common::builder_for;
+
+    const PATH: &str = "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions";
+    const CHAT: &str = include_str!("fixtures/conformance/chat_text.json");
+
+    fn rejection(field: &str, other: &str) -> String {
+        json!({
+            "error": {
+                "message": format!(
+                    "Unsupported parameter: '{}' is not supported with this model. Use '{}' instead.",
+                    field, other
+                ),
+                "type": "invalid_request_error",
+                "param": field
+            }
+        })
+        .to_string()
+    }
+
+    fn request() -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hi")],
+            ..Default::default()
+        }
+        .with_token_limit(64)
+    }
+
+    fn builder(server: &ServerGuard) -> ClientBuilder {
+        builder_for(server.url())
+    }
+
+    /// Mock answering requests that carry the limit in `field` with `status`
+    fn mock_field(server: &mut ServerGuard, field: &str, status: usize, body: String) -> Mock {
+        server
+            .mock("POST", PATH)
+            .match_body(Matcher::PartialJson(json!({ field: 64 })))
+            .with_status(status)
+            .with_header("content-type", "application/json")
+            .with_body(body)
+    }
+
+    #[test]
+    fn test_with_token_limit_sets_current_field() {
+        let request = ChatCompletionRequest {
+            max_tokens: Some(10),
+            ..Default::default()
+        }
+        .with_token_limit(64);
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["max_completion_tokens"], 64);
+        assert!(json.get("max_tokens").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_mode_sends_max_tokens() {
+        let mut server = mockito::Server::new_async().await;
+        let current = mock_field(&mut server, "max_completion_tokens", 200, CHAT.to_string())
+            .expect(0)
+            .create_async()
+            .await;
+        let legacy = mock_field(&mut server, "max_tokens", 200, CHAT.to_string())
+            .create_async()
+            .await;
+
+        let client = builder(&server)
+            .compat_mode(CompatMode::Legacy)
+            .build()
+            .unwrap();
+        client.chat_completions("agent-1", request()).await.unwrap();
+
+        current.assert_async().await;
+        legacy.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_legacy_mode_applies_to_default_limit() {
+        let mut server = mockito::Server::new_async().await;
+        let legacy = mock_field(&mut server, "max_tokens", 200, CHAT.to_string())
+            .create_async()
+            .await;
+
+        let client = builder(&server)
+            .compat_mode(CompatMode::Legacy)
+            .default_chat_params(ChatDefaults {
+                max_completion_tokens: Some(64),
+                ..Default::default()
+            })
+            .build()
+            .unwrap();
+        let request = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hi")],
+            ..Default::default()
+        };
+        client.chat_completions("agent-1", request).await.unwrap();
+
+        legacy.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_fallback_resends_with_other_field() {
+        let mut server = mockito::Server::new_async().await;
+        let rejected = mock_field(
+            &mut server,
+            "max_completion_tokens",
+            400,
+            rejection("max_completion_tokens", "max_tokens"),
+        )
+        .create_async()
+        .await;
+        let accepted = mock_field(&mut server, "max_tokens", 200, CHAT.to_string())
+            .create_async()
+            .await;
+
+        let client = builder(&server).token_limit_fallback(true).build().unwrap();
+        let response = client.chat_completions("agent-1", request()).await.unwrap();
+
+        assert!(response.first_text().is_some());
+        rejected.assert_async().await;
+        accepted.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_fallback_from_legacy_field() {
+        let mut server = mockito::Server::new_async().await;
+        let rejected = mock_field(
+            &mut server,
+            "max_tokens",
+            400,
+            rejection("max_tokens", "max_completion_tokens"),
+        )
+        .create_async()
+        .await;
+        let accepted = mock_field(&mut server, "max_completion_tokens", 200, CHAT.to_string())
+            .create_async()
+            .await;
+
+        let client = builder(&server)
+            .compat_mode(CompatMode::Legacy)
+            .token_limit_fallback(true)
+            .build()
+            .unwrap();
+        client.chat_completions("agent-1", request()).await.unwrap();
+
+        rejected.assert_async().await;
+        accepted.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_fallback_is_opt_in() {
+        let mut server = mockito::Server::new_async().await;
+        let rejected = mock_field(
+            &mut server,
+            "max_completion_tokens",
+            400,
+            rejection("max_completion_tokens", "max_tokens"),
+        )
+        .create_async()
+        .await;
+        let accepted = mock_field(&mut server, "max_tokens", 200, CHAT.to_string())
+            .expect(0)
+            .create_async()
+            .await;
+
+        let client = builder(&server).build().unwrap();
+        let error = client
+            .chat_completions("agent-1", request())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error.inner(), TwcError::InvalidRequest(_)));
+        rejected.assert_async().await;
+        accepted.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_fallback_ignores_unrelated_rejections() {
+        let mut server = mockito::Server::new_async().await;
+        let message = "max_tokens_per_minute exceeded for max_completion_tokens_total";
+        let body = json!({"error": {"message": message}}).to_string();
+        let rejected = mock_field(&mut server, "max_completion_tokens", 400, body)
+            .create_async()
+            .await;
+        let accepted = mock_field(&mut server, "max_tokens", 200, CHAT.to_string())
+            .expect(0)
+            .create_async()
+            .await;
+
+        let client = builder(&server).token_limit_fallback(true).build().unwrap();
+        let error = client
+            .chat_completions("agent-1", request())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(error.inner(), TwcError::InvalidRequest(_)));
+        rejected.assert_async().await;
+        accepted.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_stream_fallback() {
+        let mut server = mockito::Server::new_async().await;
+        let rejected = mock_field(
+            &mut server,
+            "max_completion_tokens",
+            400,
+            rejection("max_completion_tokens", "max_tokens"),
+        )
+        .create_async()
+        .await;
+        let accepted = server
+            .mock("POST", PATH)
+            .match_body(Matcher::PartialJson(
+                json!({"max_tokens": 64, "stream": true}),
+            ))
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(include_str!("fixtures/sse/chat_completion.txt"))
+            .create_async()
+            .await;
+
+        let client = builder(&server).token_limit_fallback(true).build().unwrap();
+        let stream = client.chat_completions_stream("agent-1", request()).await;
+
+        assert!(stream.is_ok());
+        rejected.assert_async().await;
+        accepted.assert_async().await;
+    }
+}
+
+/// Tool-calling response shape tests
+#[cfg(test)]
+mod tool_call {
+    use twcai::types::*;
+
+    #[test]
+    fn test_tool_call_message_without_content() {
+        let response: ChatCompletionResponse =
+            serde_json::from_str(include_str!("fixtures/chat/tool_calls.json")).unwrap();
+
+        let first = &response.choices[0];
+        assert_eq!(first.finish_reason, Some(FinishReason::ToolCalls));
+        assert_eq!(first.message.content, ChatContent::Text(String::new()));
+        let calls = first.message.tool_calls.as_ref().unwrap();
+        assert_eq!(calls[0]["function"]["name"], "get_weather");
+
+        // Content omitted entirely and generation not yet finished
+        let second = &response.choices[1];
+        assert_eq!(second.finish_reason, None);
+        assert_eq!(second.message.content, ChatContent::default());
+        assert!(second.message.tool_calls.is_some());
+    }
+
+    #[test]
+    fn test_text_completion_null_finish_reason() {
+        let response: TextCompletionResponse = serde_json::from_str(
+            r#"{
+                "id": "cmpl-1",
+                "object": "text_completion",
+                "created": 1700000000,
+                "model": "m",
+                "choices": [{"text": "Hel", "index": 0, "logprobs": null, "finish_reason": null}],
+                "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(response.choices[0].finish_reason, None);
+    }
+}
+
+/// Tool definition wire format tests
+#[cfg(test)]
+mod tool_definition {
+    use serde_json::json;
+    use twcai::types::*;
+
+    fn weather_schema() -> serde_json::Value {
+        json!({
+            "type": "object",
+            "properties": {"city": {"type": "string"}},
+            "required": ["city"]
+        })
+    }
+
+    #[test]
+    fn test_chat_function_tool_is_nested() {
+        let request = ChatCompletionRequest {
+            tools: Some(vec![Tool::Function(FunctionTool {
+                tool_type: "function".to_string(),
+                function: json!({"name": "get_weather", "parameters": weather_schema()}),
+            })]),
+            tool_choice: Some(ToolChoice::Object(
+                json!({"type": "function", "function": {"name": "get_weather"}}),
+            )),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            json["tools"],
+            json!([{
+                "type": "function",
+                "function": {"name": "get_weather", "parameters": weather_schema()}
+            }])
+        );
+        assert_eq!(json["tool_choice"]["function"]["name"], "get_weather");
+    }
+
+    #[test]
+    fn test_response_function_tool_is_flat() {
+        let tool = ResponseFunctionTool::new("get_weather", weather_schema())
+            .description("Current weather in a city")
+            .strict(true);
+        let request = CreateResponseRequest {
+            tools: Some(vec![tool.into()]),
+            tool_choice: Some(ToolChoice::Object(
+                json!({"type": "function", "name": "get_weather"}),
+            )),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            json["tools"],
+            json!([{
+                "type": "function",
+                "name": "get_weather",
+                "description": "Current weather in a city",
+                "parameters": weather_schema(),
+                "strict": true
+            }])
+        );
+        assert_eq!(
+            json["tool_choice"],
+            json!({"type": "function", "name": "get_weather"})
+        );
+    }
+
+    #[test]
+    fn test_response_tools_round_trip() {
+        let tools = json!([
+            {"type": "function", "name": "get_weather", "parameters": weather_schema()},
+            {"type": "web_search", "search_context_size": "low"}
+        ]);
+
+        let parsed: Vec<ResponseTool> = serde_json::from_value(tools.clone()).unwrap();
+        assert_eq!(
+            parsed[0],
+            ResponseTool::function("get_weather", weather_schema())
+        );
+        assert!(matches!(&parsed[1], ResponseTool::Other(tool) if tool["type"] == "web_search"));
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), tools);
+    }
+
+    #[test]
+    fn test_nested_function_tool_is_rejected() {
+        // A chat-shaped tool has no top-level name and cannot pass as a responses tool
+        let result: Result<ResponseTool, _> = serde_json::from_value(json!({
+            "type": "function",
+            "function": {"name": "get_weather"}
+        }));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_simple_tool_choice() {
+        for choice in ["none", "auto", "required"] {
+            let request = CreateResponseRequest {
+                tool_choice: Some(choice.into()),
+                ..Default::default()
+            };
+            assert_eq!(
+                serde_json::to_value(&request).unwrap()["tool_choice"],
+                choice
+            );
+        }
+    }
+}
@@ -0,0 +1,1286 @@
+//! Client configuration and connection tests for TWCai
+
+mod common;
+
+/// Auth style tests
+#[cfg(test)]
+mod auth_style {
+    use mockito::Matcher;
+    use twcai::api::{AgentClientExt, ConversationsExt, FilesExt};
+    use twcai::types::ListItemsQuery;
+    use twcai::{AuthStyle, CloudAIClient, TwcError};
+
+    use crate::common::builder_for;
+
+    const MODELS_PATH: &str = "/api/v1/cloud-ai/agents/agent-1/v1/models";
+    const MODELS_BODY: &str = r#"{"object": "list", "data": []}"#;
+
+    fn client_with_style(server: &mockito::Server, style: Option<AuthStyle>) -> CloudAIClient {
+        let mut builder = builder_for(server.url()).token("secret");
+        if let Some(style) = style {
+            builder = builder.auth_style(style);
+        }
+        builder.build().unwrap()
+    }
+
+    fn api_key() -> AuthStyle {
+        AuthStyle::Header {
+            name: "X-API-Key".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bearer_is_the_default() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", MODELS_PATH)
+            .match_header("authorization", "Bearer secret")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(MODELS_BODY)
+            .create_async()
+            .await;
+
+        client_with_style(&server, None)
+            .list_models("agent-1")
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_header_style_replaces_authorization() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", MODELS_PATH)
+            .match_header("x-api-key", "secret")
+            .match_header("authorization", Matcher::Missing)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(MODELS_BODY)
+            .create_async()
+            .await;
+
+        client_with_style(&server, Some(api_key()))
+            .list_models("agent-1")
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_query_param_style_adds_token_to_url() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock(
+                "GET",
+                "/api/v1/cloud-ai/agents/agent-1/v1/conversations/conv_1/items",
+            )
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("limit".to_string(), "5".to_string()),
+                Matcher::UrlEncoded("api_key".to_string(), "secret".to_string()),
+            ]))
+            .match_header("authorization", Matcher::Missing)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"object": "list", "data": [], "has_more": false}"#)
+            .create_async()
+            .await;
+
+        let style = AuthStyle::QueryParam {
+            name: "api_key".to_string(),
+        };
+        let query = ListItemsQuery {
+            limit: Some(5),
+            ..Default::default()
+        };
+        let _ = client_with_style(&server, Some(style))
+            .list_conversation_items("agent-1", "conv_1", Some(query))
+            .await;
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_embed_code_is_authenticated_only_when_style_is_set() {
+        let mut server = mockito::Server::new_async().await;
+        let anonymous = server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/embed.js")
+            .match_query(Matcher::Any)
+            .match_header("x-api-key", Matcher::Missing)
+            .match_header("authorization", Matcher::Missing)
+            .with_status(200)
+            .with_body("window.twc = {};")
+            .create_async()
+            .await;
+        let keyed = server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/embed.js")
+            .match_query(Matcher::Any)
+            .match_header("x-api-key", "secret")
+            .with_status(200)
+            .with_body("window.twc = {};")
+            .create_async()
+            .await;
+
+        let site = "https://site.example";
+        client_with_style(&server, None)
+            .get_embed_code("agent-1", None, site, site)
+            .await
+            .unwrap();
+        client_with_style(&server, Some(api_key()))
+            .get_embed_code("agent-1", None, site, site)
+            .await
+            .unwrap();
+
+        anonymous.assert_async().await;
+        keyed.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_multipart_upload_uses_style() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/v1/cloud-ai/agents/agent-1/v1/files")
+            .match_header("x-api-key", "secret")
+            .match_header("authorization", Matcher::Missing)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("{}")
+            .create_async()
+            .await;
+
+        let _ = client_with_style(&server, Some(api_key()))
+            .upload_file("agent-1", "a.txt", b"a".to_vec(), "assistants")
+            .await;
+
+        mock.assert_async().await;
+    }
+
+    #[test]
+    fn test_invalid_styles_are_rejected() {
+        let invalid = [
+            AuthStyle::Header {
+                name: "bad header".to_string(),
+            },
+            AuthStyle::QueryParam {
+                name: String::new(),
+            },
+        ];
+        for style in invalid {
+            let result = CloudAIClient::builder()
+                .token("secret")
+                .auth_style(style)
+                .build();
+            assert!(matches!(result, Err(TwcError::Configuration(_))));
+        }
+    }
+}
+
+/// Client configuration tests
+#[cfg(test)]
+mod config {
+    use std::time::Duration;
+
+    use twcai::api::AgentClientExt;
+    use twcai::{AuthStyle, ClientConfig, CloudAIClient};
+
+    use crate::common::client_for;
+
+    const MODELS_BODY: &str = r#"{"object":"list","data":[]}"#;
+
+    #[tokio::test]
+    async fn test_client_from_built_config() {
+        let mut server = mockito::Server::new_async().await;
+        let models = server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .match_header("authorization", "Bearer secret-token")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"object":"list","data":[]}"#)
+            .create_async()
+            .await;
+
+        let config = ClientConfig::builder()
+            .base_url(server.url())
+            .token("secret-token")
+            .timeout(Duration::from_secs(10))
+            .max_retries(1)
+            .build_config()
+            .unwrap();
+
+        assert_eq!(config.base_url(), server.url());
+        assert_eq!(config.timeout(), Duration::from_secs(10));
+        assert_eq!(config.max_retries(), 1);
+        assert_eq!(config.default_model(), None);
+
+        let client = CloudAIClient::from_config(config);
+        client.list_models("agent-1").await.unwrap();
+        models.assert_async().await;
+    }
+
+    #[test]
+    fn test_token_hint_masks_token() {
+        let config = ClientConfig::builder()
+            .token("secret-token")
+            .build_config()
+            .unwrap();
+        assert_eq!(config.token_hint(), "****oken");
+
+        let config = ClientConfig::builder()
+            .token("short")
+            .build_config()
+            .unwrap();
+        assert_eq!(config.token_hint(), "****");
+    }
+
+    #[tokio::test]
+    async fn test_rotated_token_replaces_cached_header() {
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .match_header("x-api-key", "first-token")
+            .with_status(200)
+            .with_body(MODELS_BODY)
+            .create_async()
+            .await;
+        let second = server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .match_header("x-api-key", "second-token")
+            .with_status(200)
+            .with_body(MODELS_BODY)
+            .create_async()
+            .await;
+
+        let client = CloudAIClient::builder()
+            .base_url(server.url())
+            .token("first-token")
+            .auth_style(AuthStyle::Header {
+                name: "X-API-Key".to_string(),
+            })
+            .build()
+            .unwrap();
+        let rotated = client.with_token("second-token");
+
+        rotated.list_models("agent-1").await.unwrap();
+        client.list_models("agent-1").await.unwrap();
+
+        first.assert_async().await;
+        second.assert_async().await;
+        assert_eq!(rotated.config().token_hint(), "****oken");
+    }
+
+    #[tokio::test]
+    async fn test_base_url_with_trailing_slash() {
+        let mut server = mockito::Server::new_async().await;
+        let models = server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent%201/v1/models")
+            .match_header("authorization", "Bearer test-token")
+            .with_status(200)
+            .with_body(MODELS_BODY)
+            .create_async()
+            .await;
+
+        let client = client_for(format!("{}/", server.url()));
+        client.list_models("agent 1").await.unwrap();
+
+        models.assert_async().await;
+    }
+}
+
+/// Connection override tests
+#[cfg(test)]
+mod connect {
+    use twcai::api::AgentClientExt;
+
+    use crate::common::builder_for;
+
+    const MODELS_BODY: &str = r#"{"object": "list", "data": []}"#;
+
+    #[tokio::test]
+    async fn test_resolve_fake_hostname() {
+        let mut server = mockito::Server::new_async().await;
+        let addr = server.socket_address();
+        let models = server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .match_header(
+                "host",
+                format!("gateway.twcai.test:{}", addr.port()).as_str(),
+            )
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(MODELS_BODY)
+            .create_async()
+            .await;
+
+        let client = builder_for(format!("http://gateway.twcai.test:{}", addr.port()))
+            .no_proxy()
+            .resolve("gateway.twcai.test", addr)
+            .build()
+            .unwrap();
+        client.list_models("agent-1").await.unwrap();
+
+        models.assert_async().await;
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_socket_ignores_base_url_host() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gateway.sock");
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut chunk = [0u8; 1024];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = socket.read(&mut chunk).await.unwrap();
+                assert!(n > 0);
+                request.extend_from_slice(&chunk[..n]);
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                MODELS_BODY.len(),
+                MODELS_BODY
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let client = builder_for("http://sidecar.invalid")
+            .unix_socket(&path)
+            .build()
+            .unwrap();
+        client.list_models("agent-1").await.unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /api/v1/cloud-ai/agents/agent-1/v1/models HTTP/1.1\r\n"));
+        assert!(
+            request
+                .to_ascii_lowercase()
+                .contains("host: sidecar.invalid\r\n")
+        );
+    }
+}
+
+/// Connection handling tests
+#[cfg(test)]
+mod connection {
+    use std::time::{Duration, Instant};
+
+    use twcai::api::AgentClientExt;
+    use twcai::{CloudAIClient, TwcError};
+
+    use crate::common::{CountingServer, builder_for, client_for};
+
+    const MODELS_BODY: &str = r#"{"object":"list","data":[]}"#;
+
+    #[tokio::test]
+    async fn test_default_pool_reuses_connection() {
+        let server = CountingServer::start(MODELS_BODY).await;
+        let client = client_for(&server.url);
+
+        client.list_models("agent-1").await.unwrap();
+        client.list_models("agent-1").await.unwrap();
+
+        assert_eq!(server.connection_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pool_max_idle_per_host_zero_disables_reuse() {
+        let server = CountingServer::start(MODELS_BODY).await;
+        let client = builder_for(&server.url)
+            .pool_max_idle_per_host(0)
+            .build()
+            .unwrap();
+
+        client.list_models("agent-1").await.unwrap();
+        client.list_models("agent-1").await.unwrap();
+
+        assert_eq!(server.connection_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_pool_idle_timeout_expires_connections() {
+        let server = CountingServer::start(MODELS_BODY).await;
+        let client = builder_for(&server.url)
+            .pool_idle_timeout(Some(Duration::from_millis(50)))
+            .build()
+            .unwrap();
+
+        client.list_models("agent-1").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        client.list_models("agent-1").await.unwrap();
+
+        assert_eq!(server.connection_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_keepalive_and_http1_only() {
+        let server = CountingServer::start(MODELS_BODY).await;
+        let client = builder_for(&server.url)
+            .tcp_keepalive(Duration::from_secs(30))
+            .http1_only()
+            .build()
+            .unwrap();
+
+        client.list_models("agent-1").await.unwrap();
+        client.list_models("agent-1").await.unwrap();
+
+        assert_eq!(server.connection_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_http2_prior_knowledge_reaches_transport() {
+        // The test server only speaks HTTP/1.1, so forcing HTTP/2 must fail
+        let server = CountingServer::start(MODELS_BODY).await;
+        let client = builder_for(&server.url)
+            .http2_prior_knowledge()
+            .timeout(Duration::from_secs(2))
+            .build()
+            .unwrap();
+
+        assert!(client.list_models("agent-1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_with_token_shares_pool() {
+        let server = CountingServer::start(MODELS_BODY).await;
+        let client = CloudAIClient::builder()
+            .base_url(&server.url)
+            .token("tenant-a")
+            .build()
+            .unwrap();
+        let other = client.with_token("tenant-b");
+
+        client.list_models("agent-1").await.unwrap();
+        other.list_models("agent-1").await.unwrap();
+
+        assert_eq!(server.connection_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_with_token_sends_own_token() {
+        let mut server = mockito::Server::new_async().await;
+        let tenant_a = server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .match_header("authorization", "Bearer tenant-a")
+            .with_status(200)
+            .with_body(MODELS_BODY)
+            .create_async()
+            .await;
+        let tenant_b = server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .match_header("authorization", "Bearer tenant-b")
+            .with_status(200)
+            .with_body(MODELS_BODY)
+            .create_async()
+            .await;
+
+        let client = CloudAIClient::builder()
+            .base_url(server.url())
+            .token("tenant-a")
+            .build()
+            .unwrap();
+        let other = client.with_token("tenant-b");
+
+        client.list_models("agent-1").await.unwrap();
+        other.list_models("agent-1").await.unwrap();
+
+        tenant_a.assert_async().await;
+        tenant_b.assert_async().await;
+        assert_eq!(client.config().token_hint(), "****nt-a");
+        assert_eq!(other.config().token_hint(), "****nt-b");
+    }
+
+    #[tokio::test]
+    async fn test_refused_connection_is_connect_error() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let client = builder_for(format!("http://{}", addr))
+            .no_proxy()
+            .build()
+            .unwrap();
+
+        let err = client.list_models("agent-1").await.unwrap_err();
+        assert!(matches!(err, TwcError::Connect(_)), "{:?}", err);
+        assert!(err.is_retryable());
+    }
+
+    /// Needs a network where 10.255.255.1 is black-holed rather than
+    /// rejected; run with `cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore]
+    async fn test_unreachable_host_bounded_by_connect_timeout() {
+        let client = builder_for("http://10.255.255.1")
+            .no_proxy()
+            .connect_timeout(Duration::from_millis(500))
+            .build()
+            .unwrap();
+
+        let started = Instant::now();
+        let err = client.list_models("agent-1").await.unwrap_err();
+
+        assert!(matches!(err, TwcError::Connect(_)), "{:?}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
+
+/// Endpoint path tests
+#[cfg(test)]
+mod endpoint {
+    use twcai::CloudAIClient;
+    use twcai::api::{
+        AgentClientExt, AgentsAdminExt, ConversationsExt, FilesExt, KnowledgeExt, ResponsesExt,
+        StatsExt,
+    };
+    use twcai::types::*;
+
+    use crate::common::{ScriptedServer, builder_for};
+
+    /// `200 OK` with an empty JSON object; the bodies do not matter here
+    fn ok() -> String {
+        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{}"
+            .to_string()
+    }
+
+    async fn server(requests: usize) -> ScriptedServer {
+        ScriptedServer::start(vec![ok(); requests]).await
+    }
+
+    fn client_at(base_url: &str) -> CloudAIClient {
+        builder_for(base_url)
+            .control_plane_url(base_url)
+            .build()
+            .unwrap()
+    }
+
+    /// `METHOD /path` of each request, without query or protocol
+    fn routes(server: &ScriptedServer) -> Vec<String> {
+        server
+            .requests()
+            .iter()
+            .map(|line| {
+                let mut parts = line.split(' ');
+                let method = parts.next().unwrap();
+                let target = parts.next().unwrap();
+                let path = target.split('?').next().unwrap();
+                format!("{} {}", method, path)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    #[allow(deprecated)]
+    async fn test_agent_routes() {
+        let server = server(6).await;
+        let client = client_at(&server.url);
+        let chat = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hi")],
+            ..Default::default()
+        };
+        let from = UsageDate::new(2025, 1, 1).unwrap();
+        let to = UsageDate::new(2025, 1, 31).unwrap();
+
+        let _ = client
+            .call_agent("agent-1", AgentCallRequest::new("Hi"))
+            .await;
+        let _ = client.chat_completions("agent-1", chat).await;
+        let _ = client
+            .text_completions("agent-1", TextCompletionRequest::new("Hi"))
+            .await;
+        let _ = client.list_models("agent-1").await;
+        let site = "https://site.example";
+        let _ = client.get_embed_code("agent-1", None, site, site).await;
+        let _ = client.get_agent_usage("agent-1", from, to).await;
+
+        assert_eq!(
+            routes(&server),
+            [
+                "POST /api/v1/cloud-ai/agents/agent-1/call",
+                "POST /api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+                "POST /api/v1/cloud-ai/agents/agent-1/v1/completions",
+                "GET /api/v1/cloud-ai/agents/agent-1/v1/models",
+                "GET /api/v1/cloud-ai/agents/agent-1/embed.js",
+                "GET /api/v1/cloud-ai/agents/agent-1/usage",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_conversation_routes() {
+        let server = server(6).await;
+        let client = client_at(&server.url);
+
+        let request = CreateConversationRequest::default();
+        let _ = client.create_conversation("agent-1", request).await;
+        let _ = client.get_conversation("agent-1", "conv_1").await;
+        let _ = client.delete_conversation("agent-1", "conv_1").await;
+        let _ = client
+            .list_conversation_items("agent-1", "conv_1", None)
+            .await;
+        let _ = client
+            .get_conversation_item("agent-1", "conv_1", "msg_1", None)
+            .await;
+        let _ = client
+            .delete_conversation_item("agent-1", "conv_1", "msg_1")
+            .await;
+
+        assert_eq!(
+            routes(&server),
+            [
+                "POST /api/v1/cloud-ai/agents/agent-1/v1/conversations",
+                "GET /api/v1/cloud-ai/agents/agent-1/v1/conversations/conv_1",
+                "DELETE /api/v1/cloud-ai/agents/agent-1/v1/conversations/conv_1",
+                "GET /api/v1/cloud-ai/agents/agent-1/v1/conversations/conv_1/items",
+                "GET /api/v1/cloud-ai/agents/agent-1/v1/conversations/conv_1/items/msg_1",
+                "DELETE /api/v1/cloud-ai/agents/agent-1/v1/conversations/conv_1/items/msg_1",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_response_routes() {
+        let server = server(5).await;
+        let client = client_at(&server.url);
+
+        let request = CreateResponseRequest {
+            input: Some(ResponseInput::Text("Hi".to_string())),
+            ..Default::default()
+        };
+        let _ = client.create_response("agent-1", request).await;
+        let _ = client.get_response("agent-1", "resp_1", None).await;
+        let _ = client
+            .list_response_input_items("agent-1", "resp_1", None)
+            .await;
+        let _ = client.cancel_response("agent-1", "resp_1").await;
+        let _ = client.delete_response("agent-1", "resp_1").await;
+
+        assert_eq!(
+            routes(&server),
+            [
+                "POST /api/v1/cloud-ai/agents/agent-1/v1/responses",
+                "GET /api/v1/cloud-ai/agents/agent-1/v1/responses/resp_1",
+                "GET /api/v1/cloud-ai/agents/agent-1/v1/responses/resp_1/input_items",
+                "POST /api/v1/cloud-ai/agents/agent-1/v1/responses/resp_1/cancel",
+                "DELETE /api/v1/cloud-ai/agents/agent-1/v1/responses/resp_1",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_routes() {
+        let server = server(4).await;
+        let client = client_at(&server.url);
+
+        let _ = client
+            .upload_file("agent-1", "a.txt", b"a".to_vec(), "assistants")
+            .await;
+        let _ = client.list_files("agent-1").await;
+        let _ = client.get_file("agent-1", "file_1").await;
+        let _ = client.delete_file("agent-1", "file_1").await;
+
+        assert_eq!(
+            routes(&server),
+            [
+                "POST /api/v1/cloud-ai/agents/agent-1/v1/files",
+                "GET /api/v1/cloud-ai/agents/agent-1/v1/files",
+                "GET /api/v1/cloud-ai/agents/agent-1/v1/files/file_1",
+                "DELETE /api/v1/cloud-ai/agents/agent-1/v1/files/file_1",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_control_plane_routes() {
+        let server = server(5).await;
+        let client = client_at(&server.url);
+
+        let _ = client.list_agents().await;
+        let _ = client.get_agent(7).await;
+        let _ = client.list_knowledge_documents(3).await;
+        let _ = client
+            .upload_knowledge_document(3, "a.txt", b"a".to_vec(), "text/plain")
+            .await;
+        let _ = client.delete_knowledge_document(3, 9).await;
+
+        assert_eq!(
+            routes(&server),
+            [
+                "GET /api/v1/cloud-ai/agents",
+                "GET /api/v1/cloud-ai/agents/7",
+                "GET /api/v1/cloud-ai/knowledge-bases/3/documents",
+                "POST /api/v1/cloud-ai/knowledge-bases/3/documents",
+                "DELETE /api/v1/cloud-ai/knowledge-bases/3/documents/9",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ids_are_percent_encoded() {
+        let server = server(2).await;
+        let client = client_at(&server.url);
+
+        let _ = client.get_conversation("agent 1", "conv/1?x#y").await;
+        let _ = client
+            .get_conversation_item("agent-1", "conv_1", "../msg_1", None)
+            .await;
+
+        assert_eq!(
+            routes(&server),
+            [
+                "GET /api/v1/cloud-ai/agents/agent%201/v1/conversations/conv%2F1%3Fx%23y",
+                "GET /api/v1/cloud-ai/agents/agent-1/v1/conversations/conv_1/items/..%2Fmsg_1",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_base_url_trailing_slash_is_ignored() {
+        let server = server(1).await;
+        let client = client_at(&format!("{}/", server.url));
+
+        let _ = client.list_models("agent-1").await;
+
+        assert_eq!(
+            routes(&server),
+            ["GET /api/v1/cloud-ai/agents/agent-1/v1/models"]
+        );
+    }
+}
+
+/// Configuration profile tests
+#[cfg(all(test, feature = "config-file"))]
+mod profile {
+    use std::time::Duration;
+
+    use twcai::{ClientBuilder, TwcError};
+
+    const CONFIG: &str = r#"
+[profiles.staging]
+base_url = "http://staging.local"
+token = "staging-token"
+agent_id = "agent-staging"
+timeout = 15
+
+[profiles.prod]
+token_file = "secrets/prod.token"
+agent_id = "agent-prod"
+region = "ru-1"
+"#;
+
+    fn write_config(dir: &tempfile::TempDir, name: &str, contents: &str) -> std::path::PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn configuration_error(result: twcai::Result<ClientBuilder>) -> String {
+        match result {
+            Err(TwcError::Configuration(message)) => message,
+            Err(e) => panic!("expected configuration error, got {:?}", e),
+            Ok(_) => panic!("expected configuration error"),
+        }
+    }
+
+    #[test]
+    fn test_profile_with_inline_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(&dir, "config.toml", CONFIG);
+
+        let client = ClientBuilder::from_profile(Some(path), "staging")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(client.config().base_url(), "http://staging.local");
+        assert_eq!(client.config().token_hint(), "****oken");
+        assert_eq!(client.default_agent(), Some("agent-staging"));
+        assert_eq!(client.config().timeout(), Duration::from_secs(15));
+    }
+
+    #[test]
+    fn test_profile_with_token_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(&dir, "config.toml", CONFIG);
+        std::fs::create_dir(dir.path().join("secrets")).unwrap();
+        std::fs::write(dir.path().join("secrets/prod.token"), "prod-token\n").unwrap();
+
+        // Unknown keys (`region`) are ignored
+        let client = ClientBuilder::from_profile(Some(path), "prod")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(client.config().base_url(), "https://agent.timeweb.cloud");
+        assert_eq!(client.config().token_hint(), "****oken");
+        assert_eq!(client.default_agent(), Some("agent-prod"));
+    }
+
+    #[test]
+    fn test_missing_token_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(&dir, "config.toml", CONFIG);
+
+        let message = configuration_error(ClientBuilder::from_profile(Some(path), "prod"));
+        assert!(message.contains("prod.token"));
+    }
+
+    #[test]
+    fn test_missing_profile_lists_available() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(&dir, "config.toml", CONFIG);
+
+        let message = configuration_error(ClientBuilder::from_profile(Some(path), "dev"));
+        assert!(message.contains("\"dev\""));
+        assert!(message.contains("prod, staging"));
+    }
+
+    #[test]
+    fn test_json_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(
+            &dir,
+            "config.json",
+            r#"{"profiles": {"default": {"token": "json-token", "timeout": 5}}}"#,
+        );
+
+        let client = ClientBuilder::from_profile(Some(path), "default")
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(client.config().token_hint(), "****oken");
+        assert_eq!(client.config().timeout(), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_token_and_token_file_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_config(
+            &dir,
+            "config.toml",
+            "[profiles.both]\ntoken = \"a\"\ntoken_file = \"b\"\n",
+        );
+
+        let message = configuration_error(ClientBuilder::from_profile(Some(path), "both"));
+        assert!(message.contains("token_file"));
+    }
+}
+
+/// Proxy configuration tests
+#[cfg(test)]
+mod proxy {
+    use twcai::api::AgentClientExt;
+    use twcai::{CloudAIClient, TwcError};
+
+    use crate::common::builder_for;
+
+    const MODELS_BODY: &str = r#"{"object": "list", "data": []}"#;
+
+    /// Address nothing listens on, so a request routed there fails
+    const DEAD_PROXY: &str = "http://127.0.0.1:9";
+
+    #[tokio::test]
+    async fn test_requests_go_through_proxy_with_auth() {
+        let mut proxy = mockito::Server::new_async().await;
+        let forwarded = proxy
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .match_header("host", "agent.example")
+            .match_header("proxy-authorization", "Basic dXNlcjpzZWNyZXQ=")
+            .match_header("authorization", "Bearer test-token")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(MODELS_BODY)
+            .create_async()
+            .await;
+
+        let client = builder_for("http://agent.example")
+            .proxy(proxy.url())
+            .proxy_auth("user", "secret")
+            .build()
+            .unwrap();
+        client.list_models("agent-1").await.unwrap();
+
+        forwarded.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_bypass_and_disable() {
+        let mut server = mockito::Server::new_async().await;
+        let direct = server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(MODELS_BODY)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let bypassing = builder_for(server.url())
+            .proxy(DEAD_PROXY)
+            .proxy_bypass("127.0.0.1,localhost")
+            .build()
+            .unwrap();
+        bypassing.list_models("agent-1").await.unwrap();
+
+        let disabled = builder_for(server.url())
+            .proxy(DEAD_PROXY)
+            .no_proxy()
+            .build()
+            .unwrap();
+        disabled.list_models("agent-1").await.unwrap();
+
+        direct.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_proxy_is_used() {
+        let client = builder_for("http://agent.example")
+            .proxy(DEAD_PROXY)
+            .build()
+            .unwrap();
+
+        let err = client.list_models("agent-1").await.unwrap_err();
+        assert!(matches!(err, TwcError::Connect(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn test_invalid_proxy_configuration() {
+        let result = CloudAIClient::builder()
+            .token("test-token")
+            .proxy("not a url")
+            .build();
+        assert!(matches!(result, Err(TwcError::Configuration(_))));
+
+        let result = CloudAIClient::builder()
+            .token("test-token")
+            .proxy_auth("user", "secret")
+            .build();
+        assert!(matches!(result, Err(TwcError::Configuration(_))));
+    }
+}
+
+/// Send and Sync bound tests
+#[cfg(test)]
+mod send_sync {
+    use twcai::api::*;
+    use twcai::pagination::{Page, Paginator};
+    use twcai::stream::TwcStream;
+    use twcai::types::*;
+    use twcai::{
+        ClientBuilder, ClientConfig, CloudAIClient, MemoryConversationIndex, RequestOptions,
+        StreamOptionsLocal, TwcError,
+    };
+
+    use crate::common::builder_for;
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    fn assert_send_static<T: Send + 'static>() {}
+
+    fn default_agent_client() -> CloudAIClient {
+        builder_for("http://127.0.0.1:9")
+            .default_agent("agent-1")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_shared_types_are_send_sync() {
+        assert_send_sync::<CloudAIClient>();
+        assert_send_sync::<ClientConfig>();
+        assert_send_sync::<ClientBuilder>();
+        assert_send_sync::<TwcError>();
+        assert_send_sync::<MemoryConversationIndex>();
+        assert_send_sync::<ResponseStreamAccumulator>();
+        assert_send_sync::<WithMeta<ChatCompletionResponse>>();
+        assert_send_sync::<Page<ConversationItem>>();
+        assert_send_sync::<twcai::pricing::CostTracker>();
+    }
+
+    #[test]
+    fn test_stream_types_are_send() {
+        assert_send_static::<TwcStream<ChatCompletionStreamResponse>>();
+        assert_send_static::<TwcStream<ResponseStreamEvent>>();
+        assert_send_static::<TwcStream<String>>();
+        assert_send_static::<Paginator<ConversationItem>>();
+        assert_send_static::<Paginator<ResponseInputItem>>();
+    }
+
+    #[tokio::test]
+    async fn test_agent_client_futures_are_send() {
+        let client = default_agent_client();
+        let chat = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hi")],
+            ..Default::default()
+        };
+        let mut sink = Vec::new();
+
+        assert_send(&client.call_agent("agent-1", AgentCallRequest::new("Hi")));
+        assert_send(&client.chat_completions("agent-1", chat.clone()));
+        assert_send(&client.chat_completions_stream("agent-1", chat.clone()));
+        assert_send(&client.chat_completions_stream_with_options(
+            "agent-1",
+            chat.clone(),
+            StreamOptionsLocal::default(),
+        ));
+        assert_send(&client.chat_completions_stream_text("agent-1", chat.clone()));
+        assert_send(&client.chat_completions_batch("agent-1", vec![chat.clone()], 2));
+        assert_send(&client.list_models("agent-1"));
+        assert_send(&client.ping("agent-1"));
+        assert_send(&client.warm_up());
+        assert_send(&client.get_embed_code_to(
+            "agent-1",
+            EmbedOptions::new("https://example.com", "https://example.com"),
+            &mut sink,
+        ));
+
+        assert_send(&client.chat(chat.clone()));
+        assert_send(&client.chat_stream(chat));
+    }
+
+    #[tokio::test]
+    async fn test_resource_futures_are_send() {
+        let client = default_agent_client();
+        let index = MemoryConversationIndex::new();
+        let mut sink = Vec::new();
+
+        assert_send(&client.create_response("agent-1", CreateResponseRequest::default()));
+        assert_send(&client.create_response_stream("agent-1", CreateResponseRequest::default()));
+        assert_send(&client.wait_for_response("agent-1", "resp_1"));
+        assert_send(&client.get_response_raw_to("agent-1", "resp_1", None, &mut sink));
+
+        assert_send(&client.create_conversation_with_options(
+            "agent-1",
+            CreateConversationRequest::default(),
+            RequestOptions::new(),
+        ));
+        assert_send(&client.send_to_conversation("agent-1", "conv_1", "Hi"));
+        assert_send(&client.find_or_create_conversation(
+            "agent-1",
+            ("ticket", "T-1"),
+            CreateConversationRequest::default(),
+            &index,
+        ));
+
+        assert_send(&client.upload_file("agent-1", "a.txt", b"a".to_vec(), "assistants"));
+        assert_send(&client.list_files("agent-1"));
+        assert_send(&client.list_agents());
+        assert_send(&client.verify_token());
+        assert_send(&client.list_knowledge_documents(1));
+    }
+
+    #[tokio::test]
+    async fn test_calls_can_be_spawned() {
+        let client = default_agent_client();
+
+        let handle = tokio::spawn(async move {
+            let chat = ChatCompletionRequest {
+                messages: vec![ChatMessage::user("Hi")],
+                ..Default::default()
+            };
+            let mut stream = client.chat_completions_stream("agent-1", chat).await?;
+            futures_util::StreamExt::next(&mut stream).await.transpose()
+        });
+
+        // Nothing listens on the discard port, so the call fails to connect
+        assert!(handle.await.unwrap().is_err());
+    }
+}
+
+/// TLS configuration tests
+#[cfg(test)]
+mod tls {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio_native_tls::{TlsAcceptor, native_tls};
+    use twcai::api::AgentClientExt;
+    use twcai::{CloudAIClient, TlsVersion, TwcError};
+
+    use crate::common::builder_for;
+
+    const CA_PEM: &[u8] = include_bytes!("fixtures/tls/ca.pem");
+    const CA_DER: &[u8] = include_bytes!("fixtures/tls/ca.der");
+    const SERVER_PEM: &[u8] = include_bytes!("fixtures/tls/server.pem");
+    const SERVER_KEY: &[u8] = include_bytes!("fixtures/tls/server.key");
+
+    const MODELS_BODY: &str = r#"{"object": "list", "data": []}"#;
+
+    /// Serve every connection with a fixed models response over TLS, using a
+    /// certificate for `localhost` signed by the fixture CA
+    async fn tls_server() -> String {
+        let identity = native_tls::Identity::from_pkcs8(SERVER_PEM, SERVER_KEY).unwrap();
+        let acceptor = TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(mut stream) = acceptor.accept(socket).await else {
+                        return;
+                    };
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match stream.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => request.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        MODELS_BODY.len(),
+                        MODELS_BODY
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                });
+            }
+        });
+
+        format!("https://localhost:{}", port)
+    }
+
+    #[tokio::test]
+    async fn test_self_signed_server_rejected_by_default() {
+        let client = builder_for(tls_server().await).no_proxy().build().unwrap();
+
+        let err = client.list_models("agent-1").await.unwrap_err();
+        assert!(matches!(err, TwcError::Connect(_)), "{:?}", err);
+    }
+
+    #[tokio::test]
+    async fn test_added_root_certificate_is_trusted() {
+        let base_url = tls_server().await;
+
+        for root in [CA_PEM, CA_DER] {
+            let client = builder_for(&base_url)
+                .no_proxy()
+                .add_root_certificate(root)
+                .min_tls_version(TlsVersion::TLS_1_2)
+                .build()
+                .unwrap();
+
+            let models = client.list_models("agent-1").await.unwrap();
+            assert!(models.data.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_danger_accept_invalid_certs() {
+        let client = builder_for(tls_server().await)
+            .no_proxy()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .unwrap();
+
+        client.list_models("agent-1").await.unwrap();
+    }
+
+    #[test]
+    fn test_invalid_root_certificate_fails_build() {
+        let result = CloudAIClient::builder()
+            .token("test-token")
+            .add_root_certificate(b"not a certificate".to_vec())
+            .build();
+        assert!(matches!(result, Err(TwcError::Configuration(_))));
+    }
+}
+
+/// Connection warm-up tests
+#[cfg(test)]
+mod warm_up {
+    use std::time::Duration;
+
+    use reqwest::StatusCode;
+    use twcai::TwcError;
+    use twcai::api::AgentClientExt;
+
+    use crate::common::{CountingServer, builder_for, client_for};
+
+    const MODELS_BODY: &str = r#"{"object":"list","data":[]}"#;
+
+    #[tokio::test]
+    async fn test_warm_up_connection_is_reused() {
+        let server = CountingServer::start(MODELS_BODY).await;
+        let client = client_for(&server.url);
+
+        let report = client.warm_up().await.unwrap();
+        assert_eq!(report.status, StatusCode::OK);
+        assert!(report.latency > Duration::ZERO);
+        assert_eq!(server.connection_count(), 1);
+
+        client.list_models("agent-1").await.unwrap();
+        assert_eq!(server.connection_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_build_async_warms_up() {
+        let server = CountingServer::start(MODELS_BODY).await;
+        let client = builder_for(&server.url)
+            .warm_up_on_build(true)
+            .build_async()
+            .await
+            .unwrap();
+        assert_eq!(server.connection_count(), 1);
+
+        client.list_models("agent-1").await.unwrap();
+        client.list_models("agent-1").await.unwrap();
+        assert_eq!(server.connection_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_build_async_without_warm_up_connects_lazily() {
+        let server = CountingServer::start(MODELS_BODY).await;
+        builder_for(&server.url).build_async().await.unwrap();
+
+        assert_eq!(server.connection_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_status_does_not_matter() {
+        let mut server = mockito::Server::new_async().await;
+        let head = server
+            .mock("HEAD", "/")
+            .match_header("authorization", mockito::Matcher::Missing)
+            .with_status(404)
+            .create_async()
+            .await;
+        let client = client_for(server.url());
+
+        let report = client.warm_up().await.unwrap();
+
+        head.assert_async().await;
+        assert_eq!(report.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_unreachable_host() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let client = client_for(url);
+        let err = client.warm_up().await.unwrap_err();
+        assert!(matches!(err, TwcError::Connect(_)), "{:?}", err);
+
+        // A failed warm-up does not fail the build
+        let url = client.config().base_url().to_string();
+        builder_for(url)
+            .warm_up_on_build(true)
+            .build_async()
+            .await
+            .unwrap();
+    }
+}
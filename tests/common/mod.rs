@@ -0,0 +1,481 @@
+//! Shared helpers for integration tests
+//!
+//! Tests that only script responses and inspect requests run on
+//! `twcai::testing::FixtureTransport` through `client_with`. The rest use
+//! mockito or the raw TCP servers below, because they need what fixtures
+//! cannot provide (see the `testing` module docs): real connections, reqwest's
+//! own headers, multipart bodies, or responses chosen by request body.
+
+#![allow(dead_code)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use twcai::{ClientBuilder, CloudAIClient};
+
+/// Builder for a client of the server at `url`, with a test token
+pub fn builder_for(url: impl Into<String>) -> ClientBuilder {
+    CloudAIClient::builder().base_url(url).token("test-token")
+}
+
+/// Client of the server at `url`, with a test token
+pub fn client_for(url: impl Into<String>) -> CloudAIClient {
+    builder_for(url).build().unwrap()
+}
+
+/// Builder for a client answering every request from `transport`, with a
+/// test token
+#[cfg(feature = "testing")]
+pub fn builder_with(transport: &twcai::testing::FixtureTransport) -> ClientBuilder {
+    CloudAIClient::builder()
+        .token("test-token")
+        .transport(transport.clone())
+}
+
+/// Client answering every request from `transport`, with a test token
+#[cfg(feature = "testing")]
+pub fn client_with(transport: &twcai::testing::FixtureTransport) -> CloudAIClient {
+    builder_with(transport).build().unwrap()
+}
+
+/// Minimal keep-alive HTTP/1.1 server that answers every request with `body`
+/// (only the headers for `HEAD`) and counts accepted TCP connections.
+pub struct CountingServer {
+    /// Base URL of the server
+    pub url: String,
+    /// Number of TCP connections accepted so far
+    pub connections: Arc<AtomicUsize>,
+}
+
+impl CountingServer {
+    pub async fn start(body: &'static str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    loop {
+                        let n = match socket.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => n,
+                        };
+                        buf.extend_from_slice(&chunk[..n]);
+                        while let Some(end) = find_header_end(&buf) {
+                            let consumed = end + content_length(&buf[..end]);
+                            if buf.len() < consumed {
+                                break;
+                            }
+                            let head = buf.starts_with(b"HEAD ");
+                            buf.drain(..consumed);
+                            let response = format!(
+                                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                                body.len(),
+                                if head { "" } else { body }
+                            );
+                            if socket.write_all(response.as_bytes()).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        Self {
+            url: format!("http://{}", addr),
+            connections,
+        }
+    }
+
+    pub fn connection_count(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+}
+
+/// HTTP/1.1 server that answers the n-th connection with the n-th scripted
+/// raw response and then closes it. Responses may be cut short on purpose
+/// to simulate dropped connections.
+pub struct ScriptedServer {
+    /// Base URL of the server
+    pub url: String,
+    /// Request lines (`METHOD /path?query HTTP/1.1`) in arrival order
+    pub requests: Arc<Mutex<Vec<String>>>,
+    /// Full request heads (request line and headers) in arrival order
+    pub heads: Arc<Mutex<Vec<String>>>,
+    /// Request bodies in arrival order
+    pub bodies: Arc<Mutex<Vec<String>>>,
+}
+
+impl ScriptedServer {
+    pub async fn start(script: Vec<String>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let heads = Arc::new(Mutex::new(Vec::new()));
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+        let log = requests.clone();
+        let head_log = heads.clone();
+        let body_log = bodies.clone();
+
+        tokio::spawn(async move {
+            let mut script = script.into_iter();
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let Some(response) = script.next() else {
+                    return;
+                };
+                let log = log.clone();
+                let head_log = head_log.clone();
+                let body_log = body_log.clone();
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    let head_end = loop {
+                        if let Some(end) = find_header_end(&buf) {
+                            break end;
+                        }
+                        match socket.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    };
+                    let body_len = content_length(&buf[..head_end]);
+                    while buf.len() < head_end + body_len {
+                        match socket.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+
+                    let head = String::from_utf8_lossy(&buf[..head_end]);
+                    let request_line = head.lines().next().unwrap_or_default().to_string();
+                    log.lock().unwrap().push(request_line);
+                    head_log.lock().unwrap().push(head.to_string());
+                    let body = &buf[head_end..head_end + body_len];
+                    body_log
+                        .lock()
+                        .unwrap()
+                        .push(String::from_utf8_lossy(body).to_string());
+
+                    let _ = socket.write_all(response.as_bytes()).await;
+                    let _ = socket.shutdown().await;
+                });
+            }
+        });
+
+        Self {
+            url: format!("http://{}", addr),
+            requests,
+            heads,
+            bodies,
+        }
+    }
+
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Request bodies parsed as JSON, in arrival order
+    pub fn json_bodies(&self) -> Vec<serde_json::Value> {
+        self.bodies
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|body| serde_json::from_str(body).unwrap())
+            .collect()
+    }
+
+    /// Value of header `name` in each request, in arrival order
+    pub fn header_values(&self, name: &str) -> Vec<Option<String>> {
+        self.heads
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|head| {
+                head.lines().skip(1).find_map(|line| {
+                    let (key, value) = line.split_once(':')?;
+                    key.trim()
+                        .eq_ignore_ascii_case(name)
+                        .then(|| value.trim().to_string())
+                })
+            })
+            .collect()
+    }
+}
+
+/// HTTP/1.1 server that answers GET requests with `body` after `delay`,
+/// tracking how many requests are in flight at once. POST requests get a
+/// server-sent event stream that never ends.
+pub struct SlowServer {
+    /// Base URL of the server
+    pub url: String,
+    /// Highest number of GET requests in flight at the same time
+    pub peak: Arc<AtomicUsize>,
+}
+
+impl SlowServer {
+    pub async fn start(body: &'static str, delay: std::time::Duration) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let peak_seen = peak.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let in_flight = in_flight.clone();
+                let peak = peak_seen.clone();
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    let head_end = loop {
+                        if let Some(end) = find_header_end(&buf) {
+                            break end;
+                        }
+                        match socket.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    };
+                    let body_len = content_length(&buf[..head_end]);
+                    while buf.len() < head_end + body_len {
+                        match socket.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+
+                    if buf.starts_with(b"POST") {
+                        let head = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n";
+                        let _ = socket.write_all(head.as_bytes()).await;
+                        let _ = socket.read(&mut chunk).await;
+                        return;
+                    }
+
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(delay).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                    let _ = socket.shutdown().await;
+                });
+            }
+        });
+
+        Self {
+            url: format!("http://{}", addr),
+            peak,
+        }
+    }
+
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::SeqCst)
+    }
+}
+
+/// HTTP/1.1 server that answers streaming requests with `events` and then
+/// keeps the connection open until the client closes it. Requests to a
+/// `/cancel` path get an empty JSON object.
+pub struct OpenStreamServer {
+    /// Base URL of the server
+    pub url: String,
+    /// Request lines (`METHOD /path?query HTTP/1.1`) in arrival order
+    pub requests: Arc<Mutex<Vec<String>>>,
+    /// Notified each time a client closes a stream connection
+    pub closed: Arc<tokio::sync::Notify>,
+}
+
+impl OpenStreamServer {
+    pub async fn start(events: &'static str) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let closed = Arc::new(tokio::sync::Notify::new());
+        let log = requests.clone();
+        let close_signal = closed.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let log = log.clone();
+                let close_signal = close_signal.clone();
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 4096];
+                    let head_end = loop {
+                        if let Some(end) = find_header_end(&buf) {
+                            break end;
+                        }
+                        match socket.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    };
+                    let body_len = content_length(&buf[..head_end]);
+                    while buf.len() < head_end + body_len {
+                        match socket.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+
+                    let head = String::from_utf8_lossy(&buf[..head_end]);
+                    let request_line = head.lines().next().unwrap_or_default().to_string();
+                    let cancel = request_line.contains("/cancel");
+                    log.lock().unwrap().push(request_line);
+
+                    if cancel {
+                        let reply = "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 2\r\nconnection: close\r\n\r\n{}";
+                        let _ = socket.write_all(reply.as_bytes()).await;
+                        let _ = socket.shutdown().await;
+                        return;
+                    }
+
+                    let head = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n";
+                    if socket.write_all(head.as_bytes()).await.is_err()
+                        || socket.write_all(events.as_bytes()).await.is_err()
+                    {
+                        return;
+                    }
+                    // Wait for the client to hang up
+                    while let Ok(n) = socket.read(&mut chunk).await {
+                        if n == 0 {
+                            break;
+                        }
+                    }
+                    close_signal.notify_one();
+                });
+            }
+        });
+
+        Self {
+            url: format!("http://{}", addr),
+            requests,
+            closed,
+        }
+    }
+
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Raw SSE response head; with `declared_len` larger than the body that
+/// follows, the client sees the connection drop mid-stream
+pub fn sse_head(declared_len: usize) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+        declared_len
+    )
+}
+
+/// Index just past the `\r\n\r\n` terminating the request head
+fn find_header_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|w| w == b"\r\n\r\n").map(|p| p + 4)
+}
+
+/// Value of the Content-Length header in a request head, or 0
+fn content_length(head: &[u8]) -> usize {
+    String::from_utf8_lossy(head)
+        .lines()
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            if name.eq_ignore_ascii_case("content-length") {
+                value.trim().parse().ok()
+            } else {
+                None
+            }
+        })
+        .unwrap_or(0)
+}
+
+/// Error of connecting to a port nothing listens on
+#[cfg(feature = "tower")]
+pub async fn connect_error() -> twcai::TwcError {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    twcai::TwcError::Connect(client.get(url).send().await.unwrap_err())
+}
+
+/// Transport layer failing the requests at the given positions (counting
+/// from 0) with a connect error, as if the server refused the connection;
+/// the other requests go through
+#[cfg(feature = "tower")]
+#[derive(Clone)]
+pub struct RefuseLayer {
+    refused: Arc<[usize]>,
+    count: Arc<AtomicUsize>,
+}
+
+#[cfg(feature = "tower")]
+impl RefuseLayer {
+    pub fn new(refused: &[usize]) -> Self {
+        Self {
+            refused: refused.into(),
+            count: Arc::default(),
+        }
+    }
+}
+
+#[cfg(feature = "tower")]
+impl<S> tower::Layer<S> for RefuseLayer {
+    type Service = RefuseService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RefuseService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Service built by [`RefuseLayer`]
+#[cfg(feature = "tower")]
+#[derive(Clone)]
+pub struct RefuseService<S> {
+    inner: S,
+    layer: RefuseLayer,
+}
+
+#[cfg(feature = "tower")]
+impl<S> tower::Service<reqwest::Request> for RefuseService<S>
+where
+    S: tower::Service<reqwest::Request, Response = reqwest::Response, Error = twcai::TwcError>,
+    S::Future: Send + 'static,
+{
+    type Response = reqwest::Response;
+    type Error = twcai::TwcError;
+    type Future = std::pin::Pin<
+        Box<dyn std::future::Future<Output = twcai::Result<reqwest::Response>> + Send>,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<twcai::Result<()>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: reqwest::Request) -> Self::Future {
+        let position = self.layer.count.fetch_add(1, Ordering::SeqCst);
+        if self.layer.refused.contains(&position) {
+            Box::pin(async { Err(connect_error().await) })
+        } else {
+            Box::pin(self.inner.call(request))
+        }
+    }
+}
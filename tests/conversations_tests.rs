@@ -0,0 +1,1406 @@
+//! Conversations API tests for TWCai
+
+mod common;
+
+/// Content item tagging tests
+#[cfg(test)]
+mod content_item {
+    use serde_json::json;
+    use twcai::types::*;
+
+    #[test]
+    fn test_tag_derived_from_variant() {
+        let items = vec![
+            ContentItem::Text(TextContent::new("Hi")),
+            ContentItem::ImageUrl(
+                ImageUrlContent::new("https://example.com/cat.png").detail(ImageDetail::Low),
+            ),
+            ContentItem::InputAudio(InputAudioContent::new(
+                InputAudio::from_bytes(b"RIFF", AudioFormat::Wav).unwrap(),
+            )),
+            ContentItem::File(FileContent::new(FilePart::by_id("file-1"))),
+            ContentItem::Refusal(RefusalContent::new("No.")),
+        ];
+
+        assert_eq!(
+            serde_json::to_value(&items).unwrap(),
+            json!([
+                {"type": "text", "text": "Hi"},
+                {"type": "image_url", "image_url": {"url": "https://example.com/cat.png", "detail": "low"}},
+                {"type": "input_audio", "input_audio": {"data": "UklGRg==", "format": "wav"}},
+                {"type": "file", "file": {"file_id": "file-1"}},
+                {"type": "refusal", "refusal": "No."}
+            ])
+        );
+        let tags: Vec<_> = items.iter().map(ContentItem::content_type).collect();
+        assert_eq!(
+            tags,
+            ["text", "image_url", "input_audio", "file", "refusal"]
+        );
+
+        let back: Vec<ContentItem> =
+            serde_json::from_value(serde_json::to_value(&items).unwrap()).unwrap();
+        assert_eq!(back, items);
+    }
+
+    #[test]
+    fn test_legacy_parts_still_load() {
+        let items: Vec<ContentItem> = serde_json::from_value(json!([
+            {"text": "untagged"},
+            {"type": "input_text", "text": "responses style"},
+            {"type": "output_text", "text": "answer", "annotations": []},
+            {"image_url": {"url": "https://example.com/cat.png"}},
+            {"type": "something_new", "refusal": "No."}
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            items,
+            [
+                ContentItem::Text(TextContent::new("untagged")),
+                ContentItem::Text(TextContent::new("responses style")),
+                ContentItem::Text(TextContent::new("answer")),
+                ContentItem::ImageUrl(ImageUrlContent::new("https://example.com/cat.png")),
+                ContentItem::Refusal(RefusalContent::new("No.")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unrecognized_part_fails() {
+        let err = serde_json::from_value::<ContentItem>(json!({"type": "video", "video": {}}))
+            .unwrap_err();
+        assert!(err.to_string().contains("unrecognized content item"));
+
+        assert!(serde_json::from_value::<ContentItem>(json!({"type": "text"})).is_err());
+    }
+}
+
+/// Conversation item content tests
+#[cfg(test)]
+mod conversation_content {
+    use serde_json::json;
+    use twcai::types::*;
+
+    #[test]
+    fn test_create_conversation_with_image_golden() {
+        let request = CreateConversationRequest {
+            items: Some(vec![ConversationItemMessage::user_with_image(
+                "What is in this picture?",
+                "https://example.com/cat.png",
+            )]),
+            metadata: None,
+        };
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "items": [{
+                    "type": "message",
+                    "role": "user",
+                    "content": [
+                        {"type": "input_text", "text": "What is in this picture?"},
+                        {"type": "input_image", "image_url": "https://example.com/cat.png"}
+                    ]
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn test_create_items_with_image_and_file_golden() {
+        let request = CreateItemsRequest {
+            items: vec![CreateItemRequest {
+                item_type: "message".to_string(),
+                role: "user".to_string(),
+                content: vec![
+                    ConversationItemContent::input_image(
+                        "data:image/png;base64,iVBORw0KGgo=",
+                        Some(ImageDetail::High),
+                    ),
+                    ConversationItemContent::InputImage {
+                        image_url: None,
+                        file_id: Some("file-img".to_string()),
+                        detail: None,
+                    },
+                    ConversationItemContent::input_file("file-pdf"),
+                ],
+            }],
+        };
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "items": [{
+                    "type": "message",
+                    "role": "user",
+                    "content": [
+                        {
+                            "type": "input_image",
+                            "image_url": "data:image/png;base64,iVBORw0KGgo=",
+                            "detail": "high"
+                        },
+                        {"type": "input_image", "file_id": "file-img"},
+                        {"type": "input_file", "file_id": "file-pdf"}
+                    ]
+                }]
+            })
+        );
+    }
+
+    #[test]
+    fn test_item_with_image_deserializes() {
+        let item: ConversationItem = serde_json::from_value(json!({
+            "type": "message",
+            "id": "msg_1",
+            "status": "completed",
+            "role": "user",
+            "content": [
+                {"type": "input_text", "text": "Describe it"},
+                {
+                    "type": "input_image",
+                    "image_url": "https://example.com/cat.png",
+                    "detail": "auto"
+                },
+                {"type": "input_file", "file_id": "file-1", "filename": "report.pdf"}
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            item.content,
+            [
+                ConversationItemContent::input_text("Describe it"),
+                ConversationItemContent::input_image(
+                    "https://example.com/cat.png",
+                    Some(ImageDetail::Auto)
+                ),
+                ConversationItemContent::InputFile {
+                    file_id: Some("file-1".to_string()),
+                    file_url: None,
+                    file_data: None,
+                    filename: Some("report.pdf".to_string()),
+                },
+            ]
+        );
+        assert_eq!(item.content[0].text(), Some("Describe it"));
+        assert_eq!(item.content[1].text(), None);
+    }
+
+    #[test]
+    fn test_unknown_part_round_trips() {
+        let part = json!({"type": "input_audio", "input_audio": {"data": "AAAA", "format": "wav"}});
+
+        let content: ConversationItemContent = serde_json::from_value(part.clone()).unwrap();
+
+        assert_eq!(content.content_type(), "input_audio");
+        assert!(matches!(content, ConversationItemContent::Other(_)));
+        assert_eq!(serde_json::to_value(&content).unwrap(), part);
+    }
+
+    #[test]
+    fn test_message_constructors() {
+        let user = ConversationItemMessage::user("Hi");
+        assert_eq!(user.item_type, "message");
+        assert_eq!(user.role, "user");
+        assert_eq!(user.content, [ConversationItemContent::input_text("Hi")]);
+
+        let assistant = ConversationItemMessage::assistant("Hello");
+        assert_eq!(assistant.role, "assistant");
+        assert_eq!(assistant.content[0].content_type(), "output_text");
+    }
+}
+
+/// Conversation reference tests
+#[cfg(test)]
+mod conversation_ref {
+    use serde_json::json;
+    use twcai::TwcError;
+    use twcai::types::*;
+
+    #[test]
+    fn test_id_wire_shape() {
+        let conversation: ConversationRef = serde_json::from_value(json!("conv_123")).unwrap();
+
+        assert_eq!(conversation, ConversationRef::Id("conv_123".to_string()));
+        assert_eq!(conversation.id(), "conv_123");
+        assert_eq!(
+            serde_json::to_value(&conversation).unwrap(),
+            json!("conv_123")
+        );
+    }
+
+    #[test]
+    fn test_object_wire_shape() {
+        let conversation: ConversationRef =
+            serde_json::from_value(json!({"id": "conv_123"})).unwrap();
+
+        assert_eq!(
+            conversation,
+            ConversationRef::Object {
+                id: "conv_123".to_string()
+            }
+        );
+        assert_eq!(conversation.id(), "conv_123");
+        assert_eq!(
+            serde_json::to_value(&conversation).unwrap(),
+            json!({"id": "conv_123"})
+        );
+    }
+
+    #[test]
+    fn test_request_reads_both_shapes() {
+        for conversation in [json!("conv_1"), json!({"id": "conv_1"})] {
+            let request: CreateResponseRequest =
+                serde_json::from_value(json!({"input": "Hi", "conversation": conversation}))
+                    .unwrap();
+            assert_eq!(
+                request.conversation.as_ref().map(ConversationRef::id),
+                Some("conv_1")
+            );
+            assert_eq!(
+                serde_json::to_value(&request).unwrap()["conversation"],
+                conversation
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_conversation() {
+        let conversation: Conversation = serde_json::from_value(json!({
+            "id": "conv_abc",
+            "object": "conversation",
+            "created_at": 1741900000,
+            "metadata": {"topic": "demo"}
+        }))
+        .unwrap();
+
+        assert_eq!(
+            ConversationRef::from(&conversation),
+            ConversationRef::Id("conv_abc".to_string())
+        );
+        let request = CreateResponseRequest::builder()
+            .input_text("Hi")
+            .conversation(&conversation)
+            .build()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(&request).unwrap()["conversation"],
+            "conv_abc"
+        );
+    }
+
+    #[test]
+    fn test_empty_id_rejected_by_builder() {
+        for id in ["", "  "] {
+            let result = CreateResponseRequest::builder().conversation_id(id).build();
+            assert!(
+                matches!(result, Err(TwcError::InvalidRequest(_))),
+                "{:?}",
+                result
+            );
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_empty_id_rejected_before_sending() {
+        use twcai::api::ResponsesExt;
+        use twcai::testing::FixtureTransport;
+
+        use crate::common::client_with;
+
+        let fixtures = FixtureTransport::new();
+        let client = client_with(&fixtures);
+
+        let request = CreateResponseRequest {
+            input: Some(ResponseInput::Text("Hi".to_string())),
+            conversation: Some(ConversationRef::Object { id: String::new() }),
+            ..Default::default()
+        };
+        let result = client.create_response("agent-1", request).await;
+
+        assert!(
+            matches!(result, Err(TwcError::InvalidRequest(_))),
+            "{:?}",
+            result
+        );
+        assert!(fixtures.requests().is_empty());
+    }
+}
+
+/// `send_to_conversation` tests
+#[cfg(all(test, feature = "testing"))]
+mod conversation_turn {
+    use reqwest::Method;
+    use serde_json::json;
+    use twcai::api::ConversationsExt;
+    use twcai::testing::{Fixture, FixtureTransport};
+
+    use crate::common::client_with;
+
+    #[tokio::test]
+    async fn test_send_to_conversation_returns_reply() {
+        let fixtures = FixtureTransport::new().route(
+            Method::POST,
+            "/api/v1/cloud-ai/agents/agent-1/v1/responses",
+            Fixture::raw(
+                200,
+                "application/json",
+                r#"{
+                    "id": "resp_1",
+                    "object": "response",
+                    "created_at": 1700000000,
+                    "model": "gpt-4.1",
+                    "status": "completed",
+                    "usage": {"prompt_tokens": 5, "completion_tokens": 6, "total_tokens": 11},
+                    "output": [
+                        {"type": "reasoning", "id": "rs_1", "summary": []},
+                        {
+                            "type": "message",
+                            "id": "msg_1",
+                            "status": "completed",
+                            "role": "assistant",
+                            "content": [
+                                {"type": "output_text", "text": "A systems ", "annotations": []},
+                                {"type": "output_text", "text": "language.", "annotations": []}
+                            ]
+                        }
+                    ]
+                }"#,
+            ),
+        );
+
+        let turn = client_with(&fixtures)
+            .send_to_conversation("agent-1", "conv_123", "What is Rust?")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fixtures.requests()[0].json().unwrap(),
+            json!({
+                "input": "What is Rust?",
+                "conversation": "conv_123"
+            })
+        );
+        assert_eq!(turn.text, "A systems language.");
+        assert_eq!(turn.items.len(), 2);
+        assert_eq!(turn.items[1]["id"], "msg_1");
+        assert_eq!(turn.response.id, "resp_1");
+    }
+}
+
+/// Conversation export tests
+#[cfg(test)]
+mod export {
+    use serde_json::{Value, json};
+    use twcai::export::{self, ExportFormat};
+    use twcai::types::Conversation;
+
+    const CONVERSATION: &str = include_str!("fixtures/export/conversation_fixture.json");
+    const ITEMS: &str = include_str!("fixtures/export/items_fixture.json");
+    const EXPECTED_JSON: &str = include_str!("fixtures/export/expected.json");
+    const EXPECTED_MARKDOWN: &str = include_str!("fixtures/export/expected.md");
+
+    #[cfg(feature = "testing")]
+    const ITEMS_PATH: &str = "/api/v1/cloud-ai/agents/agent-1/v1/conversations/conv_123/items";
+
+    fn fixture() -> (Conversation, Vec<Value>) {
+        (
+            serde_json::from_str(CONVERSATION).unwrap(),
+            serde_json::from_str(ITEMS).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_render_json_golden() {
+        let (conversation, items) = fixture();
+        let output = export::render(&conversation, &items, ExportFormat::Json).unwrap();
+        assert_eq!(output, EXPECTED_JSON);
+    }
+
+    #[test]
+    fn test_render_markdown_golden() {
+        let (conversation, items) = fixture();
+        let output = export::render(&conversation, &items, ExportFormat::Markdown).unwrap();
+        assert_eq!(output, EXPECTED_MARKDOWN);
+    }
+
+    #[test]
+    fn test_markdown_fence_outgrows_backticks() {
+        let (conversation, _) = fixture();
+        let items = vec![json!({
+            "type": "message",
+            "role": "assistant",
+            "content": [{"type": "output_text", "text": "````\nnested\n````"}]
+        })];
+        let output = export::render(&conversation, &items, ExportFormat::Markdown).unwrap();
+        assert!(output.contains("`````text\n````\nnested\n````\n`````\n"));
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_export_conversation_follows_pagination() {
+        use reqwest::Method;
+        use twcai::api::ConversationsExt;
+        use twcai::testing::{Fixture, FixtureTransport};
+
+        use crate::common::client_with;
+
+        let (_, items) = fixture();
+        let (first, second) = items.split_at(2);
+
+        let fixtures = FixtureTransport::new()
+            .route(
+                Method::GET,
+                "/api/v1/cloud-ai/agents/agent-1/v1/conversations/conv_123",
+                Fixture::raw(200, "application/json", CONVERSATION),
+            )
+            .route_once(
+                Method::GET,
+                ITEMS_PATH,
+                Fixture::json(
+                    200,
+                    &json!({
+                        "object": "list",
+                        "data": first,
+                        "first_id": "item_1",
+                        "last_id": "item_2",
+                        "has_more": true
+                    }),
+                ),
+            )
+            .route_once(
+                Method::GET,
+                ITEMS_PATH,
+                Fixture::json(
+                    200,
+                    &json!({
+                        "object": "list",
+                        "data": second,
+                        "first_id": "item_3",
+                        "last_id": "item_4",
+                        "has_more": false
+                    }),
+                ),
+            );
+
+        let output = client_with(&fixtures)
+            .export_conversation("agent-1", "conv_123", ExportFormat::Json)
+            .await
+            .unwrap();
+
+        let pages: Vec<_> = fixtures
+            .requests()
+            .into_iter()
+            .filter(|request| request.path == ITEMS_PATH)
+            .map(|request| request.query)
+            .collect();
+        assert_eq!(
+            pages,
+            [
+                Some("limit=100&order=asc".to_string()),
+                Some("after=item_2&limit=100&order=asc".to_string())
+            ]
+        );
+        assert_eq!(output, EXPECTED_JSON);
+    }
+}
+
+/// Conversation find-or-create tests
+#[cfg(test)]
+mod find_or_create {
+    use mockito::Matcher;
+    use serde_json::json;
+    use twcai::api::ConversationsExt;
+    use twcai::types::*;
+    use twcai::{ConversationIndex, MemoryConversationIndex, RequestOptions, TwcError};
+
+    use crate::common::client_for;
+
+    const CONVERSATIONS_PATH: &str = "/api/v1/cloud-ai/agents/agent-1/v1/conversations";
+
+    fn conversation(id: &str) -> String {
+        json!({
+            "id": id,
+            "object": "conversation",
+            "created_at": 1741900000,
+            "metadata": {"ticket": "T-1"}
+        })
+        .to_string()
+    }
+
+    fn init() -> CreateConversationRequest {
+        CreateConversationRequest {
+            items: Some(vec![ConversationItemMessage::user("Printer is on fire")]),
+            metadata: Some(json!({"source": "helpdesk"})),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_creates_once_then_finds() {
+        let mut server = mockito::Server::new_async().await;
+        let create = server
+            .mock("POST", CONVERSATIONS_PATH)
+            .match_body(Matcher::PartialJson(json!({
+                "metadata": {"source": "helpdesk", "ticket": "T-1"}
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(conversation("conv_1"))
+            .expect(1)
+            .create_async()
+            .await;
+        let get = server
+            .mock("GET", format!("{}/conv_1", CONVERSATIONS_PATH).as_str())
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(conversation("conv_1"))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = client_for(server.url());
+        let index = MemoryConversationIndex::new();
+        let key = ("ticket", "T-1");
+
+        let created = client
+            .find_or_create_conversation("agent-1", key, init(), &index)
+            .await
+            .unwrap();
+        let found = client
+            .find_or_create_conversation("agent-1", key, init(), &index)
+            .await
+            .unwrap();
+
+        create.assert_async().await;
+        get.assert_async().await;
+        assert_eq!(created.id, "conv_1");
+        assert_eq!(found.id, "conv_1");
+        assert_eq!(
+            index.lookup("ticket", "T-1").await.unwrap().as_deref(),
+            Some("conv_1")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_calls_create_one_conversation() {
+        let mut server = mockito::Server::new_async().await;
+        let create = server
+            .mock("POST", CONVERSATIONS_PATH)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(conversation("conv_1"))
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", format!("{}/conv_1", CONVERSATIONS_PATH).as_str())
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(conversation("conv_1"))
+            .create_async()
+            .await;
+
+        let client = client_for(server.url());
+        let other = client.clone();
+        let index = MemoryConversationIndex::new();
+        let key = ("ticket", "T-1");
+
+        let (first, second) = tokio::join!(
+            client.find_or_create_conversation("agent-1", key, init(), &index),
+            other.find_or_create_conversation("agent-1", key, init(), &index),
+        );
+
+        create.assert_async().await;
+        assert_eq!(first.unwrap().id, "conv_1");
+        assert_eq!(second.unwrap().id, "conv_1");
+    }
+
+    #[tokio::test]
+    async fn test_deleted_conversation_is_replaced() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", format!("{}/conv_gone", CONVERSATIONS_PATH).as_str())
+            .with_status(404)
+            .with_body(r#"{"message": "Conversation not found"}"#)
+            .create_async()
+            .await;
+        server
+            .mock("POST", CONVERSATIONS_PATH)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(conversation("conv_2"))
+            .create_async()
+            .await;
+
+        let index = MemoryConversationIndex::new();
+        index.store("ticket", "T-1", "conv_gone").await.unwrap();
+
+        let conversation = client_for(server.url())
+            .find_or_create_conversation("agent-1", ("ticket", "T-1"), init(), &index)
+            .await
+            .unwrap();
+
+        assert_eq!(conversation.id, "conv_2");
+        assert_eq!(
+            index.lookup("ticket", "T-1").await.unwrap().as_deref(),
+            Some("conv_2")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deleted_conversation_is_replaced_with_request_id() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", format!("{}/conv_gone", CONVERSATIONS_PATH).as_str())
+            .match_header("x-request-id", "req-42")
+            .with_status(404)
+            .with_body(r#"{"message": "Conversation not found"}"#)
+            .create_async()
+            .await;
+        let create = server
+            .mock("POST", CONVERSATIONS_PATH)
+            .match_header("x-request-id", "req-42")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(conversation("conv_2"))
+            .create_async()
+            .await;
+
+        let index = MemoryConversationIndex::new();
+        index.store("ticket", "T-1", "conv_gone").await.unwrap();
+
+        let conversation = client_for(server.url())
+            .find_or_create_conversation_with_options(
+                "agent-1",
+                ("ticket", "T-1"),
+                init(),
+                &index,
+                RequestOptions::new().request_id("req-42"),
+            )
+            .await
+            .unwrap();
+
+        create.assert_async().await;
+        assert_eq!(conversation.id, "conv_2");
+        assert_eq!(
+            index.lookup("ticket", "T-1").await.unwrap().as_deref(),
+            Some("conv_2")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_non_object_metadata_is_rejected() {
+        let server = mockito::Server::new_async().await;
+        let init = CreateConversationRequest {
+            metadata: Some(json!(["not", "an", "object"])),
+            ..Default::default()
+        };
+
+        let result = client_for(server.url())
+            .find_or_create_conversation(
+                "agent-1",
+                ("ticket", "T-1"),
+                init,
+                &MemoryConversationIndex::new(),
+            )
+            .await;
+
+        assert!(matches!(result, Err(TwcError::InvalidRequest(_))));
+    }
+}
+
+/// Conversation import tests
+#[cfg(test)]
+mod import {
+    use mockito::Matcher;
+    use serde_json::{Value, json};
+    use twcai::TwcError;
+    use twcai::api::ConversationsExt;
+    use twcai::export::ImportSource;
+
+    use crate::common::client_for;
+
+    const EXPORT: &str = include_str!("fixtures/export/expected.json");
+
+    const CONVERSATIONS_PATH: &str = "/api/v1/cloud-ai/agents/agent-1/v1/conversations";
+    const ITEMS_PATH: &str = "/api/v1/cloud-ai/agents/agent-1/v1/conversations/conv_new/items";
+
+    const CONVERSATION_BODY: &str =
+        r#"{"id":"conv_new","object":"conversation","created_at":1700000100}"#;
+    const ITEMS_BODY: &str =
+        r#"{"object":"list","data":[],"first_id":"","last_id":"","has_more":false}"#;
+
+    fn item(role: &str, content_type: &str, text: &str) -> Value {
+        json!({
+            "type": "message",
+            "role": role,
+            "content": [{"type": content_type, "text": text}]
+        })
+    }
+
+    #[tokio::test]
+    async fn test_import_messages_chunks_items() {
+        let mut messages = vec![json!({"role": "system", "content": "Be brief."})];
+        for i in 0..25 {
+            let role = if i % 2 == 0 { "user" } else { "assistant" };
+            messages.push(json!({"role": role, "content": format!("message {}", i)}));
+        }
+        messages
+            .push(json!({"role": "developer", "content": [{"type": "text", "text": "Use Rust."}]}));
+
+        let mut expected: Vec<Value> = vec![item("system", "input_text", "Be brief.\n\nUse Rust.")];
+        for i in 0..25 {
+            let (role, content_type) = if i % 2 == 0 {
+                ("user", "input_text")
+            } else {
+                ("assistant", "output_text")
+            };
+            expected.push(item(role, content_type, &format!("message {}", i)));
+        }
+
+        let (initial, remaining) = expected.split_at(20);
+
+        let mut server = mockito::Server::new_async().await;
+        let create = server
+            .mock("POST", CONVERSATIONS_PATH)
+            .match_body(Matcher::Json(json!({"items": initial})))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(CONVERSATION_BODY)
+            .expect(1)
+            .create_async()
+            .await;
+        let add = server
+            .mock("POST", ITEMS_PATH)
+            .match_body(Matcher::Json(json!({"items": remaining})))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(ITEMS_BODY)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let conversation = client_for(server.url())
+            .import_conversation("agent-1", ImportSource::Messages(messages))
+            .await
+            .unwrap();
+
+        assert_eq!(conversation.id, "conv_new");
+        create.assert_async().await;
+        add.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_import_export_document_keeps_metadata() {
+        let mut document: Value = serde_json::from_str(EXPORT).unwrap();
+        let items = document["items"].as_array_mut().unwrap();
+        // Function call items and image parts cannot be recreated
+        items.pop();
+        items[2]["content"].as_array_mut().unwrap().pop();
+
+        let mut server = mockito::Server::new_async().await;
+        let create = server
+            .mock("POST", CONVERSATIONS_PATH)
+            .match_body(Matcher::Json(json!({
+                "metadata": {"ticket": "T-42"},
+                "items": [
+                    item("user", "input_text", "Hello, can you help me with Rust lifetimes?"),
+                    item(
+                        "assistant",
+                        "output_text",
+                        "Sure! Here is an example:\n```rust\nfn longest<'a>(a: &'a str, b: &'a str) -> &'a str { a }\n```"
+                    ),
+                    item("user", "input_text", "What about this diagram?")
+                ]
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(CONVERSATION_BODY)
+            .expect(1)
+            .create_async()
+            .await;
+        let add = server
+            .mock("POST", ITEMS_PATH)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let source = ImportSource::from_json(&document.to_string()).unwrap();
+        client_for(server.url())
+            .import_conversation("agent-1", source)
+            .await
+            .unwrap();
+
+        create.assert_async().await;
+        add.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_import_reports_all_malformed_entries() {
+        let mut server = mockito::Server::new_async().await;
+        let create = server
+            .mock("POST", CONVERSATIONS_PATH)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let messages = vec![
+            json!({"role": "user", "content": "fine"}),
+            json!({"content": "no role"}),
+            json!({"role": "tool", "content": "result"}),
+            json!({"role": "assistant", "content": [{"type": "image_url", "image_url": {"url": "x"}}]}),
+        ];
+
+        let err = client_for(server.url())
+            .import_conversation("agent-1", ImportSource::Messages(messages))
+            .await
+            .unwrap_err();
+
+        match err {
+            TwcError::InvalidRequest(message) => {
+                assert!(!message.contains("entry 0"));
+                assert!(message.contains("entry 1: missing role"));
+                assert!(message.contains("entry 2: unsupported role \"tool\""));
+                assert!(message.contains("entry 3: content part 0 has no text"));
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+        create.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_unknown_format() {
+        let server = mockito::Server::new_async().await;
+        let source = ImportSource::from_json(r#"{"format":"other.v9","items":[]}"#).unwrap();
+
+        let err = client_for(server.url())
+            .import_conversation("agent-1", source)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, TwcError::InvalidRequest(ref m) if m.contains("other.v9")));
+        assert!(ImportSource::from_json("42").is_err());
+    }
+}
+
+/// Chat message to conversation item conversion tests
+#[cfg(test)]
+mod item_conversion {
+    use serde_json::json;
+    use twcai::TwcError;
+    use twcai::types::*;
+
+    fn convert(message: ChatMessage) -> CreateItemRequest {
+        CreateItemRequest::try_from(&message).unwrap()
+    }
+
+    #[test]
+    fn test_user_message() {
+        let item = convert(ChatMessage::user("Hello"));
+        assert_eq!(
+            serde_json::to_value(&item).unwrap(),
+            json!({
+                "type": "message",
+                "role": "user",
+                "content": [{"type": "input_text", "text": "Hello"}]
+            })
+        );
+    }
+
+    #[test]
+    fn test_system_and_developer_messages() {
+        let item = convert(ChatMessage::system("Be brief."));
+        assert_eq!(item.role, "system");
+        assert_eq!(item.content[0].content_type(), "input_text");
+
+        let item = convert(ChatMessage::developer("Be brief."));
+        assert_eq!(item.role, "developer");
+        assert_eq!(item.content[0].content_type(), "input_text");
+    }
+
+    #[test]
+    fn test_assistant_message() {
+        let item = convert(ChatMessage::assistant("Hi there"));
+        assert_eq!(item.role, "assistant");
+        assert_eq!(item.content[0].content_type(), "output_text");
+        assert_eq!(item.content[0].text(), Some("Hi there"));
+    }
+
+    #[test]
+    fn test_text_parts_kept() {
+        let message = ChatMessage::user_multimodal(vec![
+            ContentItem::Text(TextContent {
+                text: "one".to_string(),
+            }),
+            ContentItem::Text(TextContent {
+                text: "two".to_string(),
+            }),
+        ]);
+
+        let item = convert(message);
+        let texts: Vec<_> = item.content.iter().filter_map(|c| c.text()).collect();
+        assert_eq!(texts, ["one", "two"]);
+    }
+
+    #[test]
+    fn test_image_url_becomes_input_image() {
+        let message = ChatMessage::user_multimodal(vec![
+            ContentItem::Text(TextContent {
+                text: "What is this?".to_string(),
+            }),
+            ContentItem::ImageUrl(ImageUrlContent {
+                image_url: ImageUrl {
+                    url: "https://example.com/cat.png".to_string(),
+                    detail: Some(ImageDetail::Low),
+                },
+            }),
+        ]);
+
+        assert_eq!(
+            serde_json::to_value(convert(message)).unwrap(),
+            json!({
+                "type": "message",
+                "role": "user",
+                "content": [
+                    {"type": "input_text", "text": "What is this?"},
+                    {
+                        "type": "input_image",
+                        "image_url": "https://example.com/cat.png",
+                        "detail": "low"
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_multimodal_rejected() {
+        let message = ChatMessage::user_multimodal(vec![ContentItem::InputAudio(
+            InputAudioContent::new(InputAudio {
+                data: "AAAA".to_string(),
+                format: "wav".to_string(),
+            }),
+        )]);
+
+        match CreateItemRequest::try_from(&message).unwrap_err() {
+            TwcError::InvalidRequest(reason) => assert!(reason.contains("input_audio")),
+            other => panic!("expected InvalidRequest, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tool_message_rejected() {
+        let message = ChatMessage {
+            role: Role::Tool,
+            tool_call_id: Some("call_1".to_string()),
+            ..ChatMessage::user("{\"ok\":true}")
+        };
+
+        assert!(matches!(
+            CreateItemRequest::try_from(&message),
+            Err(TwcError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_messages_chunks_by_limit() {
+        let messages: Vec<_> = (0..45)
+            .map(|i| ChatMessage::user(format!("message {}", i)))
+            .collect();
+
+        let requests = CreateItemsRequest::from_messages(&messages).unwrap();
+        let sizes: Vec<_> = requests.iter().map(|r| r.items.len()).collect();
+        assert_eq!(sizes, [20, 20, 5]);
+        assert_eq!(requests[2].items[4].content[0].text(), Some("message 44"));
+    }
+
+    #[test]
+    fn test_from_messages_names_failing_index() {
+        let messages = vec![
+            ChatMessage::user("Hi"),
+            ChatMessage {
+                role: Role::Function,
+                ..ChatMessage::assistant("{}")
+            },
+        ];
+
+        match CreateItemsRequest::from_messages(&messages).unwrap_err() {
+            TwcError::InvalidRequest(reason) => assert!(reason.starts_with("message 1:")),
+            other => panic!("expected InvalidRequest, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_from_messages_empty() {
+        assert!(CreateItemsRequest::from_messages(&[]).unwrap().is_empty());
+    }
+}
+
+/// Conversation metadata tests
+#[cfg(test)]
+mod metadata {
+    use serde_json::json;
+    use twcai::types::*;
+
+    fn conversation(metadata: serde_json::Value) -> Conversation {
+        serde_json::from_value(json!({
+            "id": "conv_1",
+            "object": "conversation",
+            "created_at": 1741900000,
+            "metadata": metadata
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_flat_metadata_is_typed() {
+        let conversation = conversation(json!({"ticket": "T-1", "source": "helpdesk"}));
+        let metadata = conversation.metadata.unwrap();
+
+        let expected: Metadata = [("ticket", "T-1"), ("source", "helpdesk")]
+            .into_iter()
+            .collect();
+        assert_eq!(metadata.as_metadata(), Some(&expected));
+        assert_eq!(metadata.get("ticket"), Some("T-1"));
+        assert_eq!(metadata.get("missing"), None);
+        assert_eq!(
+            serde_json::to_value(&metadata).unwrap(),
+            json!({"ticket": "T-1", "source": "helpdesk"})
+        );
+    }
+
+    #[test]
+    fn test_nested_metadata_is_kept_raw() {
+        let raw = json!({"ticket": "T-1", "owner": {"team": "support"}, "priority": 2});
+        let conversation = conversation(raw.clone());
+        let metadata = conversation.metadata.unwrap();
+
+        assert_eq!(metadata, ConversationMetadata::MetadataRaw(raw.clone()));
+        assert_eq!(metadata.as_metadata(), None);
+        assert_eq!(metadata.get("ticket"), Some("T-1"));
+        assert_eq!(metadata.get("owner"), None);
+        assert_eq!(serde_json::to_value(&metadata).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_null_and_missing_metadata() {
+        assert_eq!(conversation(json!(null)).metadata, None);
+
+        let conversation: Conversation = serde_json::from_value(json!({
+            "id": "conv_1",
+            "object": "conversation",
+            "created_at": 1741900000
+        }))
+        .unwrap();
+        assert_eq!(conversation.metadata, None);
+    }
+
+    #[test]
+    fn test_empty_metadata_is_flat() {
+        let metadata = conversation(json!({})).metadata.unwrap();
+
+        assert_eq!(metadata.as_metadata(), Some(&Metadata::new()));
+    }
+
+    #[test]
+    fn test_metadata_builds_from_pairs() {
+        let mut metadata: Metadata = [("b", "2"), ("a", "1")].into_iter().collect();
+        assert_eq!(metadata.insert("a", "3"), Some("1".to_string()));
+
+        assert_eq!(metadata.len(), 2);
+        assert_eq!(
+            metadata.iter().collect::<Vec<_>>(),
+            [("a", "3"), ("b", "2")]
+        );
+        assert_eq!(
+            serde_json::to_value(&metadata).unwrap(),
+            json!({"a": "3", "b": "2"})
+        );
+    }
+}
+
+/// Pagination tests
+#[cfg(test)]
+mod pagination {
+    use std::sync::{Arc, Mutex};
+
+    use futures_util::StreamExt;
+    use serde_json::json;
+    use twcai::TwcError;
+    use twcai::pagination::{Page, Paginator};
+    use twcai::types::*;
+
+    #[cfg(feature = "testing")]
+    const ITEMS_PATH: &str = "/api/v1/cloud-ai/agents/agent-1/v1/conversations/conv_123/items";
+
+    fn item(id: &str) -> serde_json::Value {
+        json!({
+            "type": "message",
+            "id": id,
+            "status": "completed",
+            "role": "user",
+            "content": [{"type": "input_text", "text": id}]
+        })
+    }
+
+    #[test]
+    fn test_page_from_item_list() {
+        let list: ConversationItemList = serde_json::from_value(json!({
+            "object": "list",
+            "data": [item("item_1"), item("item_2")],
+            "first_id": "item_1",
+            "last_id": "item_2",
+            "has_more": true
+        }))
+        .unwrap();
+
+        let page = Page::from(list.clone());
+        assert_eq!(page.items, list.data);
+        assert!(page.has_more);
+        assert_eq!(page.next_cursor.as_deref(), Some("item_2"));
+
+        let last = Page::from(ConversationItemList {
+            has_more: false,
+            ..list
+        });
+        assert_eq!(last.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn test_paginator_walks_cursors() {
+        let cursors = Arc::new(Mutex::new(Vec::new()));
+        let seen = cursors.clone();
+
+        let paginator = Paginator::new(move |cursor: Option<String>| {
+            seen.lock().unwrap().push(cursor.clone());
+            async move {
+                Ok(match cursor.as_deref() {
+                    None => Page::new(vec![1, 2], true, Some("b".to_string())),
+                    Some("b") => Page::new(vec![], true, Some("c".to_string())),
+                    Some("c") => Page::new(vec![3], false, Some("d".to_string())),
+                    Some(other) => panic!("unexpected cursor {}", other),
+                })
+            }
+        });
+
+        let items: Vec<i32> = paginator.map(|item| item.unwrap()).collect().await;
+        assert_eq!(items, [1, 2, 3]);
+        assert_eq!(
+            *cursors.lock().unwrap(),
+            [None, Some("b".to_string()), Some("c".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_paginator_stops_after_error() {
+        let mut paginator = Paginator::new(|cursor: Option<String>| async move {
+            match cursor {
+                None => Ok(Page::new(vec!["a"], true, Some("a".to_string()))),
+                Some(_) => Err(TwcError::InvalidRequest("boom".to_string())),
+            }
+        });
+
+        assert_eq!(paginator.next().await.unwrap().unwrap(), "a");
+        assert!(matches!(
+            paginator.next().await,
+            Some(Err(TwcError::InvalidRequest(_)))
+        ));
+        assert!(paginator.next().await.is_none());
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_stream_conversation_items() {
+        use reqwest::Method;
+        use twcai::api::ConversationsExt;
+        use twcai::testing::{Fixture, FixtureTransport};
+
+        use crate::common::client_with;
+
+        let fixtures = FixtureTransport::new()
+            .route_once(
+                Method::GET,
+                ITEMS_PATH,
+                Fixture::raw(
+                    200,
+                    "application/json",
+                    list(vec![item("item_1"), item("item_2")], true),
+                ),
+            )
+            .route_once(
+                Method::GET,
+                ITEMS_PATH,
+                Fixture::raw(200, "application/json", list(vec![item("item_3")], false)),
+            );
+
+        let query = ListItemsQuery {
+            limit: Some(2),
+            order: Some("asc".to_string()),
+            ..Default::default()
+        };
+        let ids: Vec<String> = client_with(&fixtures)
+            .stream_conversation_items("agent-1", "conv_123", Some(query))
+            .map(|item| item.unwrap().id)
+            .collect()
+            .await;
+
+        assert_eq!(
+            queries(&fixtures),
+            ["limit=2&order=asc", "after=item_2&limit=2&order=asc"]
+        );
+        assert_eq!(ids, ["item_1", "item_2", "item_3"]);
+    }
+
+    #[cfg(feature = "testing")]
+    fn item_at(id: &str, created_at: i64) -> serde_json::Value {
+        let mut item = item(id);
+        item["created_at"] = json!(created_at);
+        item
+    }
+
+    #[cfg(feature = "testing")]
+    fn list(data: Vec<serde_json::Value>, has_more: bool) -> String {
+        let first_id = data.first().map(|item| item["id"].clone());
+        let last_id = data.last().map(|item| item["id"].clone());
+        json!({
+            "object": "list",
+            "data": data,
+            "first_id": first_id,
+            "last_id": last_id,
+            "has_more": has_more
+        })
+        .to_string()
+    }
+
+    /// Query strings of the requests received so far
+    #[cfg(feature = "testing")]
+    fn queries(fixtures: &twcai::testing::FixtureTransport) -> Vec<String> {
+        fixtures
+            .requests()
+            .into_iter()
+            .map(|request| request.query.unwrap_or_default())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_paginator_dedup_and_stop() {
+        let paginator = Paginator::new(|cursor: Option<String>| async move {
+            Ok(match cursor.as_deref() {
+                None => Page::new(vec![1, 2, 3], true, Some("3".to_string())),
+                Some("3") => Page::new(vec![2, 3, 4, 0, 5], false, None),
+                Some(other) => panic!("unexpected cursor {}", other),
+            })
+        })
+        .dedup_by(|n: &i32| *n)
+        .stop_when(|n| *n == 0);
+
+        let items: Vec<i32> = paginator.map(|item| item.unwrap()).collect().await;
+        assert_eq!(items, [1, 2, 3, 4]);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_stream_items_with_concurrent_append() {
+        use reqwest::Method;
+        use twcai::api::ConversationsExt;
+        use twcai::testing::{Fixture, FixtureTransport};
+
+        use crate::common::client_with;
+
+        let fixtures = FixtureTransport::new().route_once(
+            Method::GET,
+            ITEMS_PATH,
+            Fixture::raw(
+                200,
+                "application/json",
+                list(vec![item_at("item_1", 1000), item_at("item_2", 1001)], true),
+            ),
+        );
+
+        let client = client_with(&fixtures);
+        let query = ListItemsQuery {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let mut stream = client
+            .stream_conversation_items("agent-1", "conv_123", Some(query))
+            .snapshot();
+
+        let mut ids = vec![stream.next().await.unwrap().unwrap().id];
+
+        // A message is appended after the first page was fetched; the next
+        // page overlaps the previous one and ends with the new item
+        let appended = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64
+            + 60;
+        let _ = fixtures.clone().route_once(
+            Method::GET,
+            ITEMS_PATH,
+            Fixture::raw(
+                200,
+                "application/json",
+                list(
+                    vec![
+                        item_at("item_2", 1001),
+                        item_at("item_3", 1002),
+                        item_at("item_4", appended),
+                    ],
+                    false,
+                ),
+            ),
+        );
+
+        while let Some(item) = stream.next().await {
+            ids.push(item.unwrap().id);
+        }
+
+        assert_eq!(
+            queries(&fixtures),
+            ["limit=2&order=asc", "after=item_2&limit=2&order=asc"]
+        );
+        assert_eq!(ids, ["item_1", "item_2", "item_3"]);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_stream_items_sends_order_and_skips_duplicates() {
+        use reqwest::Method;
+        use twcai::api::ConversationsExt;
+        use twcai::testing::{Fixture, FixtureTransport};
+
+        use crate::common::client_with;
+
+        let fixtures = FixtureTransport::new()
+            .route_once(
+                Method::GET,
+                ITEMS_PATH,
+                Fixture::raw(
+                    200,
+                    "application/json",
+                    list(vec![item("item_1"), item("item_2")], true),
+                ),
+            )
+            .route_once(
+                Method::GET,
+                ITEMS_PATH,
+                Fixture::raw(
+                    200,
+                    "application/json",
+                    list(vec![item("item_1"), item("item_2"), item("item_3")], false),
+                ),
+            );
+
+        let ids: Vec<String> = client_with(&fixtures)
+            .stream_conversation_items("agent-1", "conv_123", None)
+            .map(|item| item.unwrap().id)
+            .collect()
+            .await;
+
+        assert_eq!(queries(&fixtures), ["order=asc", "after=item_2&order=asc"]);
+        assert_eq!(ids, ["item_1", "item_2", "item_3"]);
+    }
+}
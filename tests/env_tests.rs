@@ -0,0 +1,129 @@
+//! Environment configuration tests for TWCai
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use twcai::api::DefaultAgentExt;
+    use twcai::types::*;
+    use twcai::{CloudAIClient, TwcError};
+
+    const VARS: [&str; 5] = [
+        "TWCAI_BASE_URL",
+        "TWCAI_API_TOKEN",
+        "TWCAI_AGENT_ID",
+        "TWCAI_TIMEOUT_SECS",
+        "TWCAI_MAX_RETRIES",
+    ];
+
+    fn set_env(vars: &[(&str, &str)]) {
+        // SAFETY: this binary has a single test touching the environment
+        unsafe {
+            for name in VARS {
+                std::env::remove_var(name);
+            }
+            for (name, value) in vars {
+                std::env::set_var(name, value);
+            }
+        }
+    }
+
+    fn configuration_error(result: twcai::Result<CloudAIClient>) -> String {
+        match result {
+            Err(TwcError::Configuration(message)) => message,
+            Err(e) => panic!("expected configuration error, got {:?}", e),
+            Ok(_) => panic!("expected configuration error"),
+        }
+    }
+
+    // Process environment is global, so all cases run in one test
+    #[test]
+    fn test_from_env() {
+        set_env(&[]);
+        assert!(configuration_error(CloudAIClient::from_env()).contains("TWCAI_API_TOKEN"));
+
+        set_env(&[("TWCAI_API_TOKEN", "token")]);
+        let client = CloudAIClient::from_env().unwrap();
+        assert_eq!(client.config().base_url(), "https://agent.timeweb.cloud");
+        assert_eq!(client.config().timeout(), Duration::from_secs(120));
+        assert_eq!(client.config().max_retries(), 0);
+        assert_eq!(client.default_agent(), None);
+
+        set_env(&[
+            ("TWCAI_API_TOKEN", "env-token"),
+            ("TWCAI_BASE_URL", "http://localhost:8080"),
+            ("TWCAI_AGENT_ID", "agent-1"),
+            ("TWCAI_TIMEOUT_SECS", "30"),
+            ("TWCAI_MAX_RETRIES", "3"),
+        ]);
+        let client = CloudAIClient::from_env().unwrap();
+        assert_eq!(client.config().base_url(), "http://localhost:8080");
+        assert_eq!(client.config().token_hint(), "****oken");
+        assert_eq!(client.default_agent(), Some("agent-1"));
+        assert_eq!(client.config().timeout(), Duration::from_secs(30));
+        assert_eq!(client.config().max_retries(), 3);
+
+        // The builder can still be customized after reading the environment
+        let client = twcai::ClientBuilder::from_env()
+            .unwrap()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        assert_eq!(client.config().timeout(), Duration::from_secs(5));
+        assert_eq!(client.default_agent(), Some("agent-1"));
+
+        set_env(&[
+            ("TWCAI_API_TOKEN", "token"),
+            ("TWCAI_AGENT_ID", ""),
+            ("TWCAI_MAX_RETRIES", " "),
+        ]);
+        let client = CloudAIClient::from_env().unwrap();
+        assert_eq!(client.default_agent(), None);
+        assert_eq!(client.config().max_retries(), 0);
+
+        set_env(&[("TWCAI_API_TOKEN", "token"), ("TWCAI_TIMEOUT_SECS", "soon")]);
+        let message = configuration_error(CloudAIClient::from_env());
+        assert!(message.contains("TWCAI_TIMEOUT_SECS"));
+        assert!(message.contains("soon"));
+
+        set_env(&[("TWCAI_API_TOKEN", "token"), ("TWCAI_MAX_RETRIES", "-1")]);
+        assert!(configuration_error(CloudAIClient::from_env()).contains("TWCAI_MAX_RETRIES"));
+
+        set_env(&[]);
+    }
+
+    #[tokio::test]
+    async fn test_default_agent_required() {
+        let client = CloudAIClient::builder().token("token").build().unwrap();
+        let request = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hello")],
+            ..Default::default()
+        };
+
+        let result = client.chat(request).await;
+        assert!(matches!(result, Err(TwcError::Configuration(m)) if m.contains("TWCAI_AGENT_ID")));
+    }
+
+    #[tokio::test]
+    async fn test_default_agent_used() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/v1/cloud-ai/agents/agent-1/call")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"message": "Hi", "id": "msg-1", "finish_reason": "stop"}"#)
+            .create_async()
+            .await;
+
+        let client = CloudAIClient::builder()
+            .base_url(server.url())
+            .token("token")
+            .default_agent("agent-1")
+            .build()
+            .unwrap();
+
+        let response = client.call(AgentCallRequest::new("Hello")).await.unwrap();
+        assert_eq!(response.message, "Hi");
+        mock.assert_async().await;
+    }
+}
@@ -0,0 +1,452 @@
+//! Files, downloads and knowledge base tests for TWCai
+
+mod common;
+
+/// Files API tests
+#[cfg(test)]
+mod file_uploads {
+    use mockito::Matcher;
+    use twcai::api::FilesExt;
+
+    use crate::common::{builder_for, client_for};
+
+    const FILES_PATH: &str = "/api/v1/cloud-ai/agents/agent-1/v1/files";
+    const FILE_BODY: &str = r#"{
+        "id": "file-abc",
+        "object": "file",
+        "bytes": 11,
+        "created_at": 1700000000,
+        "filename": "notes.txt",
+        "purpose": "assistants"
+    }"#;
+
+    fn multipart_body_matcher() -> Matcher {
+        Matcher::AllOf(vec![
+            Matcher::Regex(r#"name="purpose"\r\n\r\nassistants\r\n"#.to_string()),
+            Matcher::Regex(r#"name="file"; filename="notes.txt""#.to_string()),
+            Matcher::Regex("\r\n\r\nhello world\r\n".to_string()),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_sends_multipart() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", FILES_PATH)
+            .match_header(
+                "content-type",
+                Matcher::Regex("^multipart/form-data; boundary=".to_string()),
+            )
+            .match_body(multipart_body_matcher())
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(FILE_BODY)
+            .create_async()
+            .await;
+
+        let file = client_for(server.url())
+            .upload_file(
+                "agent-1",
+                "notes.txt",
+                b"hello world".to_vec(),
+                "assistants",
+            )
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(file.id, "file-abc");
+        assert_eq!(file.bytes, 11);
+        assert_eq!(file.purpose.as_deref(), Some("assistants"));
+    }
+
+    #[tokio::test]
+    async fn test_upload_file_stream_from_reader() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", FILES_PATH)
+            .match_body(multipart_body_matcher())
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(FILE_BODY)
+            .create_async()
+            .await;
+
+        let reader = std::io::Cursor::new(b"hello world".to_vec());
+        let file = client_for(server.url())
+            .upload_file_stream("agent-1", "notes.txt", reader, Some(11), "assistants")
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(file.filename, "notes.txt");
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn test_upload_failure_carries_request_id() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", FILES_PATH)
+            .match_header("x-request-id", Matcher::Regex("^[0-9a-f]{16}$".to_string()))
+            .match_body(multipart_body_matcher())
+            .with_status(413)
+            .create_async()
+            .await;
+
+        let _subscriber = tracing::subscriber::set_default(tracing_subscriber::registry());
+        let span = tracing::info_span!("upload");
+        let _entered = span.enter();
+
+        let client = builder_for(server.url())
+            .request_id_from_span(true)
+            .build()
+            .unwrap();
+        let err = client
+            .upload_file(
+                "agent-1",
+                "notes.txt",
+                b"hello world".to_vec(),
+                "assistants",
+            )
+            .await
+            .unwrap_err();
+
+        mock.assert_async().await;
+        assert!(err.request_id().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_list_get_delete_files() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", FILES_PATH)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(format!(r#"{{"object":"list","data":[{}]}}"#, FILE_BODY))
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/files/file-abc")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(FILE_BODY)
+            .create_async()
+            .await;
+        server
+            .mock(
+                "DELETE",
+                "/api/v1/cloud-ai/agents/agent-1/v1/files/file-abc",
+            )
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"id":"file-abc","object":"file","deleted":true}"#)
+            .create_async()
+            .await;
+
+        let client = client_for(server.url());
+        let files = client.list_files("agent-1").await.unwrap();
+        assert_eq!(files.data.len(), 1);
+        assert_eq!(
+            client
+                .get_file("agent-1", "file-abc")
+                .await
+                .unwrap()
+                .created_at,
+            1700000000
+        );
+        assert!(
+            client
+                .delete_file("agent-1", "file-abc")
+                .await
+                .unwrap()
+                .deleted
+        );
+    }
+}
+
+/// Streamed download tests
+#[cfg(test)]
+mod download {
+    use mockito::Matcher;
+    use reqwest::StatusCode;
+    use twcai::api::{AgentClientExt, ResponsesExt};
+    use twcai::types::*;
+    use twcai::{CloudAIClient, TwcError};
+
+    use crate::common::builder_for;
+
+    fn limited_client(server: &mockito::Server) -> CloudAIClient {
+        builder_for(server.url())
+            .max_response_bytes(64)
+            .build()
+            .unwrap()
+    }
+
+    /// Response JSON well past the client's 64-byte buffering limit
+    fn large_response() -> String {
+        let text = "lorem ipsum ".repeat(1000);
+        format!(
+            r#"{{"id":"resp_1","object":"response","status":"completed","output":[{{"type":"message","content":[{{"type":"output_text","text":"{}"}}]}}]}}"#,
+            text
+        )
+    }
+
+    #[tokio::test]
+    async fn test_get_response_raw_to_vec() {
+        let mut server = mockito::Server::new_async().await;
+        let body = large_response();
+        server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/responses/resp_1")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(&body)
+            .create_async()
+            .await;
+
+        let mut written = Vec::new();
+        let download = limited_client(&server)
+            .get_response_raw_to("agent-1", "resp_1", None, &mut written)
+            .await
+            .unwrap();
+
+        assert_eq!(download.status, StatusCode::OK);
+        assert_eq!(download.content_type.as_deref(), Some("application/json"));
+        assert_eq!(download.bytes, body.len() as u64);
+        assert_eq!(written, body.as_bytes());
+    }
+
+    #[tokio::test]
+    async fn test_get_response_raw_to_reports_error_status() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock(
+                "GET",
+                "/api/v1/cloud-ai/agents/agent-1/v1/responses/resp_404",
+            )
+            .with_status(404)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"error":{"message":"No response with id resp_404"}}"#)
+            .create_async()
+            .await;
+
+        let mut written = Vec::new();
+        let err = limited_client(&server)
+            .get_response_raw_to("agent-1", "resp_404", None, &mut written)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, TwcError::NotFound(ref m) if m == "No response with id resp_404"));
+        assert!(written.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_response_raw_to_rejects_stream() {
+        let server = mockito::Server::new_async().await;
+        let query = GetResponseQuery {
+            stream: Some(true),
+            ..Default::default()
+        };
+
+        let mut written = Vec::new();
+        let err = limited_client(&server)
+            .get_response_raw_to("agent-1", "resp_1", Some(query), &mut written)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, TwcError::InvalidRequest(_)));
+    }
+
+    #[tokio::test]
+    async fn test_get_embed_code_to_file() {
+        let mut server = mockito::Server::new_async().await;
+        let script = format!("window.twc = {{ config: \"{}\" }};", "x".repeat(4096));
+        server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/embed.js")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_header("content-type", "application/javascript")
+            .with_body(&script)
+            .create_async()
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("embed.js");
+        let mut file = tokio::fs::File::create(&path).await.unwrap();
+        let options = EmbedOptions::new("https://example.com", "https://example.com");
+        let download = limited_client(&server)
+            .get_embed_code_to("agent-1", options, &mut file)
+            .await
+            .unwrap();
+        drop(file);
+
+        assert_eq!(download.status, StatusCode::OK);
+        assert_eq!(
+            download.content_type.as_deref(),
+            Some("application/javascript")
+        );
+        assert_eq!(download.bytes, script.len() as u64);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), script);
+    }
+
+    #[tokio::test]
+    async fn test_get_embed_code_to_not_modified() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/embed.js")
+            .match_query(Matcher::Any)
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .create_async()
+            .await;
+
+        let cached = EmbedCode {
+            etag: Some("\"v1\"".to_string()),
+            ..Default::default()
+        };
+        let options =
+            EmbedOptions::new("https://example.com", "https://example.com").cached(&cached);
+        let mut written = Vec::new();
+        let download = limited_client(&server)
+            .get_embed_code_to("agent-1", options, &mut written)
+            .await
+            .unwrap();
+
+        assert_eq!(download.status, StatusCode::NOT_MODIFIED);
+        assert_eq!(download.bytes, 0);
+        assert!(written.is_empty());
+    }
+}
+
+/// Knowledge base tests
+#[cfg(test)]
+mod knowledge {
+    use mockito::Matcher;
+    use twcai::api::KnowledgeExt;
+    use twcai::types::*;
+    use twcai::{CloudAIClient, TwcError};
+
+    use crate::common::builder_for;
+
+    const DOCUMENTS_PATH: &str = "/api/v1/cloud-ai/knowledge-bases/7/documents";
+
+    fn control_plane_client(server: &mockito::Server) -> CloudAIClient {
+        builder_for("http://agents.invalid")
+            .control_plane_url(server.url())
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_list_documents_with_unknown_status() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", DOCUMENTS_PATH)
+            .match_header("authorization", "Bearer test-token")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                    "documents": [
+                        {"id": 1, "name": "faq.pdf", "status": "indexed", "size": 2048, "created_at": "2025-01-01T10:00:00Z"},
+                        {"id": 2, "name": "price.csv", "status": "chunking", "size": 512, "created_at": "2025-01-02T10:00:00Z"}
+                    ],
+                    "meta": {"total": 2}
+                }"#,
+            )
+            .create_async()
+            .await;
+
+        let documents = control_plane_client(&server)
+            .list_knowledge_documents(7)
+            .await
+            .unwrap();
+
+        assert_eq!(documents.len(), 2);
+        assert_eq!(documents[0].status, KnowledgeDocumentStatus::Indexed);
+        assert_eq!(
+            documents[1].status,
+            KnowledgeDocumentStatus::Other("chunking".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_upload_document_sets_mime_type() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", DOCUMENTS_PATH)
+            .match_body(Matcher::AllOf(vec![
+                Matcher::Regex(r#"filename="faq.md""#.to_string()),
+                Matcher::Regex("(?i)content-type: text/markdown".to_string()),
+                Matcher::Regex("# FAQ".to_string()),
+            ]))
+            .with_status(201)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"document": {"id": 3, "name": "faq.md", "status": "uploaded", "size": 5, "created_at": "2025-01-03T10:00:00Z"}}"#,
+            )
+            .create_async()
+            .await;
+
+        let document = control_plane_client(&server)
+            .upload_knowledge_document(7, "faq.md", b"# FAQ".to_vec(), "text/markdown")
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(document.id, 3);
+        assert_eq!(document.status, KnowledgeDocumentStatus::Uploaded);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn test_upload_failure_carries_request_id() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", DOCUMENTS_PATH)
+            .match_header("x-request-id", Matcher::Regex("^[0-9a-f]{16}$".to_string()))
+            .with_status(422)
+            .create_async()
+            .await;
+
+        let _subscriber = tracing::subscriber::set_default(tracing_subscriber::registry());
+        let span = tracing::info_span!("upload");
+        let _entered = span.enter();
+
+        let client = CloudAIClient::builder()
+            .base_url("http://agents.invalid")
+            .control_plane_url(server.url())
+            .token("test-token")
+            .request_id_from_span(true)
+            .build()
+            .unwrap();
+        let err = client
+            .upload_knowledge_document(7, "faq.md", b"# FAQ".to_vec(), "text/markdown")
+            .await
+            .unwrap_err();
+
+        mock.assert_async().await;
+        assert!(err.request_id().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_delete_document() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("DELETE", "/api/v1/cloud-ai/knowledge-bases/7/documents/3")
+            .with_status(204)
+            .create_async()
+            .await;
+        server
+            .mock("DELETE", "/api/v1/cloud-ai/knowledge-bases/7/documents/4")
+            .with_status(404)
+            .with_body(r#"{"message":"Document not found"}"#)
+            .create_async()
+            .await;
+
+        let client = control_plane_client(&server);
+        client.delete_knowledge_document(7, 3).await.unwrap();
+        let err = client.delete_knowledge_document(7, 4).await.unwrap_err();
+        assert!(matches!(err, TwcError::NotFound(_)));
+    }
+}
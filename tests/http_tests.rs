@@ -0,0 +1,1881 @@
+//! Shared request and response handling tests for TWCai
+
+mod common;
+
+/// Shared request and response handling tests
+#[cfg(test)]
+mod request_handling {
+    use mockito::Matcher;
+    use serde_json::json;
+    use twcai::api::{AgentClientExt, ConversationsExt, ResponsesExt};
+    use twcai::types::*;
+    use twcai::{CloudAIClient, TwcError};
+
+    use crate::common::builder_for;
+
+    const CONVERSATION_PATH: &str = "/api/v1/cloud-ai/agents/agent-1/v1/conversations/conv_123";
+
+    fn no_retry_client(url: &str) -> CloudAIClient {
+        builder_for(url).max_retries(0).build().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_success_decodes_json() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", CONVERSATION_PATH)
+            .match_header("authorization", "Bearer test-token")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({"id": "conv_123", "object": "conversation", "created_at": 1700000000})
+                    .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let conversation = no_retry_client(&server.url())
+            .get_conversation("agent-1", "conv_123")
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(conversation.id, "conv_123");
+    }
+
+    #[tokio::test]
+    async fn test_request_carries_query_and_body() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", format!("{}/items", CONVERSATION_PATH).as_str())
+            .match_query(Matcher::Exact(
+                "include[]=message.output_text.logprobs".to_string(),
+            ))
+            .match_header("content-type", "application/json")
+            .match_body(Matcher::PartialJson(
+                json!({"items": [{"type": "message", "role": "user"}]}),
+            ))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                json!({
+                    "object": "list",
+                    "data": [],
+                    "first_id": "",
+                    "last_id": "",
+                    "has_more": false
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let query = CreateItemsQuery {
+            include: Some(vec![Include::MessageOutputTextLogprobs]),
+        };
+        let request = CreateItemsRequest {
+            items: vec![CreateItemRequest::try_from(&ChatMessage::user("Hi")).unwrap()],
+        };
+        no_retry_client(&server.url())
+            .create_conversation_items("agent-1", "conv_123", request, Some(query))
+            .await
+            .unwrap();
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_empty_success_body_fails_to_decode() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", CONVERSATION_PATH)
+            .with_status(200)
+            .create_async()
+            .await;
+
+        let err = no_retry_client(&server.url())
+            .get_conversation("agent-1", "conv_123")
+            .await
+            .unwrap_err();
+
+        match err {
+            TwcError::Decode { snippet, .. } => assert_eq!(snippet, ""),
+            other => panic!("expected Decode, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expect_empty_tolerates_bodies() {
+        let cases: [(usize, &str); 4] = [
+            (204, r#"{"deleted": true}"#),
+            (200, ""),
+            (200, "  \r\n\t"),
+            (200, r#"{"id": "resp_1", "deleted": true}"#),
+        ];
+
+        let mut server = mockito::Server::new_async().await;
+        let client = no_retry_client(&server.url());
+
+        for (i, (status, body)) in cases.into_iter().enumerate() {
+            let id = format!("resp_{}", i);
+            let path = format!("/api/v1/cloud-ai/agents/agent-1/v1/responses/{}", id);
+            server
+                .mock("DELETE", path.as_str())
+                .with_status(status)
+                .with_body(body)
+                .create_async()
+                .await;
+
+            let result = client.delete_response("agent-1", &id).await;
+            assert!(result.is_ok(), "{} {:?}: {:?}", status, body, result);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_whitespace_body_fails_to_decode_struct() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", CONVERSATION_PATH)
+            .with_status(200)
+            .with_body(" \n")
+            .create_async()
+            .await;
+
+        let err = no_retry_client(&server.url())
+            .get_conversation("agent-1", "conv_123")
+            .await
+            .unwrap_err();
+
+        match err {
+            TwcError::Decode { source, snippet } => {
+                assert!(source.to_string().contains("null"), "{}", source);
+                assert_eq!(snippet, " \n");
+            }
+            other => panic!("expected Decode, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_error_with_json_body() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", CONVERSATION_PATH)
+            .with_status(404)
+            .with_header("content-type", "application/json")
+            .with_body(json!({"error": {"message": "Conversation not found"}}).to_string())
+            .create_async()
+            .await;
+
+        let err = no_retry_client(&server.url())
+            .get_conversation("agent-1", "conv_123")
+            .await
+            .unwrap_err();
+
+        match err {
+            TwcError::NotFound(message) => assert_eq!(message, "Conversation not found"),
+            other => panic!("expected NotFound, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_error_without_body() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock(
+                "DELETE",
+                "/api/v1/cloud-ai/agents/agent-1/v1/responses/resp_1",
+            )
+            .with_status(400)
+            .create_async()
+            .await;
+
+        let err = no_retry_client(&server.url())
+            .delete_response("agent-1", "resp_1")
+            .await
+            .unwrap_err();
+
+        match err {
+            TwcError::InvalidRequest(message) => assert_eq!(message, "Bad request"),
+            other => panic!("expected InvalidRequest, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_error_with_text_body() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .with_status(422)
+            .with_body("model list unavailable\n")
+            .create_async()
+            .await;
+
+        let err = no_retry_client(&server.url())
+            .list_models("agent-1")
+            .await
+            .unwrap_err();
+
+        match err {
+            TwcError::UnprocessableEntity(message) => {
+                assert_eq!(message, "model list unavailable")
+            }
+            other => panic!("expected UnprocessableEntity, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_error() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/api/v1/cloud-ai/agents/agent-1/call")
+            .with_status(503)
+            .with_body(json!({"message": "Upstream unavailable"}).to_string())
+            .create_async()
+            .await;
+
+        let err = no_retry_client(&server.url())
+            .call_agent("agent-1", AgentCallRequest::new("Hello"))
+            .await
+            .unwrap_err();
+
+        match err {
+            TwcError::ServerError {
+                status, message, ..
+            } => {
+                assert_eq!(status, 503);
+                assert_eq!(message, "Upstream unavailable");
+            }
+            other => panic!("expected ServerError, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_undecodable_body_keeps_snippet() {
+        let mut server = mockito::Server::new_async().await;
+        let body = format!(r#"{{"id": "conv_123", "padding": "{}""#, "x".repeat(1000));
+        server
+            .mock("GET", CONVERSATION_PATH)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(&body)
+            .create_async()
+            .await;
+
+        let err = no_retry_client(&server.url())
+            .get_conversation("agent-1", "conv_123")
+            .await
+            .unwrap_err();
+
+        match err {
+            TwcError::Decode { snippet, .. } => {
+                assert_eq!(snippet.len(), 256);
+                assert!(body.starts_with(&snippet));
+            }
+            other => panic!("expected Decode, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_invalid_utf8_bodies_are_decoded_lossily() {
+        let mut server = mockito::Server::new_async().await;
+        let body = b"{\"id\": \"conv\xff\xfe\"}".to_vec();
+        server
+            .mock("GET", CONVERSATION_PATH)
+            .with_status(200)
+            .with_body(&body)
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/api/v1/cloud-ai/agents/agent-1/call")
+            .with_status(502)
+            .with_header("content-type", "text/plain")
+            .with_body(b"bad \xc3\x28 gateway")
+            .create_async()
+            .await;
+
+        let client = no_retry_client(&server.url());
+        let err = client
+            .get_conversation("agent-1", "conv_123")
+            .await
+            .unwrap_err();
+        match err {
+            TwcError::Decode { snippet, .. } => {
+                assert_eq!(snippet, "{\"id\": \"conv\u{FFFD}\u{FFFD}\"}")
+            }
+            other => panic!("expected Decode, got {:?}", other),
+        }
+
+        let err = client
+            .call_agent("agent-1", AgentCallRequest::new("Hello"))
+            .await
+            .unwrap_err();
+        match err {
+            TwcError::ServerError {
+                status, message, ..
+            } => {
+                assert_eq!(status, 502);
+                assert_eq!(message, "bad \u{FFFD}( gateway");
+            }
+            other => panic!("expected ServerError, got {:?}", other),
+        }
+    }
+}
+
+/// Circuit breaker tests
+#[cfg(all(test, feature = "testing"))]
+mod circuit_breaker {
+    use std::time::Duration;
+
+    use reqwest::Method;
+    use serde_json::json;
+    use twcai::api::AgentClientExt;
+    use twcai::testing::{Fixture, FixtureTransport};
+    use twcai::{CircuitBreakerConfig, CloudAIClient, TwcError};
+
+    const MODELS: &str = "/api/v1/cloud-ai/agents/*/v1/models";
+
+    fn unavailable() -> Fixture {
+        Fixture::json(503, &json!({"error": {"message": "upstream down"}}))
+    }
+
+    /// `failures` one-shot 503 responses, then 200 for every request
+    fn transport(failures: usize) -> FixtureTransport {
+        let transport = (0..failures).fold(FixtureTransport::new(), |transport, _| {
+            transport.route_once(Method::GET, MODELS, unavailable())
+        });
+        transport.route(
+            Method::GET,
+            MODELS,
+            Fixture::json(200, &json!({"object": "list", "data": []})),
+        )
+    }
+
+    fn breaker_client(transport: &FixtureTransport) -> CloudAIClient {
+        CloudAIClient::builder()
+            .token("test-token")
+            .transport(transport.clone())
+            .circuit_breaker(CircuitBreakerConfig {
+                failure_threshold: 3,
+                open_duration: Duration::from_secs(30),
+                half_open_max: 1,
+            })
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_opens_after_consecutive_failures() {
+        let transport = transport(3);
+        let client = breaker_client(&transport);
+
+        for _ in 0..3 {
+            let err = client.list_models("agent-1").await.unwrap_err();
+            assert!(
+                matches!(err, TwcError::ServerError { status: 503, .. }),
+                "{:?}",
+                err
+            );
+        }
+
+        let err = client.list_models("agent-1").await.unwrap_err();
+        assert!(
+            matches!(err, TwcError::CircuitOpen(left) if left == Duration::from_secs(30)),
+            "{:?}",
+            err
+        );
+        assert_eq!(transport.requests().len(), 3);
+
+        tokio::time::advance(Duration::from_secs(29)).await;
+        let err = client.clone().list_models("agent-1").await.unwrap_err();
+        assert!(matches!(err, TwcError::CircuitOpen(left) if left == Duration::from_secs(1)));
+        assert_eq!(transport.requests().len(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_half_open_probe_decides() {
+        let transport = transport(4);
+        let client = breaker_client(&transport);
+        for _ in 0..3 {
+            client.list_models("agent-1").await.unwrap_err();
+        }
+
+        // A failing probe opens the circuit for another cool-down
+        tokio::time::advance(Duration::from_secs(30)).await;
+        let err = client.list_models("agent-1").await.unwrap_err();
+        assert!(matches!(err, TwcError::ServerError { .. }), "{:?}", err);
+        let err = client.list_models("agent-1").await.unwrap_err();
+        assert!(matches!(err, TwcError::CircuitOpen(_)), "{:?}", err);
+        assert_eq!(transport.requests().len(), 4);
+
+        // A succeeding probe closes it, for every clone of the client
+        tokio::time::advance(Duration::from_secs(30)).await;
+        client.list_models("agent-1").await.unwrap();
+        client
+            .with_token("other-token")
+            .list_models("agent-1")
+            .await
+            .unwrap();
+        assert_eq!(transport.requests().len(), 6);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_success_resets_failure_count() {
+        let transport = FixtureTransport::new()
+            .route_once(Method::GET, MODELS, unavailable())
+            .route_once(Method::GET, MODELS, unavailable())
+            .route_once(
+                Method::GET,
+                MODELS,
+                Fixture::json(404, &json!({"error": "no agent"})),
+            )
+            .route_once(Method::GET, MODELS, unavailable())
+            .route_once(Method::GET, MODELS, unavailable())
+            .route(
+                Method::GET,
+                MODELS,
+                Fixture::json(200, &json!({"object": "list", "data": []})),
+            );
+        let client = breaker_client(&transport);
+
+        for _ in 0..5 {
+            client.list_models("agent-1").await.unwrap_err();
+        }
+        client.list_models("agent-1").await.unwrap();
+        assert_eq!(transport.requests().len(), 6);
+    }
+
+    #[test]
+    fn test_invalid_config_rejected() {
+        let result = CloudAIClient::builder()
+            .token("test-token")
+            .circuit_breaker(CircuitBreakerConfig {
+                half_open_max: 0,
+                ..Default::default()
+            })
+            .build();
+        assert!(matches!(result, Err(TwcError::Configuration(_))));
+    }
+}
+
+/// Request compression tests
+#[cfg(all(test, feature = "testing"))]
+mod compression {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+    use reqwest::Method;
+    use twcai::api::AgentClientExt;
+    use twcai::testing::{Fixture, FixtureTransport};
+    use twcai::{CloudAIClient, TwcError, types::*};
+
+    use crate::common::builder_with;
+
+    const CHAT_PATH: &str = "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions";
+
+    const CHAT_RESPONSE: &str = r#"{
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "deepseek-reason",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "I hear birds."},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 10, "completion_tokens": 4, "total_tokens": 14}
+    }"#;
+
+    fn audio_request(len: usize) -> ChatCompletionRequest {
+        let data = "UklGRiQAAABXQVZFZm10IBAAAAABAAEA".repeat(len / 32 + 1);
+        ChatCompletionRequest {
+            messages: vec![ChatMessage::user_multimodal(vec![
+                ContentItem::Text(TextContent {
+                    text: "What do you hear?".to_string(),
+                }),
+                ContentItem::InputAudio(InputAudioContent {
+                    input_audio: InputAudio {
+                        data,
+                        format: "wav".to_string(),
+                    },
+                }),
+            ])],
+            ..Default::default()
+        }
+    }
+
+    fn chat_fixtures() -> FixtureTransport {
+        FixtureTransport::new().route(
+            Method::POST,
+            CHAT_PATH,
+            Fixture::raw(200, "application/json", CHAT_RESPONSE),
+        )
+    }
+
+    fn compressing_client(fixtures: &FixtureTransport, compress: bool) -> CloudAIClient {
+        builder_with(fixtures)
+            .compress_requests(compress)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_large_body_is_gzipped_when_enabled() {
+        let request = audio_request(2 * 1024 * 1024);
+        let original = serde_json::to_vec(&request).unwrap();
+        assert!(original.len() > 1024 * 1024);
+
+        let fixtures = chat_fixtures();
+        let client = compressing_client(&fixtures, true);
+        client.chat_completions("agent-1", request).await.unwrap();
+
+        let sent = &fixtures.requests()[0];
+        assert_eq!(sent.headers["content-encoding"], "gzip");
+        assert!(sent.body.len() < original.len());
+
+        let mut decoded = Vec::new();
+        GzDecoder::new(&sent.body[..])
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, original);
+    }
+
+    #[tokio::test]
+    async fn test_compression_off_by_default() {
+        let fixtures = chat_fixtures();
+        let client = compressing_client(&fixtures, false);
+        client
+            .chat_completions("agent-1", audio_request(2 * 1024 * 1024))
+            .await
+            .unwrap();
+
+        assert!(
+            !fixtures.requests()[0]
+                .headers
+                .contains_key("content-encoding")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_small_body_not_compressed() {
+        let fixtures = chat_fixtures();
+        let client = compressing_client(&fixtures, true);
+        let request = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hello!")],
+            ..Default::default()
+        };
+        client.chat_completions("agent-1", request).await.unwrap();
+
+        assert!(
+            !fixtures.requests()[0]
+                .headers
+                .contains_key("content-encoding")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_encoding_suggests_disabling_compression() {
+        let fixtures = FixtureTransport::new().route(
+            Method::POST,
+            CHAT_PATH,
+            Fixture::raw(415, "text/plain", ""),
+        );
+
+        let client = compressing_client(&fixtures, true);
+        let result = client
+            .chat_completions("agent-1", audio_request(2 * 1024 * 1024))
+            .await;
+
+        match result {
+            Err(TwcError::InvalidRequest(message)) => {
+                assert!(message.contains("compress_requests"));
+            }
+            other => panic!("expected InvalidRequest, got {:?}", other),
+        }
+    }
+}
+
+/// Concurrency limit tests
+#[cfg(test)]
+mod concurrency {
+    use std::time::Duration;
+
+    use twcai::api::AgentClientExt;
+    use twcai::types::*;
+    use twcai::{CloudAIClient, TwcError};
+
+    use crate::common::{SlowServer, builder_for};
+
+    const MODELS_BODY: &str = r#"{"object": "list", "data": []}"#;
+
+    fn limited(url: &str, max: usize) -> twcai::ClientBuilder {
+        builder_for(url).max_concurrent_requests(max)
+    }
+
+    fn chat() -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hi")],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_requests_are_capped() {
+        let server = SlowServer::start(MODELS_BODY, Duration::from_millis(150)).await;
+        let client = limited(&server.url, 2).build().unwrap();
+
+        let calls = (0..5).map(|_| {
+            let client = client.clone();
+            async move { client.list_models("agent-1").await }
+        });
+        let results = futures_util::future::join_all(calls).await;
+
+        assert!(results.iter().all(Result::is_ok));
+        assert_eq!(server.peak(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_queue_timeout() {
+        let server = SlowServer::start(MODELS_BODY, Duration::from_millis(300)).await;
+        let client = limited(&server.url, 1)
+            .queue_timeout(Duration::from_millis(50))
+            .build()
+            .unwrap();
+
+        let (first, second) =
+            tokio::join!(client.list_models("agent-1"), client.list_models("agent-1"),);
+
+        let errors: Vec<_> = [first, second]
+            .into_iter()
+            .filter_map(Result::err)
+            .collect();
+        assert_eq!(errors.len(), 1);
+        assert!(
+            matches!(errors[0], TwcError::QueueTimeout(wait) if wait == Duration::from_millis(50)),
+            "{:?}",
+            errors[0]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_releases_permit_after_headers() {
+        let server = SlowServer::start(MODELS_BODY, Duration::from_millis(10)).await;
+        let client = limited(&server.url, 1)
+            .queue_timeout(Duration::from_millis(500))
+            .build()
+            .unwrap();
+
+        let _stream = client
+            .chat_completions_stream("agent-1", chat())
+            .await
+            .unwrap();
+        client.list_models("agent-1").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stream_can_hold_permit() {
+        let server = SlowServer::start(MODELS_BODY, Duration::from_millis(10)).await;
+        let client = limited(&server.url, 1)
+            .queue_timeout(Duration::from_millis(100))
+            .hold_permit_while_streaming(true)
+            .build()
+            .unwrap();
+
+        let stream = client
+            .chat_completions_stream("agent-1", chat())
+            .await
+            .unwrap();
+        let err = client.list_models("agent-1").await.unwrap_err();
+        assert!(matches!(err, TwcError::QueueTimeout(_)), "{:?}", err);
+
+        drop(stream);
+        client.list_models("agent-1").await.unwrap();
+    }
+
+    #[test]
+    fn test_zero_limit_rejected() {
+        let result = CloudAIClient::builder()
+            .token("test-token")
+            .max_concurrent_requests(0)
+            .build();
+        assert!(matches!(result, Err(TwcError::Configuration(_))));
+    }
+}
+
+/// Error mapping tests
+#[cfg(all(test, feature = "testing"))]
+mod error {
+    use reqwest::Method;
+    use twcai::api::AgentClientExt;
+    use twcai::testing::{Fixture, FixtureTransport};
+    use twcai::{Result, TwcError, types::ModelsResponse};
+
+    use crate::common::client_with;
+
+    const MODELS_PATH: &str = "/api/v1/cloud-ai/agents/agent-1/v1/models";
+
+    async fn list_models_with_status(status: u16, body: &str) -> Result<ModelsResponse> {
+        let fixtures = FixtureTransport::new().route(
+            Method::GET,
+            MODELS_PATH,
+            Fixture::raw(status, "application/json", body.to_string()),
+        );
+
+        client_with(&fixtures).list_models("agent-1").await
+    }
+
+    #[tokio::test]
+    async fn test_payment_required() {
+        let err = list_models_with_status(402, r#"{"error":{"message":"Insufficient balance"}}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TwcError::PaymentRequired(ref m) if m == "Insufficient balance"));
+        assert!(!err.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let err = list_models_with_status(408, r#"{"message":"Timed out"}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TwcError::RequestTimeout(ref m) if m == "Timed out"));
+        assert!(err.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_conflict() {
+        let err = list_models_with_status(409, r#"{"detail":"Conversation is locked"}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TwcError::Conflict(ref m) if m == "Conversation is locked"));
+        assert!(!err.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_payload_too_large() {
+        let err = list_models_with_status(413, "Request Entity Too Large")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TwcError::PayloadTooLarge(ref m) if m == "Request Entity Too Large"));
+        assert!(!err.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_unprocessable_entity() {
+        let err =
+            list_models_with_status(422, r#"{"error":"detail must be one of low, high, auto"}"#)
+                .await
+                .unwrap_err();
+        assert!(matches!(
+            err,
+            TwcError::UnprocessableEntity(ref m) if m == "detail must be one of low, high, auto"
+        ));
+        assert!(!err.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_empty_body_uses_default_message() {
+        let err = list_models_with_status(409, "").await.unwrap_err();
+        assert!(matches!(err, TwcError::Conflict(ref m) if m == "Conflict"));
+    }
+
+    #[tokio::test]
+    async fn test_server_error_is_retryable() {
+        let err = list_models_with_status(503, r#"{"error":{"message":"Overloaded"}}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            TwcError::ServerError { status: 503, ref message, .. } if message == "Overloaded"
+        ));
+        assert!(err.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn test_other_client_errors_stay_invalid_request() {
+        let err = list_models_with_status(400, r#"{"error":{"message":"Bad field"}}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TwcError::InvalidRequest(ref m) if m == "Bad field"));
+        assert!(!err.is_retryable());
+        assert!(!TwcError::Unauthorized.is_retryable());
+    }
+}
+
+/// Non-JSON error body tests
+#[cfg(test)]
+mod error_body {
+    use twcai::api::AgentClientExt;
+    use twcai::{ClientBuilder, TwcError};
+
+    use crate::common::{ScriptedServer, builder_for};
+
+    const BAD_GATEWAY_PAGE: &str = "<!DOCTYPE html>\n<html>\n<head>\n  <title>502 Bad Gateway</title>\n  <style>body { color: red; }</style>\n</head>\n<body>\n  <center><h1>502 Bad Gateway</h1></center>\n  <hr><center>nginx &amp; friends</center>\n  <script>console.log('<b>ignored</b>');</script>\n</body>\n</html>\n";
+
+    async fn models_error(
+        status: &str,
+        content_type: Option<&str>,
+        body: &str,
+        builder: impl FnOnce(ClientBuilder) -> ClientBuilder,
+    ) -> TwcError {
+        let content_type = content_type
+            .map(|content_type| format!("content-type: {}\r\n", content_type))
+            .unwrap_or_default();
+        let server = ScriptedServer::start(vec![format!(
+            "HTTP/1.1 {}\r\n{}content-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        )])
+        .await;
+
+        let client = builder(builder_for(&server.url)).build().unwrap();
+        client.list_models("agent-1").await.unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn test_html_page_reduced_to_text() {
+        let err = models_error(
+            "502 Bad Gateway",
+            Some("text/html; charset=utf-8"),
+            BAD_GATEWAY_PAGE,
+            |b| b,
+        )
+        .await;
+
+        match err {
+            TwcError::ServerError {
+                status,
+                message,
+                content_type,
+            } => {
+                assert_eq!(status, 502);
+                assert_eq!(message, "502 Bad Gateway 502 Bad Gateway nginx & friends");
+                assert_eq!(content_type.as_deref(), Some("text/html; charset=utf-8"));
+            }
+            other => panic!("expected ServerError, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_untyped_html_detected() {
+        let page = "<html><body><h1>Gateway Timeout</h1></body></html>";
+        let err = models_error("504 Gateway Timeout", None, page, |b| b).await;
+
+        assert!(matches!(
+            err,
+            TwcError::ServerError { status: 504, ref message, content_type: None, .. }
+                if message == "Gateway Timeout"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_plain_text_truncated() {
+        let body = "upstream connect error ".repeat(10);
+        let err = models_error("503 Service Unavailable", Some("text/plain"), &body, |b| {
+            b.max_error_message_len(30)
+        })
+        .await;
+
+        match err {
+            TwcError::ServerError {
+                message,
+                content_type,
+                ..
+            } => {
+                assert_eq!(message, "upstream connect error upstre…");
+                assert_eq!(message.chars().count(), 30);
+                assert_eq!(content_type.as_deref(), Some("text/plain"));
+            }
+            other => panic!("expected ServerError, got {:?}", other),
+        }
+
+        let err = models_error("503 Service Unavailable", Some("text/plain"), &body, |b| {
+            b.max_error_message_len(1)
+        })
+        .await;
+        assert!(matches!(err, TwcError::ServerError { message, .. } if message == "…"));
+
+        let err = models_error("503 Service Unavailable", Some("text/plain"), &body, |b| {
+            b.max_error_message_len(0)
+        })
+        .await;
+        assert!(matches!(
+            err,
+            TwcError::ServerError { message, .. } if message == "Service Unavailable"
+        ));
+
+        let err = models_error("403 Forbidden", Some("text/plain"), &body, |b| b).await;
+        assert!(matches!(err, TwcError::Forbidden));
+        let err = models_error(
+            "400 Bad Request",
+            Some("text/plain"),
+            &"x".repeat(2000),
+            |b| b,
+        )
+        .await;
+        assert!(matches!(err, TwcError::InvalidRequest(ref m) if m.chars().count() == 501));
+    }
+
+    #[tokio::test]
+    async fn test_empty_bad_gateway() {
+        let err = models_error("502 Bad Gateway", None, "", |b| b).await;
+
+        assert!(matches!(
+            err,
+            TwcError::ServerError { status: 502, ref message, content_type: None }
+                if message == "Bad Gateway"
+        ));
+        assert!(err.is_retryable());
+    }
+}
+
+/// Default header tests
+#[cfg(test)]
+mod headers {
+    use mockito::Matcher;
+    use twcai::api::AgentClientExt;
+    use twcai::{CloudAIClient, TwcError};
+
+    use crate::common::{builder_for, client_for};
+
+    const MODELS_BODY: &str = r#"{"object": "list", "data": []}"#;
+
+    #[tokio::test]
+    async fn test_default_user_agent_and_accept() {
+        let mut server = mockito::Server::new_async().await;
+        let models = server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .match_header(
+                "user-agent",
+                format!("twcai/{}", env!("CARGO_PKG_VERSION")).as_str(),
+            )
+            .match_header("accept", "application/json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(MODELS_BODY)
+            .create_async()
+            .await;
+
+        let client = client_for(server.url());
+        client.list_models("agent-1").await.unwrap();
+
+        models.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_custom_headers_on_json_and_embed_requests() {
+        let mut server = mockito::Server::new_async().await;
+        let models = server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .match_header("user-agent", "acme-gateway/2.0")
+            .match_header("x-tenant", "acme")
+            .match_header("accept", "application/json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(MODELS_BODY)
+            .create_async()
+            .await;
+        let embed = server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/embed.js")
+            .match_query(Matcher::Any)
+            .match_header("user-agent", "acme-gateway/2.0")
+            .match_header("x-tenant", "acme")
+            .match_header("accept", "application/javascript")
+            .match_header("authorization", Matcher::Missing)
+            .with_status(200)
+            .with_header("content-type", "application/javascript")
+            .with_body("window.twc = {};")
+            .create_async()
+            .await;
+
+        let client = builder_for(server.url())
+            .user_agent("acme-gateway/2.0")
+            .default_header("x-tenant", "acme")
+            .build()
+            .unwrap();
+
+        client.list_models("agent-1").await.unwrap();
+        let code = client
+            .get_embed_code(
+                "agent-1",
+                Some(true),
+                "https://example.com",
+                "https://example.com",
+            )
+            .await
+            .unwrap();
+
+        models.assert_async().await;
+        embed.assert_async().await;
+        assert_eq!(code, "window.twc = {};");
+    }
+
+    #[test]
+    fn test_invalid_default_header_fails_build() {
+        let result = CloudAIClient::builder()
+            .token("test-token")
+            .default_header("bad header", "value")
+            .build();
+        assert!(matches!(result, Err(TwcError::Configuration(_))));
+
+        let result = CloudAIClient::builder()
+            .token("test-token")
+            .default_header("x-tenant", "line\nbreak")
+            .build();
+        assert!(matches!(result, Err(TwcError::Configuration(_))));
+    }
+}
+
+/// Idempotency key tests
+#[cfg(test)]
+mod idempotency {
+    use twcai::api::{AgentClientExt, ConversationsExt, ResponsesExt};
+    use twcai::types::*;
+    use twcai::{CloudAIClient, RequestOptions};
+
+    use crate::common::{ScriptedServer, builder_for};
+
+    const RESPONSE_BODY: &str = r#"{"id": "resp-1", "object": "response", "created_at": 1, "model": "gpt-4.1", "status": "completed", "output": []}"#;
+    const CONVERSATION_BODY: &str =
+        r#"{"id": "conv-1", "object": "conversation", "created_at": 1}"#;
+    const MODELS_BODY: &str = r#"{"object": "list", "data": []}"#;
+
+    fn raw(status: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
+    fn retrying_client(url: &str, max_retries: u32) -> CloudAIClient {
+        builder_for(url).max_retries(max_retries).build().unwrap()
+    }
+
+    fn request() -> CreateResponseRequest {
+        CreateResponseRequest {
+            input: Some(ResponseInput::Text("Hi".to_string())),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_explicit_key_reused_across_retries() {
+        let server = ScriptedServer::start(vec![
+            raw("503 Service Unavailable", ""),
+            raw("200 OK", RESPONSE_BODY),
+        ])
+        .await;
+
+        let response = retrying_client(&server.url, 1)
+            .create_response_with_options(
+                "agent-1",
+                request(),
+                RequestOptions::new().idempotency_key("create-42"),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.id, "resp-1");
+        assert_eq!(
+            server.header_values("idempotency-key"),
+            [Some("create-42".to_string()), Some("create-42".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_generated_key_reused_across_retries() {
+        let server = ScriptedServer::start(vec![
+            raw("503 Service Unavailable", ""),
+            raw("200 OK", CONVERSATION_BODY),
+        ])
+        .await;
+
+        retrying_client(&server.url, 1)
+            .create_conversation("agent-1", CreateConversationRequest::default())
+            .await
+            .unwrap();
+
+        let keys = server.header_values("idempotency-key");
+        assert_eq!(keys.len(), 2);
+        let key = keys[0].clone().expect("a key is generated");
+        assert_eq!(key.len(), 36);
+        assert_eq!(keys[1].as_deref(), Some(key.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_no_generated_key_without_retries_or_for_get() {
+        let server = ScriptedServer::start(vec![
+            raw("200 OK", CONVERSATION_BODY),
+            raw("200 OK", MODELS_BODY),
+            raw("200 OK", CONVERSATION_BODY),
+        ])
+        .await;
+
+        retrying_client(&server.url, 0)
+            .create_conversation("agent-1", CreateConversationRequest::default())
+            .await
+            .unwrap();
+        retrying_client(&server.url, 2)
+            .list_models("agent-1")
+            .await
+            .unwrap();
+        retrying_client(&server.url, 0)
+            .create_conversation_with_options(
+                "agent-1",
+                CreateConversationRequest::default(),
+                RequestOptions::new().idempotency_key("conv-key"),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            server.header_values("idempotency-key"),
+            [None, None, Some("conv-key".to_string())]
+        );
+    }
+}
+
+/// Request interceptor tests
+#[cfg(test)]
+mod interceptor {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use reqwest::header::HeaderValue;
+    use twcai::RequestInterceptor;
+    use twcai::api::AgentClientExt;
+
+    use crate::common::builder_for;
+
+    const MODELS_BODY: &str = r#"{"object":"list","data":[]}"#;
+
+    /// Adds a signature header derived from the body length
+    struct Signer;
+
+    impl RequestInterceptor for Signer {
+        fn on_request(&self, request: &mut reqwest::Request) {
+            let len = request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map_or(0, <[u8]>::len);
+            request.headers_mut().insert(
+                "x-signature",
+                HeaderValue::from_str(&format!("len-{}", len)).unwrap(),
+            );
+        }
+    }
+
+    #[derive(Default)]
+    struct Counter {
+        requests: AtomicUsize,
+        statuses: Mutex<Vec<u16>>,
+    }
+
+    impl RequestInterceptor for Counter {
+        fn on_request(&self, _request: &mut reqwest::Request) {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn on_response(&self, response: &reqwest::Response, _elapsed: Duration) {
+            self.statuses
+                .lock()
+                .unwrap()
+                .push(response.status().as_u16());
+        }
+    }
+
+    /// Appends its name to a shared header, recording the order of calls
+    struct Tag(&'static str);
+
+    impl RequestInterceptor for Tag {
+        fn on_request(&self, request: &mut reqwest::Request) {
+            let previous = request
+                .headers()
+                .get("x-order")
+                .and_then(|v| v.to_str().ok())
+                .map(|v| format!("{},", v))
+                .unwrap_or_default();
+            request.headers_mut().insert(
+                "x-order",
+                HeaderValue::from_str(&format!("{}{}", previous, self.0)).unwrap(),
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interceptor_adds_header() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .match_header("x-signature", "len-0")
+            .with_status(200)
+            .with_body(MODELS_BODY)
+            .create_async()
+            .await;
+
+        let client = builder_for(server.url())
+            .interceptor(Arc::new(Signer))
+            .build()
+            .unwrap();
+
+        client.list_models("agent-1").await.unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_interceptor_counts_calls() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .with_status(200)
+            .with_body(MODELS_BODY)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-2/v1/models")
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let counter = Arc::new(Counter::default());
+        let client = builder_for(server.url())
+            .interceptor(counter.clone())
+            .build()
+            .unwrap();
+
+        client.list_models("agent-1").await.unwrap();
+        assert!(client.list_models("agent-2").await.is_err());
+
+        assert_eq!(counter.requests.load(Ordering::SeqCst), 2);
+        assert_eq!(*counter.statuses.lock().unwrap(), vec![200, 404]);
+    }
+
+    #[tokio::test]
+    async fn test_interceptors_run_in_registration_order() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .match_header("x-order", "first,second")
+            .with_status(200)
+            .with_body(MODELS_BODY)
+            .create_async()
+            .await;
+
+        let client = builder_for(server.url())
+            .interceptor(Arc::new(Tag("first")))
+            .interceptor(Arc::new(Tag("second")))
+            .build()
+            .unwrap();
+
+        client.list_models("agent-1").await.unwrap();
+        mock.assert_async().await;
+    }
+}
+
+/// Response metadata tests
+#[cfg(all(test, feature = "testing"))]
+mod meta {
+    use std::time::Duration;
+
+    use reqwest::Method;
+    use twcai::api::{AgentClientExt, ResponsesExt};
+    use twcai::testing::{Fixture, FixtureTransport};
+    use twcai::types::*;
+
+    use crate::common::client_with;
+
+    #[tokio::test]
+    async fn test_chat_completions_with_meta_exposes_headers() {
+        let fixtures = FixtureTransport::new().route(
+            Method::POST,
+            "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            Fixture::raw(
+                200,
+                "application/json",
+                r#"{
+                    "id": "chatcmpl-1",
+                    "object": "chat.completion",
+                    "created": 1700000000,
+                    "model": "deepseek-reason",
+                    "choices": [{
+                        "index": 0,
+                        "message": {"role": "assistant", "content": "Hi"},
+                        "finish_reason": "stop"
+                    }],
+                    "usage": {"prompt_tokens": 3, "completion_tokens": 1, "total_tokens": 4}
+                }"#,
+            )
+            .header("x-ratelimit-limit-requests", "60")
+            .header("x-ratelimit-remaining-requests", "59")
+            .header("x-ratelimit-remaining-tokens", "149984")
+            .header("x-ratelimit-reset-requests", "1s")
+            .header("x-ratelimit-reset-tokens", "6m0.5s")
+            .header("x-model-version", "2025-01-01"),
+        );
+        let client = client_with(&fixtures);
+
+        let request = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hello")],
+            ..Default::default()
+        };
+        let response = client
+            .chat_completions_with_meta("agent-1", request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.data.id, "chatcmpl-1");
+        assert_eq!(response.meta.status, 200);
+        assert_eq!(response.meta.header("x-model-version"), Some("2025-01-01"));
+
+        let ratelimit = response.meta.ratelimit().unwrap();
+        assert_eq!(ratelimit.limit_requests, Some(60));
+        assert_eq!(ratelimit.limit_tokens, None);
+        assert_eq!(ratelimit.remaining_requests, Some(59));
+        assert_eq!(ratelimit.remaining_tokens, Some(149984));
+        assert_eq!(ratelimit.reset_requests, Some(Duration::from_secs(1)));
+        assert_eq!(ratelimit.reset_tokens, Some(Duration::from_millis(360_500)));
+    }
+
+    #[tokio::test]
+    async fn test_create_response_with_meta_without_ratelimit() {
+        let fixtures = FixtureTransport::new().route(
+            Method::POST,
+            "/api/v1/cloud-ai/agents/agent-1/v1/responses",
+            Fixture::raw(
+                200,
+                "application/json",
+                r#"{
+                    "id": "resp_1",
+                    "object": "response",
+                    "created_at": 1700000000,
+                    "model": "gpt-4.1",
+                    "status": "completed",
+                    "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2}
+                }"#,
+            ),
+        );
+
+        let response = client_with(&fixtures)
+            .create_response_with_meta("agent-1", CreateResponseRequest::default())
+            .await
+            .unwrap();
+
+        assert_eq!(response.data.id, "resp_1");
+        assert!(response.meta.ratelimit().is_none());
+    }
+}
+
+/// Request id propagation tests
+#[cfg(test)]
+mod request_id {
+    use mockito::Matcher;
+    use twcai::api::{ConversationsExt, ResponsesExt};
+    use twcai::types::*;
+    use twcai::{CloudAIClient, RequestOptions, TwcError};
+
+    use crate::common::builder_for;
+
+    const CONVERSATION_BODY: &str =
+        r#"{"id": "conv-1", "object": "conversation", "created_at": 1}"#;
+
+    #[tokio::test]
+    async fn test_request_id_header_round_trips() {
+        let mut server = mockito::Server::new_async().await;
+        let created = server
+            .mock("POST", "/api/v1/cloud-ai/agents/agent-1/v1/conversations")
+            .match_header("x-request-id", "trace-7f3a")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_header("x-request-id", "trace-7f3a")
+            .with_body(CONVERSATION_BODY)
+            .create_async()
+            .await;
+        let plain = server
+            .mock("POST", "/api/v1/cloud-ai/agents/agent-2/v1/conversations")
+            .match_header("x-request-id", Matcher::Missing)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(CONVERSATION_BODY)
+            .create_async()
+            .await;
+
+        let client = builder_for(server.url()).build().unwrap();
+        client
+            .create_conversation_with_options(
+                "agent-1",
+                CreateConversationRequest::default(),
+                RequestOptions::new().request_id("trace-7f3a"),
+            )
+            .await
+            .unwrap();
+        client
+            .create_conversation("agent-2", CreateConversationRequest::default())
+            .await
+            .unwrap();
+
+        created.assert_async().await;
+        plain.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_custom_header_and_error_carries_id() {
+        let mut server = mockito::Server::new_async().await;
+        let missing = server
+            .mock("POST", "/api/v1/cloud-ai/agents/agent-1/v1/responses")
+            .match_header("x-correlation-id", "corr-42")
+            .match_header("x-request-id", Matcher::Missing)
+            .with_status(404)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"error": {"message": "Agent not found"}}"#)
+            .create_async()
+            .await;
+
+        let client = builder_for(server.url())
+            .request_id_header("x-correlation-id")
+            .build()
+            .unwrap();
+        let err = client
+            .create_response_with_options(
+                "agent-1",
+                CreateResponseRequest::default(),
+                RequestOptions::new().request_id("corr-42"),
+            )
+            .await
+            .unwrap_err();
+
+        missing.assert_async().await;
+        assert_eq!(err.request_id(), Some("corr-42"));
+        assert!(matches!(err.inner(), TwcError::NotFound(message) if message == "Agent not found"));
+        assert!(err.to_string().contains("corr-42"));
+        assert!(!err.is_retryable());
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn test_span_id_is_sent_only_when_opted_in() {
+        let mut server = mockito::Server::new_async().await;
+        let without_id = server
+            .mock(
+                "GET",
+                "/api/v1/cloud-ai/agents/agent-1/v1/conversations/conv-1",
+            )
+            .match_header("x-request-id", Matcher::Missing)
+            .with_status(404)
+            .create_async()
+            .await;
+        let with_span_id = server
+            .mock(
+                "GET",
+                "/api/v1/cloud-ai/agents/agent-2/v1/conversations/conv-1",
+            )
+            .match_header("x-request-id", Matcher::Regex("^[0-9a-f]{16}$".to_string()))
+            .with_status(404)
+            .create_async()
+            .await;
+
+        let _subscriber = tracing::subscriber::set_default(tracing_subscriber::registry());
+        let span = tracing::info_span!("handler");
+        let _entered = span.enter();
+
+        let client = builder_for(server.url()).build().unwrap();
+        let err = client
+            .get_conversation("agent-1", "conv-1")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TwcError::NotFound(_)), "{:?}", err);
+
+        let client = builder_for(server.url())
+            .request_id_from_span(true)
+            .build()
+            .unwrap();
+        let err = client
+            .get_conversation("agent-2", "conv-1")
+            .await
+            .unwrap_err();
+        assert!(err.request_id().is_some());
+        assert!(matches!(err.inner(), TwcError::NotFound(_)));
+
+        without_id.assert_async().await;
+        with_span_id.assert_async().await;
+    }
+
+    #[test]
+    fn test_invalid_header_name_fails_build() {
+        let result = CloudAIClient::builder()
+            .token("test-token")
+            .request_id_header("bad header")
+            .build();
+        assert!(matches!(result, Err(TwcError::Configuration(_))));
+        assert_eq!(TwcError::Unauthorized.request_id(), None);
+    }
+}
+
+/// Response size limit tests
+#[cfg(test)]
+mod response_limit {
+    use std::io::Write;
+
+    use twcai::api::AgentClientExt;
+    use twcai::{CloudAIClient, TwcError};
+
+    use crate::common::builder_for;
+
+    const MODELS_PATH: &str = "/api/v1/cloud-ai/agents/agent-1/v1/models";
+
+    fn client_with_limit(server: &mockito::Server, limit: usize) -> CloudAIClient {
+        builder_for(server.url())
+            .max_response_bytes(limit)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_with_content_length_rejected() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", MODELS_PATH)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(vec![b' '; 10 * 1024])
+            .create_async()
+            .await;
+
+        let client = client_with_limit(&server, 1024);
+        let result = client.list_models("agent-1").await;
+
+        match result {
+            Err(TwcError::ResponseTooLarge { limit, received }) => {
+                assert_eq!(limit, 1024);
+                assert!(received > 1024);
+            }
+            other => panic!("expected ResponseTooLarge, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oversized_chunked_body_aborted_early() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", MODELS_PATH)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_chunked_body(|w| {
+                for _ in 0..1024 {
+                    w.write_all(&[b' '; 1024])?;
+                }
+                Ok(())
+            })
+            .create_async()
+            .await;
+
+        let client = client_with_limit(&server, 4096);
+        let result = client.list_models("agent-1").await;
+
+        match result {
+            Err(TwcError::ResponseTooLarge { limit, received }) => {
+                assert_eq!(limit, 4096);
+                assert!(received > 4096);
+                assert!(received < 1024 * 1024);
+            }
+            other => panic!("expected ResponseTooLarge, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_oversized_error_body_rejected() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", MODELS_PATH)
+            .with_status(502)
+            .with_header("content-type", "text/html")
+            .with_body(format!("<html>{}</html>", "x".repeat(10 * 1024)))
+            .create_async()
+            .await;
+
+        let client = client_with_limit(&server, 1024);
+        let result = client.list_models("agent-1").await;
+
+        assert!(matches!(result, Err(TwcError::ResponseTooLarge { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_body_within_limit_accepted() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", MODELS_PATH)
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"object":"list","data":[]}"#)
+            .create_async()
+            .await;
+
+        let client = client_with_limit(&server, 1024);
+        let models = client.list_models("agent-1").await.unwrap();
+
+        assert!(models.data.is_empty());
+    }
+}
+
+/// Retry tests
+#[cfg(test)]
+mod retry {
+    use std::time::{Duration, Instant};
+
+    use reqwest::StatusCode;
+    use twcai::api::AgentClientExt;
+    use twcai::types::*;
+    use twcai::{CloudAIClient, TwcError};
+
+    use crate::common::{ScriptedServer, builder_for, client_for};
+
+    /// Allowance for scheduling delays in timing assertions
+    const SLOP: Duration = Duration::from_millis(250);
+
+    const OK_BODY: &str = r#"{"message": "Hi", "id": "msg-1", "finish_reason": "stop"}"#;
+
+    fn raw(status: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
+    fn retrying_client(url: &str, max_retries: u32) -> CloudAIClient {
+        builder_for(url).max_retries(max_retries).build().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_retried() {
+        let server = ScriptedServer::start(vec![
+            raw("503 Service Unavailable", ""),
+            raw("502 Bad Gateway", ""),
+            raw("200 OK", OK_BODY),
+        ])
+        .await;
+
+        let response = retrying_client(&server.url, 2)
+            .call_agent("agent-1", AgentCallRequest::new("Hello"))
+            .await
+            .unwrap();
+
+        assert_eq!(response.message, "Hi");
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retries_exhausted() {
+        let server = ScriptedServer::start(vec![
+            raw("503 Service Unavailable", ""),
+            raw("503 Service Unavailable", ""),
+            raw("200 OK", OK_BODY),
+        ])
+        .await;
+
+        let result = retrying_client(&server.url, 1)
+            .call_agent("agent-1", AgentCallRequest::new("Hello"))
+            .await;
+
+        match result {
+            Err(TwcError::RetriesExhausted { attempts, last, .. }) => {
+                assert_eq!(attempts, 2);
+                assert!(matches!(*last, TwcError::ServerError { status: 503, .. }));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_total_deadline_bounds_retries() {
+        let server = ScriptedServer::start(vec![raw("503 Service Unavailable", ""); 20]).await;
+        let client = builder_for(&server.url)
+            .max_retries(10)
+            .total_deadline(Duration::from_millis(700))
+            .build()
+            .unwrap();
+
+        let started = Instant::now();
+        let result = client
+            .call_agent("agent-1", AgentCallRequest::new("Hello"))
+            .await;
+        let took = started.elapsed();
+
+        assert!(took < Duration::from_millis(700) + SLOP, "took {:?}", took);
+        match result {
+            Err(TwcError::RetriesExhausted {
+                attempts,
+                elapsed,
+                last,
+                history,
+            }) => {
+                // Backoffs of 200 and 400 ms fit the budget, 800 ms does not
+                assert_eq!(attempts, 3);
+                assert_eq!(history.len(), 3);
+                assert!(elapsed <= took);
+                assert!(matches!(*last, TwcError::ServerError { status: 503, .. }));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_total_deadline_shrinks_attempt_timeout() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        // Accept connections but never answer
+        tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+
+        let client = builder_for(url)
+            .timeout(Duration::from_secs(60))
+            .max_retries(3)
+            .total_deadline(Duration::from_millis(300))
+            .build()
+            .unwrap();
+
+        let started = Instant::now();
+        let result = client
+            .call_agent("agent-1", AgentCallRequest::new("Hello"))
+            .await;
+
+        assert!(started.elapsed() < Duration::from_millis(300) + SLOP);
+        match result {
+            Err(TwcError::RetriesExhausted {
+                attempts: 1, last, ..
+            }) => {
+                assert!(last.is_retryable(), "{:?}", last);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_not_retried() {
+        let server = ScriptedServer::start(vec![
+            raw("422 Unprocessable Entity", r#"{"message": "bad input"}"#),
+            raw("200 OK", OK_BODY),
+        ])
+        .await;
+
+        let result = retrying_client(&server.url, 3)
+            .call_agent("agent-1", AgentCallRequest::new("Hello"))
+            .await;
+
+        assert!(matches!(result, Err(TwcError::UnprocessableEntity(_))));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_no_retries_by_default() {
+        let server = ScriptedServer::start(vec![
+            raw("503 Service Unavailable", ""),
+            raw("200 OK", OK_BODY),
+        ])
+        .await;
+
+        let result = client_for(&server.url)
+            .call_agent("agent-1", AgentCallRequest::new("Hello"))
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(TwcError::ServerError { status: 503, .. })
+        ));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_attempt_history_on_success() {
+        let server = ScriptedServer::start(vec![
+            raw("503 Service Unavailable", ""),
+            raw("502 Bad Gateway", ""),
+            raw("200 OK", OK_BODY),
+        ])
+        .await;
+
+        let response = retrying_client(&server.url, 2)
+            .call_agent_with_meta("agent-1", AgentCallRequest::new("Hello"))
+            .await
+            .unwrap();
+
+        let attempts = &response.meta.attempts;
+        assert!(response.meta.retried());
+        let outcomes: Vec<_> = attempts.iter().map(|a| a.outcome.clone()).collect();
+        assert_eq!(
+            outcomes,
+            [
+                AttemptOutcome::Status(StatusCode::SERVICE_UNAVAILABLE),
+                AttemptOutcome::Status(StatusCode::BAD_GATEWAY),
+                AttemptOutcome::Status(StatusCode::OK),
+            ]
+        );
+        let delays: Vec<_> = attempts.iter().map(|a| a.retry_delay).collect();
+        assert_eq!(
+            delays,
+            [
+                Some(Duration::from_millis(200)),
+                Some(Duration::from_millis(400)),
+                None
+            ]
+        );
+        assert!(attempts[0].started_at < attempts[1].started_at);
+        assert!(attempts[1].started_at < attempts[2].started_at);
+    }
+
+    #[tokio::test]
+    async fn test_single_attempt_is_not_retried() {
+        let server = ScriptedServer::start(vec![raw("200 OK", OK_BODY)]).await;
+
+        let response = retrying_client(&server.url, 2)
+            .call_agent_with_meta("agent-1", AgentCallRequest::new("Hello"))
+            .await
+            .unwrap();
+
+        assert!(!response.meta.retried());
+        assert_eq!(response.meta.attempts.len(), 1);
+        assert_eq!(
+            response.meta.attempts[0].outcome,
+            AttemptOutcome::Status(StatusCode::OK)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_attempt_history_on_exhaustion() {
+        let server = ScriptedServer::start(vec![
+            raw("503 Service Unavailable", ""),
+            raw("500 Internal Server Error", ""),
+        ])
+        .await;
+
+        let result = retrying_client(&server.url, 1)
+            .call_agent("agent-1", AgentCallRequest::new("Hello"))
+            .await;
+
+        match result {
+            Err(TwcError::RetriesExhausted { history, .. }) => {
+                let outcomes: Vec<_> = history.iter().map(|a| a.outcome.clone()).collect();
+                assert_eq!(
+                    outcomes,
+                    [
+                        AttemptOutcome::Status(StatusCode::SERVICE_UNAVAILABLE),
+                        AttemptOutcome::Status(StatusCode::INTERNAL_SERVER_ERROR),
+                    ]
+                );
+                assert_eq!(history[0].retry_delay, Some(Duration::from_millis(200)));
+                assert_eq!(history[1].retry_delay, None);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -44,6 +44,19 @@ mod tests {
         assert!(request.file_ids.is_none());
     }
 
+    #[test]
+    fn test_agent_call_request_builder() {
+        let request = AgentCallRequest::new("Hi")
+            .parent("msg-1")
+            .files(["file-1", "file-2"]);
+        assert_eq!(request.message.as_deref(), Some("Hi"));
+        assert_eq!(request.parent_message_id.as_deref(), Some("msg-1"));
+        assert_eq!(
+            request.file_ids,
+            Some(vec!["file-1".to_string(), "file-2".to_string()])
+        );
+    }
+
     #[test]
     fn test_chat_completion_request_default() {
         let request = ChatCompletionRequest::default();
@@ -71,6 +84,7 @@ mod tests {
             prompt_tokens: 10,
             completion_tokens: 20,
             total_tokens: 30,
+            ..Default::default()
         };
         assert_eq!(
             usage.total_tokens,
@@ -102,15 +116,13 @@ mod tests {
     #[test]
     fn test_multimodal_content_creation() {
         let text_content = TextContent {
-            content_type: "text".to_string(),
             text: "What's in this image?".to_string(),
         };
 
         let image_content = ImageUrlContent {
-            content_type: "image_url".to_string(),
             image_url: ImageUrl {
                 url: "https://example.com/image.jpg".to_string(),
-                detail: Some("auto".to_string()),
+                detail: Some(ImageDetail::Auto),
             },
         };
 
@@ -0,0 +1,129 @@
+//! Logging tests for TWCai (requires the `log` feature)
+
+#![cfg(feature = "log")]
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use log::{Level, LevelFilter, Log, Metadata, Record};
+    use twcai::CloudAIClient;
+    use twcai::api::AgentClientExt;
+    use twcai::types::*;
+
+    const TOKEN: &str = "super-secret-token-value";
+
+    struct CapturingLogger {
+        records: Mutex<Vec<(Level, String)>>,
+    }
+
+    impl Log for CapturingLogger {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            self.records
+                .lock()
+                .unwrap()
+                .push((record.level(), record.args().to_string()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger {
+        records: Mutex::new(Vec::new()),
+    };
+
+    #[tokio::test]
+    async fn test_requests_logged_without_token() {
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(LevelFilter::Trace);
+
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock(
+                "POST",
+                "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            )
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                    "id": "chatcmpl-1",
+                    "object": "chat.completion",
+                    "created": 1700000000,
+                    "model": "deepseek-reason",
+                    "choices": [{
+                        "index": 0,
+                        "message": {"role": "assistant", "content": "Hi"},
+                        "finish_reason": "stop"
+                    }],
+                    "usage": {"prompt_tokens": 3, "completion_tokens": 1, "total_tokens": 4}
+                }"#,
+            )
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .with_status(401)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-2/v1/models")
+            .with_status(503)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let client = CloudAIClient::builder()
+            .base_url(server.url())
+            .token(TOKEN)
+            .build()
+            .unwrap();
+
+        let request = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hello")],
+            ..Default::default()
+        };
+        client.chat_completions("agent-1", request).await.unwrap();
+        assert!(client.list_models("agent-1").await.is_err());
+
+        let retrying = CloudAIClient::builder()
+            .base_url(server.url())
+            .token(TOKEN)
+            .max_retries(1)
+            .build()
+            .unwrap();
+        assert!(retrying.list_models("agent-2").await.is_err());
+
+        let records = LOGGER.records.lock().unwrap();
+        let ours: Vec<_> = records
+            .iter()
+            .filter(|(_, message)| {
+                message.contains("/api/v1/cloud-ai") || message.contains("usage")
+            })
+            .collect();
+
+        assert!(ours.iter().any(|(level, message)| *level == Level::Debug
+            && message.starts_with("-> POST /api/v1/cloud-ai/agents/agent-1/v1/chat/completions")));
+        assert!(
+            ours.iter()
+                .any(|(level, message)| *level == Level::Debug && message.contains("200"))
+        );
+        assert!(
+            ours.iter()
+                .any(|(_, message)| message.contains("\"total_tokens\":4"))
+        );
+        assert!(
+            ours.iter()
+                .any(|(level, message)| *level == Level::Warn && message.contains("401"))
+        );
+        assert!(ours.iter().any(|(level, message)| *level == Level::Warn
+            && message.starts_with("<- GET /api/v1/cloud-ai/agents/agent-2/")
+            && message.contains("models attempt 1 failed")
+            && message.contains("retrying in")));
+        assert!(records.iter().all(|(_, message)| !message.contains(TOKEN)));
+    }
+}
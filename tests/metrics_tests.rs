@@ -0,0 +1,105 @@
+//! Metrics tests for TWCai (requires the `metrics` feature)
+
+#![cfg(feature = "metrics")]
+
+#[cfg(test)]
+mod tests {
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use twcai::CloudAIClient;
+    use twcai::api::AgentClientExt;
+    use twcai::types::*;
+
+    #[tokio::test]
+    async fn test_requests_and_tokens_recorded() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        recorder.install().unwrap();
+
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock(
+                "POST",
+                "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            )
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                    "id": "chatcmpl-1",
+                    "object": "chat.completion",
+                    "created": 1700000000,
+                    "model": "deepseek-reason",
+                    "choices": [{
+                        "index": 0,
+                        "message": {"role": "assistant", "content": "Hi"},
+                        "finish_reason": "stop"
+                    }],
+                    "usage": {"prompt_tokens": 3, "completion_tokens": 1, "total_tokens": 4}
+                }"#,
+            )
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .with_status(503)
+            .create_async()
+            .await;
+
+        let client = CloudAIClient::builder()
+            .base_url(server.url())
+            .token("token")
+            .build()
+            .unwrap();
+
+        let request = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hello")],
+            ..Default::default()
+        };
+        client.chat_completions("agent-1", request).await.unwrap();
+        assert!(client.list_models("agent-1").await.is_err());
+
+        let metrics: Vec<_> = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let key = key.key();
+                let mut labels: Vec<_> = key
+                    .labels()
+                    .map(|l| format!("{}={}", l.key(), l.value()))
+                    .collect();
+                labels.sort();
+                (key.name().to_string(), labels.join(","), value)
+            })
+            .collect();
+
+        let counter = |name: &str, labels: &str| {
+            metrics.iter().find_map(|(n, l, value)| match value {
+                DebugValue::Counter(count) if n == name && l == labels => Some(*count),
+                _ => None,
+            })
+        };
+
+        assert_eq!(
+            counter(
+                "twcai_requests_total",
+                "endpoint=POST /agents/{id}/v1/chat/completions,status=200"
+            ),
+            Some(1)
+        );
+        assert_eq!(
+            counter(
+                "twcai_requests_total",
+                "endpoint=GET /agents/{id}/v1/models,status=503"
+            ),
+            Some(1)
+        );
+        assert_eq!(counter("twcai_tokens_total", "kind=prompt"), Some(3));
+        assert_eq!(counter("twcai_tokens_total", "kind=completion"), Some(1));
+        assert!(metrics.iter().any(|(name, labels, value)| {
+            name == "twcai_request_duration_seconds"
+                && labels == "endpoint=POST /agents/{id}/v1/chat/completions"
+                && matches!(value, DebugValue::Histogram(samples) if samples.len() == 1)
+        }));
+    }
+}
@@ -0,0 +1,1195 @@
+//! Responses API tests for TWCai
+
+mod common;
+
+/// Batch helper tests
+#[cfg(test)]
+mod batch {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use mockito::Matcher;
+    use twcai::api::{AgentClientExt, BatchReport};
+    use twcai::{TwcError, types::*};
+
+    use crate::common::client_for;
+
+    const CHAT_RESPONSE: &str = r#"{
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "deepseek-reason",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "Done."},
+            "finish_reason": "stop"
+        }],
+        "usage": {"prompt_tokens": 5, "completion_tokens": 2, "total_tokens": 7}
+    }"#;
+
+    fn tagged_request(tag: &str) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Summarize this")],
+            user: Some(tag.to_string()),
+            ..Default::default()
+        }
+    }
+
+    async fn batch_server() -> mockito::ServerGuard {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock(
+                "POST",
+                "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            )
+            .match_body(Matcher::Regex(r#""user":"ok-\d+""#.to_string()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(CHAT_RESPONSE)
+            .expect_at_least(1)
+            .create_async()
+            .await;
+        server
+            .mock(
+                "POST",
+                "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            )
+            .match_body(Matcher::Regex(r#""user":"fail-\d+""#.to_string()))
+            .with_status(500)
+            .with_body("upstream exploded")
+            .expect_at_least(1)
+            .create_async()
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_batch_preserves_order_and_reports_per_item() {
+        let server = batch_server().await;
+        let client = client_for(server.url());
+
+        let requests = vec![
+            tagged_request("ok-0"),
+            tagged_request("fail-1"),
+            tagged_request("ok-2"),
+            tagged_request("ok-3"),
+            tagged_request("fail-4"),
+            tagged_request("ok-5"),
+        ];
+
+        let results = client.chat_completions_batch("agent-1", requests, 3).await;
+
+        assert_eq!(results.len(), 6);
+        for (index, result) in results.iter().enumerate() {
+            if index == 1 || index == 4 {
+                assert!(matches!(
+                    result,
+                    Err(TwcError::ServerError { status: 500, .. })
+                ));
+            } else {
+                assert_eq!(result.as_ref().unwrap().id, "chatcmpl-1");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_progress_callback() {
+        let server = batch_server().await;
+        let client = client_for(server.url());
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let last_total = Arc::new(AtomicUsize::new(0));
+        let (calls_seen, total_seen) = (calls.clone(), last_total.clone());
+
+        let requests = (0..5)
+            .map(|i| tagged_request(&format!("ok-{}", i)))
+            .collect();
+        let results = client
+            .chat_completions_batch_with_progress("agent-1", requests, 0, move |done, total| {
+                calls_seen.fetch_add(1, Ordering::SeqCst);
+                assert!(done <= total);
+                total_seen.store(total, Ordering::SeqCst);
+            })
+            .await;
+
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+        assert_eq!(last_total.load(Ordering::SeqCst), 5);
+    }
+
+    fn mixed_requests() -> Vec<ChatCompletionRequest> {
+        ["ok-0", "fail-1", "ok-2", "ok-3", "fail-4", "ok-5"]
+            .into_iter()
+            .map(tagged_request)
+            .collect()
+    }
+
+    fn indices<T>(items: &[(usize, T)]) -> Vec<usize> {
+        items.iter().map(|(index, _)| *index).collect()
+    }
+
+    #[tokio::test]
+    async fn test_batch_report_partitions_items() {
+        let server = batch_server().await;
+        let client = client_for(server.url());
+
+        let report = client
+            .chat_completions_batch_report("agent-1", mixed_requests(), 3)
+            .await;
+
+        assert_eq!(indices(&report.succeeded), [0, 2, 3, 5]);
+        assert_eq!(indices(&report.failed), [1, 4]);
+        assert!(
+            report
+                .failed
+                .iter()
+                .all(|(_, error)| matches!(error, TwcError::ServerError { status: 500, .. }))
+        );
+        assert_eq!(report.usage_total.prompt_tokens, 20);
+        assert_eq!(report.usage_total.total_tokens, 28);
+        assert!((report.success_rate() - 4.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_batch_report_retries_only_failed_items() {
+        let server = batch_server().await;
+        let client = client_for(server.url());
+        let mut report = client
+            .chat_completions_batch_report("agent-1", mixed_requests(), 3)
+            .await;
+
+        let mut healthy = mockito::Server::new_async().await;
+        let retried = healthy
+            .mock(
+                "POST",
+                "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            )
+            .match_body(Matcher::Regex(r#""user":"fail-(1|4)""#.to_string()))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(CHAT_RESPONSE)
+            .expect(2)
+            .create_async()
+            .await;
+        let repeated = healthy
+            .mock(
+                "POST",
+                "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            )
+            .match_body(Matcher::Regex(r#""user":"ok-\d+""#.to_string()))
+            .expect(0)
+            .create_async()
+            .await;
+        let healthy_client = client_for(healthy.url());
+
+        report.retry_failed(&healthy_client, 2).await;
+
+        retried.assert_async().await;
+        repeated.assert_async().await;
+        assert_eq!(indices(&report.succeeded), [0, 1, 2, 3, 4, 5]);
+        assert!(report.failed.is_empty());
+        assert_eq!(report.usage_total.total_tokens, 42);
+        assert_eq!(report.success_rate(), 1.0);
+
+        // Nothing left to retry
+        report.retry_failed(&healthy_client, 2).await;
+        assert_eq!(report.succeeded.len(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_batch_report_keeps_repeated_failures() {
+        let server = batch_server().await;
+        let client = client_for(server.url());
+        let mut report: BatchReport = client
+            .chat_completions_batch_report("agent-1", mixed_requests(), 3)
+            .await;
+
+        report.retry_failed(&client, 3).await;
+
+        assert_eq!(indices(&report.succeeded), [0, 2, 3, 5]);
+        assert_eq!(indices(&report.failed), [1, 4]);
+        assert_eq!(report.usage_total.total_tokens, 28);
+    }
+
+    #[tokio::test]
+    async fn test_empty_batch() {
+        let client = client_for("http://127.0.0.1:9");
+
+        let results = client
+            .chat_completions_batch("agent-1", Vec::new(), 4)
+            .await;
+        assert!(results.is_empty());
+
+        let report = client
+            .chat_completions_batch_report("agent-1", Vec::new(), 4)
+            .await;
+        assert!(report.succeeded.is_empty() && report.failed.is_empty());
+        assert_eq!(report.success_rate(), 1.0);
+    }
+}
+
+/// Conditional response polling tests
+#[cfg(test)]
+mod conditional_poll {
+    use twcai::api::ResponsesExt;
+
+    use crate::common::{ScriptedServer, client_for};
+
+    fn response_body(status: &str, text: &str) -> String {
+        serde_json::json!({
+            "id": "resp_1",
+            "object": "response",
+            "created_at": 1741476700,
+            "model": "deepseek-reason",
+            "status": status,
+            "output": [{
+                "type": "message",
+                "id": "msg_1",
+                "role": "assistant",
+                "content": [{"type": "output_text", "text": text, "annotations": []}]
+            }]
+        })
+        .to_string()
+    }
+
+    fn ok(status: &str, etag: Option<&str>) -> String {
+        let body = response_body(status, &"Lorem ipsum ".repeat(200));
+        let etag = etag
+            .map(|etag| format!("etag: {}\r\n", etag))
+            .unwrap_or_default();
+        format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\n{}content-length: {}\r\nconnection: close\r\n\r\n{}",
+            etag,
+            body.len(),
+            body
+        )
+    }
+
+    fn not_modified(etag: &str) -> String {
+        format!(
+            "HTTP/1.1 304 Not Modified\r\netag: {}\r\nconnection: close\r\n\r\n",
+            etag
+        )
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_polls_use_etag() {
+        let server = ScriptedServer::start(vec![
+            ok("in_progress", Some("\"v1\"")),
+            not_modified("\"v1\""),
+            not_modified("\"v1\""),
+            ok("completed", Some("\"v2\"")),
+        ])
+        .await;
+
+        let response = client_for(&server.url)
+            .wait_for_response("agent-1", "resp_1")
+            .await
+            .unwrap();
+
+        assert_eq!(response.status, "completed");
+        assert_eq!(
+            server.header_values("if-none-match"),
+            [
+                None,
+                Some("\"v1\"".to_string()),
+                Some("\"v1\"".to_string()),
+                Some("\"v1\"".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_changed_etag_is_sent_next() {
+        let server = ScriptedServer::start(vec![
+            ok("queued", Some("\"v1\"")),
+            ok("in_progress", Some("\"v2\"")),
+            ok("completed", Some("\"v3\"")),
+        ])
+        .await;
+
+        client_for(&server.url)
+            .wait_for_response("agent-1", "resp_1")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            server.header_values("if-none-match"),
+            [None, Some("\"v1\"".to_string()), Some("\"v2\"".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_plain_polling_without_etag() {
+        let server = ScriptedServer::start(vec![
+            ok("in_progress", None),
+            ok("in_progress", None),
+            ok("completed", None),
+        ])
+        .await;
+
+        let response = client_for(&server.url)
+            .wait_for_response("agent-1", "resp_1")
+            .await
+            .unwrap();
+
+        assert_eq!(response.status, "completed");
+        assert_eq!(server.header_values("if-none-match"), [None, None, None]);
+    }
+
+    #[tokio::test]
+    async fn test_already_finished_response_is_fetched_once() {
+        let server = ScriptedServer::start(vec![ok("failed", Some("\"v1\""))]).await;
+
+        let response = client_for(&server.url)
+            .wait_for_response("agent-1", "resp_1")
+            .await
+            .unwrap();
+
+        assert_eq!(response.status, "failed");
+        assert_eq!(server.requests().len(), 1);
+    }
+}
+
+/// Include option tests
+#[cfg(test)]
+mod include {
+    use serde_json::json;
+    use twcai::types::*;
+
+    #[test]
+    fn test_include_wire_values() {
+        assert_eq!(
+            Include::MessageOutputTextLogprobs.as_str(),
+            "message.output_text.logprobs"
+        );
+        assert_eq!(
+            Include::ReasoningEncryptedContent.to_string(),
+            "reasoning.encrypted_content"
+        );
+        assert_eq!(
+            serde_json::to_value(Include::Custom("x.y".to_string())).unwrap(),
+            json!("x.y")
+        );
+    }
+
+    #[test]
+    fn test_include_deserialize() {
+        let values: Vec<Include> =
+            serde_json::from_value(json!(["file_search_call.results", "future.option"])).unwrap();
+
+        assert_eq!(
+            values,
+            vec![
+                Include::FileSearchCallResults,
+                Include::Custom("future.option".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_include_in_request_body() {
+        let request = CreateResponseRequest {
+            include: Some(vec![Include::MessageOutputTextLogprobs]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({"include": ["message.output_text.logprobs"]})
+        );
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_include_in_query_string() {
+        use reqwest::Method;
+        use twcai::api::ConversationsExt;
+        use twcai::testing::{Fixture, FixtureTransport};
+
+        use crate::common::client_with;
+
+        let fixtures = FixtureTransport::new().route(
+            Method::GET,
+            "/api/v1/cloud-ai/agents/agent-1/v1/conversations/conv_1/items",
+            Fixture::json(
+                200,
+                &json!({"object": "list", "data": [], "first_id": "", "last_id": "", "has_more": false}),
+            ),
+        );
+        let client = client_with(&fixtures);
+
+        let query = ListItemsQuery {
+            include: Some(vec![
+                Include::MessageOutputTextLogprobs,
+                Include::MessageInputImageUrl,
+            ]),
+            limit: Some(5),
+            ..Default::default()
+        };
+        client
+            .list_conversation_items("agent-1", "conv_1", Some(query))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fixtures.requests()[0].query.as_deref(),
+            Some(
+                "include%5B%5D=message.output_text.logprobs&include%5B%5D=message.input_image.image_url&limit=5"
+            )
+        );
+    }
+}
+
+/// Response input items tests
+#[cfg(test)]
+mod input_items {
+    use serde_json::json;
+    use twcai::pagination::Page;
+    use twcai::types::*;
+
+    #[cfg(feature = "testing")]
+    const INPUT_ITEMS_PATH: &str =
+        "/api/v1/cloud-ai/agents/agent-1/v1/responses/resp_123/input_items";
+
+    fn message(id: &str) -> serde_json::Value {
+        json!({
+            "type": "message",
+            "id": id,
+            "status": "completed",
+            "role": "user",
+            "content": [{"type": "input_text", "text": id}]
+        })
+    }
+
+    fn list_body(data: Vec<serde_json::Value>, has_more: bool) -> String {
+        let first_id = data.first().map(|item| item["id"].clone());
+        let last_id = data.last().map(|item| item["id"].clone());
+        json!({
+            "object": "list",
+            "data": data,
+            "first_id": first_id,
+            "last_id": last_id,
+            "has_more": has_more
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_typed_input_items() {
+        let list: ResponseInputItemList =
+            serde_json::from_str(include_str!("fixtures/conformance/input_items.json")).unwrap();
+
+        let ResponseInputItem::Message(message) = &list.data[0] else {
+            panic!("expected a message, got {:?}", list.data[0]);
+        };
+        assert_eq!(message.role, "user");
+        assert_eq!(message.text(), "What is the weather in Moscow?");
+
+        let ResponseInputItem::FunctionCall(call) = &list.data[1] else {
+            panic!("expected a function call, got {:?}", list.data[1]);
+        };
+        assert_eq!(call.name, "get_weather");
+        assert_eq!(call.call_id, "call_abc");
+
+        let ResponseInputItem::FunctionCallOutput(output) = &list.data[2] else {
+            panic!("expected a function call output, got {:?}", list.data[2]);
+        };
+        assert_eq!(output.call_id, call.call_id);
+        assert_eq!(output.output, json!("{\"temperature\":12}"));
+
+        let ResponseInputItem::Other { item_type, data } = &list.data[3] else {
+            panic!("expected an unknown item, got {:?}", list.data[3]);
+        };
+        assert_eq!(item_type, "reasoning");
+        assert_eq!(data["summary"][0]["text"], "Looked up the weather.");
+
+        let ids: Vec<_> = list.data.iter().map(|item| item.id()).collect();
+        assert_eq!(
+            ids,
+            [Some("msg_in_1"), Some("fc_1"), Some("fco_1"), Some("rs_1")]
+        );
+    }
+
+    #[test]
+    fn test_untyped_item_is_a_message() {
+        let item: ResponseInputItem = serde_json::from_value(json!({
+            "id": "msg_1",
+            "role": "developer",
+            "content": [{"type": "input_text", "text": "Be brief."}]
+        }))
+        .unwrap();
+
+        assert!(matches!(&item, ResponseInputItem::Message(m) if m.role == "developer"));
+        assert_eq!(serde_json::to_value(&item).unwrap()["type"], "message");
+    }
+
+    #[test]
+    fn test_page_from_input_item_list() {
+        let body = list_body(vec![message("msg_1"), message("msg_2")], true);
+        let list: ResponseInputItemList = serde_json::from_str(&body).unwrap();
+
+        let page = Page::from(list.clone());
+        assert_eq!(page.items, list.data);
+        assert_eq!(page.next_cursor.as_deref(), Some("msg_2"));
+
+        let empty: ResponseInputItemList = serde_json::from_str(&list_body(vec![], false)).unwrap();
+        let page = Page::from(empty);
+        assert!(page.items.is_empty());
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_list_response_input_items() {
+        use reqwest::Method;
+        use twcai::api::ResponsesExt;
+        use twcai::testing::{Fixture, FixtureTransport};
+
+        use crate::common::client_with;
+
+        let fixtures = FixtureTransport::new().route(
+            Method::GET,
+            INPUT_ITEMS_PATH,
+            Fixture::raw(
+                200,
+                "application/json",
+                list_body(vec![message("msg_1")], false),
+            ),
+        );
+
+        let query = ListItemsQuery {
+            limit: Some(10),
+            order: Some("desc".to_string()),
+            ..Default::default()
+        };
+        let list = client_with(&fixtures)
+            .list_response_input_items("agent-1", "resp_123", Some(query))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fixtures.requests()[0].query.as_deref(),
+            Some("limit=10&order=desc")
+        );
+        assert_eq!(list.data.len(), 1);
+        assert!(!list.has_more);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_stream_response_input_items() {
+        use futures_util::StreamExt;
+        use reqwest::Method;
+        use twcai::api::ResponsesExt;
+        use twcai::testing::{Fixture, FixtureTransport};
+
+        use crate::common::client_with;
+
+        let fixtures = FixtureTransport::new()
+            .route_once(
+                Method::GET,
+                INPUT_ITEMS_PATH,
+                Fixture::raw(
+                    200,
+                    "application/json",
+                    list_body(vec![message("msg_1"), message("msg_2")], true),
+                ),
+            )
+            .route_once(
+                Method::GET,
+                INPUT_ITEMS_PATH,
+                Fixture::raw(
+                    200,
+                    "application/json",
+                    list_body(vec![message("msg_3")], false),
+                ),
+            );
+
+        let query = ListItemsQuery {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let ids: Vec<String> = client_with(&fixtures)
+            .stream_response_input_items("agent-1", "resp_123", Some(query))
+            .map(|item| item.unwrap().id().unwrap().to_string())
+            .collect()
+            .await;
+
+        let queries: Vec<_> = fixtures
+            .requests()
+            .into_iter()
+            .map(|request| request.query)
+            .collect();
+        assert_eq!(
+            queries,
+            [
+                Some("limit=2".to_string()),
+                Some("after=msg_2&limit=2".to_string())
+            ]
+        );
+        assert_eq!(ids, ["msg_1", "msg_2", "msg_3"]);
+    }
+}
+
+/// Response request builder tests
+#[cfg(test)]
+mod response_builder {
+    use serde_json::json;
+    use twcai::TwcError;
+    use twcai::types::*;
+
+    fn to_json(builder: CreateResponseRequestBuilder) -> serde_json::Value {
+        serde_json::to_value(builder.build().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_empty_builder_golden() {
+        let request = CreateResponseRequest::builder().build().unwrap();
+
+        assert_eq!(request, CreateResponseRequest::default());
+        assert_eq!(serde_json::to_value(&request).unwrap(), json!({}));
+    }
+
+    #[test]
+    fn test_text_input_golden() {
+        let builder = CreateResponseRequest::builder()
+            .instructions("Answer in one sentence")
+            .input_text("What is Rust?")
+            .max_output_tokens(100)
+            .temperature(0.5);
+
+        assert_eq!(
+            to_json(builder),
+            json!({
+                "instructions": "Answer in one sentence",
+                "input": "What is Rust?",
+                "max_output_tokens": 100,
+                "temperature": 0.5
+            })
+        );
+    }
+
+    #[test]
+    fn test_conversation_golden() {
+        let builder = CreateResponseRequest::builder()
+            .input_message("developer", "Be brief")
+            .input_message("user", "Hello")
+            .conversation_id("conv_123")
+            .store(true)
+            .metadata([("topic", "greeting")]);
+
+        assert_eq!(
+            to_json(builder),
+            json!({
+                "input": [
+                    {"role": "developer", "content": "Be brief"},
+                    {"role": "user", "content": "Hello"}
+                ],
+                "conversation": "conv_123",
+                "store": true,
+                "metadata": {"topic": "greeting"}
+            })
+        );
+    }
+
+    #[test]
+    fn test_background_tools_golden() {
+        let tool = ResponseTool::function("get_weather", json!({}));
+        let builder = CreateResponseRequest::builder()
+            .input_messages([json!({"role": "user", "content": "Weather in Moscow?"})])
+            .previous_response_id("resp_1")
+            .background(true)
+            .tools([tool])
+            .tool_choice("required")
+            .parallel_tool_calls(false)
+            .include([Include::ReasoningEncryptedContent])
+            .truncation(Truncation::Auto)
+            .service_tier(ServiceTier::Flex)
+            .extra_param("vendor_hint", "fast");
+
+        assert_eq!(
+            to_json(builder),
+            json!({
+                "input": [{"role": "user", "content": "Weather in Moscow?"}],
+                "previous_response_id": "resp_1",
+                "background": true,
+                "tools": [{"type": "function", "name": "get_weather", "parameters": {}}],
+                "tool_choice": "required",
+                "parallel_tool_calls": false,
+                "include": ["reasoning.encrypted_content"],
+                "truncation": "auto",
+                "service_tier": "flex",
+                "vendor_hint": "fast"
+            })
+        );
+    }
+
+    #[test]
+    fn test_input_messages_replace_earlier_messages() {
+        let request = CreateResponseRequest::builder()
+            .input_message("user", "dropped")
+            .input_messages([json!({"role": "user", "content": "kept"})])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            request.input,
+            Some(ResponseInput::Messages(vec![
+                json!({"role": "user", "content": "kept"})
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_conflicting_fields_are_rejected() {
+        let cases = [
+            (
+                CreateResponseRequest::builder()
+                    .input_text("Hi")
+                    .input_message("user", "Hi"),
+                "input_text",
+            ),
+            (
+                CreateResponseRequest::builder()
+                    .previous_response_id("resp_1")
+                    .conversation_id("conv_1"),
+                "previous_response_id",
+            ),
+            (
+                CreateResponseRequest::builder()
+                    .background(true)
+                    .store(false),
+                "background",
+            ),
+        ];
+
+        for (builder, field) in cases {
+            match builder.build() {
+                Err(TwcError::InvalidRequest(message)) => {
+                    assert!(message.contains(field), "{}", message)
+                }
+                other => panic!("expected InvalidRequest naming {}, got {:?}", field, other),
+            }
+        }
+    }
+}
+
+/// Response stream event tests
+#[cfg(test)]
+mod response_events {
+    use twcai::TwcError;
+    use twcai::types::*;
+
+    /// Captured transcript of a run that reasons and then calls a tool
+    const TOOL_CALL_STREAM: &str = include_str!("fixtures/sse/response_tool_call.txt");
+
+    fn events() -> Vec<ResponseStreamEvent> {
+        TOOL_CALL_STREAM
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect()
+    }
+
+    fn fold(events: &[ResponseStreamEvent]) -> ResponseStreamAccumulator {
+        let mut accumulator = ResponseStreamAccumulator::new();
+        for event in events {
+            accumulator.push(event);
+        }
+        accumulator
+    }
+
+    #[test]
+    fn test_transcript_events_are_typed() {
+        let events = events();
+        assert_eq!(events.len(), 14);
+
+        match &events[4] {
+            ResponseStreamEvent::ReasoningSummaryTextDelta(e) => {
+                assert_eq!(e.item_id, "rs_1");
+                assert_eq!(e.output_index, 0);
+                assert_eq!(e.summary_index, 0);
+                assert_eq!(e.delta, "The user asks for the weather, ");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        match &events[8] {
+            ResponseStreamEvent::OutputItemAdded(e) => {
+                assert_eq!(e.output_index, 1);
+                assert_eq!(e.item["type"], "function_call");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        match &events[9] {
+            ResponseStreamEvent::FunctionCallArgumentsDelta(e) => {
+                assert_eq!(e.item_id, "fc_1");
+                assert_eq!(e.output_index, 1);
+                assert_eq!(e.delta, "{\"city\":");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        match &events[11] {
+            ResponseStreamEvent::FunctionCallArgumentsDone(e) => {
+                assert_eq!(e.arguments, r#"{"city":"Moscow"}"#);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        // Summary part events are not typed
+        match &events[3] {
+            ResponseStreamEvent::Other { event, .. } => {
+                assert_eq!(event, "response.reasoning_summary_part.added");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        let sequence: Vec<_> = events
+            .iter()
+            .filter_map(ResponseStreamEvent::sequence_number)
+            .collect();
+        assert_eq!(sequence, (1..=14).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_accumulator_folds_transcript() {
+        let response = fold(&events()).into_response().unwrap();
+
+        assert_eq!(response.id, "resp_67ccd");
+        assert_eq!(response.status, "completed");
+        assert_eq!(response.total_tokens(), 99);
+        assert_eq!(
+            response.reasoning_summary(),
+            "The user asks for the weather, so I should call get_weather."
+        );
+        let call = &response.output_items()[1];
+        assert_eq!(call["type"], "function_call");
+        assert_eq!(call["name"], "get_weather");
+        assert_eq!(call["arguments"], r#"{"city":"Moscow"}"#);
+    }
+
+    #[test]
+    fn test_accumulator_rebuilds_cut_stream() {
+        // The stream broke off before the arguments were complete
+        let events = events();
+        let response = fold(&events[..10]).into_response().unwrap();
+
+        assert_eq!(response.status, "in_progress");
+        assert_eq!(response.output_items().len(), 2);
+        assert_eq!(
+            response.reasoning_summary(),
+            "The user asks for the weather, so I should call get_weather."
+        );
+        assert_eq!(response.output_items()[1]["arguments"], "{\"city\":");
+    }
+
+    #[test]
+    fn test_accumulator_builds_output_text_from_deltas() {
+        let events: Vec<ResponseStreamEvent> = [
+            r#"{"type":"response.created","sequence_number":1,"response":{"id":"resp_1","object":"response","created_at":1741476542,"status":"in_progress","model":"gpt-4.1"}}"#,
+            r#"{"type":"response.output_text.delta","sequence_number":2,"item_id":"msg_1","output_index":0,"content_index":0,"delta":"Hello, "}"#,
+            r#"{"type":"response.output_text.delta","sequence_number":3,"item_id":"msg_1","output_index":0,"content_index":0,"delta":"world"}"#,
+        ]
+        .iter()
+        .map(|data| serde_json::from_str(data).unwrap())
+        .collect();
+
+        let mut accumulator = fold(&events);
+        let response = accumulator.clone().into_response().unwrap();
+        assert_eq!(
+            response.output_items()[0]["content"][0]["text"],
+            "Hello, world"
+        );
+
+        let done = serde_json::from_str(
+            r#"{"type":"response.output_item.done","sequence_number":4,"output_index":0,"item":{"id":"msg_1","type":"message","role":"assistant","content":[{"type":"output_text","text":"Hello, world"}]}}"#,
+        )
+        .unwrap();
+        accumulator.push(&done);
+        assert_eq!(
+            accumulator.into_response().unwrap().output_text(),
+            "Hello, world"
+        );
+    }
+
+    #[test]
+    fn test_into_response_needs_a_lifecycle_event() {
+        let result = ResponseStreamAccumulator::new().into_response();
+
+        assert!(matches!(result, Err(TwcError::Json(_))));
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_accumulates_streamed_response() {
+        use futures_util::StreamExt;
+        use reqwest::Method;
+        use twcai::api::ResponsesExt;
+        use twcai::testing::{Fixture, FixtureTransport};
+
+        use crate::common::client_with;
+
+        let fixtures = FixtureTransport::new().route(
+            Method::POST,
+            "/api/v1/cloud-ai/agents/agent-1/v1/responses",
+            Fixture::sse(TOOL_CALL_STREAM),
+        );
+        let client = client_with(&fixtures);
+
+        let request = CreateResponseRequest {
+            input: Some(ResponseInput::Text("Weather in Moscow?".to_string())),
+            ..Default::default()
+        };
+        let mut stream = client
+            .create_response_stream("agent-1", request)
+            .await
+            .unwrap();
+        let mut accumulator = ResponseStreamAccumulator::new();
+        while let Some(event) = stream.next().await {
+            accumulator.push(&event.unwrap());
+        }
+
+        let expected = fold(&events()).into_response().unwrap();
+        assert_eq!(accumulator.into_response().unwrap(), expected);
+    }
+}
+
+/// Response streaming tests
+#[cfg(test)]
+mod response_stream {
+    use futures_util::StreamExt;
+    use twcai::TwcError;
+    use twcai::api::ResponsesExt;
+    use twcai::types::*;
+
+    use crate::common::{ScriptedServer, builder_for, client_for, sse_head};
+
+    /// SSE frame for the event with the given sequence number: 1 is
+    /// `response.created`, 10 is `response.completed`, the rest are deltas
+    fn event(sequence: u32) -> String {
+        let data = match sequence {
+            1 => r#"{"type":"response.created","sequence_number":1,"response":{"id":"resp_1","status":"in_progress"}}"#
+                .to_string(),
+            10 => r#"{"type":"response.completed","sequence_number":10,"response":{"id":"resp_1","status":"completed"}}"#
+                .to_string(),
+            n => format!(
+                r#"{{"type":"response.output_text.delta","sequence_number":{},"item_id":"msg_1","output_index":0,"content_index":0,"delta":"t{} "}}"#,
+                n, n
+            ),
+        };
+        let name = data.split('"').nth(3).unwrap().to_string();
+        format!("event: {}\ndata: {}\n\n", name, data)
+    }
+
+    fn events(sequences: std::ops::RangeInclusive<u32>) -> String {
+        sequences.map(event).collect()
+    }
+
+    /// Response that delivers `body` and then drops the connection
+    fn cut(body: String) -> String {
+        format!("{}{}", sse_head(body.len() + 1024), body)
+    }
+
+    /// Response that delivers `body` completely
+    fn complete(body: String) -> String {
+        format!("{}{}", sse_head(body.len()), body)
+    }
+
+    fn request() -> CreateResponseRequest {
+        CreateResponseRequest {
+            input: Some(ResponseInput::Text("Count to ten".to_string())),
+            background: Some(true),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resumes_after_cut_without_duplicates() {
+        let server = ScriptedServer::start(vec![
+            cut(events(1..=5)),
+            // The server replays event 4 and 5 before continuing
+            complete(events(4..=10)),
+        ])
+        .await;
+
+        let stream = client_for(&server.url)
+            .resilient_response_stream("agent-1", request(), 3)
+            .await
+            .unwrap();
+        let received: Vec<_> = stream.map(|e| e.unwrap()).collect().await;
+
+        let sequences: Vec<_> = received
+            .iter()
+            .filter_map(|e| e.sequence_number())
+            .collect();
+        assert_eq!(sequences, (1..=10).collect::<Vec<_>>());
+        assert!(matches!(received[0], ResponseStreamEvent::Created(_)));
+        assert!(matches!(received[9], ResponseStreamEvent::Completed(_)));
+
+        let text: String = received
+            .iter()
+            .filter_map(|e| match e {
+                ResponseStreamEvent::OutputTextDelta(delta) => Some(delta.delta.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "t2 t3 t4 t5 t6 t7 t8 t9 ");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].starts_with("POST /api/v1/cloud-ai/agents/agent-1/v1/responses "));
+        assert!(requests[1].starts_with(
+            "GET /api/v1/cloud-ai/agents/agent-1/v1/responses/resp_1?starting_after=5&stream=true "
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_bad_event_keeps_connection() {
+        let body = format!("{}data: {{not json}}\n\n{}", events(1..=3), events(4..=10));
+        let server = ScriptedServer::start(vec![complete(body)]).await;
+
+        let stream = client_for(&server.url)
+            .resilient_response_stream("agent-1", request(), 3)
+            .await
+            .unwrap();
+        let received: Vec<_> = stream.collect().await;
+
+        assert_eq!(received.len(), 11);
+        assert!(matches!(received[3], Err(TwcError::Json(_))));
+        let sequences: Vec<_> = received
+            .iter()
+            .filter_map(|e| e.as_ref().ok()?.sequence_number())
+            .collect();
+        assert_eq!(sequences, (1..=10).collect::<Vec<_>>());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_error_surfaces_after_reconnects_exhausted() {
+        let server = ScriptedServer::start(vec![
+            cut(events(1..=3)),
+            cut(events(4..=5)),
+            cut(events(6..=7)),
+        ])
+        .await;
+
+        let stream = client_for(&server.url)
+            .resilient_response_stream("agent-1", request(), 2)
+            .await
+            .unwrap();
+        let received: Vec<_> = stream.collect().await;
+
+        assert_eq!(received.len(), 8);
+        assert!(received[..7].iter().all(|e| e.is_ok()));
+        assert!(matches!(received[7], Err(TwcError::Http(_))));
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[cfg(feature = "tower")]
+    #[tokio::test]
+    async fn test_resumes_after_refused_reconnect() {
+        use crate::common::RefuseLayer;
+
+        let server =
+            ScriptedServer::start(vec![cut(events(1..=5)), complete(events(6..=10))]).await;
+        // The first reconnect is refused, the second one succeeds
+        let client = builder_for(&server.url)
+            .layer(RefuseLayer::new(&[1]))
+            .build()
+            .unwrap();
+
+        let stream = client
+            .resilient_response_stream("agent-1", request(), 3)
+            .await
+            .unwrap();
+        let received: Vec<_> = stream.map(|e| e.unwrap()).collect().await;
+
+        let sequences: Vec<_> = received
+            .iter()
+            .filter_map(|e| e.sequence_number())
+            .collect();
+        assert_eq!(sequences, (1..=10).collect::<Vec<_>>());
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_plain_stream_does_not_resume() {
+        let server = ScriptedServer::start(vec![cut(events(1..=5))]).await;
+
+        let stream = client_for(&server.url)
+            .create_response_stream("agent-1", request())
+            .await
+            .unwrap();
+        let received: Vec<_> = stream.collect().await;
+
+        assert_eq!(received.len(), 6);
+        assert!(received.last().unwrap().is_err());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_events_are_kept_raw() {
+        let event: ResponseStreamEvent = serde_json::from_str(
+            r#"{"type":"response.content_part.added","sequence_number":7,"item_id":"msg_1"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(event.sequence_number(), Some(7));
+        match event {
+            ResponseStreamEvent::Other { event, data } => {
+                assert_eq!(event, "response.content_part.added");
+                assert_eq!(data["item_id"], "msg_1");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_response_rejects_stream_query() {
+        let server = mockito::Server::new_async().await;
+        let client = client_for(server.url());
+
+        let query = GetResponseQuery {
+            stream: Some(true),
+            ..Default::default()
+        };
+        let err = client
+            .get_response("agent-1", "resp_1", Some(query))
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(err, TwcError::InvalidRequest(ref m) if m.contains("get_response_stream"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_response_stream_replays_events() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/responses/resp_1")
+            .match_query(mockito::Matcher::Exact(
+                "starting_after=8&stream=true".to_string(),
+            ))
+            .match_header("accept", "text/event-stream")
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(events(9..=10))
+            .create_async()
+            .await;
+        let client = client_for(server.url());
+
+        let query = GetResponseQuery {
+            starting_after: Some(8),
+            ..Default::default()
+        };
+        let stream = client
+            .get_response_stream("agent-1", "resp_1", Some(query))
+            .await
+            .unwrap();
+        let received: Vec<_> = stream.map(|e| e.unwrap()).collect().await;
+
+        mock.assert_async().await;
+        assert_eq!(received.len(), 2);
+        assert_eq!(received[1].response_id(), Some("resp_1"));
+    }
+}
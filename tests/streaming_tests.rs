@@ -0,0 +1,1402 @@
+//! Streaming tests for TWCai
+
+mod common;
+
+/// Chat and response streaming tests
+#[cfg(test)]
+mod chat_streams {
+    use std::io::Write;
+    use std::time::Duration;
+
+    use futures_util::StreamExt;
+    use mockito::Matcher;
+    use twcai::api::{AgentClientExt, ResponsesExt};
+    use twcai::{StreamOptionsLocal, TwcError, types::*};
+
+    use crate::common::{builder_for, client_for};
+
+    const CHAT_COMPLETION_SSE: &str = include_str!("fixtures/sse/chat_completion.txt");
+    const TEXT_COMPLETION_SSE: &str = include_str!("fixtures/sse/text_completion.txt");
+    const TEXT_COMPLETION_LOGPROBS_SSE: &str =
+        include_str!("fixtures/sse/text_completion_logprobs.txt");
+
+    fn chat_request() -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            messages: vec![ChatMessage::user("What is the capital of France?")],
+            ..Default::default()
+        }
+    }
+
+    fn chunk_event(content: &str) -> String {
+        format!(
+            "data: {{\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"m\",\"choices\":[{{\"index\":0,\"delta\":{{\"content\":\"{}\"}},\"finish_reason\":null}}]}}\n\n",
+            content
+        )
+    }
+
+    fn text_request(prompt: &str) -> TextCompletionRequest {
+        TextCompletionRequest::new(prompt)
+    }
+
+    #[tokio::test]
+    #[allow(deprecated)]
+    async fn test_text_completions_stream_yields_deltas() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/v1/cloud-ai/agents/agent-1/v1/completions")
+            .match_body(Matcher::PartialJsonString(r#"{"stream":true}"#.to_string()))
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(TEXT_COMPLETION_SSE)
+            .create_async()
+            .await;
+
+        let client = client_for(server.url());
+        let stream = client
+            .text_completions_stream("agent-1", text_request("Say hello"))
+            .await
+            .unwrap();
+        let chunks: Vec<_> = stream.collect().await;
+
+        mock.assert_async().await;
+        let chunks: Vec<_> = chunks.into_iter().map(|c| c.unwrap()).collect();
+        assert_eq!(chunks.len(), 4);
+
+        let text: String = chunks.iter().map(|c| c.text_delta.as_str()).collect();
+        assert_eq!(text, "Hello, world");
+        assert!(chunks.iter().all(|c| c.index == 0));
+        assert!(chunks[..3].iter().all(|c| c.finish_reason.is_none()));
+        assert_eq!(chunks[3].finish_reason.as_deref(), Some("stop"));
+    }
+
+    #[tokio::test]
+    #[allow(deprecated)]
+    async fn test_text_completions_stream_logprobs() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/api/v1/cloud-ai/agents/agent-1/v1/completions")
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(TEXT_COMPLETION_LOGPROBS_SSE)
+            .create_async()
+            .await;
+
+        let client = client_for(server.url());
+        let mut stream = client
+            .text_completions_stream("agent-1", text_request("Answer yes or no"))
+            .await
+            .unwrap();
+
+        let first = stream.next().await.unwrap().unwrap();
+        let logprobs = first.logprobs.unwrap();
+        assert_eq!(logprobs.tokens, vec!["Yes".to_string()]);
+        assert_eq!(logprobs.token_logprobs, vec![-0.12]);
+
+        let last = stream.next().await.unwrap().unwrap();
+        assert!(last.logprobs.is_none());
+        assert_eq!(last.finish_reason.as_deref(), Some("length"));
+
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    #[allow(deprecated)]
+    async fn test_text_completions_stream_error_status() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/api/v1/cloud-ai/agents/agent-1/v1/completions")
+            .with_status(401)
+            .create_async()
+            .await;
+
+        let client = client_for(server.url());
+        let result = client
+            .text_completions_stream("agent-1", text_request("Say hello"))
+            .await;
+
+        assert!(matches!(result, Err(TwcError::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn test_chat_completions_stream_yields_chunks() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock(
+                "POST",
+                "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            )
+            .match_header("accept", "text/event-stream")
+            .match_body(Matcher::PartialJsonString(r#"{"stream":true}"#.to_string()))
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(CHAT_COMPLETION_SSE)
+            .create_async()
+            .await;
+
+        let client = client_for(server.url());
+        let stream = client
+            .chat_completions_stream("agent-1", chat_request())
+            .await
+            .unwrap();
+        let chunks: Vec<_> = stream.map(|c| c.unwrap()).collect().await;
+
+        mock.assert_async().await;
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(
+            chunks[0].choices[0].delta.role.as_deref(),
+            Some("assistant")
+        );
+
+        let text: String = chunks
+            .iter()
+            .filter_map(|c| c.choices[0].delta.content.as_deref())
+            .collect();
+        assert_eq!(text, "The capital of France is Paris.");
+        assert_eq!(chunks[3].choices[0].finish_reason, Some(FinishReason::Stop));
+    }
+
+    #[tokio::test]
+    async fn test_chat_stream_outlives_overall_timeout() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock(
+                "POST",
+                "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            )
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_chunked_body(|w| {
+                for i in 0..8 {
+                    w.write_all(chunk_event(&format!("part{} ", i)).as_bytes())?;
+                    w.flush()?;
+                    std::thread::sleep(Duration::from_millis(150));
+                }
+                w.write_all(b"data: [DONE]\n\n")
+            })
+            .create_async()
+            .await;
+
+        // Total stream time (~1.2s) far exceeds the overall timeout, but
+        // chunks arrive well within the idle timeout.
+        let client = builder_for(server.url())
+            .timeout(Duration::from_millis(300))
+            .stream_idle_timeout(Duration::from_secs(1))
+            .build()
+            .unwrap();
+
+        let stream = client
+            .chat_completions_stream("agent-1", chat_request())
+            .await
+            .unwrap();
+        let chunks: Vec<_> = stream.collect().await;
+
+        assert_eq!(chunks.len(), 8);
+        assert!(chunks.iter().all(|c| c.is_ok()));
+    }
+
+    #[tokio::test]
+    async fn test_chat_stream_idle_timeout() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock(
+                "POST",
+                "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            )
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_chunked_body(|w| {
+                w.write_all(chunk_event("hello").as_bytes())?;
+                w.flush()?;
+                std::thread::sleep(Duration::from_millis(800));
+                w.write_all(b"data: [DONE]\n\n")
+            })
+            .create_async()
+            .await;
+
+        let client = builder_for(server.url())
+            .stream_idle_timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+
+        let mut stream = client
+            .chat_completions_stream("agent-1", chat_request())
+            .await
+            .unwrap();
+
+        assert!(stream.next().await.unwrap().is_ok());
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(TwcError::Timeout(_)))
+        ));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_options_idle_timeout_overrides_client() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock(
+                "POST",
+                "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            )
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_chunked_body(|w| {
+                w.write_all(chunk_event("hello").as_bytes())?;
+                w.flush()?;
+                std::thread::sleep(Duration::from_millis(800));
+                w.write_all(b"data: [DONE]\n\n")
+            })
+            .create_async()
+            .await;
+
+        let client = builder_for(server.url())
+            .stream_idle_timeout(Duration::from_secs(10))
+            .build()
+            .unwrap();
+
+        let options = StreamOptionsLocal::idle_timeout(Duration::from_millis(200));
+        let mut stream = client
+            .chat_completions_stream_with_options("agent-1", chat_request(), options)
+            .await
+            .unwrap();
+
+        assert!(stream.next().await.unwrap().is_ok());
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(TwcError::Timeout(idle))) if idle == Duration::from_millis(200)
+        ));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_comments_reset_idle_timeout() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock(
+                "POST",
+                "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            )
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_chunked_body(|w| {
+                w.write_all(chunk_event("hello").as_bytes())?;
+                w.flush()?;
+                // 600 ms without events, but a heartbeat every 100 ms
+                for _ in 0..6 {
+                    std::thread::sleep(Duration::from_millis(100));
+                    w.write_all(b": ping\n\n")?;
+                    w.flush()?;
+                }
+                w.write_all(chunk_event(" world").as_bytes())?;
+                w.write_all(b"data: [DONE]\n\n")
+            })
+            .create_async()
+            .await;
+
+        let options = StreamOptionsLocal::idle_timeout(Duration::from_millis(300));
+        let chunks: Vec<_> = client_for(server.url())
+            .chat_completions_stream_with_options("agent-1", chat_request(), options)
+            .await
+            .unwrap()
+            .collect()
+            .await;
+
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|c| c.is_ok()));
+    }
+
+    #[tokio::test]
+    async fn test_response_stream_options_idle_timeout() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/api/v1/cloud-ai/agents/agent-1/v1/responses")
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_chunked_body(|w| {
+                w.write_all(b"event: response.created\ndata: {\"type\":\"response.created\",\"sequence_number\":1,\"response\":{\"id\":\"resp_1\"}}\n\n")?;
+                w.flush()?;
+                std::thread::sleep(Duration::from_millis(800));
+                w.write_all(b"data: [DONE]\n\n")
+            })
+            .create_async()
+            .await;
+
+        let request = CreateResponseRequest {
+            input: Some(ResponseInput::Text("Hi".to_string())),
+            ..Default::default()
+        };
+        let options = StreamOptionsLocal::idle_timeout(Duration::from_millis(200));
+        let mut stream = client_for(server.url())
+            .create_response_stream_with_options("agent-1", request, options)
+            .await
+            .unwrap();
+
+        assert!(stream.next().await.unwrap().is_ok());
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(TwcError::Timeout(_)))
+        ));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[test]
+    fn test_stream_options_default_is_generous() {
+        assert_eq!(
+            StreamOptionsLocal::default().idle_timeout,
+            Duration::from_secs(90)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_non_streaming_request_honors_overall_timeout() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/models")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_chunked_body(|w| {
+                std::thread::sleep(Duration::from_millis(800));
+                w.write_all(br#"{"object":"list","data":[]}"#)
+            })
+            .create_async()
+            .await;
+
+        let client = builder_for(server.url())
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+
+        let result = client.list_models("agent-1").await;
+        assert!(matches!(result, Err(TwcError::Http(ref e)) if e.is_timeout()));
+    }
+}
+
+/// Stream abort tests
+#[cfg(test)]
+mod abort {
+    use std::time::Duration;
+
+    use futures_util::StreamExt;
+    use twcai::api::{AgentClientExt, ResponsesExt};
+    use twcai::types::*;
+
+    use crate::common::{OpenStreamServer, client_for};
+
+    /// Time allowed for the server to see the connection close
+    const CLOSE_WITHIN: Duration = Duration::from_secs(2);
+
+    const CHAT_EVENTS: &str = "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Once upon\"},\"finish_reason\":null}]}\n\n";
+
+    const RESPONSE_EVENTS: &str = "event: response.created\ndata: {\"type\":\"response.created\",\"sequence_number\":1,\"response\":{\"id\":\"resp_1\",\"status\":\"in_progress\"}}\n\n";
+
+    fn chat_request() -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Tell me a long story")],
+            ..Default::default()
+        }
+    }
+
+    fn response_request() -> CreateResponseRequest {
+        CreateResponseRequest {
+            input: Some(ResponseInput::Text("Tell me a long story".to_string())),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drop_closes_connection() {
+        let server = OpenStreamServer::start(CHAT_EVENTS).await;
+        let mut stream = client_for(&server.url)
+            .chat_completions_stream("agent-1", chat_request())
+            .await
+            .unwrap();
+        assert!(stream.next().await.unwrap().is_ok());
+
+        drop(stream);
+
+        tokio::time::timeout(CLOSE_WITHIN, server.closed.notified())
+            .await
+            .expect("connection still open after the stream was dropped");
+    }
+
+    #[tokio::test]
+    async fn test_abort_chat_stream_closes_connection() {
+        let server = OpenStreamServer::start(CHAT_EVENTS).await;
+        let mut stream = client_for(&server.url)
+            .chat_completions_stream("agent-1", chat_request())
+            .await
+            .unwrap();
+        assert!(stream.next().await.unwrap().is_ok());
+
+        stream.abort().await;
+
+        tokio::time::timeout(CLOSE_WITHIN, server.closed.notified())
+            .await
+            .expect("connection still open after abort");
+        // Chat completions cannot be cancelled server-side
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_abort_response_stream_cancels_response() {
+        let server = OpenStreamServer::start(RESPONSE_EVENTS).await;
+        let mut stream = client_for(&server.url)
+            .create_response_stream("agent-1", response_request())
+            .await
+            .unwrap();
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(first.response_id(), Some("resp_1"));
+
+        stream.abort().await;
+
+        tokio::time::timeout(CLOSE_WITHIN, server.closed.notified())
+            .await
+            .expect("connection still open after abort");
+        assert_eq!(
+            server.requests()[1],
+            "POST /api/v1/cloud-ai/agents/agent-1/v1/responses/resp_1/cancel HTTP/1.1"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_abort_before_response_id_skips_cancel() {
+        let server = OpenStreamServer::start(RESPONSE_EVENTS).await;
+        let stream = client_for(&server.url)
+            .create_response_stream("agent-1", response_request())
+            .await
+            .unwrap();
+
+        stream.abort().await;
+
+        tokio::time::timeout(CLOSE_WITHIN, server.closed.notified())
+            .await
+            .expect("connection still open after abort");
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_abort_replayed_stream_uses_known_id() {
+        let server = OpenStreamServer::start(RESPONSE_EVENTS).await;
+        let stream = client_for(&server.url)
+            .get_response_stream("agent-1", "resp_9", None)
+            .await
+            .unwrap();
+
+        stream.abort().await;
+
+        let cancel = "POST /api/v1/cloud-ai/agents/agent-1/v1/responses/resp_9/cancel HTTP/1.1";
+        assert!(server.requests().iter().any(|line| line == cancel));
+    }
+}
+
+/// Cancellation tests
+#[cfg(test)]
+mod cancellation {
+    use std::io::Write;
+    use std::time::Duration;
+
+    use twcai::api::{AgentClientExt, ResponsesExt};
+    use twcai::{CancellationToken, TwcError, types::*};
+
+    use crate::common::client_for;
+
+    fn response_body(status: &str) -> String {
+        format!(
+            r#"{{
+                "id": "resp_1",
+                "object": "response",
+                "created_at": 1700000000,
+                "model": "deepseek-reason",
+                "status": "{}",
+                "usage": {{"prompt_tokens": 0, "completion_tokens": 0, "total_tokens": 0}}
+            }}"#,
+            status
+        )
+    }
+
+    fn cancel_after(token: &CancellationToken, delay: Duration) {
+        let token = token.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            token.cancel();
+        });
+    }
+
+    #[tokio::test]
+    async fn test_background_response_cancel_hits_server() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/api/v1/cloud-ai/agents/agent-1/v1/responses")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(response_body("queued"))
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/responses/resp_1")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(response_body("in_progress"))
+            .create_async()
+            .await;
+        let cancel = server
+            .mock(
+                "POST",
+                "/api/v1/cloud-ai/agents/agent-1/v1/responses/resp_1/cancel",
+            )
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(response_body("cancelled"))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = client_for(server.url());
+        let token = CancellationToken::new();
+        cancel_after(&token, Duration::from_millis(700));
+
+        let request = CreateResponseRequest {
+            input: Some(ResponseInput::Text("Write a long essay".to_string())),
+            background: Some(true),
+            ..Default::default()
+        };
+        let result = client
+            .create_response_cancellable("agent-1", request, token)
+            .await;
+
+        assert!(matches!(result, Err(TwcError::Cancelled)));
+        cancel.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_background_response_completes_without_cancel() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/api/v1/cloud-ai/agents/agent-1/v1/responses")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(response_body("queued"))
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/cloud-ai/agents/agent-1/v1/responses/resp_1")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(response_body("completed"))
+            .create_async()
+            .await;
+        let cancel = server
+            .mock(
+                "POST",
+                "/api/v1/cloud-ai/agents/agent-1/v1/responses/resp_1/cancel",
+            )
+            .expect(0)
+            .create_async()
+            .await;
+
+        let client = client_for(server.url());
+        let request = CreateResponseRequest {
+            background: Some(true),
+            ..Default::default()
+        };
+        let response = client
+            .create_response_cancellable("agent-1", request, CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status, "completed");
+        cancel.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_chat_completions_cancellable_aborts_slow_request() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock(
+                "POST",
+                "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            )
+            .with_status(200)
+            .with_chunked_body(|w| {
+                std::thread::sleep(Duration::from_secs(2));
+                w.write_all(b"{}")
+            })
+            .create_async()
+            .await;
+
+        let client = client_for(server.url());
+        let token = CancellationToken::new();
+        cancel_after(&token, Duration::from_millis(100));
+
+        let request = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hello")],
+            ..Default::default()
+        };
+        let started = std::time::Instant::now();
+        let result = client
+            .chat_completions_cancellable("agent-1", request, token)
+            .await;
+
+        assert!(matches!(result, Err(TwcError::Cancelled)));
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
+
+/// Server-sent events parser tests
+#[cfg(test)]
+mod sse {
+    use bytes::Bytes;
+    use futures_util::StreamExt;
+    use twcai::sse::{EventStream, SseEvent};
+    use twcai::{Result, TwcError};
+
+    const TRANSCRIPT: &str = "event: response.created\n\
+        id: 1\n\
+        data: {\"a\":1}\n\
+        \n\
+        : heartbeat\n\
+        \n\
+        data: first line\n\
+        data: second line\n\
+        data\n\
+        \n\
+        data: [DONE]\n\
+        \n";
+
+    fn event(name: Option<&str>, data: &str, id: Option<&str>) -> SseEvent {
+        SseEvent {
+            event: name.map(str::to_string),
+            data: data.to_string(),
+            id: id.map(str::to_string),
+        }
+    }
+
+    fn data(data: &str) -> SseEvent {
+        event(None, data, None)
+    }
+
+    fn expected_transcript() -> Vec<SseEvent> {
+        vec![
+            event(Some("response.created"), "{\"a\":1}", Some("1")),
+            data("first line\nsecond line\n"),
+            data("[DONE]"),
+        ]
+    }
+
+    async fn parse_results(chunks: Vec<Vec<u8>>) -> Vec<Result<SseEvent>> {
+        let bytes = futures_util::stream::iter(
+            chunks
+                .into_iter()
+                .map(|chunk| Ok::<_, TwcError>(Bytes::from(chunk))),
+        );
+        EventStream::new(bytes).collect().await
+    }
+
+    async fn parse(chunks: Vec<Vec<u8>>) -> Vec<SseEvent> {
+        parse_results(chunks)
+            .await
+            .into_iter()
+            .map(|event| event.unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_single_chunk() {
+        let events = parse(vec![TRANSCRIPT.as_bytes().to_vec()]).await;
+        assert_eq!(events, expected_transcript());
+    }
+
+    #[tokio::test]
+    async fn test_every_split_point() {
+        let bytes = TRANSCRIPT.as_bytes();
+        for split in 0..=bytes.len() {
+            let chunks = vec![bytes[..split].to_vec(), bytes[split..].to_vec()];
+            assert_eq!(
+                parse(chunks).await,
+                expected_transcript(),
+                "split at {}",
+                split
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_byte_by_byte() {
+        let chunks = TRANSCRIPT.bytes().map(|b| vec![b]).collect();
+        assert_eq!(parse(chunks).await, expected_transcript());
+    }
+
+    #[tokio::test]
+    async fn test_crlf_line_endings() {
+        let crlf = TRANSCRIPT.replace('\n', "\r\n");
+        assert_eq!(
+            parse(vec![crlf.clone().into_bytes()]).await,
+            expected_transcript()
+        );
+
+        // The `\r` and `\n` of one line ending may arrive in different chunks
+        let bytes = crlf.as_bytes();
+        for split in 0..=bytes.len() {
+            let chunks = vec![bytes[..split].to_vec(), bytes[split..].to_vec()];
+            assert_eq!(
+                parse(chunks).await,
+                expected_transcript(),
+                "split at {}",
+                split
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_comment_heartbeats_are_skipped() {
+        let body = ": keep-alive\n\n: keep-alive\n\ndata: x\n\n:\n\n";
+        assert_eq!(parse(vec![body.as_bytes().to_vec()]).await, [data("x")]);
+
+        let only_heartbeats = ": ping\n\n".repeat(3);
+        assert!(parse(vec![only_heartbeats.into_bytes()]).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_last_event_without_blank_line() {
+        let cases = [
+            ("data: last\n", "last"),
+            ("data: last", "last"),
+            ("data: last\r\n", "last"),
+            ("data: a\ndata: b", "a\nb"),
+        ];
+        for (body, expected) in cases {
+            assert_eq!(
+                parse(vec![body.as_bytes().to_vec()]).await,
+                [data(expected)],
+                "{:?}",
+                body
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_field_parsing() {
+        // One leading space is stripped, further ones are data; unknown fields
+        // and `retry` are ignored
+        let body = "data:no space\ndata:  two spaces\nretry: 100\nfoo: bar\n\n";
+        assert_eq!(
+            parse(vec![body.as_bytes().to_vec()]).await,
+            [data("no space\n two spaces")]
+        );
+
+        // A colon inside the value is kept
+        let body = "data: {\"url\":\"http://x\"}\n\n";
+        assert_eq!(
+            parse(vec![body.as_bytes().to_vec()]).await,
+            [data("{\"url\":\"http://x\"}")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_multibyte_utf8_split_across_chunks() {
+        let body = "data: Привет, мир\n\n".as_bytes();
+        for split in 0..=body.len() {
+            let chunks = vec![body[..split].to_vec(), body[split..].to_vec()];
+            assert_eq!(
+                parse(chunks).await,
+                [data("Привет, мир")],
+                "split at {}",
+                split
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_malformed_utf8_errors_one_event() {
+        let mut body = b"data: before\n\ndata: ".to_vec();
+        body.extend_from_slice(&[0xff, 0xfe]);
+        body.extend_from_slice(b"\ndata: rest of bad event\n\ndata: after\n\n");
+
+        let events = parse_results(vec![body]).await;
+
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].as_ref().unwrap(), &data("before"));
+        assert!(
+            matches!(events[1], Err(TwcError::InvalidEvent(_))),
+            "{:?}",
+            events[1]
+        );
+        assert_eq!(events[2].as_ref().unwrap(), &data("after"));
+    }
+
+    #[tokio::test]
+    async fn test_transport_error_ends_stream() {
+        let chunks = vec![
+            Ok(Bytes::from_static(b"data: one\n\n")),
+            Err(TwcError::Timeout(std::time::Duration::from_secs(1))),
+            Ok(Bytes::from_static(b"data: two\n\n")),
+        ];
+        let events: Vec<_> = EventStream::new(futures_util::stream::iter(chunks))
+            .collect()
+            .await;
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].as_ref().unwrap(), &data("one"));
+        assert!(matches!(events[1], Err(TwcError::Timeout(_))));
+    }
+}
+
+/// Chat stream resume tests
+#[cfg(test)]
+mod stream_resume {
+    use futures_util::StreamExt;
+    use twcai::api::AgentClientExt;
+    use twcai::types::*;
+    use twcai::{ResumePolicy, StreamOptionsLocal, TwcError};
+
+    use crate::common::{ScriptedServer, builder_for, client_for, sse_head};
+
+    fn chunk_event(content: &str) -> String {
+        format!(
+            "data: {{\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"m\",\"choices\":[{{\"index\":0,\"delta\":{{\"content\":\"{}\"}},\"finish_reason\":null}}]}}\n\n",
+            content
+        )
+    }
+
+    fn chunks(contents: &[&str]) -> String {
+        contents
+            .iter()
+            .map(|content| chunk_event(content))
+            .collect()
+    }
+
+    /// Response that delivers `body` and then drops the connection
+    fn cut(body: String) -> String {
+        format!("{}{}", sse_head(body.len() + 1024), body)
+    }
+
+    /// Response that delivers `body` and the end marker completely
+    fn complete(body: String) -> String {
+        let body = format!("{}data: [DONE]\n\n", body);
+        format!("{}{}", sse_head(body.len()), body)
+    }
+
+    fn request() -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Greet the world")],
+            ..Default::default()
+        }
+    }
+
+    fn options(policy: ResumePolicy) -> StreamOptionsLocal {
+        StreamOptionsLocal::default().resume_policy(policy)
+    }
+
+    fn text(received: &[twcai::Result<ChatCompletionStreamResponse>]) -> String {
+        received
+            .iter()
+            .filter_map(|chunk| chunk.as_ref().ok())
+            .filter_map(|chunk| chunk.choices[0].delta.content.as_deref())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_default_policy_yields_transport_error() {
+        let server = ScriptedServer::start(vec![cut(chunks(&["Hello", ", "]))]).await;
+
+        let stream = client_for(&server.url)
+            .chat_completions_stream_with_options("agent-1", request(), options(ResumePolicy::Off))
+            .await
+            .unwrap();
+        let received: Vec<_> = stream.collect().await;
+
+        assert_eq!(received.len(), 3);
+        assert!(matches!(received[2], Err(TwcError::Http(_))));
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_surface_carries_partial_text() {
+        let server = ScriptedServer::start(vec![cut(chunks(&["Hello", ", ", "wor"]))]).await;
+
+        let stream = client_for(&server.url)
+            .chat_completions_stream_with_options(
+                "agent-1",
+                request(),
+                options(ResumePolicy::Surface),
+            )
+            .await
+            .unwrap();
+        let received: Vec<_> = stream.collect().await;
+
+        assert_eq!(received.len(), 4);
+        assert_eq!(text(&received), "Hello, wor");
+        match &received[3] {
+            Err(TwcError::StreamInterrupted {
+                partial_text,
+                source,
+            }) => {
+                assert_eq!(partial_text, "Hello, wor");
+                assert!(matches!(**source, TwcError::Http(_)));
+            }
+            other => panic!("expected StreamInterrupted, got {:?}", other),
+        }
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_continue_reissues_with_partial_text() {
+        let server = ScriptedServer::start(vec![
+            cut(chunks(&["Hello", ", "])),
+            complete(chunks(&["world"])),
+        ])
+        .await;
+
+        let policy = ResumePolicy::Continue { max_attempts: 2 };
+        let stream = client_for(&server.url)
+            .chat_completions_stream_with_options("agent-1", request(), options(policy))
+            .await
+            .unwrap();
+        let received: Vec<_> = stream.collect().await;
+
+        assert!(received.iter().all(|chunk| chunk.is_ok()));
+        assert_eq!(text(&received), "Hello, world");
+
+        let bodies = server.json_bodies();
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[0]["messages"].as_array().unwrap().len(), 1);
+        let messages = bodies[1]["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[0]["content"], "Greet the world");
+        assert_eq!(messages[1]["role"], "assistant");
+        assert_eq!(messages[1]["content"], "Hello, ");
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(bodies[1]["stream"], true);
+    }
+
+    #[cfg(feature = "tower")]
+    #[tokio::test]
+    async fn test_continue_survives_refused_reissue() {
+        use crate::common::RefuseLayer;
+
+        let server = ScriptedServer::start(vec![
+            cut(chunks(&["Hello", ", "])),
+            complete(chunks(&["world"])),
+        ])
+        .await;
+        // The first re-send is refused, the second one succeeds
+        let client = builder_for(&server.url)
+            .layer(RefuseLayer::new(&[1]))
+            .build()
+            .unwrap();
+
+        let policy = ResumePolicy::Continue { max_attempts: 2 };
+        let stream = client
+            .chat_completions_stream_with_options("agent-1", request(), options(policy))
+            .await
+            .unwrap();
+        let received: Vec<_> = stream.collect().await;
+
+        assert!(received.iter().all(|chunk| chunk.is_ok()));
+        assert_eq!(text(&received), "Hello, world");
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_continue_surfaces_after_attempts_exhausted() {
+        let server = ScriptedServer::start(vec![
+            cut(chunks(&["Hello", ", "])),
+            cut(chunks(&["wor"])),
+            complete(chunks(&["ld"])),
+        ])
+        .await;
+
+        let policy = ResumePolicy::Continue { max_attempts: 1 };
+        let stream = client_for(&server.url)
+            .chat_completions_stream_with_options("agent-1", request(), options(policy))
+            .await
+            .unwrap();
+        let received: Vec<_> = stream.collect().await;
+
+        assert_eq!(received.len(), 4);
+        match received.last().unwrap() {
+            Err(TwcError::StreamInterrupted { partial_text, .. }) => {
+                assert_eq!(partial_text, "Hello, wor");
+            }
+            other => panic!("expected StreamInterrupted, got {:?}", other),
+        }
+        assert_eq!(server.requests().len(), 2);
+    }
+}
+
+/// Streaming retry tests
+#[cfg(test)]
+mod stream_retry {
+    use futures_util::StreamExt;
+    use twcai::api::AgentClientExt;
+    use twcai::types::*;
+    use twcai::{CloudAIClient, TwcError};
+
+    use crate::common::{ScriptedServer, builder_for, sse_head};
+
+    fn chunk_event(content: &str) -> String {
+        format!(
+            "data: {{\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"m\",\"choices\":[{{\"index\":0,\"delta\":{{\"content\":\"{}\"}},\"finish_reason\":null}}]}}\n\n",
+            content
+        )
+    }
+
+    fn chunks(contents: &[&str]) -> String {
+        contents
+            .iter()
+            .map(|content| chunk_event(content))
+            .collect()
+    }
+
+    /// Response that delivers `body` and then drops the connection
+    fn cut(body: String) -> String {
+        format!("{}{}", sse_head(body.len() + 1024), body)
+    }
+
+    /// Response that delivers `body` and the end marker completely
+    fn complete(body: String) -> String {
+        let body = format!("{}data: [DONE]\n\n", body);
+        format!("{}{}", sse_head(body.len()), body)
+    }
+
+    fn unavailable() -> String {
+        let body = r#"{"message":"unavailable"}"#;
+        format!(
+            "HTTP/1.1 503 Service Unavailable\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    }
+
+    fn retrying_client(server: &ScriptedServer) -> CloudAIClient {
+        builder_for(&server.url).max_retries(2).build().unwrap()
+    }
+
+    fn request() -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Greet the world")],
+            ..Default::default()
+        }
+    }
+
+    fn text(received: &[twcai::Result<ChatCompletionStreamResponse>]) -> String {
+        received
+            .iter()
+            .filter_map(|chunk| chunk.as_ref().ok())
+            .filter_map(|chunk| chunk.choices[0].delta.content.as_deref())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_connection_errors_are_retried() {
+        let server = ScriptedServer::start(vec![
+            unavailable(),
+            unavailable(),
+            complete(chunks(&["Hello", ", world"])),
+        ])
+        .await;
+
+        let stream = retrying_client(&server)
+            .chat_completions_stream("agent-1", request())
+            .await
+            .unwrap();
+        let received: Vec<_> = stream.collect().await;
+
+        assert!(received.iter().all(Result::is_ok));
+        assert_eq!(text(&received), "Hello, world");
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_stream_cut_before_first_event_is_retried() {
+        let server = ScriptedServer::start(vec![
+            cut(String::new()),
+            complete(chunks(&["Hello", ", world"])),
+        ])
+        .await;
+
+        let stream = retrying_client(&server)
+            .chat_completions_stream("agent-1", request())
+            .await
+            .unwrap();
+        let received: Vec<_> = stream.collect().await;
+
+        assert!(received.iter().all(Result::is_ok));
+        assert_eq!(text(&received), "Hello, world");
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_resends_share_the_retry_budget() {
+        // The first send uses one retry, so a single re-send is left
+        let server = ScriptedServer::start(vec![
+            unavailable(),
+            cut(String::new()),
+            cut(String::new()),
+            complete(chunks(&["Hello"])),
+        ])
+        .await;
+
+        let stream = retrying_client(&server)
+            .chat_completions_stream("agent-1", request())
+            .await
+            .unwrap();
+        let received: Vec<_> = stream.collect().await;
+
+        assert_eq!(received.len(), 1);
+        match &received[0] {
+            Err(TwcError::RetriesExhausted { attempts, last, .. }) => {
+                assert_eq!(*attempts, 3);
+                assert!(matches!(**last, TwcError::Http(_)));
+            }
+            other => panic!("expected RetriesExhausted, got {:?}", other),
+        }
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_stream_resend_repeats_idempotency_key() {
+        let server =
+            ScriptedServer::start(vec![cut(String::new()), complete(chunks(&["Hello"]))]).await;
+
+        let stream = retrying_client(&server)
+            .chat_completions_stream("agent-1", request())
+            .await
+            .unwrap();
+        let received: Vec<_> = stream.collect().await;
+        assert!(received.iter().all(Result::is_ok));
+
+        let keys = server.header_values("idempotency-key");
+        assert_eq!(keys.len(), 2);
+        assert!(keys[0].is_some());
+        assert_eq!(keys[0], keys[1]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_cut_after_events_is_not_retried() {
+        let server = ScriptedServer::start(vec![
+            cut(chunks(&["Hello", ", "])),
+            complete(chunks(&["Hello", ", world"])),
+        ])
+        .await;
+
+        let stream = retrying_client(&server)
+            .chat_completions_stream("agent-1", request())
+            .await
+            .unwrap();
+        let received: Vec<_> = stream.collect().await;
+
+        assert_eq!(received.len(), 3);
+        assert_eq!(text(&received), "Hello, ");
+        assert!(matches!(received[2], Err(TwcError::Http(_))));
+        assert_eq!(server.requests().len(), 1);
+    }
+}
+
+/// Streamed text tests
+#[cfg(test)]
+mod stream_text {
+    use futures_util::StreamExt;
+    use twcai::api::AgentClientExt;
+    use twcai::stream::TextStreamExt;
+    use twcai::{TwcError, types::*};
+
+    use crate::common::client_for;
+
+    const PATH: &str = "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions";
+
+    async fn server_with(body: &str) -> (mockito::ServerGuard, mockito::Mock) {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", PATH)
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(body)
+            .create_async()
+            .await;
+        (server, mock)
+    }
+
+    fn request() -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            messages: vec![ChatMessage::user("What is the capital of France?")],
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_text_yields_content_deltas() {
+        let (server, _mock) = server_with(include_str!("fixtures/sse/chat_completion.txt")).await;
+
+        let deltas: Vec<String> = client_for(server.url())
+            .chat_completions_stream_text("agent-1", request())
+            .await
+            .unwrap()
+            .map(|delta| delta.unwrap())
+            .collect()
+            .await;
+
+        // The role chunk, its empty content and the finish chunk are skipped
+        assert_eq!(deltas, ["The capital", " of France is Paris."]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_text_follows_first_choice() {
+        let (server, _mock) = server_with(include_str!("fixtures/sse/chat_two_choices.txt")).await;
+
+        let text = client_for(server.url())
+            .chat_completions_stream_text("agent-1", request())
+            .await
+            .unwrap()
+            .collect_text()
+            .await
+            .unwrap();
+
+        assert_eq!(text, "Paris.");
+    }
+
+    #[tokio::test]
+    async fn test_stream_text_skips_usage_chunk() {
+        let usage = "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"deepseek-reason\",\"choices\":[],\"usage\":{\"prompt_tokens\":9,\"completion_tokens\":6,\"total_tokens\":15}}\n\n";
+        let body = include_str!("fixtures/sse/chat_completion.txt").replace("data: [DONE]", "");
+        let (server, _mock) = server_with(&format!("{}{}data: [DONE]\n\n", body, usage)).await;
+
+        let text = client_for(server.url())
+            .chat_completions_stream_text("agent-1", request())
+            .await
+            .unwrap()
+            .collect_text()
+            .await
+            .unwrap();
+
+        assert_eq!(text, "The capital of France is Paris.");
+    }
+
+    #[tokio::test]
+    async fn test_stream_text_surfaces_refusal() {
+        let (server, _mock) = server_with(include_str!("fixtures/sse/chat_refusal.txt")).await;
+
+        let mut stream = client_for(server.url())
+            .chat_completions_stream_text("agent-1", request())
+            .await
+            .unwrap();
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), "Let me see.");
+        match stream.next().await {
+            Some(Err(TwcError::Refusal(refusal))) => {
+                assert_eq!(refusal, "I'm sorry, I can't help with that.")
+            }
+            other => panic!("expected a refusal, got {:?}", other),
+        }
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_collect_text_fails_on_refusal() {
+        let (server, _mock) = server_with(include_str!("fixtures/sse/chat_refusal.txt")).await;
+
+        let result = client_for(server.url())
+            .chat_completions_stream_text("agent-1", request())
+            .await
+            .unwrap()
+            .collect_text()
+            .await;
+
+        assert!(matches!(result, Err(TwcError::Refusal(_))), "{:?}", result);
+    }
+
+    #[tokio::test]
+    async fn test_refusal_without_finish_reason() {
+        let body = include_str!("fixtures/sse/chat_refusal.txt").replace(
+            "\"delta\":{},\"finish_reason\":\"stop\"",
+            "\"delta\":{},\"finish_reason\":null",
+        );
+        let (server, _mock) = server_with(&body).await;
+
+        let result = client_for(server.url())
+            .chat_completions_stream_text("agent-1", request())
+            .await
+            .unwrap()
+            .collect_text()
+            .await;
+
+        // The [DONE] sentinel ends the stream; the gathered refusal still surfaces
+        assert!(matches!(result, Err(TwcError::Refusal(r)) if r.ends_with("with that.")));
+    }
+}
+
+/// Stream usage accounting tests
+#[cfg(test)]
+mod stream_usage {
+    use futures_util::StreamExt;
+    use mockito::Matcher;
+    use twcai::api::AgentClientExt;
+    use twcai::types::*;
+
+    use crate::common::client_for;
+
+    const SSE_WITH_USAGE: &str = concat!(
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"Hel\"},\"finish_reason\":null}]}\n\n",
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"lo\"},\"finish_reason\":\"stop\"}],\"usage\":null}\n\n",
+        "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"m\",\"choices\":[],\"usage\":{\"prompt_tokens\":9,\"completion_tokens\":2,\"total_tokens\":11}}\n\n",
+        "data: [DONE]\n\n",
+    );
+
+    fn request() -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hi")],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_stream_options_serialized_only_when_set() {
+        let json = serde_json::to_value(request()).unwrap();
+        assert!(json.get("stream_options").is_none());
+
+        let json = serde_json::to_value(request().with_stream_usage()).unwrap();
+        assert_eq!(
+            json["stream_options"],
+            serde_json::json!({"include_usage": true})
+        );
+
+        let json = serde_json::to_value(StreamOptions::default()).unwrap();
+        assert_eq!(json, serde_json::json!({}));
+    }
+
+    #[tokio::test]
+    async fn test_summary_surfaces_usage_chunk() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock(
+                "POST",
+                "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            )
+            .match_body(Matcher::PartialJsonString(
+                r#"{"stream":true,"stream_options":{"include_usage":true}}"#.to_string(),
+            ))
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(SSE_WITH_USAGE)
+            .create_async()
+            .await;
+
+        let client = client_for(server.url());
+
+        let mut stream = client
+            .chat_completions_stream("agent-1", request().with_stream_usage())
+            .await
+            .unwrap();
+        let mut summary = ChatStreamSummary::default();
+        while let Some(chunk) = stream.next().await {
+            summary.push(&chunk.unwrap());
+        }
+
+        mock.assert_async().await;
+        assert_eq!(summary.id, "chatcmpl-1");
+        assert_eq!(summary.content, "Hello");
+        assert_eq!(summary.finish_reason, Some(FinishReason::Stop));
+        assert_eq!(summary.usage.map(|u| u.total_tokens), Some(11));
+    }
+}
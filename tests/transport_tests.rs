@@ -0,0 +1,306 @@
+//! Custom transport tests for TWCai
+
+mod common;
+
+/// tower transport tests
+#[cfg(all(test, feature = "tower"))]
+mod tower_transport {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tower::ServiceExt;
+    use tower::limit::ConcurrencyLimitLayer;
+    use twcai::api::AgentClientExt;
+
+    use crate::common::{builder_for, client_for};
+
+    const MODELS_BODY: &str = r#"{"object":"list","data":[]}"#;
+
+    /// Server answering each request after `delay`, tracking the peak number
+    /// of requests in flight at once
+    async fn slow_server(delay: Duration) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let peak_out = peak.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let active = active.clone();
+                let peak = peak.clone();
+                tokio::spawn(async move {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match socket.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(delay).await;
+                    active.fetch_sub(1, Ordering::SeqCst);
+
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                        MODELS_BODY.len(),
+                        MODELS_BODY
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+
+        (url, peak_out)
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit_layer_serializes_requests() {
+        let (url, peak) = slow_server(Duration::from_millis(100)).await;
+        let client = builder_for(url)
+            .layer(ConcurrencyLimitLayer::new(1))
+            .build()
+            .unwrap();
+
+        let started = std::time::Instant::now();
+        let (a, b) = tokio::join!(client.list_models("agent-1"), client.list_models("agent-1"));
+        a.unwrap();
+        b.unwrap();
+
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_without_layers_requests_overlap() {
+        let (url, peak) = slow_server(Duration::from_millis(100)).await;
+        let client = client_for(url);
+
+        let (a, b) = tokio::join!(client.list_models("agent-1"), client.list_models("agent-1"));
+        a.unwrap();
+        b.unwrap();
+
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_transport_is_a_service() {
+        let (url, _) = slow_server(Duration::ZERO).await;
+        let client = client_for(&url);
+
+        let request = reqwest::Request::new(
+            reqwest::Method::GET,
+            format!("{}/health", url).parse().unwrap(),
+        );
+        let response = client.transport().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), 200);
+    }
+}
+
+/// Recording transport tests
+#[cfg(all(test, feature = "testing"))]
+mod recording {
+    use reqwest::Method;
+    use serde_json::{Value, json};
+    use twcai::api::{AgentClientExt, ConversationsExt};
+    use twcai::testing::{Fixture, FixtureTransport, REDACTED, RecordingLayer};
+    use twcai::types::*;
+    use twcai::{AuthStyle, CloudAIClient};
+
+    fn upstream() -> FixtureTransport {
+        FixtureTransport::new()
+            .route(
+                Method::POST,
+                "/api/v1/cloud-ai/agents/*/call",
+                Fixture::json(
+                    200,
+                    &json!({"message": "Hi there", "id": "msg-secret", "finish_reason": "stop"}),
+                )
+                .header("x-request-id", "req-1")
+                .header("date", "Thu, 01 Jan 2026 00:00:00 GMT"),
+            )
+            .route(
+                Method::GET,
+                "/api/v1/cloud-ai/agents/*/v1/conversations/*/items",
+                Fixture::json(
+                    200,
+                    &json!({
+                        "object": "list",
+                        "data": [
+                            {"type": "message", "id": "item_1", "status": "completed", "role": "user", "content": []},
+                            {"type": "message", "id": "item_2", "status": "completed", "role": "user", "content": []}
+                        ],
+                        "first_id": "item_1",
+                        "last_id": "item_2",
+                        "has_more": false
+                    }),
+                ),
+            )
+    }
+
+    fn recordings(dir: &std::path::Path) -> Vec<(String, Value)> {
+        let mut entries: Vec<_> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        entries.sort();
+        entries
+            .into_iter()
+            .map(|path| {
+                let contents = std::fs::read_to_string(&path).unwrap();
+                (
+                    path.file_name().unwrap().to_string_lossy().into_owned(),
+                    serde_json::from_str(&contents).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_records_sanitized_pairs() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = CloudAIClient::builder()
+            .token("secret-token")
+            .transport(upstream())
+            .layer(
+                RecordingLayer::new(dir.path())
+                    .redact_header("x-request-id")
+                    .redact_request_field("/message")
+                    .redact_response_field("/id"),
+            )
+            .build()
+            .unwrap();
+
+        let reply = client
+            .call_agent("agent-1", AgentCallRequest::new("my password is hunter2"))
+            .await
+            .unwrap();
+        assert_eq!(reply.id, "msg-secret");
+
+        let files = recordings(dir.path());
+        assert_eq!(files.len(), 1);
+        let (name, recording) = &files[0];
+        assert_eq!(name, "0000-post-api-v1-cloud-ai-agents-agent-1-call.json");
+
+        assert_eq!(recording["request"]["method"], "POST");
+        assert_eq!(
+            recording["request"]["path"],
+            "/api/v1/cloud-ai/agents/agent-1/call"
+        );
+        assert_eq!(recording["request"]["json"]["message"], REDACTED);
+        assert!(
+            recording["request"]["headers"]
+                .get("authorization")
+                .is_none()
+        );
+        assert_eq!(recording["response"]["status"], 200);
+        assert_eq!(recording["response"]["json"]["id"], REDACTED);
+        assert_eq!(recording["response"]["json"]["message"], "Hi there");
+        assert_eq!(
+            recording["response"]["headers"],
+            json!({"content-type": "application/json"})
+        );
+
+        let raw = std::fs::read_to_string(dir.path().join(name)).unwrap();
+        assert!(!raw.contains("secret-token"));
+        assert!(!raw.contains("hunter2"));
+        assert!(raw.contains("\n  \"request\": {"));
+    }
+
+    #[tokio::test]
+    async fn test_wildcard_redaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = CloudAIClient::builder()
+            .token("test-token")
+            .transport(upstream())
+            .layer(RecordingLayer::new(dir.path()).redact_response_field("/data/*/id"))
+            .build()
+            .unwrap();
+
+        client
+            .list_conversation_items("agent-1", "conv_1", None)
+            .await
+            .unwrap();
+
+        let (_, recording) = &recordings(dir.path())[0];
+        let data = recording["response"]["json"]["data"].as_array().unwrap();
+        assert!(data.iter().all(|item| item["id"] == REDACTED));
+        assert_eq!(recording["response"]["json"]["last_id"], "item_2");
+    }
+
+    #[tokio::test]
+    async fn test_query_param_auth_is_not_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = CloudAIClient::builder()
+            .token("secret-token")
+            .auth_style(AuthStyle::QueryParam {
+                name: "api_key".to_string(),
+            })
+            .transport(upstream())
+            .layer(RecordingLayer::new(dir.path()).redact_query_param("api_key"))
+            .build()
+            .unwrap();
+
+        let query = ListItemsQuery {
+            limit: Some(5),
+            ..Default::default()
+        };
+        client
+            .list_conversation_items("agent-1", "conv_1", Some(query))
+            .await
+            .unwrap();
+
+        let (name, recording) = &recordings(dir.path())[0];
+        assert_eq!(recording["request"]["query"], "limit=5");
+        let raw = std::fs::read_to_string(dir.path().join(name)).unwrap();
+        assert!(!raw.contains("secret-token"));
+        assert!(!raw.contains("api_key"));
+    }
+
+    #[tokio::test]
+    async fn test_replay_recorded_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let recorder = CloudAIClient::builder()
+            .token("test-token")
+            .transport(upstream())
+            .layer(RecordingLayer::new(dir.path()))
+            .build()
+            .unwrap();
+
+        let live_call = recorder
+            .call_agent("agent-1", AgentCallRequest::new("Hello"))
+            .await
+            .unwrap();
+        let live_items = recorder
+            .list_conversation_items("agent-1", "conv_1", None)
+            .await
+            .unwrap();
+
+        let replay = FixtureTransport::from_recordings(dir.path()).unwrap();
+        let client = CloudAIClient::builder()
+            .token("test-token")
+            .transport(replay.clone())
+            .build()
+            .unwrap();
+
+        let items = client
+            .list_conversation_items("agent-1", "conv_1", None)
+            .await
+            .unwrap();
+        let call = client
+            .call_agent("agent-1", AgentCallRequest::new("Hello"))
+            .await
+            .unwrap();
+
+        assert_eq!(items, live_items);
+        assert_eq!(call.message, live_call.message);
+        assert_eq!(call.id, live_call.id);
+        assert_eq!(replay.requests().len(), 2);
+    }
+}
@@ -0,0 +1,488 @@
+//! Wire format tests for TWCai
+
+mod common;
+
+/// Wire format conformance tests
+#[cfg(test)]
+mod conformance {
+    use serde::Serialize;
+    use serde::de::DeserializeOwned;
+    use serde_json::Value;
+    use twcai::types::*;
+
+    struct Case {
+        fixture: &'static str,
+        json: &'static str,
+        round_trip: fn(&str) -> serde_json::Result<Value>,
+        /// Fields the types do not model yet (JSON pointers, `*` matches any
+        /// element); they are removed from the fixture before comparing
+        unmodeled: &'static [&'static str],
+    }
+
+    macro_rules! case {
+        ($fixture:literal, $ty:ty) => {
+            case!($fixture, $ty, [])
+        };
+        ($fixture:literal, $ty:ty, [$($unmodeled:literal),* $(,)?]) => {
+            Case {
+                fixture: $fixture,
+                json: include_str!(concat!("fixtures/", $fixture)),
+                round_trip: round_trip::<$ty>,
+                unmodeled: &[$($unmodeled),*],
+            }
+        };
+    }
+
+    fn cases() -> Vec<Case> {
+        vec![
+            case!(
+                "conformance/chat_text.json",
+                ChatCompletionResponse,
+                [
+                    "/usage/completion_tokens_details/accepted_prediction_tokens",
+                    "/usage/completion_tokens_details/rejected_prediction_tokens",
+                ]
+            ),
+            case!("conformance/chat_n_choices.json", ChatCompletionResponse),
+            case!("chat/tool_calls.json", ChatCompletionResponse),
+            case!("chat/refusal.json", ChatCompletionResponse),
+            case!("conformance/response_completed.json", Response),
+            case!("conformance/response_failed.json", Response),
+            case!("conformance/response_background.json", Response),
+            case!("conformance/conversation.json", Conversation),
+            case!("conformance/conversation_deleted.json", ConversationDeleted),
+            case!(
+                "conformance/item_list.json",
+                ConversationItemList,
+                [
+                    "/data/*/content/*/annotations",
+                    "/data/*/content/*/logprobs"
+                ]
+            ),
+            case!(
+                "conformance/item.json",
+                ConversationItem,
+                ["/content/*/annotations"]
+            ),
+            case!("conformance/input_items.json", ResponseInputItemList),
+            case!("conformance/models.json", ModelsResponse),
+        ]
+    }
+
+    fn round_trip<T: DeserializeOwned + Serialize>(json: &str) -> serde_json::Result<Value> {
+        let typed: T = serde_json::from_str(json)?;
+        serde_json::to_value(&typed)
+    }
+
+    /// Usage field names accepted as aliases, mapped to the serialized name
+    const USAGE_ALIASES: [(&str, &str); 4] = [
+        ("input_tokens", "prompt_tokens"),
+        ("output_tokens", "completion_tokens"),
+        ("input_tokens_details", "prompt_tokens_details"),
+        ("output_tokens_details", "completion_tokens_details"),
+    ];
+
+    /// Reduce JSON to what is semantically relevant
+    ///
+    /// Null and absent fields are equal, a null message content equals the
+    /// empty text the types read it as, and usage aliases are renamed.
+    fn normalize(value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                for (alias, name) in USAGE_ALIASES {
+                    if let Some(v) = map.remove(alias) {
+                        map.insert(name.to_string(), v);
+                    }
+                }
+                map.retain(|key, v| !v.is_null() && !(key == "content" && *v == ""));
+                map.values_mut().for_each(normalize);
+            }
+            Value::Array(items) => items.iter_mut().for_each(normalize),
+            _ => {}
+        }
+    }
+
+    fn remove_pointer(value: &mut Value, segments: &[&str]) {
+        let Some((first, rest)) = segments.split_first() else {
+            return;
+        };
+        let children: Vec<&mut Value> = match value {
+            Value::Object(map) if rest.is_empty() => {
+                map.remove(*first);
+                return;
+            }
+            Value::Object(map) if *first == "*" => map.values_mut().collect(),
+            Value::Object(map) => map.get_mut(*first).into_iter().collect(),
+            Value::Array(items) if *first == "*" => items.iter_mut().collect(),
+            Value::Array(items) => first
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| items.get_mut(i))
+                .into_iter()
+                .collect(),
+            _ => return,
+        };
+        for child in children {
+            remove_pointer(child, rest);
+        }
+    }
+
+    fn check(case: &Case) -> Result<(), String> {
+        let mut expected: Value = serde_json::from_str(case.json)
+            .map_err(|e| format!("fixture is not valid JSON: {}", e))?;
+        let mut actual =
+            (case.round_trip)(case.json).map_err(|e| format!("does not deserialize: {}", e))?;
+
+        for pointer in case.unmodeled {
+            let segments: Vec<&str> = pointer.split('/').skip(1).collect();
+            remove_pointer(&mut expected, &segments);
+        }
+        normalize(&mut expected);
+        normalize(&mut actual);
+
+        if expected == actual {
+            Ok(())
+        } else {
+            Err(format!(
+                "round trip differs\n--- fixture\n{}\n--- serialized\n{}",
+                serde_json::to_string_pretty(&expected).unwrap(),
+                serde_json::to_string_pretty(&actual).unwrap()
+            ))
+        }
+    }
+
+    #[test]
+    fn test_fixtures_round_trip() {
+        let failures: Vec<String> = cases()
+            .iter()
+            .filter_map(|case| {
+                check(case)
+                    .err()
+                    .map(|e| format!("{}: {}", case.fixture, e))
+            })
+            .collect();
+
+        assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+    }
+
+    #[test]
+    fn test_unmodeled_pointers_exist() {
+        // A stale entry would hide a regression of a field once it is modeled
+        for case in cases() {
+            let fixture: Value = serde_json::from_str(case.json).unwrap();
+            for pointer in case.unmodeled {
+                let segments: Vec<&str> = pointer.split('/').skip(1).collect();
+                let mut stripped = fixture.clone();
+                remove_pointer(&mut stripped, &segments);
+                assert_ne!(
+                    stripped, fixture,
+                    "{}: {} matches nothing",
+                    case.fixture, pointer
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_typed_accessors_on_fixtures() {
+        let response: Response =
+            serde_json::from_str(include_str!("fixtures/conformance/response_completed.json"))
+                .unwrap();
+        assert_eq!(response.status, "completed");
+        assert_eq!(response.total_tokens(), 123);
+        assert!(response.output_text().starts_with("In a peaceful grove"));
+
+        let failed: Response =
+            serde_json::from_str(include_str!("fixtures/conformance/response_failed.json"))
+                .unwrap();
+        assert_eq!(failed.status, "failed");
+        assert!(failed.usage.is_none());
+        assert_eq!(failed.extra["error"]["code"], "server_error");
+
+        let chat: ChatCompletionResponse =
+            serde_json::from_str(include_str!("fixtures/conformance/chat_text.json")).unwrap();
+        assert!(chat.is_chat_completion());
+        assert_eq!(chat.created, 1741569952);
+        assert_eq!(chat.model, "gpt-4.1-2025-04-14");
+        assert_eq!(chat.system_fingerprint.as_deref(), Some("fp_3a5b2c1d9e"));
+        assert_eq!(chat.service_tier, Some(ServiceTier::Default));
+        assert!(chat.extra.is_empty());
+
+        let chat: ChatCompletionResponse =
+            serde_json::from_str(include_str!("fixtures/conformance/chat_n_choices.json")).unwrap();
+        assert_eq!(chat.choices.len(), 3);
+        assert_eq!(chat.choices[2].finish_reason, Some(FinishReason::Length));
+
+        let item: ConversationItem =
+            serde_json::from_str(include_str!("fixtures/conformance/item.json")).unwrap();
+        assert_eq!(item.created_at, Some(1756492484));
+        assert_eq!(item.text(), "Rust is a systems language focused on safety.");
+        assert!(item.is_assistant());
+        assert!(!item.is_user());
+        assert_eq!(item.extra["response_id"], "resp_68b1f2a0");
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_error_bodies() {
+        use reqwest::Method;
+        use twcai::TwcError;
+        use twcai::api::AgentClientExt;
+        use twcai::testing::{Fixture, FixtureTransport};
+
+        use crate::common::client_with;
+
+        let cases: [(&str, u16, &str); 4] = [
+            ("error_openai.json", 400, "invalid_request"),
+            ("error_detail.json", 404, "not_found"),
+            ("error_message.json", 402, "payment_required"),
+            ("error_gateway.html", 502, "server_error"),
+        ];
+
+        let fixtures = FixtureTransport::new();
+        let client = client_with(&fixtures);
+
+        for (i, (fixture, status, kind)) in cases.into_iter().enumerate() {
+            let agent = format!("agent-{}", i);
+            let body = Fixture::from_file(
+                std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                    .join("tests/fixtures/conformance")
+                    .join(fixture),
+            )
+            .unwrap()
+            .status(status);
+            let _ = fixtures.clone().route(
+                Method::GET,
+                &format!("/api/v1/cloud-ai/agents/{}/v1/models", agent),
+                body,
+            );
+
+            let err = client.list_models(&agent).await.unwrap_err();
+            match (kind, err) {
+                ("invalid_request", TwcError::InvalidRequest(message)) => assert_eq!(
+                    message,
+                    "Invalid 'messages[0].role': expected one of system, user, assistant."
+                ),
+                ("not_found", TwcError::NotFound(message)) => {
+                    assert_eq!(message, "Agent not found")
+                }
+                ("payment_required", TwcError::PaymentRequired(message)) => {
+                    assert_eq!(message, "Insufficient balance")
+                }
+                ("server_error", TwcError::ServerError { status, .. }) => assert_eq!(status, 502),
+                (kind, other) => panic!("{}: expected {}, got {:?}", fixture, kind, other),
+            }
+        }
+    }
+}
+
+/// Field naming variant tests
+#[cfg(test)]
+mod naming {
+    use twcai::types::*;
+
+    #[test]
+    fn test_response_responses_style() {
+        let response: Response = serde_json::from_str(include_str!(
+            "fixtures/naming/response_responses_style.json"
+        ))
+        .unwrap();
+
+        assert_eq!(response.created_at, 1741476542);
+        let usage = response.usage.as_ref().unwrap();
+        assert_eq!(usage.prompt_tokens, 36);
+        assert_eq!(usage.completion_tokens, 87);
+        assert_eq!(response.total_tokens(), 123);
+        assert_eq!(response.output_text(), "In a peaceful grove...");
+    }
+
+    #[test]
+    fn test_response_chat_style() {
+        let response: Response =
+            serde_json::from_str(include_str!("fixtures/naming/response_chat_style.json")).unwrap();
+
+        assert_eq!(response.created_at, 1741476542);
+        assert_eq!(response.usage.as_ref().unwrap().completion_tokens, 87);
+    }
+
+    #[test]
+    fn test_response_without_usage() {
+        let response: Response = serde_json::from_str(
+            r#"{"id": "resp_3", "object": "response", "created_at": 1, "model": "m", "status": "in_progress", "usage": null}"#,
+        )
+        .unwrap();
+
+        assert_eq!(response.usage, None);
+        assert_eq!(response.total_tokens(), 0);
+    }
+
+    #[test]
+    fn test_models_naming_variants() {
+        let models: ModelsResponse =
+            serde_json::from_str(include_str!("fixtures/naming/models.json")).unwrap();
+
+        assert_eq!(models.data[0].created, Some(1700000000));
+        assert_eq!(models.data[0].owned_by.as_deref(), Some("timeweb"));
+        assert_eq!(models.data[1].created, Some(1700000500));
+        assert_eq!(models.data[1].owned_by, None);
+        assert_eq!(models.data[2].object, "model");
+        assert_eq!(models.data[2].created, None);
+    }
+}
+
+/// Request serialization snapshot tests
+#[cfg(test)]
+mod serialization {
+    use serde_json::json;
+    use twcai::types::*;
+
+    #[test]
+    fn test_default_requests_have_no_nulls() {
+        assert_eq!(
+            serde_json::to_string(&ChatCompletionRequest::default()).unwrap(),
+            r#"{"messages":[]}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&CreateResponseRequest::default()).unwrap(),
+            "{}"
+        );
+        assert_eq!(
+            serde_json::to_string(&StreamOptions::default()).unwrap(),
+            "{}"
+        );
+        assert_eq!(
+            serde_json::to_string(&ListItemsQuery::default()).unwrap(),
+            "{}"
+        );
+        assert_eq!(
+            serde_json::to_string(&GetResponseQuery::default()).unwrap(),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn test_text_completion_request_builder() {
+        assert_eq!(
+            serde_json::to_value(TextCompletionRequest::default()).unwrap(),
+            json!({"prompt": ""})
+        );
+
+        let request = TextCompletionRequest::new("Once upon a time")
+            .model("gpt-3.5-turbo-instruct")
+            .max_tokens(16)
+            .stop(vec!["\n", "."]);
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "prompt": "Once upon a time",
+                "model": "gpt-3.5-turbo-instruct",
+                "max_tokens": 16,
+                "stop": ["\n", "."]
+            })
+        );
+    }
+
+    #[test]
+    fn test_image_without_detail() {
+        let message = ChatMessage::user_multimodal(vec![ContentItem::ImageUrl(ImageUrlContent {
+            image_url: ImageUrl {
+                url: "https://example.com/cat.png".to_string(),
+                detail: None,
+            },
+        })]);
+
+        let json = serde_json::to_string(&message).unwrap();
+        assert!(!json.contains("null"));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            json!({
+                "role": "user",
+                "content": [{"type": "image_url", "image_url": {"url": "https://example.com/cat.png"}}]
+            })
+        );
+    }
+
+    #[test]
+    fn test_image_detail_casing() {
+        for (detail, name) in [
+            (ImageDetail::Low, "low"),
+            (ImageDetail::High, "high"),
+            (ImageDetail::Auto, "auto"),
+        ] {
+            let url = ImageUrl {
+                url: "u".to_string(),
+                detail: Some(detail),
+            };
+            let json = serde_json::to_value(&url).unwrap();
+            assert_eq!(json, json!({"url": "u", "detail": name}));
+            assert_eq!(serde_json::from_value::<ImageUrl>(json).unwrap(), url);
+        }
+    }
+}
+
+/// Unknown response field tests
+#[cfg(test)]
+mod unknown_fields {
+    use serde_json::json;
+    use twcai::types::*;
+
+    #[test]
+    fn test_unknown_chat_fields_round_trip() {
+        let payload = json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-4.1",
+            "service_tier": "default",
+            "provider": {"name": "azure", "region": "westeurope"},
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": "Paris",
+                    "annotations": [{"type": "url_citation", "url": "https://example.com"}]
+                },
+                "finish_reason": "stop",
+                "content_filter_results": {"hate": {"filtered": false}}
+            }],
+            "usage": {"prompt_tokens": 5, "completion_tokens": 1, "total_tokens": 6}
+        });
+
+        let response: ChatCompletionResponse = serde_json::from_value(payload.clone()).unwrap();
+        assert_eq!(response.service_tier, Some(ServiceTier::Default));
+        assert_eq!(response.extra["provider"]["region"], "westeurope");
+        let choice = &response.choices[0];
+        assert_eq!(
+            choice.extra["content_filter_results"]["hate"]["filtered"],
+            false
+        );
+        assert_eq!(
+            choice.message.extra["annotations"][0]["type"],
+            "url_citation"
+        );
+        assert_eq!(response.first_text(), Some("Paris"));
+
+        let back = serde_json::to_value(&response).unwrap();
+        assert_eq!(back["service_tier"], payload["service_tier"]);
+        assert_eq!(back["provider"], payload["provider"]);
+        assert_eq!(
+            back["choices"][0]["content_filter_results"],
+            payload["choices"][0]["content_filter_results"]
+        );
+        assert_eq!(
+            back["choices"][0]["message"]["annotations"],
+            payload["choices"][0]["message"]["annotations"]
+        );
+        assert!(back.get("extra").is_none());
+    }
+
+    #[test]
+    fn test_constructed_messages_have_no_extra() {
+        let message = ChatMessage::user("Hi");
+        assert!(message.extra.is_empty());
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            json!({"role": "user", "content": "Hi"})
+        );
+    }
+}
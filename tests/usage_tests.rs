@@ -0,0 +1,557 @@
+//! Usage, token and cost accounting tests for TWCai
+
+mod common;
+
+/// Usage breakdown tests
+#[cfg(test)]
+mod usage_breakdown {
+    use twcai::types::*;
+
+    #[test]
+    fn test_usage_details() {
+        let usage: Usage = serde_json::from_str(
+            r#"{
+                "prompt_tokens": 1200,
+                "completion_tokens": 800,
+                "total_tokens": 2000,
+                "prompt_tokens_details": {"cached_tokens": 1024, "audio_tokens": 0},
+                "completion_tokens_details": {"reasoning_tokens": 640, "accepted_prediction_tokens": 0}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(usage.cached_tokens(), 1024);
+        assert_eq!(usage.reasoning_tokens(), 640);
+        assert_eq!(usage.prompt_tokens_details.unwrap().audio_tokens, Some(0));
+    }
+
+    #[test]
+    fn test_usage_without_details() {
+        let usage: Usage = serde_json::from_str(
+            r#"{"prompt_tokens": 3, "completion_tokens": 1, "total_tokens": 4}"#,
+        )
+        .unwrap();
+
+        assert_eq!(usage.cached_tokens(), 0);
+        assert_eq!(usage.reasoning_tokens(), 0);
+        assert_eq!(
+            serde_json::to_string(&usage).unwrap(),
+            r#"{"prompt_tokens":3,"completion_tokens":1,"total_tokens":4}"#
+        );
+    }
+
+    #[test]
+    fn test_response_usage_responses_naming() {
+        let usage: ResponseUsage = serde_json::from_str(
+            r#"{
+                "input_tokens": 36,
+                "input_tokens_details": {"cached_tokens": 32},
+                "output_tokens": 87,
+                "output_tokens_details": {"reasoning_tokens": 51},
+                "total_tokens": 123
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(usage.prompt_tokens, 36);
+        assert_eq!(usage.completion_tokens, 87);
+        assert_eq!(usage.total_tokens, 123);
+        assert_eq!(usage.cached_tokens(), 32);
+        assert_eq!(usage.reasoning_tokens(), 51);
+    }
+
+    #[test]
+    fn test_response_usage_chat_naming() {
+        let usage: ResponseUsage = serde_json::from_str(
+            r#"{"prompt_tokens": 5, "completion_tokens": 7, "total_tokens": 12}"#,
+        )
+        .unwrap();
+
+        assert_eq!(usage.prompt_tokens, 5);
+        assert_eq!(usage.completion_tokens, 7);
+        assert_eq!(usage.cached_tokens(), 0);
+    }
+}
+
+/// Context window fitting tests
+#[cfg(test)]
+mod context {
+    use twcai::context::{TruncationStrategy, fit_messages};
+    use twcai::tokens::{IMAGE_TOKENS, estimate_request_tokens};
+    use twcai::types::*;
+
+    fn conversation() -> Vec<ChatMessage> {
+        vec![
+            ChatMessage::system("You are a terse assistant."),
+            ChatMessage::user("First question about the weather today?"),
+            ChatMessage::assistant("Sunny and warm, around twenty degrees."),
+            ChatMessage::user("Second question: what should I wear outside?"),
+            ChatMessage::assistant("A t-shirt and light trousers will do."),
+            ChatMessage::user("Third question: do I need sunscreen?"),
+        ]
+    }
+
+    fn prompt_tokens(messages: &[ChatMessage]) -> usize {
+        estimate_request_tokens(&ChatCompletionRequest {
+            messages: messages.to_vec(),
+            ..Default::default()
+        })
+    }
+
+    fn texts(messages: &[ChatMessage]) -> Vec<&str> {
+        messages
+            .iter()
+            .map(|m| match &m.content {
+                ChatContent::Text(text) => text.as_str(),
+                ChatContent::Array(_) => "<multimodal>",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_everything_fits() {
+        let messages = conversation();
+        let budget = prompt_tokens(&messages);
+        let fitted = fit_messages(messages.clone(), budget, TruncationStrategy::DropOldest);
+        assert_eq!(fitted, messages);
+    }
+
+    #[test]
+    fn test_drop_oldest_removes_whole_turns() {
+        let messages = conversation();
+        let kept = [
+            messages[0].clone(),
+            messages[3].clone(),
+            messages[4].clone(),
+            messages[5].clone(),
+        ];
+        let budget = prompt_tokens(&kept);
+
+        let fitted = fit_messages(messages, budget, TruncationStrategy::DropOldest);
+
+        assert_eq!(
+            texts(&fitted),
+            vec![
+                "You are a terse assistant.",
+                "Second question: what should I wear outside?",
+                "A t-shirt and light trousers will do.",
+                "Third question: do I need sunscreen?",
+            ]
+        );
+        assert!(prompt_tokens(&fitted) <= budget);
+    }
+
+    #[test]
+    fn test_system_message_is_never_dropped() {
+        let fitted = fit_messages(conversation(), 1, TruncationStrategy::DropOldest);
+        assert_eq!(
+            texts(&fitted),
+            vec![
+                "You are a terse assistant.",
+                "Third question: do I need sunscreen?"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keep_last_does_not_orphan_answers() {
+        // The last 4 messages would start with an answer; the window moves
+        // forward to the next question instead
+        let fitted = fit_messages(conversation(), usize::MAX, TruncationStrategy::KeepLast(4));
+        assert_eq!(
+            texts(&fitted),
+            vec![
+                "You are a terse assistant.",
+                "Second question: what should I wear outside?",
+                "A t-shirt and light trousers will do.",
+                "Third question: do I need sunscreen?",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_images_count_with_flat_cost() {
+        let image = ChatMessage::user_multimodal(vec![ContentItem::ImageUrl(ImageUrlContent {
+            image_url: ImageUrl {
+                url: "https://example.com/cat.png".to_string(),
+                detail: None,
+            },
+        })]);
+        let messages = vec![
+            ChatMessage::system("Describe images."),
+            image,
+            ChatMessage::assistant("A cat."),
+            ChatMessage::user("And now?"),
+        ];
+
+        let fitted = fit_messages(messages, IMAGE_TOKENS, TruncationStrategy::DropOldest);
+        assert_eq!(texts(&fitted), vec!["Describe images.", "And now?"]);
+    }
+}
+
+/// Cost estimation tests
+#[cfg(test)]
+mod pricing {
+    use twcai::TwcError;
+    use twcai::pricing::{CostTracker, ModelPrice, PriceTable};
+    use twcai::types::*;
+
+    fn table() -> PriceTable {
+        PriceTable::new("RUB")
+            .model(
+                "deepseek-reason",
+                ModelPrice::new(50.0, 200.0).cached_input(12.5),
+            )
+            .model("tiny", ModelPrice::new(0.4, 0.4))
+    }
+
+    fn usage(prompt: u32, completion: u32) -> Usage {
+        Usage {
+            prompt_tokens: prompt,
+            completion_tokens: completion,
+            total_tokens: prompt + completion,
+            ..Default::default()
+        }
+    }
+
+    fn with_cached(mut usage: Usage, cached: u32) -> Usage {
+        usage.prompt_tokens_details = Some(PromptTokensDetails {
+            cached_tokens: Some(cached),
+            audio_tokens: None,
+        });
+        usage
+    }
+
+    #[test]
+    fn test_cost_per_million_tokens() {
+        let cost = table().cost("deepseek-reason", &usage(1234, 567)).unwrap();
+        assert_eq!(cost.input, 0.0617);
+        assert_eq!(cost.output, 0.1134);
+        assert_eq!(cost.total, 0.1751);
+        assert_eq!(cost.currency, "RUB");
+    }
+
+    #[test]
+    fn test_cost_rounds_to_six_places() {
+        let table = table();
+        // 3 * 0.4 / 1M = 0.0000012
+        assert_eq!(table.cost("tiny", &usage(3, 0)).unwrap().input, 0.000001);
+        // 4 * 0.4 / 1M = 0.0000016
+        assert_eq!(table.cost("tiny", &usage(4, 0)).unwrap().input, 0.000002);
+        assert_eq!(table.cost("tiny", &usage(1, 0)).unwrap().total, 0.0);
+    }
+
+    #[test]
+    fn test_unknown_model_has_no_cost() {
+        assert!(table().cost("gpt-unknown", &usage(10, 10)).is_none());
+    }
+
+    #[test]
+    fn test_cached_tokens_discount() {
+        let table = table();
+        let cost = table
+            .cost("deepseek-reason", &with_cached(usage(1000, 100), 400))
+            .unwrap();
+        assert_eq!(cost.input, 0.035);
+        assert_eq!(cost.output, 0.02);
+        assert_eq!(cost.total, 0.055);
+
+        // Without the breakdown every prompt token is charged in full
+        let cost = table.cost("deepseek-reason", &usage(1000, 100)).unwrap();
+        assert_eq!(cost.input, 0.05);
+
+        // Without a cached price the breakdown changes nothing
+        let cost = table
+            .cost("tiny", &with_cached(usage(1000, 0), 400))
+            .unwrap();
+        assert_eq!(cost.input, 0.0004);
+    }
+
+    #[test]
+    fn test_load_json_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prices.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "currency": "RUB",
+                "models": {
+                    "deepseek-reason": {"input": 50.0, "cached_input": 12.5, "output": 200.0},
+                    "tiny": {"input": 0.4, "output": 0.4}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(PriceTable::from_file(&path).unwrap(), table());
+    }
+
+    #[cfg(feature = "config-file")]
+    #[test]
+    fn test_load_toml_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prices.toml");
+        std::fs::write(
+            &path,
+            r#"
+currency = "RUB"
+
+[models.deepseek-reason]
+input = 50.0
+cached_input = 12.5
+output = 200.0
+
+[models.tiny]
+input = 0.4
+output = 0.4
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(PriceTable::from_file(&path).unwrap(), table());
+    }
+
+    #[cfg(not(feature = "config-file"))]
+    #[test]
+    fn test_toml_file_needs_feature() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prices.toml");
+        std::fs::write(&path, "currency = \"RUB\"\n").unwrap();
+
+        let error = PriceTable::from_file(&path).unwrap_err();
+        assert!(
+            matches!(error, TwcError::Configuration(message) if message.contains("config-file"))
+        );
+    }
+
+    #[test]
+    fn test_load_invalid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prices.json");
+        std::fs::write(&path, r#"{"models": {}}"#).unwrap();
+
+        let error = PriceTable::from_file(&path).unwrap_err();
+        assert!(
+            matches!(error, TwcError::Configuration(message) if message.contains("prices.json"))
+        );
+        assert!(PriceTable::from_file(dir.path().join("missing.json")).is_err());
+    }
+
+    #[test]
+    fn test_tracker_totals() {
+        let tracker = CostTracker::new(table());
+        tracker.record("deepseek-reason", &usage(1234, 567));
+        tracker.record("deepseek-reason", &usage(1000, 100));
+        tracker.record("tiny", &usage(1_000_000, 0));
+        assert!(tracker.record("gpt-unknown", &usage(1, 1)).is_none());
+
+        let reason = tracker.model_total("deepseek-reason").unwrap();
+        assert_eq!(reason.total, 0.2451);
+        assert_eq!(tracker.total().total, 0.6451);
+        assert_eq!(tracker.total().currency, "RUB");
+        assert_eq!(tracker.unpriced(), 1);
+
+        tracker.reset();
+        assert_eq!(tracker.total().total, 0.0);
+        assert_eq!(tracker.unpriced(), 0);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_client_records_costs() {
+        use reqwest::Method;
+        use twcai::api::AgentClientExt;
+        use twcai::testing::{Fixture, FixtureTransport};
+
+        use crate::common::builder_with;
+
+        let fixtures = FixtureTransport::new().route(
+            Method::POST,
+            "/api/v1/cloud-ai/agents/agent-1/v1/chat/completions",
+            Fixture::raw(
+                200,
+                "application/json",
+                r#"{
+                    "id": "chatcmpl-1",
+                    "object": "chat.completion",
+                    "created": 1700000000,
+                    "model": "deepseek-reason",
+                    "choices": [{
+                        "index": 0,
+                        "message": {"role": "assistant", "content": "Done."},
+                        "finish_reason": "stop"
+                    }],
+                    "usage": {"prompt_tokens": 1000, "completion_tokens": 100, "total_tokens": 1100}
+                }"#,
+            ),
+        );
+
+        let tracker = CostTracker::new(table());
+        let client = builder_with(&fixtures)
+            .cost_tracker(tracker.clone())
+            .build()
+            .unwrap();
+
+        let request = ChatCompletionRequest {
+            messages: vec![ChatMessage::user("Hi")],
+            ..Default::default()
+        };
+        client
+            .chat_completions("agent-1", request.clone())
+            .await
+            .unwrap();
+        client.chat_completions("agent-1", request).await.unwrap();
+
+        assert_eq!(fixtures.requests().len(), 2);
+        assert_eq!(tracker.total().total, 0.14);
+        assert_eq!(tracker.unpriced(), 0);
+    }
+}
+
+/// Usage statistics tests
+#[cfg(test)]
+mod stats {
+    use twcai::types::*;
+
+    const USAGE: &str = include_str!("fixtures/stats/agent_usage.json");
+
+    fn date(year: i32, month: u8, day: u8) -> UsageDate {
+        UsageDate::new(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_usage_report_deserialization() {
+        let report: AgentUsageReport = serde_json::from_str(USAGE).unwrap();
+
+        assert_eq!(report.from, date(2025, 1, 30));
+        assert_eq!(report.days.len(), 3);
+        assert_eq!(report.days[1].date, date(2025, 1, 31));
+        assert_eq!(report.totals(), (17, 4300, 1650));
+    }
+
+    #[test]
+    fn test_usage_date_validation_and_format() {
+        assert_eq!(date(2025, 3, 7).to_string(), "2025-03-07");
+        assert_eq!(
+            "2024-02-29".parse::<UsageDate>().unwrap(),
+            date(2024, 2, 29)
+        );
+        assert!(UsageDate::new(2025, 2, 29).is_err());
+        assert!(UsageDate::new(2025, 13, 1).is_err());
+        assert!("2025/01/01".parse::<UsageDate>().is_err());
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_get_agent_usage_query() {
+        use reqwest::Method;
+        use twcai::TwcError;
+        use twcai::api::StatsExt;
+        use twcai::testing::{Fixture, FixtureTransport};
+
+        use crate::common::client_with;
+
+        let fixtures = FixtureTransport::new().route(
+            Method::GET,
+            "/api/v1/cloud-ai/agents/agent-1/usage",
+            Fixture::raw(200, "application/json", USAGE),
+        );
+        let client = client_with(&fixtures);
+
+        let report = client
+            .get_agent_usage("agent-1", date(2025, 1, 30), date(2025, 2, 1))
+            .await
+            .unwrap();
+        assert_eq!(report.days[2].completion_tokens, 450);
+
+        let err = client
+            .get_agent_usage("agent-1", date(2025, 2, 1), date(2025, 1, 30))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TwcError::InvalidRequest(_)));
+
+        let requests = fixtures.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].query.as_deref(),
+            Some("from=2025-01-30&to=2025-02-01")
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn test_chrono_conversion() {
+        let naive = chrono::NaiveDate::from_ymd_opt(2025, 1, 30).unwrap();
+        let converted = UsageDate::from(naive);
+        assert_eq!(converted, date(2025, 1, 30));
+        assert_eq!(chrono::NaiveDate::from(converted), naive);
+    }
+}
+
+/// Token estimation tests
+#[cfg(test)]
+mod tokens {
+    use serde::Deserialize;
+    use twcai::tokens::*;
+    use twcai::types::*;
+
+    const PROMPTS: &str = include_str!("fixtures/tokens/prompts.json");
+
+    #[derive(Deserialize)]
+    struct Fixture {
+        description: String,
+        prompt_tokens: usize,
+        request: ChatCompletionRequest,
+    }
+
+    #[test]
+    fn test_request_estimates_within_tolerance() {
+        let fixtures: Vec<Fixture> = serde_json::from_str(PROMPTS).unwrap();
+
+        for fixture in fixtures {
+            let estimate = estimate_request_tokens(&fixture.request) as f64;
+            let actual = fixture.prompt_tokens as f64;
+            let error = (estimate - actual).abs() / actual;
+            assert!(
+                error <= 0.15,
+                "{}: estimated {} vs {} reported ({:.0}% off)",
+                fixture.description,
+                estimate,
+                actual,
+                error * 100.0
+            );
+        }
+    }
+
+    #[test]
+    fn test_estimate_tokens_heuristics() {
+        assert_eq!(estimate_tokens(""), 0);
+        // Never fewer tokens than words
+        assert_eq!(estimate_tokens("a b c d e"), 5);
+        // Roughly four characters per token for long words
+        assert_eq!(estimate_tokens("internationalization"), 5);
+        // One token per CJK character
+        assert_eq!(estimate_tokens("你好世界"), 4);
+        assert_eq!(estimate_tokens("hello 世界"), 4);
+    }
+
+    #[test]
+    fn test_multimodal_parts_use_flat_costs() {
+        let message = ChatMessage::user_multimodal(vec![
+            ContentItem::Text(TextContent {
+                text: "What is this?".to_string(),
+            }),
+            ContentItem::ImageUrl(ImageUrlContent {
+                image_url: ImageUrl {
+                    url: "https://example.com/cat.png".to_string(),
+                    detail: None,
+                },
+            }),
+        ]);
+
+        assert_eq!(
+            estimate_message_tokens(&message),
+            TOKENS_PER_MESSAGE + 1 + estimate_tokens("What is this?") + IMAGE_TOKENS
+        );
+    }
+}